@@ -0,0 +1,54 @@
+//! Benchmarks of laying out large schemas
+//!
+//! Schemas are generated with `n` tables, each of which refers to two earlier tables, so that
+//! edges span the whole grid like in real schemas. Run with `cargo bench --bench layout`.
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use seiren::layout::{LayoutEngine, SimpleLayoutEngine};
+use seiren::parser::parse;
+
+/// Returns the source of a schema of `n` tables. References are chosen by a fixed linear
+/// congruential generator, so the schema is the same on every run.
+fn schema(n: usize) -> String {
+    let mut state: u64 = 1;
+    let mut earlier = |i: usize| {
+        state = state
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1);
+        (state >> 33) as usize % i
+    };
+    let mut src = String::from("erd {\n");
+
+    for i in 0..n {
+        src.push_str(&format!(
+            "t{} {{\n    id int PK\n    ref_id int FK\n    other_id int FK\n    name text\n}}\n",
+            i
+        ));
+    }
+    for i in 1..n {
+        src.push_str(&format!("t{}.ref_id o--o t{}.id\n", i, earlier(i)));
+        src.push_str(&format!("t{}.other_id o--o t{}.id\n", i, earlier(i)));
+    }
+    src.push_str("}\n");
+    src
+}
+
+fn layout(c: &mut Criterion) {
+    let mut group = c.benchmark_group("layout");
+
+    group.sample_size(10);
+    for n in [50, 200, 500] {
+        let (ast, _, _) = parse(&schema(n));
+        let module = ast.unwrap();
+
+        group.bench_with_input(BenchmarkId::from_parameter(n), &module, |b, module| {
+            b.iter(|| {
+                let mut doc = module.into_mir();
+                SimpleLayoutEngine::default().layout(&mut doc)
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, layout);
+criterion_main!(benches);
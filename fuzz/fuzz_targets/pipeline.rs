@@ -0,0 +1,48 @@
+//! Feeds arbitrary sources through parse → layout → render, and checks that the pipeline
+//! doesn't panic and writes a UTF-8 SVG.
+//!
+//! ```console
+//! $ cargo +nightly fuzz run pipeline -- -dict=fuzz/seiren.dict
+//! ```
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use seiren::layout::{LayoutEngine, SimpleLayoutEngine};
+use seiren::parser::parse;
+use seiren::renderer::{Background, Renderer, SVGRenderer};
+
+#[derive(Debug, Arbitrary)]
+struct Input<'a> {
+    src: &'a str,
+    wide_mode: bool,
+    truncate_overflow: bool,
+    css_classes: bool,
+    transparent: bool,
+}
+
+fuzz_target!(|input: Input| {
+    let (Some(ast), _, _) = parse(input.src) else {
+        return;
+    };
+
+    let mut doc = ast.into_mir();
+    let mut engine = SimpleLayoutEngine::default();
+    let mut backend = SVGRenderer::new();
+
+    backend.view_box = engine.layout(&mut doc).view_box;
+
+    backend.wide_mode = input.wide_mode;
+    backend.truncate_overflow = input.truncate_overflow;
+    backend.css_classes = input.css_classes;
+    if input.transparent {
+        backend.background = Background::Transparent;
+    }
+
+    let mut bytes: Vec<u8> = vec![];
+
+    backend.render(&doc, &mut bytes).expect("render SVG");
+
+    let svg = String::from_utf8(bytes).expect("SVG must be UTF-8");
+    assert!(svg.starts_with("<svg"));
+});
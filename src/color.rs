@@ -1,6 +1,9 @@
 use derive_more::Display;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Display)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum WebColor {
     #[display(fmt = "{}", _0)]
     RGB(RGBColor),
@@ -14,7 +17,8 @@ impl Default for WebColor {
     }
 }
 
-#[derive(Debug, Clone, Default, Display)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Display)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[display(fmt = "#{:02X}{:02X}{:02X}", red, green, blue)]
 pub struct RGBColor {
     pub red: u8,
@@ -26,9 +30,28 @@ impl RGBColor {
     pub fn new(red: u8, green: u8, blue: u8) -> Self {
         Self { red, green, blue }
     }
+
+    /// Parses a hex color notation (`#RGB` or `#RRGGBB`).
+    pub fn from_hex(s: &str) -> Option<Self> {
+        let hex = s.strip_prefix('#')?;
+
+        if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+
+        let digit = |i: usize| u8::from_str_radix(&hex[i..i + 1], 16).unwrap();
+        let byte = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap();
+
+        match hex.len() {
+            3 => Some(Self::new(digit(0) * 17, digit(1) * 17, digit(2) * 17)),
+            6 => Some(Self::new(byte(0), byte(2), byte(4))),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, Display)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum NamedColor {
     #[display(fmt = "white")]
     White,
@@ -52,6 +75,18 @@ mod tests {
         assert_eq!(color.to_string(), "#497B91");
     }
 
+    #[test]
+    fn rgb_color_from_hex() {
+        assert_eq!(
+            RGBColor::from_hex("#497B91").unwrap().to_string(),
+            "#497B91"
+        );
+        assert_eq!(RGBColor::from_hex("#fff").unwrap().to_string(), "#FFFFFF");
+        assert!(RGBColor::from_hex("497B91").is_none());
+        assert!(RGBColor::from_hex("#497B9").is_none());
+        assert!(RGBColor::from_hex("#GGGGGG").is_none());
+    }
+
     #[test]
     fn named_color() {
         assert_eq!(NamedColor::White.to_string(), "white");
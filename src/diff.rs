@@ -0,0 +1,159 @@
+//! Schema diff
+//!
+//! Compares two versions of a module field by field. Fields are matched by entity and field
+//! names, so a renamed field is reported as a removal and an addition.
+use crate::erd::{EntityDefinition, EntityField, EntityPath, Module, ModuleEntry};
+use crate::mir::FieldChange;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Default)]
+pub struct SchemaDiff {
+    /// Changes of fields in the new module.
+    changes: HashMap<EntityPath, FieldChange>,
+    /// Fields which exist only in the old module, keyed by entity name. Entities removed as a
+    /// whole are not tracked.
+    removed_fields: HashMap<String, Vec<EntityField>>,
+}
+
+impl SchemaDiff {
+    pub fn new(old: &Module, new: &Module) -> Self {
+        let mut diff = Self::default();
+
+        for definition in definitions(new) {
+            let old_definition = old.entity(definition.name());
+
+            for field in definition.fields() {
+                let old_field =
+                    old_definition.and_then(|d| d.fields().find(|f| f.name() == field.name()));
+                let change = match old_field {
+                    None => Some(FieldChange::Added),
+                    Some(old_field) if is_retyped(old, old_field, new, field) => {
+                        Some(FieldChange::Modified)
+                    }
+                    Some(_) => None,
+                };
+
+                if let Some(change) = change {
+                    let path = EntityPath::Field(definition.name().into(), field.name().into());
+                    diff.changes.insert(path, change);
+                }
+            }
+
+            let Some(old_definition) = old_definition else {
+                continue;
+            };
+            let removed: Vec<EntityField> = old_definition
+                .fields()
+                .filter(|f| definition.fields().all(|field| field.name() != f.name()))
+                .cloned()
+                .collect();
+
+            if !removed.is_empty() {
+                diff.removed_fields
+                    .insert(definition.name().to_string(), removed);
+            }
+        }
+
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty() && self.removed_fields.is_empty()
+    }
+
+    /// Returns the change of a field in the new module.
+    pub fn field_change(&self, entity_name: &str, field_name: &str) -> Option<FieldChange> {
+        self.changes
+            .get(&EntityPath::Field(entity_name.into(), field_name.into()))
+            .copied()
+    }
+
+    /// Returns fields of an entity which are removed in the new module.
+    pub fn removed_fields(&self, entity_name: &str) -> &[EntityField] {
+        self.removed_fields
+            .get(entity_name)
+            .map_or(&[], |fields| fields.as_slice())
+    }
+}
+
+fn definitions(module: &Module) -> impl Iterator<Item = &EntityDefinition> {
+    module.entries().filter_map(|entry| match entry {
+        ModuleEntry::EntityDefinition(definition) => Some(definition),
+        _ => None,
+    })
+}
+
+/// A field is retyped if its type (after resolving aliases) or nullability is changed.
+fn is_retyped(
+    old: &Module,
+    old_field: &EntityField,
+    new: &Module,
+    new_field: &EntityField,
+) -> bool {
+    old.resolve_type(old_field.field_type()).to_string()
+        != new.resolve_type(new_field.field_type()).to_string()
+        || old_field.is_nullable() != new_field.is_nullable()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+
+    fn module(src: &str) -> Module {
+        let (ast, _, _) = parse(src);
+        ast.unwrap()
+    }
+
+    #[test]
+    fn field_changes() {
+        let old = module(
+            "erd {
+users {
+    id int PK
+    name text
+    age int
+}
+}",
+        );
+        let new = module(
+            "erd {
+users {
+    id int PK
+    age text
+    email text
+}
+tags { id int }
+}",
+        );
+        let diff = SchemaDiff::new(&old, &new);
+
+        assert!(!diff.is_empty());
+        assert_eq!(diff.field_change("users", "id"), None);
+        assert_eq!(
+            diff.field_change("users", "age"),
+            Some(FieldChange::Modified)
+        );
+        assert_eq!(
+            diff.field_change("users", "email"),
+            Some(FieldChange::Added)
+        );
+        assert_eq!(diff.field_change("tags", "id"), Some(FieldChange::Added));
+
+        let removed: Vec<_> = diff
+            .removed_fields("users")
+            .iter()
+            .map(|f| f.name())
+            .collect();
+        assert_eq!(removed, vec!["name"]);
+        assert!(diff.removed_fields("tags").is_empty());
+    }
+
+    #[test]
+    fn aliases_are_resolved() {
+        let old = module("erd { users { id int } }");
+        let new = module("erd { type ID = int\nusers { id ID } }");
+
+        assert!(SchemaDiff::new(&old, &new).is_empty());
+    }
+}
@@ -1,14 +1,62 @@
 //! ER diagram AST
-use crate::color::{NamedColor, RGBColor, WebColor};
+use crate::color::{RGBColor, WebColor};
+use crate::diff::SchemaDiff;
+use crate::error::RenameError;
+use crate::geometry::Point;
 use crate::mir;
+use crate::parser::Span;
+use crate::theme::Theme;
 use derive_more::Display;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fmt;
+use std::str::FromStr;
+use std::sync::OnceLock;
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Module {
     name: Option<String>,
     entries: Vec<ModuleEntry>,
+    /// Built on the first lookup and dropped when entries are added.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    index: OnceLock<ModuleIndex>,
+}
+
+/// Lookup tables from an entity name to entry positions.
+#[derive(Debug, Clone, Default)]
+struct ModuleIndex {
+    /// The first definition of each entity.
+    entities: HashMap<String, usize>,
+    /// Relations which have the entity at either end, in source order.
+    relations: HashMap<String, Vec<usize>>,
+}
+
+impl ModuleIndex {
+    fn new(entries: &[ModuleEntry]) -> Self {
+        let mut index = Self::default();
+
+        for (i, entry) in entries.iter().enumerate() {
+            match entry {
+                ModuleEntry::EntityDefinition(definition) => {
+                    index.entities.entry(definition.name.clone()).or_insert(i);
+                }
+                ModuleEntry::EntityRelation(relation) => {
+                    let start = relation.start_path().entity_name();
+                    let end = relation.end_path().entity_name();
+
+                    index.relations.entry(start.to_string()).or_default().push(i);
+                    if end != start {
+                        index.relations.entry(end.to_string()).or_default().push(i);
+                    }
+                }
+                ModuleEntry::Directive(_) | ModuleEntry::TypeAlias(_) => {}
+            }
+        }
+
+        index
+    }
 }
 
 impl Module {
@@ -16,6 +64,7 @@ impl Module {
         Self {
             name,
             entries: vec![],
+            index: OnceLock::new(),
         }
     }
 
@@ -29,33 +78,313 @@ impl Module {
 
     pub fn add_entry(&mut self, entry: ModuleEntry) {
         self.entries.push(entry);
+        self.index.take();
     }
 
     pub fn add_entity_definition(&mut self, definition: EntityDefinition) {
-        self.entries.push(ModuleEntry::EntityDefinition(definition));
+        self.add_entry(ModuleEntry::EntityDefinition(definition));
     }
 
     pub fn add_entity_relation(&mut self, relation: EntityRelation) {
-        self.entries.push(ModuleEntry::EntityRelation(relation));
+        self.add_entry(ModuleEntry::EntityRelation(relation));
+    }
+
+    pub fn add_directive(&mut self, directive: ModuleDirective) {
+        self.add_entry(ModuleEntry::Directive(directive));
+    }
+
+    pub fn add_type_alias(&mut self, type_alias: TypeAlias) {
+        self.add_entry(ModuleEntry::TypeAlias(type_alias));
+    }
+
+    /// Returns the definition of an entity. If the entity is defined more than once, the first
+    /// one is returned.
+    pub fn entity(&self, name: &str) -> Option<&EntityDefinition> {
+        let i = *self.index().entities.get(name)?;
+
+        match &self.entries[i] {
+            ModuleEntry::EntityDefinition(definition) => Some(definition),
+            _ => None,
+        }
+    }
+
+    /// Returns relations which have an entity at either end, in source order.
+    pub fn relations_of<'a>(&'a self, name: &str) -> impl Iterator<Item = &'a EntityRelation> {
+        let indices = self
+            .index()
+            .relations
+            .get(name)
+            .map_or(&[][..], |indices| indices.as_slice());
+
+        indices.iter().filter_map(|i| match &self.entries[*i] {
+            ModuleEntry::EntityRelation(relation) => Some(relation),
+            _ => None,
+        })
+    }
+
+    /// Renames an entity and updates all relation endpoints which refer to it. An entity which
+    /// is referenced by relations but not defined can be renamed as well.
+    pub fn rename_entity(&mut self, old: &str, new: &str) -> Result<RenameReport, RenameError> {
+        if new.is_empty() {
+            return Err(RenameError::EmptyName);
+        }
+        if self.entity(new).is_some() || self.relations_of(new).next().is_some() {
+            return Err(RenameError::AlreadyExists(EntityPath::Entity(new.into())));
+        }
+
+        let mut report = RenameReport::default();
+        let new_text = quote_identifier(new);
+
+        for entry in self.entries.iter_mut() {
+            match entry {
+                ModuleEntry::EntityDefinition(definition) if definition.name == old => {
+                    definition.name = new.into();
+                    report.definitions += 1;
+                    report.push_edit(definition.span(), &new_text);
+                }
+                ModuleEntry::EntityRelation(relation) => {
+                    let mut updated = false;
+
+                    for (path, span) in relation.paths_mut() {
+                        let (EntityPath::Entity(name) | EntityPath::Field(name, _)) = path;
+
+                        if name == old {
+                            *name = new.into();
+                            updated = true;
+                            report.push_edit(span.entity.clone(), &new_text);
+                        }
+                    }
+                    report.relations += updated as usize;
+                }
+                _ => {}
+            }
+        }
+
+        self.finish_rename(report, EntityPath::Entity(old.into()))
+    }
+
+    /// Renames a field of an entity and updates all relation endpoints which refer to it.
+    pub fn rename_field(
+        &mut self,
+        entity_name: &str,
+        old: &str,
+        new: &str,
+    ) -> Result<RenameReport, RenameError> {
+        if new.is_empty() {
+            return Err(RenameError::EmptyName);
+        }
+
+        let new_path = EntityPath::Field(entity_name.into(), new.into());
+        let has_new_field = self
+            .entity(entity_name)
+            .is_some_and(|definition| definition.fields().any(|field| field.name == new));
+
+        if has_new_field
+            || self
+                .relations_of(entity_name)
+                .any(|r| *r.start_path() == new_path || *r.end_path() == new_path)
+        {
+            return Err(RenameError::AlreadyExists(new_path));
+        }
+
+        let mut report = RenameReport::default();
+        let new_text = quote_identifier(new);
+
+        for entry in self.entries.iter_mut() {
+            match entry {
+                ModuleEntry::EntityDefinition(definition) if definition.name == entity_name => {
+                    for field in definition.fields.iter_mut().filter(|field| field.name == old) {
+                        field.name = new.into();
+                        report.definitions += 1;
+                        report.push_edit(field.name_span(), &new_text);
+                    }
+                }
+                ModuleEntry::EntityRelation(relation) => {
+                    let mut updated = false;
+
+                    for (path, span) in relation.paths_mut() {
+                        let EntityPath::Field(e, f) = path else { continue };
+
+                        if e == entity_name && f == old {
+                            *f = new.into();
+                            updated = true;
+                            if let Some(field_span) = &span.field {
+                                report.push_edit(field_span.clone(), &new_text);
+                            }
+                        }
+                    }
+                    report.relations += updated as usize;
+                }
+                _ => {}
+            }
+        }
+
+        self.finish_rename(report, EntityPath::Field(entity_name.into(), old.into()))
+    }
+
+    fn finish_rename(
+        &mut self,
+        mut report: RenameReport,
+        old_path: EntityPath,
+    ) -> Result<RenameReport, RenameError> {
+        if report.definitions == 0 && report.relations == 0 {
+            return Err(RenameError::NotFound(old_path));
+        }
+
+        self.index.take();
+        report.edits.sort_by_key(|edit| edit.span.start);
+        Ok(report)
+    }
+
+    fn index(&self) -> &ModuleIndex {
+        self.index.get_or_init(|| ModuleIndex::new(&self.entries))
+    }
+
+    /// Resolves type aliases in `field_type` and returns the underlying type.
+    ///
+    /// An alias may be declared after its use. An undefined name or a cyclic alias is returned as
+    /// it is.
+    pub fn resolve_type<'a>(&'a self, field_type: &'a EntityFieldType) -> &'a EntityFieldType {
+        let mut resolved = field_type;
+
+        // Each step must follow a distinct alias unless there is a cycle.
+        for _ in 0..self.entries.len() {
+            let EntityFieldType::Named(name) = resolved else { break };
+            let alias = self.entries.iter().find_map(|entry| match entry {
+                ModuleEntry::TypeAlias(alias) if alias.name == *name => Some(alias),
+                _ => None,
+            });
+            let Some(alias) = alias else { break };
+
+            resolved = &alias.field_type;
+        }
+
+        resolved
+    }
+
+    /// Returns the number of grid columns specified by `%columns` directive.
+    /// If the directive appears more than once, the last one wins.
+    pub fn columns(&self) -> Option<usize> {
+        self.entries.iter().rev().find_map(|entry| match entry {
+            ModuleEntry::Directive(ModuleDirective::Columns(n)) => Some(*n),
+            _ => None,
+        })
     }
 
     pub fn into_mir(&self) -> mir::Document {
-        let light_gray_color = WebColor::RGB(RGBColor::new(73, 73, 73));
-        let table_border_color = light_gray_color.clone();
-        let table_bg_color = WebColor::RGB(RGBColor::new(33, 33, 33));
-        let text_color = WebColor::Named(NamedColor::White);
+        self.into_mir_with_options(&ConvertOptions::default())
+    }
+
+    pub fn into_mir_with_options(&self, options: &ConvertOptions) -> mir::Document {
+        self.into_mir_with_node_paths(options).0
+    }
+
+    /// Converts the module into MIR and returns it with the node ID of each entity and field.
+    pub fn into_mir_with_node_paths(
+        &self,
+        options: &ConvertOptions,
+    ) -> (mir::Document, HashMap<EntityPath, mir::NodeId>) {
+        let theme = &options.theme;
+        let header_bg_color = &theme.header_bg_color;
+        let table_border_color = &theme.record_border_color;
+        let table_bg_color = &theme.record_bg_color;
+        let text_color = &theme.text_color;
+        let default_value_color = &theme.secondary_text_color;
         let mut doc = mir::Document::new();
 
+        if let mir::ShapeKind::Body(body) = doc.body_mut().kind_mut() {
+            body.n_columns = self.columns();
+            body.title = self.name().map(|name| {
+                mir::TextSpanBuilder::default()
+                    .text(name)
+                    .color(Some(text_color.clone()))
+                    .font_family(Some(mir::FontFamily::Monospace1))
+                    .font_weight(Some(mir::FontWeight::Bold))
+                    .font_size(Some(mir::FontSize::Large))
+                    .build()
+                    .unwrap()
+            });
+        }
+
+        let field_shape = |ordinal: Option<usize>, field: &EntityField| {
+            let ordinal = ordinal.filter(|_| options.show_field_ordinals).map(|ordinal| {
+                mir::TextSpanBuilder::default()
+                    .text(ordinal.to_string())
+                    .color(Some(default_value_color.clone()))
+                    .font_family(Some(mir::FontFamily::Monospace2))
+                    .font_size(Some(mir::FontSize::XSmall))
+                    .build()
+                    .unwrap()
+            });
+
+            let name = mir::TextSpanBuilder::default()
+                .text(field.name.clone())
+                .color(Some(text_color.clone()))
+                .font_family(Some(mir::FontFamily::Monospace2))
+                .font_weight(Some(mir::FontWeight::Lighter))
+                .build()
+                .unwrap();
+
+            // Rendered with the alias name, but colored by the underlying type.
+            let column_type = mir::TextSpanBuilder::default()
+                .text(field.field_type.to_string())
+                .color(Some(Module::column_type_color(
+                    theme,
+                    self.resolve_type(&field.field_type),
+                )))
+                .font_family(Some(mir::FontFamily::Monospace2))
+                .font_weight(Some(mir::FontWeight::Lighter))
+                .font_size(Some(mir::FontSize::Small))
+                .build()
+                .unwrap();
+
+            let default_value = field.default_value.as_ref().map(|value| {
+                mir::TextSpanBuilder::default()
+                    .text(value.clone())
+                    .color(Some(default_value_color.clone()))
+                    .font_family(Some(mir::FontFamily::Monospace2))
+                    .font_weight(Some(mir::FontWeight::Lighter))
+                    .font_size(Some(mir::FontSize::Small))
+                    .build()
+                    .unwrap()
+            });
+
+            mir::FieldShapeBuilder::default()
+                .ordinal(ordinal)
+                .title(name)
+                .subtitle(Some(column_type))
+                .default_value(default_value)
+                .border_color(Some(table_border_color.clone()))
+                .badges(field.field_key.iter().map(|key| key.into_mir_with_theme(theme)).collect())
+                .bg_color(options.highlight_keys.zip(field.field_key.as_ref()).and_then(
+                    |(highlight, key)| highlight.row_color(key, theme),
+                ))
+                .url(field.url().map(str::to_string))
+                .build()
+                .unwrap()
+        };
+
         // node path (e.g. ["users", "id"]) -> node ID
         let mut node_paths: HashMap<EntityPath, mir::NodeId> = HashMap::new();
 
+        // Relations are resolved after all entities are defined.
+        let mut relations: Vec<&EntityRelation> = vec![];
+        let mut group_ids: HashMap<&str, mir::NodeId> = HashMap::new();
+
         for entry in self.entries.iter() {
             match entry {
+                ModuleEntry::EntityDefinition(definition)
+                    if !options.entity_filter.matches(definition) => {}
                 ModuleEntry::EntityDefinition(definition) => {
                     // table
                     let header_node_id = {
                         let name = mir::TextSpanBuilder::default()
-                            .text(definition.name.clone())
+                            .text(
+                                definition
+                                    .display_name
+                                    .clone()
+                                    .unwrap_or_else(|| definition.name.clone()),
+                            )
                             .color(Some(text_color.clone()))
                             .font_family(Some(mir::FontFamily::Monospace1))
                             .font_weight(Some(mir::FontWeight::Bold))
@@ -63,7 +392,7 @@ impl Module {
                             .unwrap();
                         let field = mir::FieldShapeBuilder::default()
                             .title(name)
-                            .bg_color(Some(light_gray_color.clone()))
+                            .bg_color(Some(header_bg_color.clone()))
                             .build()
                             .unwrap();
 
@@ -73,36 +402,20 @@ impl Module {
                         .rounded(true)
                         .bg_color(Some(table_bg_color.clone()))
                         .border_color(Some(table_border_color.clone()))
+                        .url(definition.url().map(str::to_string))
+                        .kind(definition.kind())
                         .build()
                         .unwrap();
-                    let field_ids: Vec<_> = definition
-                        .fields
-                        .iter()
-                        .map(|field| {
-                            let name = mir::TextSpanBuilder::default()
-                                .text(field.name.clone())
-                                .color(Some(text_color.clone()))
-                                .font_family(Some(mir::FontFamily::Monospace2))
-                                .font_weight(Some(mir::FontWeight::Lighter))
-                                .build()
-                                .unwrap();
-
-                            let column_type = mir::TextSpanBuilder::default()
-                                .text(field.field_type.to_string())
-                                .color(Some(Module::column_type_color(&field.field_type)))
-                                .font_family(Some(mir::FontFamily::Monospace2))
-                                .font_weight(Some(mir::FontWeight::Lighter))
-                                .font_size(Some(mir::FontSize::Small))
-                                .build()
-                                .unwrap();
+                    let mut field_ids: Vec<_> = definition
+                        .fields_in_display_order()
+                        .into_iter()
+                        .map(|(ordinal, field)| {
+                            let mut field_node = field_shape(Some(ordinal), field);
 
-                            let field_node = mir::FieldShapeBuilder::default()
-                                .title(name)
-                                .subtitle(Some(column_type))
-                                .border_color(Some(table_border_color.clone()))
-                                .badge(field.field_key.map(|key| key.into_mir()))
-                                .build()
-                                .unwrap();
+                            field_node.change = options
+                                .diff
+                                .as_ref()
+                                .and_then(|diff| diff.field_change(&definition.name, &field.name));
 
                             let node_id = doc.create_field(field_node);
 
@@ -114,52 +427,185 @@ impl Module {
                         })
                         .collect();
 
+                    // Removed fields are kept at the bottom, but relations never connect to them.
+                    if let Some(diff) = &options.diff {
+                        for field in diff.removed_fields(&definition.name) {
+                            let mut field_node = field_shape(None, field);
+
+                            field_node.change = Some(mir::FieldChange::Removed);
+                            field_ids.push(doc.create_field(field_node));
+                        }
+                    }
+
                     let record_id = doc.create_record(record);
                     node_paths.insert(EntityPath::Entity(definition.name.clone()), record_id);
 
                     let record_node = doc.get_node_mut(record_id).unwrap();
 
+                    record_node.preferred_cell = definition.preferred_cell();
+                    record_node.preferred_origin = definition.preferred_origin();
                     record_node.append_child(header_node_id);
                     for field_id in field_ids {
                         record_node.append_child(field_id);
                     }
 
                     doc.body_mut().append_child(record_id);
+
+                    if let Some(group) = definition.group() {
+                        let group_id = *group_ids.entry(group).or_insert_with(|| {
+                            let title = mir::TextSpanBuilder::default()
+                                .text(group)
+                                .color(Some(default_value_color.clone()))
+                                .font_family(Some(mir::FontFamily::Monospace1))
+                                .font_weight(Some(mir::FontWeight::Bold))
+                                .build()
+                                .unwrap();
+                            let group = mir::GroupShapeBuilder::default()
+                                .title(title)
+                                .border_color(Some(table_border_color.clone()))
+                                .build()
+                                .unwrap();
+
+                            doc.create_group(group)
+                        });
+
+                        doc.get_node_mut(group_id).unwrap().append_child(record_id);
+                    }
                 }
-                ModuleEntry::EntityRelation(relation) => {
-                    let Some(start_node_id) = node_paths.get(relation.start_path()) else { continue };
-                    let Some(end_node_id) = node_paths.get(relation.end_path()) else { continue };
+                ModuleEntry::EntityRelation(relation) => relations.push(relation),
+                ModuleEntry::Directive(_) | ModuleEntry::TypeAlias(_) => {}
+            }
+        }
+
+        // Filtered entities are defined, so they don't become stubs.
+        let is_filtered_out = |path: &EntityPath| {
+            self.entity(path.entity_name())
+                .is_some_and(|definition| !options.entity_filter.matches(definition))
+        };
+
+        // Every field of a stub refers to the stub itself.
+        let mut stub_ids: Vec<mir::NodeId> = vec![];
+
+        for relation in relations {
+            if is_filtered_out(relation.start_path()) || is_filtered_out(relation.end_path()) {
+                continue;
+            }
+            if options.implicit_entity_stubs {
+                for path in [relation.start_path(), relation.end_path()] {
+                    let entity_path = EntityPath::Entity(path.entity_name().to_string());
+                    let stub_id = match node_paths.get(&entity_path) {
+                        Some(node_id) if stub_ids.contains(node_id) => *node_id,
+                        Some(_) => continue,
+                        None => {
+                            let stub_id =
+                                Self::create_entity_stub(&mut doc, path.entity_name(), theme);
+
+                            stub_ids.push(stub_id);
+                            node_paths.insert(entity_path, stub_id);
+                            stub_id
+                        }
+                    };
 
-                    doc.add_edge(mir::EdgeData::new(*start_node_id, *end_node_id, None));
+                    node_paths.insert(path.clone(), stub_id);
                 }
             }
+
+            let Some(start_node_id) = node_paths.get(relation.start_path()) else { continue };
+            let Some(end_node_id) = node_paths.get(relation.end_path()) else { continue };
+
+            // A relation from a field to itself can't be routed. Different fields of a stub are
+            // both the stub.
+            if relation.start_path() == relation.end_path() {
+                continue;
+            }
+
+            let mut edge = mir::EdgeData::new(*start_node_id, *end_node_id, None);
+            edge.set_markers(
+                relation.marker("start").unwrap_or_default(),
+                relation.marker("end").unwrap_or_default(),
+            );
+            edge.set_cardinalities(
+                relation.cardinality("start"),
+                relation.cardinality("end"),
+            );
+            edge.set_label(relation.label().map(str::to_string));
+            edge.set_stroke(
+                relation.stroke_style().unwrap_or_default(),
+                relation.stroke_width(),
+                relation.stroke_color(),
+            );
+            edge.set_layer(relation.layer());
+            edge.set_direction(self.relation_direction(relation));
+            doc.add_edge(edge);
         }
 
-        doc
+        (doc, node_paths)
     }
 
-    fn column_type_color(column_type: &EntityFieldType) -> WebColor {
-        let yellow = WebColor::RGB(RGBColor {
-            red: 236,
-            green: 199,
-            blue: 0,
-        });
-        let orange = WebColor::RGB(RGBColor {
-            red: 214,
-            green: 105,
-            blue: 5,
-        });
-        let green = WebColor::RGB(RGBColor {
-            red: 6,
-            green: 182,
-            blue: 151,
-        });
+    /// Returns which way a relation refers: from its foreign key to the key it references, or
+    /// else from the other end to its primary key. Relations between fields of the same kind,
+    /// or with undefined ends, have no direction.
+    fn relation_direction(&self, relation: &EntityRelation) -> mir::EdgeDirection {
+        let field_key = |path: &EntityPath| match path {
+            EntityPath::Field(entity_name, field_name) => self
+                .entity(entity_name)?
+                .fields()
+                .find(|field| field.name() == field_name)?
+                .field_key()
+                .copied(),
+            EntityPath::Entity(_) => None,
+        };
+        let is_key = |key: EntityFieldKey| {
+            [relation.start_path(), relation.end_path()].map(|path| field_key(path) == Some(key))
+        };
+
+        match (is_key(EntityFieldKey::ForeginKey), is_key(EntityFieldKey::PrimaryKey)) {
+            ([true, false], _) => mir::EdgeDirection::Forward,
+            ([false, true], _) => mir::EdgeDirection::Backward,
+            (_, [false, true]) => mir::EdgeDirection::Forward,
+            (_, [true, false]) => mir::EdgeDirection::Backward,
+            _ => mir::EdgeDirection::None,
+        }
+    }
+
+    /// Creates a ghost record (dashed border, name only) for an entity which is referenced by
+    /// relations but not defined.
+    fn create_entity_stub(doc: &mut mir::Document, name: &str, theme: &Theme) -> mir::NodeId {
+        let gray_color = theme.secondary_text_color.clone();
+
+        let name = mir::TextSpanBuilder::default()
+            .text(name)
+            .color(Some(gray_color.clone()))
+            .font_family(Some(mir::FontFamily::Monospace1))
+            .font_weight(Some(mir::FontWeight::Bold))
+            .build()
+            .unwrap();
+        let header = mir::FieldShapeBuilder::default()
+            .title(name)
+            .build()
+            .unwrap();
+        let record = mir::RecordShapeBuilder::default()
+            .rounded(true)
+            .dashed(true)
+            .border_color(Some(gray_color))
+            .build()
+            .unwrap();
 
+        let header_id = doc.create_field(header);
+        let record_id = doc.create_record(record);
+
+        doc.get_node_mut(record_id).unwrap().append_child(header_id);
+        doc.body_mut().append_child(record_id);
+        record_id
+    }
+
+    fn column_type_color(theme: &Theme, column_type: &EntityFieldType) -> WebColor {
         match column_type {
-            EntityFieldType::Int => yellow.clone(),
-            EntityFieldType::Uuid => yellow.clone(),
-            EntityFieldType::Text => orange.clone(),
-            EntityFieldType::Timestamp => green.clone(),
+            EntityFieldType::Int => theme.numeric_type_color.clone(),
+            EntityFieldType::Uuid => theme.numeric_type_color.clone(),
+            EntityFieldType::Text => theme.text_type_color.clone(),
+            EntityFieldType::Timestamp => theme.timestamp_type_color.clone(),
+            EntityFieldType::Named(_) => theme.named_type_color.clone(),
         }
     }
 }
@@ -168,7 +614,11 @@ impl fmt::Display for Module {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "erd ")?;
         if let Some(name) = &self.name {
-            write!(f, "{} ", name)?;
+            if is_identifier(name) && !KEYWORDS.contains(&name.as_str()) {
+                write!(f, "{} ", name)?;
+            } else {
+                write!(f, "{} ", quote_string(name))?;
+            }
         }
         writeln!(f, "{{")?;
         for entry in self.entries.iter() {
@@ -178,30 +628,470 @@ impl fmt::Display for Module {
     }
 }
 
+/// Returns `true` if `s` can be written as an identifier without quotes.
+fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+
+    matches!(chars.next(), Some(c) if c == '_' || c.is_ascii_alphabetic())
+        && chars.all(|c| c == '_' || c.is_ascii_alphanumeric())
+}
+
+/// Words which the tokenizer reads as keywords instead of identifiers.
+const KEYWORDS: [&str; 8] = ["erd", "int", "uuid", "text", "timestamp", "PK", "FK", "default"];
+
+/// Returns `s` as an identifier, quoted with backquotes if necessary.
+fn quote_identifier(s: &str) -> String {
+    if is_identifier(s) && !KEYWORDS.contains(&s) {
+        return s.to_string();
+    }
+    format!("`{}`", s.replace('\\', "\\\\").replace('`', "\\`"))
+}
+
+/// Returns `s` as a string literal, escaped as the parser reads it.
+fn quote_string(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            '\x08' => quoted.push_str("\\b"),
+            '\x0C' => quoted.push_str("\\f"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04X}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// A replacement of a range in the source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    pub span: Span,
+    pub new_text: String,
+}
+
+impl TextEdit {
+    /// Applies non-overlapping edits to `src`. Spans are character offsets as reported by the
+    /// parser.
+    pub fn apply_all(src: &str, edits: &[TextEdit]) -> String {
+        let chars: Vec<char> = src.chars().collect();
+        let mut edits: Vec<&TextEdit> = edits.iter().collect();
+        let mut result = String::with_capacity(src.len());
+        let mut pos = 0;
+
+        edits.sort_by_key(|edit| edit.span.start);
+        for edit in edits {
+            result.extend(&chars[pos..edit.span.start]);
+            result.push_str(&edit.new_text);
+            pos = edit.span.end;
+        }
+        result.extend(&chars[pos..]);
+        result
+    }
+}
+
+/// The result of `Module::rename_entity` or `Module::rename_field`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RenameReport {
+    /// The number of renamed definitions (entities or fields).
+    pub definitions: usize,
+    /// The number of relations whose endpoints are updated.
+    pub relations: usize,
+    /// Edits which perform the same rename on the parsed source, in source order.
+    pub edits: Vec<TextEdit>,
+}
+
+impl RenameReport {
+    fn push_edit(&mut self, span: Span, new_text: &str) {
+        // A module built without the parser has no locations.
+        if span.is_empty() {
+            return;
+        }
+        self.edits.push(TextEdit {
+            span,
+            new_text: new_text.to_string(),
+        });
+    }
+}
+
+/// Options for converting an AST into MIR.
+#[derive(Debug, Clone, Default)]
+pub struct ConvertOptions {
+    /// If `true`, an entity which is referenced by relations but not defined is rendered as a
+    /// ghost record. Otherwise, such relations are dropped.
+    pub implicit_entity_stubs: bool,
+
+    /// If `true`, each field row shows its column ordinal.
+    pub show_field_ordinals: bool,
+
+    /// Marks fields changed since an old version of the module. Removed fields are rendered
+    /// at the bottom of their entities.
+    pub diff: Option<SchemaDiff>,
+
+    /// Entities to render. Relations to entities which are filtered out are dropped.
+    pub entity_filter: EntityFilter,
+
+    /// Fills the background of key rows with the key row colors of the theme.
+    pub highlight_keys: Option<KeyHighlight>,
+
+    pub theme: Theme,
+}
+
+/// Which key rows are highlighted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Display)]
+pub enum KeyHighlight {
+    #[default]
+    #[display(fmt = "pk")]
+    PrimaryKeys,
+    /// Primary and foreign keys
+    #[display(fmt = "all")]
+    AllKeys,
+}
+
+impl KeyHighlight {
+    pub const ALL: [KeyHighlight; 2] = [KeyHighlight::PrimaryKeys, KeyHighlight::AllKeys];
+
+    /// Returns the background color of a row of `key`, if it is highlighted.
+    pub fn row_color(&self, key: &EntityFieldKey, theme: &Theme) -> Option<WebColor> {
+        match (self, key) {
+            (_, EntityFieldKey::PrimaryKey) => Some(theme.primary_key_row_color.clone()),
+            (KeyHighlight::AllKeys, EntityFieldKey::ForeginKey) => {
+                Some(theme.foreign_key_row_color.clone())
+            }
+            (KeyHighlight::PrimaryKeys, EntityFieldKey::ForeginKey) => None,
+        }
+    }
+}
+
+impl FromStr for KeyHighlight {
+    type Err = String;
+
+    /// Parses `pk` or `all`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|keys| keys.to_string() == s)
+            .ok_or_else(|| format!("unknown key highlight `{}`", s))
+    }
+}
+
+/// Selects entities by names or tags (e.g. `[tags="billing, core"]`). An entity is selected if
+/// it matches `include` (or `include` is empty) and doesn't match `exclude`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EntityFilter {
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+}
+
+impl EntityFilter {
+    pub fn new(include: Vec<String>, exclude: Vec<String>) -> Self {
+        Self { include, exclude }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+
+    pub fn matches(&self, definition: &EntityDefinition) -> bool {
+        let tags = definition.tags();
+        let matches = |pattern: &String| {
+            pattern == definition.name() || tags.iter().any(|tag| tag == pattern)
+        };
+
+        (self.include.is_empty() || self.include.iter().any(matches))
+            && !self.exclude.iter().any(matches)
+    }
+}
+
 #[derive(Debug, Clone, Display)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum ModuleEntry {
     EntityDefinition(EntityDefinition),
     EntityRelation(EntityRelation),
+    Directive(ModuleDirective),
+    TypeAlias(TypeAlias),
+}
+
+/// Module-level settings (e.g. `%columns 2`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ModuleDirective {
+    /// The number of columns in the grid layout.
+    #[display(fmt = "%columns {}", _0)]
+    Columns(usize),
+}
+
+/// A reusable column type (e.g. `type money = decimal(12,2)`).
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Display)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[display(fmt = "type {} = {}", "quote_identifier(name)", field_type)]
+pub struct TypeAlias {
+    name: String,
+    field_type: EntityFieldType,
+}
+
+impl TypeAlias {
+    pub fn new(name: String, field_type: EntityFieldType) -> Self {
+        Self { name, field_type }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn field_type(&self) -> &EntityFieldType {
+        &self.field_type
+    }
+}
+
+/// A list of attributes (e.g. `[color=#FF0000, hidden]`) attached to entities, fields, and
+/// relations.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Attributes(Vec<Attribute>);
+
+impl Attributes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = &Attribute> {
+        self.0.iter()
+    }
+
+    pub fn push(&mut self, attribute: Attribute) {
+        self.0.push(attribute);
+    }
+
+    /// Returns the attribute named `name`. If it appears more than once, the last one wins.
+    pub fn get(&self, name: &str) -> Option<&Attribute> {
+        self.0.iter().rev().find(|attribute| attribute.name == name)
+    }
+
+    /// Returns the value of the attribute named `name`.
+    pub fn value(&self, name: &str) -> Option<&AttributeValue> {
+        self.get(name)?.value.as_ref()
+    }
+
+    /// Returns `true` if the attribute named `name` is specified (with or without a value).
+    pub fn has(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+}
+
+impl FromIterator<Attribute> for Attributes {
+    fn from_iter<I: IntoIterator<Item = Attribute>>(iter: I) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+impl fmt::Display for Attributes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[")?;
+        for (i, attribute) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", attribute)?;
+        }
+        write!(f, "]")
+    }
+}
+
+/// A key/value pair (`key=value`) or a flag (`key`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Attribute {
+    name: String,
+    value: Option<AttributeValue>,
+    span: Span,
+}
+
+impl Attribute {
+    pub fn new(name: String, value: Option<AttributeValue>) -> Self {
+        Self {
+            name,
+            value,
+            span: Span::default(),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn value(&self) -> Option<&AttributeValue> {
+        self.value.as_ref()
+    }
+
+    pub fn span(&self) -> Span {
+        self.span.clone()
+    }
+
+    pub fn set_span(&mut self, span: Span) {
+        self.span = span;
+    }
+}
+
+impl fmt::Display for Attribute {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", quote_identifier(&self.name))?;
+        if let Some(value) = &self.value {
+            write!(f, "={}", value)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Display)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum AttributeValue {
+    #[display(fmt = "{}", "quote_identifier(_0)")]
+    Ident(String),
+    #[display(fmt = "{}", "quote_string(_0)")]
+    Str(String),
+    #[display(fmt = "{}", _0)]
+    Number(u64),
+    #[display(fmt = "{}", _0)]
+    Color(RGBColor),
+}
+
+impl AttributeValue {
+    /// Returns the text of an identifier or a string.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            AttributeValue::Ident(s) | AttributeValue::Str(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_number(&self) -> Option<u64> {
+        match self {
+            AttributeValue::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn as_color(&self) -> Option<&RGBColor> {
+        match self {
+            AttributeValue::Color(color) => Some(color),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct EntityDefinition {
     name: String,
+    /// The name shown in the diagram instead of `name`.
+    display_name: Option<String>,
     fields: Vec<EntityField>,
+    attributes: Attributes,
+    /// The location of the name in the source.
+    span: Span,
 }
 
 impl EntityDefinition {
     pub fn new(name: String) -> Self {
         Self {
             name,
+            display_name: None,
             fields: vec![],
+            attributes: Attributes::default(),
+            span: Span::default(),
         }
     }
 
+    pub fn attributes(&self) -> &Attributes {
+        &self.attributes
+    }
+
+    pub fn set_attributes(&mut self, attributes: Attributes) {
+        self.attributes = attributes;
+    }
+
+    /// Returns the link given by the attribute `url` (e.g. `[url="https://example.com/users"]`).
+    pub fn url(&self) -> Option<&str> {
+        self.attributes.value("url")?.as_str()
+    }
+
+    /// Returns the kind given by the attribute `kind` (e.g. `[kind=view]`). Unknown kinds are
+    /// ignored.
+    pub fn kind(&self) -> Option<mir::EntityKind> {
+        self.attributes.value("kind")?.as_str()?.parse().ok()
+    }
+
+    /// Returns the grid cell given by the attributes `row` and `column` (e.g.
+    /// `[row=1, column=2]`). Both are 1-based and required.
+    pub fn preferred_cell(&self) -> Option<mir::GridCell> {
+        let row = self.attributes.value("row")?.as_number()?.checked_sub(1)?;
+        let column = self.attributes.value("column")?.as_number()?.checked_sub(1)?;
+
+        Some(mir::GridCell::new(row as usize, column as usize))
+    }
+
+    /// Returns the origin given by the attributes `x` and `y` (e.g. `[x=100, y=400]`).
+    pub fn preferred_origin(&self) -> Option<Point> {
+        let x = self.attributes.value("x")?.as_number()?;
+        let y = self.attributes.value("y")?.as_number()?;
+
+        Some(Point::new(x as f32, y as f32))
+    }
+
+    /// Returns the group given by the attribute `group` (e.g. `[group="Billing"]`). Entities of
+    /// the same group are laid out in a box.
+    pub fn group(&self) -> Option<&str> {
+        self.attributes
+            .value("group")?
+            .as_str()
+            .map(str::trim)
+            .filter(|group| !group.is_empty())
+    }
+
+    /// Returns the comma-separated tags given by the attribute `tags` (e.g.
+    /// `[tags="billing, core"]`).
+    pub fn tags(&self) -> Vec<&str> {
+        let Some(tags) = self.attributes.value("tags").and_then(AttributeValue::as_str) else {
+            return vec![];
+        };
+
+        tags.split(',')
+            .map(str::trim)
+            .filter(|tag| !tag.is_empty())
+            .collect()
+    }
+
+    pub fn span(&self) -> Span {
+        self.span.clone()
+    }
+
+    pub fn set_span(&mut self, span: Span) {
+        self.span = span;
+    }
+
     pub fn name(&self) -> &str {
         &self.name
     }
 
+    pub fn display_name(&self) -> Option<&str> {
+        self.display_name.as_deref()
+    }
+
+    pub fn set_display_name(&mut self, display_name: Option<String>) {
+        self.display_name = display_name;
+    }
+
     pub fn fields(&self) -> impl ExactSizeIterator<Item = &EntityField> {
         self.fields.iter()
     }
@@ -209,12 +1099,57 @@ impl EntityDefinition {
     pub fn add_field(&mut self, column: EntityField) {
         self.fields.push(column);
     }
+
+    /// Returns fields with their ordinals in the order they should be displayed.
+    ///
+    /// A field with a pinned `position` is placed at that position (1-based). If the position is
+    /// out of range or already taken, the field is placed at the nearest free position after it.
+    /// The other fields fill the remaining positions in declaration order.
+    pub fn fields_in_display_order(&self) -> Vec<(usize, &EntityField)> {
+        let n_fields = self.fields.len();
+        let mut slots: Vec<Option<(usize, &EntityField)>> = vec![None; n_fields];
+        let ordinal = |index: usize, field: &EntityField| field.ordinal.unwrap_or(index + 1);
+
+        for (index, field) in self.fields.iter().enumerate() {
+            let Some(position) = field.position else { continue };
+            let start = position.clamp(1, n_fields) - 1;
+            let slot = (start..n_fields)
+                .chain(0..start)
+                .find(|i| slots[*i].is_none())
+                .unwrap();
+
+            slots[slot] = Some((ordinal(index, field), field));
+        }
+
+        let mut unpinned = self
+            .fields
+            .iter()
+            .enumerate()
+            .filter(|(_, field)| field.position.is_none());
+
+        slots
+            .into_iter()
+            .map(|slot| {
+                slot.unwrap_or_else(|| {
+                    let (index, field) = unpinned.next().unwrap();
+                    (ordinal(index, field), field)
+                })
+            })
+            .collect()
+    }
 }
 
 impl fmt::Display for EntityDefinition {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{} {{", self.name)?;
-        if self.fields.len() > 0 {
+        write!(f, "{} ", quote_identifier(&self.name))?;
+        if let Some(display_name) = &self.display_name {
+            write!(f, "{} ", quote_string(display_name))?;
+        }
+        if !self.attributes.is_empty() {
+            write!(f, "{} ", self.attributes)?;
+        }
+        write!(f, "{{")?;
+        if !self.fields.is_empty() {
             write!(f, " ")?;
 
             let mut it = self.fields.iter().peekable();
@@ -233,10 +1168,22 @@ impl fmt::Display for EntityDefinition {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct EntityField {
     name: String,
     field_type: EntityFieldType,
+    nullable: bool,
     field_key: Option<EntityFieldKey>,
+    default_value: Option<String>,
+    /// The original column ordinal (1-based, e.g. imported from SQL). If it's `None`, the
+    /// declaration order is used.
+    ordinal: Option<usize>,
+    /// The display position in the record (1-based), independent of the declaration order.
+    position: Option<usize>,
+    attributes: Attributes,
+    span: Span,
+    /// The location of the name in the source.
+    name_span: Span,
 }
 
 impl EntityField {
@@ -248,7 +1195,14 @@ impl EntityField {
         Self {
             name,
             field_type,
+            nullable: false,
             field_key,
+            default_value: None,
+            ordinal: None,
+            position: None,
+            attributes: Attributes::default(),
+            span: Span::default(),
+            name_span: Span::default(),
         }
     }
 
@@ -260,20 +1214,101 @@ impl EntityField {
         &self.field_type
     }
 
+    pub fn is_nullable(&self) -> bool {
+        self.nullable
+    }
+
+    pub fn set_nullable(&mut self, nullable: bool) {
+        self.nullable = nullable;
+    }
+
     pub fn field_key(&self) -> Option<&EntityFieldKey> {
         self.field_key.as_ref()
     }
+
+    pub fn default_value(&self) -> Option<&str> {
+        self.default_value.as_deref()
+    }
+
+    pub fn set_default_value(&mut self, default_value: Option<String>) {
+        self.default_value = default_value;
+    }
+
+    pub fn ordinal(&self) -> Option<usize> {
+        self.ordinal
+    }
+
+    pub fn set_ordinal(&mut self, ordinal: Option<usize>) {
+        self.ordinal = ordinal;
+    }
+
+    pub fn position(&self) -> Option<usize> {
+        self.position
+    }
+
+    pub fn set_position(&mut self, position: Option<usize>) {
+        self.position = position;
+    }
+
+    pub fn attributes(&self) -> &Attributes {
+        &self.attributes
+    }
+
+    pub fn set_attributes(&mut self, attributes: Attributes) {
+        self.attributes = attributes;
+    }
+
+    /// Returns the link given by the attribute `url`.
+    pub fn url(&self) -> Option<&str> {
+        self.attributes.value("url")?.as_str()
+    }
+
+    pub fn span(&self) -> Span {
+        self.span.clone()
+    }
+
+    pub fn set_span(&mut self, span: Span) {
+        self.span = span;
+    }
+
+    pub fn name_span(&self) -> Span {
+        self.name_span.clone()
+    }
+
+    pub fn set_name_span(&mut self, name_span: Span) {
+        self.name_span = name_span;
+    }
 }
 
 impl fmt::Display for EntityField {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{} {}", self.name, self.field_type)?;
-        let Some(field_key) = self.field_key else { return Ok(()) };
-        write!(f, " {}", field_key.to_keyword())
+        write!(f, "{} {}", quote_identifier(&self.name), self.field_type)?;
+        if self.nullable {
+            write!(f, "?")?;
+        }
+        if let Some(field_key) = self.field_key {
+            write!(f, " {}", field_key.to_keyword())?;
+        }
+        // Numbers are written as they are, and the other values as strings.
+        if let Some(default_value) = &self.default_value {
+            if !default_value.is_empty() && default_value.chars().all(|c| c.is_ascii_digit()) {
+                write!(f, " default {}", default_value)?;
+            } else {
+                write!(f, " default {}", quote_string(default_value))?;
+            }
+        }
+        if let Some(position) = self.position {
+            write!(f, " @{}", position)?;
+        }
+        if !self.attributes.is_empty() {
+            write!(f, " {}", self.attributes)?;
+        }
+        Ok(())
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Display)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum EntityFieldType {
     #[display(fmt = "int")]
     Int,
@@ -283,9 +1318,13 @@ pub enum EntityFieldType {
     Text,
     #[display(fmt = "timestamp")]
     Timestamp,
+    /// A type which isn't built-in (e.g. `decimal(12,2)`) or a type alias.
+    #[display(fmt = "{}", _0)]
+    Named(String),
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum EntityFieldKey {
     #[display(fmt = "Primary Key")]
     PrimaryKey,
@@ -295,14 +1334,27 @@ pub enum EntityFieldKey {
 
 impl EntityFieldKey {
     pub fn into_mir(&self) -> mir::Badge {
+        self.into_mir_with_theme(&Theme::default())
+    }
+
+    pub fn into_mir_with_theme(&self, theme: &Theme) -> mir::Badge {
         mir::BadgeBuilder::default()
             .text(self.badge_text())
-            .color(Some(self.badge_text_color()))
-            .bg_color(Some(self.badge_bg_color()))
+            .color(Some(self.badge_text_color(theme)))
+            .bg_color(Some(self.badge_bg_color(theme)))
+            .description(Some(self.description().into()))
             .build()
             .unwrap()
     }
 
+    /// Returns the meaning of the key in lower case (e.g. `primary key`).
+    pub fn description(&self) -> &'static str {
+        match self {
+            EntityFieldKey::PrimaryKey => "primary key",
+            EntityFieldKey::ForeginKey => "foreign key",
+        }
+    }
+
     pub fn to_keyword(&self) -> String {
         match self {
             EntityFieldKey::PrimaryKey => "PK".into(),
@@ -314,22 +1366,23 @@ impl EntityFieldKey {
         self.to_keyword()
     }
 
-    fn badge_text_color(&self) -> WebColor {
+    fn badge_text_color(&self, theme: &Theme) -> WebColor {
         match self {
-            EntityFieldKey::PrimaryKey => WebColor::Named(NamedColor::White),
-            EntityFieldKey::ForeginKey => WebColor::RGB(RGBColor::new(17, 112, 251)),
+            EntityFieldKey::PrimaryKey => theme.primary_key_color.clone(),
+            EntityFieldKey::ForeginKey => theme.foreign_key_color.clone(),
         }
     }
 
-    fn badge_bg_color(&self) -> WebColor {
+    fn badge_bg_color(&self, theme: &Theme) -> WebColor {
         match self {
-            EntityFieldKey::PrimaryKey => WebColor::RGB(RGBColor::new(55, 55, 55)),
-            EntityFieldKey::ForeginKey => WebColor::RGB(RGBColor::new(32, 41, 55)),
+            EntityFieldKey::PrimaryKey => theme.primary_key_bg_color.clone(),
+            EntityFieldKey::ForeginKey => theme.foreign_key_bg_color.clone(),
         }
     }
 }
 
 #[derive(Debug, Clone, Display, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum EntityPath {
     #[display(fmt = "{}", _0)]
     Entity(String),
@@ -337,11 +1390,54 @@ pub enum EntityPath {
     Field(String, String),
 }
 
-#[derive(Debug, Clone, Display)]
-#[display(fmt = "{} o--o {}", start_path, end_path)]
+impl FromStr for EntityPath {
+    type Err = String;
+
+    /// Parses `entity` or `entity.field`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('.') {
+            _ if s.is_empty() => Err("empty path".into()),
+            None => Ok(EntityPath::Entity(s.into())),
+            Some((entity, field)) if !entity.is_empty() && !field.is_empty() => {
+                Ok(EntityPath::Field(entity.into(), field.into()))
+            }
+            Some(_) => Err(format!("invalid path `{}`", s)),
+        }
+    }
+}
+
+impl EntityPath {
+    pub fn entity_name(&self) -> &str {
+        match self {
+            EntityPath::Entity(name) => name,
+            EntityPath::Field(name, _) => name,
+        }
+    }
+}
+
+/// The locations of the components of an entity path in the source.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct EntityPathSpan {
+    pub entity: Span,
+    pub field: Option<Span>,
+}
+
+impl EntityPathSpan {
+    pub fn new(entity: Span, field: Option<Span>) -> Self {
+        Self { entity, field }
+    }
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct EntityRelation {
     start_path: EntityPath,
     end_path: EntityPath,
+    attributes: Attributes,
+    span: Span,
+    start_path_span: EntityPathSpan,
+    end_path_span: EntityPathSpan,
 }
 
 impl EntityRelation {
@@ -349,9 +1445,29 @@ impl EntityRelation {
         Self {
             start_path,
             end_path,
+            attributes: Attributes::default(),
+            span: Span::default(),
+            start_path_span: EntityPathSpan::default(),
+            end_path_span: EntityPathSpan::default(),
         }
     }
 
+    pub fn attributes(&self) -> &Attributes {
+        &self.attributes
+    }
+
+    pub fn set_attributes(&mut self, attributes: Attributes) {
+        self.attributes = attributes;
+    }
+
+    pub fn span(&self) -> Span {
+        self.span.clone()
+    }
+
+    pub fn set_span(&mut self, span: Span) {
+        self.span = span;
+    }
+
     pub fn start_path(&self) -> &EntityPath {
         &self.start_path
     }
@@ -359,4 +1475,464 @@ impl EntityRelation {
     pub fn end_path(&self) -> &EntityPath {
         &self.end_path
     }
+
+    pub fn start_path_span(&self) -> &EntityPathSpan {
+        &self.start_path_span
+    }
+
+    pub fn end_path_span(&self) -> &EntityPathSpan {
+        &self.end_path_span
+    }
+
+    /// Returns `true` if both ends of the relation are the same entity or field (e.g.
+    /// `users.id o--o users.id`). Such relations have no path to draw.
+    pub fn is_self_relation(&self) -> bool {
+        self.start_path == self.end_path
+    }
+
+    pub fn set_path_spans(&mut self, start: EntityPathSpan, end: EntityPathSpan) {
+        self.start_path_span = start;
+        self.end_path_span = end;
+    }
+
+    /// Returns the edge marker given by the attribute `name` (e.g. `[start=one, end=crow]`).
+    /// Unknown markers are ignored.
+    pub fn marker(&self, name: &str) -> Option<mir::EdgeMarker> {
+        self.attributes.value(name)?.as_str()?.parse().ok()
+    }
+
+    /// Returns the cardinality of the end `name` (`start` or `end`) given by the attribute
+    /// `cardinality` (e.g. `[cardinality="1:N"]`), or denoted by the marker of the end.
+    pub fn cardinality(&self, name: &str) -> Option<mir::Cardinality> {
+        let explicit = self
+            .attributes
+            .value("cardinality")
+            .and_then(AttributeValue::as_str)
+            .and_then(|value| value.split_once(':'))
+            .and_then(|(start, end)| match name {
+                "start" => start.trim().parse().ok(),
+                "end" => end.trim().parse().ok(),
+                _ => None,
+            });
+
+        explicit.or_else(|| self.marker(name)?.cardinality())
+    }
+
+    /// Returns the label given by the attribute `label` (e.g. `[label="author"]`).
+    pub fn label(&self) -> Option<&str> {
+        self.attributes.value("label")?.as_str()
+    }
+
+    /// Returns the line style given by the attribute `style` (e.g. `[style=dashed]`). Unknown
+    /// styles are ignored.
+    pub fn stroke_style(&self) -> Option<mir::StrokeStyle> {
+        self.attributes.value("style")?.as_str()?.parse().ok()
+    }
+
+    /// Returns the line width given by the attribute `width` (e.g. `[width=3]`).
+    pub fn stroke_width(&self) -> Option<f32> {
+        self.attributes
+            .value("width")?
+            .as_number()
+            .map(|width| width as f32)
+    }
+
+    /// Returns the line color given by the attribute `color` (e.g. `[color=#FF0000]`).
+    pub fn stroke_color(&self) -> Option<WebColor> {
+        self.attributes
+            .value("color")?
+            .as_color()
+            .map(|color| WebColor::RGB(color.clone()))
+    }
+
+    /// Returns the layer given by the attribute `layer` (e.g. `[layer=below]`). Unknown layers
+    /// are ignored.
+    pub fn layer(&self) -> Option<mir::EdgeLayer> {
+        self.attributes.value("layer")?.as_str()?.parse().ok()
+    }
+
+    fn paths_mut(&mut self) -> [(&mut EntityPath, &EntityPathSpan); 2] {
+        [
+            (&mut self.start_path, &self.start_path_span),
+            (&mut self.end_path, &self.end_path_span),
+        ]
+    }
+}
+
+impl fmt::Display for EntityRelation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let quote_path = |path: &EntityPath| match path {
+            EntityPath::Entity(entity) => quote_identifier(entity),
+            EntityPath::Field(entity, field) => {
+                format!("{}.{}", quote_identifier(entity), quote_identifier(field))
+            }
+        };
+
+        write!(f, "{} o--o {}", quote_path(&self.start_path), quote_path(&self.end_path))?;
+        if !self.attributes.is_empty() {
+            write!(f, " {}", self.attributes)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field_names(definition: &EntityDefinition) -> Vec<(usize, &str)> {
+        definition
+            .fields_in_display_order()
+            .into_iter()
+            .map(|(ordinal, field)| (ordinal, field.name()))
+            .collect()
+    }
+
+    #[test]
+    fn fields_in_display_order() {
+        let mut definition = EntityDefinition::new("users".into());
+
+        for (name, position) in [
+            ("id", None),
+            ("name", None),
+            ("email", Some(1)),
+            ("age", None),
+        ] {
+            let mut field = EntityField::new(name.into(), EntityFieldType::Text, None);
+
+            field.set_position(position);
+            definition.add_field(field);
+        }
+
+        assert_eq!(
+            field_names(&definition),
+            vec![(3, "email"), (1, "id"), (2, "name"), (4, "age")]
+        );
+    }
+
+    #[test]
+    fn fields_in_display_order_with_conflicts() {
+        let mut definition = EntityDefinition::new("users".into());
+
+        for (name, position) in [("a", Some(2)), ("b", Some(2)), ("c", Some(9)), ("d", None)] {
+            let mut field = EntityField::new(name.into(), EntityFieldType::Text, None);
+
+            field.set_position(position);
+            definition.add_field(field);
+        }
+
+        // "b" is moved to the next free position, and "c" is clamped to the last one.
+        assert_eq!(
+            field_names(&definition),
+            vec![(4, "d"), (1, "a"), (2, "b"), (3, "c")]
+        );
+    }
+
+    #[test]
+    fn parse_entity_path() {
+        assert_eq!(
+            "users".parse::<EntityPath>(),
+            Ok(EntityPath::Entity("users".into()))
+        );
+        assert_eq!(
+            "users.id".parse::<EntityPath>(),
+            Ok(EntityPath::Field("users".into(), "id".into()))
+        );
+        assert!("users.".parse::<EntityPath>().is_err());
+        assert!("".parse::<EntityPath>().is_err());
+    }
+
+    #[test]
+    fn resolve_type() {
+        let named = |name: &str| EntityFieldType::Named(name.into());
+        let mut module = Module::new(None);
+
+        module.add_type_alias(TypeAlias::new("id".into(), named("key")));
+        module.add_type_alias(TypeAlias::new("key".into(), EntityFieldType::Uuid));
+        module.add_type_alias(TypeAlias::new("a".into(), named("b")));
+        module.add_type_alias(TypeAlias::new("b".into(), named("a")));
+
+        assert_eq!(module.resolve_type(&named("id")), &EntityFieldType::Uuid);
+        assert_eq!(module.resolve_type(&named("money")), &named("money"));
+        assert_eq!(module.resolve_type(&EntityFieldType::Int), &EntityFieldType::Int);
+        // cyclic aliases don't loop forever
+        assert!(matches!(module.resolve_type(&named("a")), EntityFieldType::Named(_)));
+    }
+
+    #[test]
+    fn entity_and_relations_lookup() {
+        let (ast, _, _) = crate::parser::parse(
+            "erd {
+users { id int PK }
+posts { id int PK }
+posts.user_id o--o users.id
+users.parent_id o--o users.id
+tags.post_id o--o posts.id
+}",
+        );
+        let mut module = ast.unwrap();
+        let paths = |module: &Module, name: &str| {
+            module
+                .relations_of(name)
+                .map(|r| r.start_path().to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(module.entity("users").map(|d| d.name()), Some("users"));
+        assert!(module.entity("tags").is_none());
+        assert_eq!(paths(&module, "users"), vec!["posts.user_id", "users.parent_id"]);
+        assert_eq!(paths(&module, "tags"), vec!["tags.post_id"]);
+        assert!(paths(&module, "unknown").is_empty());
+
+        // The index is rebuilt after entries are added.
+        module.add_entity_definition(EntityDefinition::new("tags".into()));
+        assert_eq!(module.entity("tags").map(|d| d.name()), Some("tags"));
+    }
+
+    #[test]
+    fn rename_entity() {
+        let src = "erd {
+users { id int PK }
+posts { user_id int FK }
+posts.user_id o--o users.id
+users.id o--o `users`.
+}";
+        let (ast, _, _) = crate::parser::parse(src);
+        let mut module = ast.unwrap();
+        let report = module.rename_entity("users", "members").unwrap();
+
+        assert_eq!((report.definitions, report.relations), (1, 2));
+        assert_eq!(
+            TextEdit::apply_all(src, &report.edits),
+            "erd {
+members { id int PK }
+posts { user_id int FK }
+posts.user_id o--o members.id
+members.id o--o members.
+}"
+        );
+        assert!(module.entity("users").is_none());
+        assert_eq!(module.relations_of("members").count(), 2);
+
+        assert_eq!(
+            module.rename_entity("users", "people"),
+            Err(RenameError::NotFound(EntityPath::Entity("users".into())))
+        );
+        assert_eq!(
+            module.rename_entity("members", "posts"),
+            Err(RenameError::AlreadyExists(EntityPath::Entity("posts".into())))
+        );
+    }
+
+    #[test]
+    fn rename_field() {
+        let src = "erd {
+users { id int PK; name text }
+posts { user_id int FK }
+posts.user_id o--o users.id
+}";
+        let (ast, _, _) = crate::parser::parse(src);
+        let mut module = ast.unwrap();
+        let report = module.rename_field("users", "id", "user id").unwrap();
+
+        assert_eq!((report.definitions, report.relations), (1, 1));
+        assert_eq!(
+            TextEdit::apply_all(src, &report.edits),
+            "erd {
+users { `user id` int PK; name text }
+posts { user_id int FK }
+posts.user_id o--o users.`user id`
+}"
+        );
+        assert_eq!(
+            module.rename_field("users", "name", "user id"),
+            Err(RenameError::AlreadyExists(EntityPath::Field(
+                "users".into(),
+                "user id".into()
+            )))
+        );
+        assert_eq!(module.rename_field("users", "name", ""), Err(RenameError::EmptyName));
+    }
+
+    #[test]
+    fn self_relation() {
+        let (ast, _, _) = crate::parser::parse(
+            "erd {
+users { id int PK; parent_id int FK }
+users.id o--o users.id
+users.parent_id o--o users.id
+}",
+        );
+        let module = ast.unwrap();
+        let self_relations: Vec<bool> = module
+            .entries()
+            .filter_map(|entry| match entry {
+                ModuleEntry::EntityRelation(relation) => Some(relation.is_self_relation()),
+                _ => None,
+            })
+            .collect();
+
+        assert_eq!(self_relations, vec![true, false]);
+        // Only the relation between different fields becomes an edge.
+        assert_eq!(module.into_mir().edges().count(), 1);
+    }
+
+    #[test]
+    fn entity_filter() {
+        let (ast, _, _) = crate::parser::parse(
+            "erd {
+users [tags=\"core, auth\"] { id int PK }
+posts [tags=\"core\"] { id int PK }
+invoices [tags=\"billing\"] { id int PK }
+}",
+        );
+        let module = ast.unwrap();
+        let selected = |filter: EntityFilter| -> Vec<&str> {
+            ["users", "posts", "invoices"]
+                .into_iter()
+                .filter(|name| filter.matches(module.entity(name).unwrap()))
+                .collect()
+        };
+        let strings = |names: &[&str]| names.iter().map(|s| s.to_string()).collect();
+
+        assert_eq!(module.entity("users").unwrap().tags(), ["core", "auth"]);
+        assert_eq!(module.entity("users").unwrap().kind(), None);
+        assert!(EntityFilter::default().is_empty());
+        assert_eq!(
+            selected(EntityFilter::default()),
+            ["users", "posts", "invoices"]
+        );
+        assert_eq!(
+            selected(EntityFilter::new(strings(&["core"]), vec![])),
+            ["users", "posts"]
+        );
+        assert_eq!(
+            selected(EntityFilter::new(strings(&["core", "invoices"]), strings(&["auth"]))),
+            ["posts", "invoices"]
+        );
+    }
+
+    #[test]
+    fn preferred_positions() {
+        let (ast, _, _) = crate::parser::parse(
+            "erd {
+users [row=2, column=1] { id int PK }
+posts [row=0, column=1, x=10] { id int PK }
+tags [x=10, y=20] { id int PK }
+}",
+        );
+        let module = ast.unwrap();
+        let entity = |name: &str| module.entity(name).unwrap();
+
+        assert_eq!(entity("users").preferred_cell(), Some(mir::GridCell::new(1, 0)));
+        assert_eq!(entity("posts").preferred_cell(), None);
+        assert_eq!(entity("posts").preferred_origin(), None);
+        assert_eq!(entity("tags").preferred_origin(), Some(Point::new(10.0, 20.0)));
+    }
+
+    #[test]
+    fn entity_groups() {
+        let (ast, _, _) = crate::parser::parse(
+            "erd {
+invoices [group=\"Billing\"] { id int PK }
+users { id int PK }
+payments [group=\"Billing\"] { id int PK }
+}",
+        );
+        let module = ast.unwrap();
+        let (doc, node_paths) = module.into_mir_with_node_paths(&ConvertOptions::default());
+        let record_id = |name: &str| node_paths[&EntityPath::Entity(name.into())];
+
+        assert_eq!(module.entity("invoices").unwrap().group(), Some("Billing"));
+        assert_eq!(module.entity("users").unwrap().group(), None);
+
+        let group_ids: Vec<_> = doc.group_ids().collect();
+        assert_eq!(group_ids.len(), 1);
+
+        let group = doc.get_node(group_ids[0]).unwrap();
+        let mir::ShapeKind::Group(shape) = group.kind() else { panic!("not a group") };
+        assert_eq!(shape.title.text, "Billing");
+        assert_eq!(
+            group.children().collect::<Vec<_>>(),
+            [record_id("invoices"), record_id("payments")]
+        );
+        assert_eq!(doc.group_of(record_id("payments")), Some(group_ids[0]));
+        assert_eq!(doc.group_of(record_id("users")), None);
+        // Members stay in the body.
+        assert_eq!(doc.body().children().len(), 3);
+    }
+
+    #[test]
+    fn highlight_keys() {
+        let (ast, _, _) = crate::parser::parse(
+            "erd {
+posts {
+    id int PK
+    user_id int FK
+    title text
+}
+}",
+        );
+        let module = ast.unwrap();
+        let row_colors = |highlight_keys: Option<KeyHighlight>| -> Vec<Option<String>> {
+            let doc = module.into_mir_with_options(&ConvertOptions {
+                highlight_keys,
+                ..ConvertOptions::default()
+            });
+            let record_id = doc.body().children().next().unwrap();
+
+            doc.get_node(record_id)
+                .unwrap()
+                .children()
+                .skip(1)
+                .map(|field_id| match doc.get_node(field_id).unwrap().kind() {
+                    mir::ShapeKind::Field(field) => field.bg_color.as_ref().map(|c| c.to_string()),
+                    _ => unreachable!(),
+                })
+                .collect()
+        };
+        let theme = Theme::default();
+        let pk = Some(theme.primary_key_row_color.to_string());
+        let fk = Some(theme.foreign_key_row_color.to_string());
+
+        assert_eq!(row_colors(None), [None, None, None]);
+        assert_eq!(row_colors(Some(KeyHighlight::PrimaryKeys)), [pk.clone(), None, None]);
+        assert_eq!(row_colors(Some(KeyHighlight::AllKeys)), [pk, fk, None]);
+        assert_eq!("all".parse(), Ok(KeyHighlight::AllKeys));
+    }
+
+    #[test]
+    fn relation_cardinality() {
+        let (ast, _, _) = crate::parser::parse(
+            "erd {
+posts.user_id o--o users.id [start=crow, end=one]
+posts.user_id o--o users.id [start=crow, cardinality=\"0..1:1..N\"]
+posts.user_id o--o users.id [cardinality=\"1:M\"]
+posts.user_id o--o users.id
+}",
+        );
+        let cardinalities: Vec<_> = ast
+            .unwrap()
+            .entries()
+            .filter_map(|entry| match entry {
+                ModuleEntry::EntityRelation(relation) => Some((
+                    relation.cardinality("start").map(|c| c.to_string()),
+                    relation.cardinality("end").map(|c| c.to_string()),
+                )),
+                _ => None,
+            })
+            .collect();
+        let some = |s: &str| Some(s.to_string());
+
+        assert_eq!(
+            cardinalities,
+            vec![
+                (some("N"), some("1")),
+                // explicit cardinalities take precedence over markers
+                (some("0..1"), some("1..N")),
+                (some("1"), None),
+                (None, None),
+            ]
+        );
+    }
 }
@@ -1,3 +1,5 @@
+use crate::erd::EntityPath;
+use crate::merge::Conflict;
 use crate::mir::NodeId;
 use std::io;
 use thiserror::Error;
@@ -9,3 +11,67 @@ pub enum BackendError {
     #[error("the node for id `{0}` is not laid out")]
     InvalidLayout(NodeId),
 }
+
+#[derive(Error, Debug, PartialEq)]
+pub enum RenderOptionsError {
+    #[error("the path radius {0} must be smaller than half of the junction margin {1}")]
+    PathRadiusTooLarge(f32, f32),
+    #[error("field column ratios must be finite, non-negative and give text columns a width")]
+    InvalidFieldColumnRatio,
+}
+
+#[derive(Error, Debug)]
+pub enum RasterError {
+    #[error("I/O failure")]
+    IoFailure(#[from] io::Error),
+    #[error("invalid SVG: {0}")]
+    InvalidSVG(String),
+    #[error("cannot allocate an image of {0}x{1} pixels")]
+    InvalidImageSize(u32, u32),
+    #[error("cannot encode PNG: {0}")]
+    EncodingFailure(String),
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum FontFileError {
+    #[error("invalid font file: {0}")]
+    InvalidFont(String),
+}
+
+#[derive(Error, Debug)]
+pub enum ThemeError {
+    #[error("line {0}: expected `key = value`")]
+    InvalidLine(usize),
+    #[error("line {0}: `base` must be specified before colors")]
+    LateBase(usize),
+    #[error("unknown theme `{0}`")]
+    UnknownTheme(String),
+    #[error("unknown color key `{0}`")]
+    UnknownKey(String),
+    #[error("line {0}: invalid color `{1}` (expected #RGB or #RRGGBB)")]
+    InvalidColor(usize, String),
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum LayoutCacheError {
+    #[error("the cache was created by seiren {0}, not {}", crate::VERSION)]
+    IncompatibleVersion(String),
+    #[error("the cache was created with different layout settings")]
+    ConfigMismatch,
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum RenameError {
+    #[error("`{0}` is not found")]
+    NotFound(EntityPath),
+    #[error("`{0}` already exists")]
+    AlreadyExists(EntityPath),
+    #[error("the new name is empty")]
+    EmptyName,
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum MergeError {
+    #[error("{0}")]
+    Conflict(Box<Conflict>),
+}
@@ -35,7 +35,7 @@ pub fn draw_erd(doc: &mir::Document, view_box: Option<Rect>) -> impl CustomDispl
 
     backend.view_box = view_box;
     backend
-        .render(&doc, &mut bytes)
+        .render(doc, &mut bytes)
         .expect("cannot generate SVG");
 
     let svg = String::from_utf8(bytes).unwrap();
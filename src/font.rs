@@ -0,0 +1,361 @@
+//! Font metrics for text measurement
+//!
+//! Fonts used by the renderer are common system fonts, so text widths are estimated from the
+//! standard AFM metrics of Helvetica (proportional fonts) and Courier (monospace fonts) instead
+//! of loading font files. Estimates are slightly generous for wider fonts such as Verdana.
+//!
+//! With the `font-file` feature, `FontFile` measures text with the advance widths of a TrueType or
+//! OpenType font file instead.
+use crate::mir::{FontFamily, FontSize, FontWeight, TextSpan};
+#[cfg(feature = "font-file")]
+use crate::error::FontFileError;
+use std::fmt;
+
+/// Advance widths of ASCII printable characters (U+0020..U+007E) of Helvetica in 1/1000 em.
+const HELVETICA_WIDTHS: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278,
+    278, // ' '..'/'
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584,
+    556, // '0'..'?'
+    1015, 667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556, 833, 722,
+    778, // '@'..'O'
+    667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 278, 278, 278, 469,
+    556, // 'P'..'_'
+    333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500, 222, 833, 556,
+    556, // '`'..'o'
+    556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584, // 'p'..'~'
+];
+
+/// Helvetica Bold is about 6% wider than Helvetica on average.
+const BOLD_SCALE: f32 = 1.06;
+
+/// Returns the font size in pixels of an absolute-size keyword (CSS Fonts Level 4).
+pub fn font_size_px(font_size: FontSize) -> f32 {
+    match font_size {
+        FontSize::XXSmall => 9.0,
+        FontSize::XSmall => 10.0,
+        FontSize::Small => 13.0,
+        FontSize::Medium => 16.0,
+        FontSize::Large => 18.0,
+        FontSize::XLarge => 24.0,
+        FontSize::XXLarge => 32.0,
+        FontSize::XXXLarge => 48.0,
+    }
+}
+
+/// Returns the estimated width of a text span in pixels.
+pub fn text_width(span: &TextSpan) -> f32 {
+    let monospace = is_monospace(span);
+    let em: f32 = span.text.chars().map(|c| char_width(c, monospace)).sum();
+
+    em * em_size(span)
+}
+
+/// Measures widths of text spans for the layout (e.g. with actual fonts in a browser).
+pub trait TextMeasure: fmt::Debug {
+    /// Returns the width of `span` in pixels.
+    fn text_width(&self, span: &TextSpan) -> f32;
+
+    /// Returns bytes which identify the measure in `SimpleLayoutEngine::config_hash`. Defaults to
+    /// the `Debug` representation.
+    fn config_bytes(&self) -> Vec<u8> {
+        format!("{:?}", self).into_bytes()
+    }
+}
+
+/// Estimates widths from the built-in metrics of `text_width`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FontMetrics;
+
+impl TextMeasure for FontMetrics {
+    fn text_width(&self, span: &TextSpan) -> f32 {
+        text_width(span)
+    }
+
+    /// The built-in metrics are the default, so they add nothing to the hash.
+    fn config_bytes(&self) -> Vec<u8> {
+        vec![]
+    }
+}
+
+/// Estimates widths as a fixed advance width in em per character, regardless of font families.
+/// Full-width characters take twice the width.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CharWidth(pub f32);
+
+impl TextMeasure for CharWidth {
+    fn text_width(&self, span: &TextSpan) -> f32 {
+        let n_chars: usize = span
+            .text
+            .chars()
+            .map(|c| if is_wide(c) { 2 } else { 1 })
+            .sum();
+
+        n_chars as f32 * self.0 * em_size(span)
+    }
+}
+
+/// Measures widths with the advance widths of a TrueType or OpenType font file, for all font
+/// families. Characters which the font lacks are estimated from the built-in metrics, and bold
+/// proportional text is widened as with the built-in metrics.
+#[cfg(feature = "font-file")]
+#[derive(Clone)]
+pub struct FontFile {
+    data: Vec<u8>,
+    /// FNV-1a hash of `data`, which identifies the font in `config_bytes`.
+    digest: u64,
+}
+
+#[cfg(feature = "font-file")]
+impl FontFile {
+    /// Loads the first font face in `data`.
+    pub fn new(data: Vec<u8>) -> Result<Self, FontFileError> {
+        ttf_parser::Face::parse(&data, 0)
+            .map_err(|err| FontFileError::InvalidFont(err.to_string()))?;
+
+        let digest = crate::layout::fnv1a(data.iter().copied());
+
+        Ok(Self { data, digest })
+    }
+
+    fn face(&self) -> ttf_parser::Face<'_> {
+        // `new` has parsed the data successfully.
+        ttf_parser::Face::parse(&self.data, 0).unwrap()
+    }
+}
+
+#[cfg(feature = "font-file")]
+impl fmt::Debug for FontFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FontFile")
+            .field("digest", &format_args!("{:016x}", self.digest))
+            .finish()
+    }
+}
+
+#[cfg(feature = "font-file")]
+impl TextMeasure for FontFile {
+    fn text_width(&self, span: &TextSpan) -> f32 {
+        let face = self.face();
+        let units_per_em = face.units_per_em() as f32;
+        let monospace = is_monospace(span);
+        let em: f32 = span
+            .text
+            .chars()
+            .map(|c| {
+                face.glyph_index(c)
+                    .and_then(|glyph| face.glyph_hor_advance(glyph))
+                    .map_or_else(
+                        || char_width(c, monospace),
+                        |advance| advance as f32 / units_per_em,
+                    )
+            })
+            .sum();
+
+        em * em_size(span)
+    }
+
+    fn config_bytes(&self) -> Vec<u8> {
+        self.digest.to_le_bytes().to_vec()
+    }
+}
+
+/// Truncates the text of `span` with an ellipsis (`…`) so that it fits in `max_width` pixels.
+/// Returns `None` if the text already fits.
+pub fn truncate_text(span: &TextSpan, max_width: f32) -> Option<String> {
+    if text_width(span) <= max_width {
+        return None;
+    }
+
+    let monospace = is_monospace(span);
+    let budget = max_width / em_size(span) - char_width('…', monospace);
+    let mut truncated = String::new();
+    let mut used = 0.0;
+
+    for c in span.text.chars() {
+        used += char_width(c, monospace);
+        if used > budget {
+            break;
+        }
+        truncated.push(c);
+    }
+
+    truncated.push('…');
+    Some(truncated)
+}
+
+/// Wraps the text of `span` into lines at spaces and line breaks, so that each line fits in
+/// `max_width` pixels measured by `measure`. Words wider than `max_width` take a line of their own.
+pub fn wrap_text(span: &TextSpan, max_width: f32, measure: &dyn TextMeasure) -> Vec<String> {
+    let width = |text: &str| {
+        measure.text_width(&TextSpan {
+            text: text.to_string(),
+            ..span.clone()
+        })
+    };
+    let mut lines = vec![];
+
+    for paragraph in span.text.lines() {
+        let mut line = String::new();
+
+        for word in paragraph.split_whitespace() {
+            if !line.is_empty() && width(&format!("{} {}", line, word)) > max_width {
+                lines.push(std::mem::take(&mut line));
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(word);
+        }
+        lines.push(line);
+    }
+
+    lines
+}
+
+fn is_monospace(span: &TextSpan) -> bool {
+    matches!(
+        span.font_family,
+        Some(FontFamily::Monospace1 | FontFamily::Monospace2)
+    )
+}
+
+/// Returns the size of 1em in pixels, including the widening of bold proportional fonts.
+fn em_size(span: &TextSpan) -> f32 {
+    let font_size = font_size_px(span.font_size.unwrap_or_default());
+    let bold = matches!(
+        span.font_weight,
+        Some(FontWeight::Bold | FontWeight::Bolder)
+    );
+
+    // Monospace fonts keep the same advance width in bold.
+    if bold && !is_monospace(span) {
+        font_size * BOLD_SCALE
+    } else {
+        font_size
+    }
+}
+
+/// Returns the advance width of `c` in em.
+fn char_width(c: char, monospace: bool) -> f32 {
+    match (is_wide(c), monospace) {
+        // Full-width characters take two cells in monospace fonts.
+        (true, true) => 1.2,
+        (true, false) => 1.0,
+        (false, true) => 0.6,
+        (false, false) if (' '..='~').contains(&c) => {
+            HELVETICA_WIDTHS[c as usize - 0x20] as f32 / 1000.0
+        }
+        (false, false) => 0.556,
+    }
+}
+
+/// Returns `true` if `c` is a full-width character (e.g. CJK ideographs and kana).
+fn is_wide(c: char) -> bool {
+    matches!(c,
+        '\u{1100}'..='\u{115F}'
+        | '\u{2E80}'..='\u{A4CF}'
+        | '\u{AC00}'..='\u{D7A3}'
+        | '\u{F900}'..='\u{FAFF}'
+        | '\u{FE30}'..='\u{FE4F}'
+        | '\u{FF00}'..='\u{FF60}'
+        | '\u{FFE0}'..='\u{FFE6}'
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mir::TextSpanBuilder;
+
+    fn span(text: &str, family: FontFamily, weight: FontWeight) -> TextSpan {
+        TextSpanBuilder::default()
+            .text(text)
+            .font_family(Some(family))
+            .font_weight(Some(weight))
+            .font_size(Some(FontSize::Small))
+            .build()
+            .unwrap()
+    }
+
+    fn assert_approx_eq(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-3, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn measure_text() {
+        let mono = span("user_id", FontFamily::Monospace2, FontWeight::Normal);
+        assert_approx_eq(text_width(&mono), 7.0 * 0.6 * 13.0);
+
+        // Bold doesn't change monospace widths.
+        let mono_bold = span("user_id", FontFamily::Monospace2, FontWeight::Bold);
+        assert_eq!(text_width(&mono_bold), text_width(&mono));
+
+        let narrow = span("iiii", FontFamily::SansSerif1, FontWeight::Normal);
+        let wide = span("MMMM", FontFamily::SansSerif1, FontWeight::Normal);
+        assert!(text_width(&narrow) < text_width(&wide));
+
+        // Full-width characters take two cells.
+        let cjk = span("ユーザー", FontFamily::Monospace2, FontWeight::Normal);
+        let ascii = span("abcd", FontFamily::Monospace2, FontWeight::Normal);
+        assert_approx_eq(text_width(&cjk), 2.0 * text_width(&ascii));
+    }
+
+    #[test]
+    fn truncate() {
+        let mono = span("created_at", FontFamily::Monospace2, FontWeight::Normal);
+        let width = text_width(&mono);
+
+        assert_eq!(truncate_text(&mono, width), None);
+        // 13px * 0.6 = 7.8px per character
+        assert_eq!(truncate_text(&mono, 7.8 * 5.5).as_deref(), Some("crea…"));
+        assert_eq!(truncate_text(&mono, 1.0).as_deref(), Some("…"));
+    }
+
+    #[test]
+    fn char_width_measure() {
+        let narrow = span("iiii", FontFamily::SansSerif1, FontWeight::Normal);
+        let wide = span("MMMM", FontFamily::SansSerif1, FontWeight::Normal);
+        let cjk = span("ユーザー", FontFamily::SansSerif1, FontWeight::Normal);
+
+        assert_approx_eq(CharWidth(0.5).text_width(&narrow), 4.0 * 0.5 * 13.0);
+        assert_eq!(
+            CharWidth(0.5).text_width(&narrow),
+            CharWidth(0.5).text_width(&wide)
+        );
+        assert_approx_eq(CharWidth(0.5).text_width(&cjk), 8.0 * 0.5 * 13.0);
+        assert_eq!(FontMetrics.text_width(&wide), text_width(&wide));
+    }
+
+    #[test]
+    fn wrap() {
+        let text = span(
+            "aaa bbb ccc\ndd",
+            FontFamily::SansSerif1,
+            FontWeight::Normal,
+        );
+
+        // 6.5px per character
+        assert_eq!(
+            wrap_text(&text, 50.0, &CharWidth(0.5)),
+            ["aaa bbb", "ccc", "dd"]
+        );
+        assert_eq!(
+            wrap_text(&text, 10.0, &CharWidth(0.5)),
+            ["aaa", "bbb", "ccc", "dd"]
+        );
+        assert_eq!(
+            wrap_text(&text, 500.0, &CharWidth(0.5)),
+            ["aaa bbb ccc", "dd"]
+        );
+    }
+
+    #[cfg(feature = "font-file")]
+    #[test]
+    fn invalid_font_file() {
+        assert!(matches!(
+            FontFile::new(b"not a font".to_vec()),
+            Err(FontFileError::InvalidFont(_))
+        ));
+    }
+}
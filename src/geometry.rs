@@ -1,7 +1,10 @@
 use derive_more::Display;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 use smallvec::{smallvec, SmallVec};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Orientation {
     Up,
     Down,
@@ -10,6 +13,7 @@ pub enum Orientation {
 }
 
 #[derive(Debug, Clone, Copy, Default, PartialEq, Display)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[display(fmt = "({}, {})", x, y)]
 pub struct Point {
     pub x: f32,
@@ -46,6 +50,7 @@ impl Point {
 }
 
 #[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Size {
     pub width: f32,
     pub height: f32,
@@ -78,6 +83,7 @@ impl Size {
 ///            *----------*----------* maxY
 /// ```
 #[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Rect {
     pub origin: Point,
     pub size: Size,
@@ -164,11 +170,11 @@ impl Rect {
     /// a returned rectangle has a zero size.
     ///
     /// - `dx` - The x-coordinate value to use for adjusting the source rectangle.
-    ///          To create an inset rectangle, specify a positive value.
-    ///          To create a larger, encompassing rectangle, specify a negative value.
+    ///   To create an inset rectangle, specify a positive value.
+    ///   To create a larger, encompassing rectangle, specify a negative value.
     /// - `dy` - The y-coordinate value to use for adjusting the source rectangle.
-    ///          To create an inset rectangle, specify a positive value.
-    ///          To create a larger, encompassing rectangle, specify a negative value.
+    ///   To create an inset rectangle, specify a positive value.
+    ///   To create a larger, encompassing rectangle, specify a negative value.
     pub fn inset_by(&self, dx: f32, dy: f32) -> Self {
         let origin = Point::new(self.origin.x + dx, self.origin.y + dy);
         let size = Size::new(
@@ -195,6 +201,14 @@ impl Rect {
         point.x >= min_x && point.x <= max_x && point.y >= min_y && point.y <= max_y
     }
 
+    /// Returns whether two rectangles overlap. Rectangles which only share an edge don't overlap.
+    pub fn intersects(&self, other: &Rect) -> bool {
+        self.min_x() < other.max_x()
+            && other.min_x() < self.max_x()
+            && self.min_y() < other.max_y()
+            && other.min_y() < self.max_y()
+    }
+
     /// Returns `true` if a line `a` to `b` intersects the rectangle.
     ///
     /// Implementation details
@@ -307,7 +321,7 @@ impl Rect {
         let xn2 = x + p2 * rn2;
         let yn2 = y + p4 * rn2;
 
-        return Some((Point::new(xn1, yn1), Point::new(xn2, yn2)));
+        Some((Point::new(xn1, yn1), Point::new(xn2, yn2)))
     }
 }
 
@@ -323,7 +337,7 @@ impl Path {
     /// Build a new `Path`.
     ///
     /// - `start_point` - You must supply the start point. A `Path` must contain at least
-    ///                   one `MoveTo` command.
+    ///   one `MoveTo` command.
     pub fn new(start_point: Point) -> Self {
         Self {
             commands: vec![PathCommand::MoveTo(start_point)],
@@ -347,7 +361,7 @@ impl Path {
     }
 
     pub fn start_point(&self) -> &Point {
-        let Some(PathCommand::MoveTo(pt)) = self.commands.get(0) else {
+        let Some(PathCommand::MoveTo(pt)) = self.commands.first() else {
             panic!("A `Path` must contain at least one `MoveTo` command.")
         };
 
@@ -384,7 +398,7 @@ mod tests {
         let pt1 = Point::new(-1.0, -1.0);
         let pt2 = Point::new(1.0, 1.0);
 
-        assert_eq!(pt1.distance(&pt2), 2.8284271247461903);
+        assert_eq!(pt1.distance(&pt2), 2.828_427);
         assert_eq!(pt1.distance(&pt2), pt2.distance(&pt1));
 
         let pt1 = Point::zero();
@@ -443,6 +457,16 @@ mod tests {
         assert!(r.contains_point(&p));
     }
 
+    #[test]
+    fn rect_intersects() {
+        let r = Rect::new(Point::new(10.0, 20.0), Size::new(50.0, 50.0));
+
+        assert!(r.intersects(&r));
+        assert!(r.intersects(&Rect::new(Point::new(50.0, 60.0), Size::new(50.0, 50.0))));
+        assert!(!r.intersects(&Rect::new(Point::new(60.0, 20.0), Size::new(10.0, 10.0))));
+        assert!(!r.intersects(&Rect::new(Point::new(0.0, 0.0), Size::new(5.0, 100.0))));
+    }
+
     #[test]
     fn rect_intersects_line() {
         let r = Rect::new(Point::new(15.0, 5.0), Size::new(30.0, 30.0));
@@ -0,0 +1,537 @@
+//! Routing grid
+//!
+//! A grid graph is a lattice of nodes placed at every `cell_size` in the world coordination.
+//! Each node is connected with its four neighbors. Nodes under obstacles (e.g. records) are
+//! removed so that routes on the grid never cross them.
+//!
+//! ```svgbob
+//!  o---o---o---o---o
+//!  |   |   |   |   |
+//!  o---o---o---o---o
+//!  |   | +-------+ |
+//!  o---o-|       |-o
+//!  |   | +-------+ |
+//!  o---o---o---o---o
+//! ```
+//!
+//! Routes are searched on the grid with costs of bends and congestion. Each grid edge has a
+//! capacity of routes, and routes over it cost more, so that later routes take other grid lines.
+use crate::geometry::{Point, Rect, Size};
+use derive_more::Display;
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+};
+
+/// A position of a node in a grid graph (column, row). It can be out of a grid graph.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Display)]
+#[display(fmt = "[{}, {}]", x, y)]
+pub struct GridPoint {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl GridPoint {
+    pub const fn new(x: i32, y: i32) -> Self {
+        Self { x, y }
+    }
+}
+
+/// How `CoordMap::to_grid` maps a world coordinate between grid lines.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Rounding {
+    /// The nearest grid line. Ties are rounded toward positive infinity.
+    #[default]
+    Nearest,
+    /// The grid line at or before the coordinate.
+    Floor,
+    /// The grid line at or after the coordinate.
+    Ceil,
+}
+
+/// Maps between world coordinates and grid points.
+///
+/// `GridPoint(0, 0)` is at `origin`, and grid lines are placed at every `cell_size`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CoordMap {
+    pub origin: Point,
+    pub cell_size: f32,
+    pub rounding: Rounding,
+}
+
+impl CoordMap {
+    pub fn new(origin: Point, cell_size: f32) -> Self {
+        assert!(cell_size > 0.0, "cell_size must be positive");
+
+        Self {
+            origin,
+            cell_size,
+            rounding: Rounding::default(),
+        }
+    }
+
+    pub fn with_rounding(self, rounding: Rounding) -> Self {
+        Self { rounding, ..self }
+    }
+
+    pub fn to_grid(&self, point: &Point) -> GridPoint {
+        let round = |v: f32| match self.rounding {
+            Rounding::Nearest => (v + 0.5).floor(),
+            Rounding::Floor => v.floor(),
+            Rounding::Ceil => v.ceil(),
+        };
+
+        GridPoint::new(
+            round((point.x - self.origin.x) / self.cell_size) as i32,
+            round((point.y - self.origin.y) / self.cell_size) as i32,
+        )
+    }
+
+    pub fn to_world(&self, p: GridPoint) -> Point {
+        Point::new(
+            self.origin.x + self.cell_size * p.x as f32,
+            self.origin.y + self.cell_size * p.y as f32,
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GridGraph {
+    coord_map: CoordMap,
+    n_columns: usize,
+    n_rows: usize,
+    /// `false` if a node is removed.
+    nodes: Vec<bool>,
+    /// The number of routes which can take a grid edge without the congestion cost.
+    capacity: u32,
+    /// The number of routes taken by each grid edge. Keys are ordered pairs of nodes.
+    usage: HashMap<(GridPoint, GridPoint), u32>,
+}
+
+/// A search state of `GridGraph::route`: a node and the direction it was entered in.
+type RouteState = (GridPoint, Option<(i32, i32)>);
+
+impl GridGraph {
+    /// The cost of a turn, in steps.
+    pub const BEND_COST: u32 = 2;
+    /// The cost of each route over the capacity of a grid edge, in steps.
+    pub const OVERFLOW_COST: u32 = 20;
+
+    /// Creates a grid graph which has `n_columns` x `n_rows` nodes. No nodes are removed.
+    pub fn new(coord_map: CoordMap, n_columns: usize, n_rows: usize) -> Self {
+        Self {
+            coord_map,
+            n_columns,
+            n_rows,
+            nodes: vec![true; n_columns * n_rows],
+            capacity: 1,
+            usage: HashMap::new(),
+        }
+    }
+
+    pub fn coord_map(&self) -> &CoordMap {
+        &self.coord_map
+    }
+
+    pub fn n_columns(&self) -> usize {
+        self.n_columns
+    }
+
+    pub fn n_rows(&self) -> usize {
+        self.n_rows
+    }
+
+    /// Returns `true` if `p` is in the grid and not removed.
+    pub fn contains(&self, p: GridPoint) -> bool {
+        self.index(p).is_some_and(|i| self.nodes[i])
+    }
+
+    pub fn remove_node(&mut self, p: GridPoint) {
+        if let Some(i) = self.index(p) {
+            self.nodes[i] = false;
+        }
+    }
+
+    /// Returns the nodes which are not removed.
+    pub fn nodes(&self) -> impl Iterator<Item = GridPoint> + '_ {
+        (0..self.n_rows)
+            .flat_map(move |y| (0..self.n_columns).map(move |x| GridPoint::new(x as i32, y as i32)))
+            .filter(|p| self.contains(*p))
+    }
+
+    /// Returns the neighbors (up, right, down, left) of `p` which are not removed.
+    pub fn neighbors(&self, p: GridPoint) -> impl Iterator<Item = GridPoint> + '_ {
+        let candidates = [
+            GridPoint::new(p.x, p.y - 1),
+            GridPoint::new(p.x + 1, p.y),
+            GridPoint::new(p.x, p.y + 1),
+            GridPoint::new(p.x - 1, p.y),
+        ];
+
+        candidates
+            .into_iter()
+            .filter(move |n| self.contains(p) && self.contains(*n))
+    }
+
+    /// Returns the world coordinate of `p`.
+    pub fn to_world(&self, p: GridPoint) -> Point {
+        self.coord_map.to_world(p)
+    }
+
+    /// Returns the grid point for `point`, or `None` if it's out of the grid.
+    pub fn to_grid(&self, point: &Point) -> Option<GridPoint> {
+        let p = self.coord_map.to_grid(point);
+
+        self.index(p).map(|_| p)
+    }
+
+    pub fn capacity(&self) -> u32 {
+        self.capacity
+    }
+
+    pub fn set_capacity(&mut self, capacity: u32) {
+        self.capacity = capacity;
+    }
+
+    /// Returns the number of routes which take the grid edge between neighbors `a` and `b`.
+    pub fn usage(&self, a: GridPoint, b: GridPoint) -> u32 {
+        self.usage.get(&Self::edge_key(a, b)).copied().unwrap_or(0)
+    }
+
+    /// Adds a route of neighboring nodes to the usage of grid edges.
+    pub fn take_route(&mut self, route: &[GridPoint]) {
+        for w in route.windows(2) {
+            *self.usage.entry(Self::edge_key(w[0], w[1])).or_default() += 1;
+        }
+    }
+
+    /// Finds the cheapest route from any of `sources` (with initial costs) to any of `targets`.
+    /// Returns the cost and nodes of the route.
+    ///
+    /// A step to a neighbor costs `1`, a turn `BEND_COST`, and a step over a grid edge at its
+    /// capacity `OVERFLOW_COST` for each route over it. Ties are broken by the order of
+    /// `sources`.
+    pub fn route(
+        &self,
+        sources: &[(GridPoint, u32)],
+        targets: &[GridPoint],
+    ) -> Option<(u32, Vec<GridPoint>)> {
+        let mut costs: HashMap<RouteState, u32> = HashMap::new();
+        let mut previous: HashMap<RouteState, RouteState> = HashMap::new();
+        let mut heap = BinaryHeap::new();
+
+        for (p, cost) in sources.iter().filter(|(p, _)| self.contains(*p)) {
+            let state = (*p, None);
+
+            if costs.get(&state).is_none_or(|c| cost < c) {
+                costs.insert(state, *cost);
+                heap.push(Reverse((*cost, heap.len(), state)));
+            }
+        }
+
+        let mut order = heap.len();
+
+        while let Some(Reverse((cost, _, state))) = heap.pop() {
+            if costs.get(&state).is_some_and(|c| *c < cost) {
+                continue;
+            }
+
+            let (p, direction) = state;
+
+            if targets.contains(&p) {
+                let mut route = vec![p];
+                let mut state = state;
+
+                while let Some(prev) = previous.get(&state) {
+                    route.push(prev.0);
+                    state = *prev;
+                }
+                route.reverse();
+                return Some((cost, route));
+            }
+
+            for n in self.neighbors(p) {
+                let d = (n.x - p.x, n.y - p.y);
+                let bend = if direction.is_some_and(|x| x != d) {
+                    Self::BEND_COST
+                } else {
+                    0
+                };
+                let overflow = (self.usage(p, n) + 1).saturating_sub(self.capacity);
+                let next = (n, Some(d));
+                let next_cost = cost + 1 + bend + overflow * Self::OVERFLOW_COST;
+
+                if costs.get(&next).is_none_or(|c| next_cost < *c) {
+                    costs.insert(next, next_cost);
+                    previous.insert(next, state);
+                    heap.push(Reverse((next_cost, order, next)));
+                    order += 1;
+                }
+            }
+        }
+
+        None
+    }
+
+    fn edge_key(a: GridPoint, b: GridPoint) -> (GridPoint, GridPoint) {
+        if a < b {
+            (a, b)
+        } else {
+            (b, a)
+        }
+    }
+
+    fn index(&self, p: GridPoint) -> Option<usize> {
+        let x = usize::try_from(p.x).ok().filter(|x| *x < self.n_columns)?;
+        let y = usize::try_from(p.y).ok().filter(|y| *y < self.n_rows)?;
+
+        Some(y * self.n_columns + x)
+    }
+}
+
+/// Builds a grid graph which covers obstacle rectangles and removes nodes under them.
+#[derive(Debug, Clone)]
+pub struct GridGraphBuilder {
+    cell_size: f32,
+    /// The number of cells around obstacles.
+    margin: usize,
+    blocked_rects: Vec<Rect>,
+}
+
+impl GridGraphBuilder {
+    /// The default number of cells around obstacles.
+    const MARGIN: usize = 1;
+
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size,
+            margin: Self::MARGIN,
+            blocked_rects: vec![],
+        }
+    }
+
+    /// Constructs a grid graph with nodes removed under `rects` in one call.
+    pub fn with_blocked_rects(rects: &[Rect], cell_size: f32) -> GridGraph {
+        let mut builder = Self::new(cell_size);
+
+        builder.blocked_rects(rects);
+        builder.build()
+    }
+
+    pub fn margin(&mut self, margin: usize) -> &mut Self {
+        self.margin = margin;
+        self
+    }
+
+    pub fn blocked_rect(&mut self, rect: Rect) -> &mut Self {
+        self.blocked_rects.push(rect);
+        self
+    }
+
+    pub fn blocked_rects(&mut self, rects: &[Rect]) -> &mut Self {
+        self.blocked_rects.extend_from_slice(rects);
+        self
+    }
+
+    /// Builds a grid graph. The grid is aligned to multiples of `cell_size` and covers all
+    /// obstacles with `margin`.
+    ///
+    /// A node is removed only if it's strictly inside an obstacle, so routes can run along the
+    /// border of obstacles.
+    pub fn build(&self) -> GridGraph {
+        let Some(bounds) = self.bounds() else {
+            return GridGraph::new(CoordMap::new(Point::zero(), self.cell_size), 0, 0);
+        };
+
+        let margin = self.cell_size * self.margin as f32;
+        let min_x = ((bounds.min_x() - margin) / self.cell_size).floor() * self.cell_size;
+        let min_y = ((bounds.min_y() - margin) / self.cell_size).floor() * self.cell_size;
+        let max_x = ((bounds.max_x() + margin) / self.cell_size).ceil() * self.cell_size;
+        let max_y = ((bounds.max_y() + margin) / self.cell_size).ceil() * self.cell_size;
+
+        let n_columns = ((max_x - min_x) / self.cell_size).round() as usize + 1;
+        let n_rows = ((max_y - min_y) / self.cell_size).round() as usize + 1;
+        let coord_map = CoordMap::new(Point::new(min_x, min_y), self.cell_size);
+        let mut grid = GridGraph::new(coord_map, n_columns, n_rows);
+
+        for rect in self.blocked_rects.iter() {
+            // Only grid points around the obstacle can be inside it.
+            let from = coord_map
+                .with_rounding(Rounding::Floor)
+                .to_grid(rect.origin());
+            let to = coord_map
+                .with_rounding(Rounding::Ceil)
+                .to_grid(&Point::new(rect.max_x(), rect.max_y()));
+
+            for y in from.y..=to.y {
+                for x in from.x..=to.x {
+                    let p = GridPoint::new(x, y);
+                    let world = grid.to_world(p);
+
+                    if world.x > rect.min_x()
+                        && world.x < rect.max_x()
+                        && world.y > rect.min_y()
+                        && world.y < rect.max_y()
+                    {
+                        grid.remove_node(p);
+                    }
+                }
+            }
+        }
+
+        grid
+    }
+
+    /// The union of all obstacles.
+    fn bounds(&self) -> Option<Rect> {
+        let first = self.blocked_rects.first()?;
+        let (min_x, min_y, max_x, max_y) = self.blocked_rects.iter().fold(
+            (first.min_x(), first.min_y(), first.max_x(), first.max_y()),
+            |(min_x, min_y, max_x, max_y), r| {
+                (
+                    min_x.min(r.min_x()),
+                    min_y.min(r.min_y()),
+                    max_x.max(r.max_x()),
+                    max_y.max(r.max_y()),
+                )
+            },
+        );
+
+        Some(Rect::new(
+            Point::new(min_x, min_y),
+            Size::new(max_x - min_x, max_y - min_y),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grid_graph_neighbors() {
+        let mut grid = GridGraph::new(CoordMap::new(Point::zero(), 10.0), 3, 3);
+
+        let mut neighbors: Vec<_> = grid.neighbors(GridPoint::new(1, 1)).collect();
+        neighbors.sort();
+        assert_eq!(
+            neighbors,
+            vec![
+                GridPoint::new(0, 1),
+                GridPoint::new(1, 0),
+                GridPoint::new(1, 2),
+                GridPoint::new(2, 1),
+            ]
+        );
+
+        grid.remove_node(GridPoint::new(1, 0));
+        assert_eq!(grid.neighbors(GridPoint::new(0, 0)).count(), 1);
+        assert_eq!(grid.neighbors(GridPoint::new(1, 0)).count(), 0);
+    }
+
+    #[test]
+    fn with_blocked_rects() {
+        // A 20x20 obstacle at (10, 10)
+        let rect = Rect::new(Point::new(10.0, 10.0), Size::new(20.0, 20.0));
+        let grid = GridGraphBuilder::with_blocked_rects(&[rect], 10.0);
+
+        // One cell margin around the obstacle: 0..=40
+        assert_eq!(grid.coord_map().origin, Point::new(0.0, 0.0));
+        assert_eq!(grid.n_columns(), 5);
+        assert_eq!(grid.n_rows(), 5);
+
+        // Only the center node is strictly inside the obstacle.
+        assert!(!grid.contains(GridPoint::new(2, 2)));
+        assert!(grid.contains(GridPoint::new(1, 1)));
+        assert!(grid.contains(GridPoint::new(3, 2)));
+        assert_eq!(grid.nodes().count(), 24);
+
+        assert_eq!(
+            grid.to_grid(&Point::new(31.0, 19.0)),
+            Some(GridPoint::new(3, 2))
+        );
+        assert_eq!(grid.to_world(GridPoint::new(3, 2)), Point::new(30.0, 20.0));
+        assert_eq!(grid.to_grid(&Point::new(-10.0, 0.0)), None);
+    }
+
+    #[test]
+    fn with_no_blocked_rects() {
+        let grid = GridGraphBuilder::with_blocked_rects(&[], 10.0);
+
+        assert_eq!(grid.nodes().count(), 0);
+    }
+
+    #[test]
+    fn route_around_obstacle() {
+        // A 20x20 obstacle at (10, 10) on a 0..=40 grid.
+        let rect = Rect::new(Point::new(10.0, 10.0), Size::new(20.0, 20.0));
+        let grid = GridGraphBuilder::with_blocked_rects(&[rect], 10.0);
+        let (cost, route) = grid
+            .route(&[(GridPoint::new(1, 2), 0)], &[GridPoint::new(3, 2)])
+            .unwrap();
+
+        // 4 steps and 2 bends around the center node.
+        assert_eq!(cost, 4 + GridGraph::BEND_COST * 2);
+        assert_eq!(route.len(), 5);
+        assert!(!route.contains(&GridPoint::new(2, 2)));
+        assert!(grid
+            .route(&[(GridPoint::new(2, 2), 0)], &[GridPoint::new(3, 2)])
+            .is_none());
+    }
+
+    #[test]
+    fn route_congestion() {
+        let mut grid = GridGraph::new(CoordMap::new(Point::zero(), 10.0), 5, 3);
+        let sources = [(GridPoint::new(0, 1), 0), (GridPoint::new(0, 0), 1)];
+        let targets = [GridPoint::new(4, 1), GridPoint::new(4, 0)];
+
+        let (_, first) = grid.route(&sources, &targets).unwrap();
+        assert_eq!(first.first(), Some(&GridPoint::new(0, 1)));
+        assert!(first.iter().all(|p| p.y == 1));
+
+        // The second route avoids the grid edges of the first one.
+        grid.take_route(&first);
+        assert_eq!(grid.usage(GridPoint::new(2, 1), GridPoint::new(1, 1)), 1);
+        let (cost, second) = grid.route(&sources, &targets).unwrap();
+        assert_eq!(cost, 5);
+        assert!(second.iter().all(|p| p.y == 0));
+
+        grid.set_capacity(2);
+        let (cost, _) = grid.route(&sources, &targets).unwrap();
+        assert_eq!(cost, 4);
+    }
+
+    #[test]
+    fn coord_map() {
+        let map = CoordMap::new(Point::new(5.0, -5.0), 10.0);
+
+        assert_eq!(map.to_grid(&Point::new(5.0, -5.0)), GridPoint::new(0, 0));
+        assert_eq!(map.to_grid(&Point::new(14.0, 6.0)), GridPoint::new(1, 1));
+        assert_eq!(
+            map.to_grid(&Point::new(-6.0, -16.0)),
+            GridPoint::new(-1, -1)
+        );
+        assert_eq!(map.to_world(GridPoint::new(2, -1)), Point::new(25.0, -15.0));
+
+        // Ties
+        assert_eq!(map.to_grid(&Point::new(10.0, -10.0)), GridPoint::new(1, 0));
+
+        let p = Point::new(14.0, 6.0);
+        assert_eq!(
+            map.with_rounding(Rounding::Floor).to_grid(&p),
+            GridPoint::new(0, 1)
+        );
+        assert_eq!(
+            map.with_rounding(Rounding::Ceil).to_grid(&p),
+            GridPoint::new(1, 2)
+        );
+
+        // Round trip
+        for x in -3..3 {
+            for y in -3..3 {
+                let p = GridPoint::new(x, y);
+                assert_eq!(map.to_grid(&map.to_world(p)), p);
+            }
+        }
+    }
+}
@@ -17,37 +17,98 @@
 //!  +---------+---------+
 //! ```
 use crate::{
+    font::{self, FontMetrics, TextMeasure},
     geometry::{Orientation, Point, Rect, Size},
+    grid::{GridGraph, GridGraphBuilder, GridPoint, Rounding},
+    metrics,
     mir::{self, ShapeKind, TerminalPort, TerminalPortId},
 };
-use derive_more::Add;
+use derive_more::{Add, Display};
 use petgraph::algo;
 use petgraph::{
-    prelude::{EdgeIndex, NodeIndex, UnGraph},
+    prelude::{DiGraph, EdgeIndex, NodeIndex, UnGraph},
     visit::EdgeRef,
 };
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 use std::{
-    collections::{HashMap, VecDeque},
+    cell::RefCell,
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap, HashSet},
     fmt,
     hash::Hash,
+    str::FromStr,
 };
 
+/// Stages of `LayoutEngine::layout` in order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display)]
+pub enum LayoutStage {
+    #[display(fmt = "place-nodes")]
+    PlaceNodes,
+    #[display(fmt = "place-terminal-ports")]
+    PlaceTerminalPorts,
+    #[display(fmt = "draw-edge-paths")]
+    DrawEdgePaths,
+    #[display(fmt = "place-edge-labels")]
+    PlaceEdgeLabels,
+}
+
+/// The outcome of `LayoutEngine::layout`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LayoutResult {
+    /// The view box computed when nodes are placed.
+    pub view_box: Option<Rect>,
+    /// Input which couldn't be laid out as given, found while laying out (e.g.
+    /// `LayoutWarning::CellOutOfRange`). See also `validate_layout`.
+    pub warnings: Vec<LayoutWarning>,
+}
+
+/// Called after each stage of `LayoutEngine::layout_with_hooks`, e.g. to adjust nodes before
+/// their terminal ports are placed.
+pub trait LayoutHooks {
+    fn after_stage(&mut self, stage: LayoutStage, doc: &mut mir::Document);
+}
+
+impl<F: FnMut(LayoutStage, &mut mir::Document)> LayoutHooks for F {
+    fn after_stage(&mut self, stage: LayoutStage, doc: &mut mir::Document) {
+        self(stage, doc)
+    }
+}
+
+/// Drivers call `layout`, which runs the stages of the engine in order (see `LayoutStage`).
+/// Stages aren't exposed, so that drivers can't call them out of order or miss one. Hooks see
+/// the document after each stage.
 pub trait LayoutEngine {
-    /// Place all nodes on 2D coordination.
-    ///
-    /// The engine must assign `origin` and `size` of all nodes.
-    /// Returns computed view box.
-    fn place_nodes(&mut self, doc: &mut mir::Document) -> Option<Rect>;
+    /// Lays out `doc`: places nodes, then their terminal ports, then draws paths of edges, and
+    /// places their labels.
+    fn layout(&mut self, doc: &mut mir::Document) -> LayoutResult {
+        self.layout_with_hooks(doc, &mut |_: LayoutStage, _: &mut mir::Document| {})
+    }
 
-    /// Place all terminal ports for every node.
-    ///
-    /// The engine must add all possible terminal ports to `terminal_ports` of nodes.
-    fn place_terminal_ports(&mut self, doc: &mut mir::Document);
+    /// `layout`, but calls `hooks` after each stage.
+    fn layout_with_hooks(
+        &mut self,
+        doc: &mut mir::Document,
+        hooks: &mut dyn LayoutHooks,
+    ) -> LayoutResult;
 
-    /// Draw path between both ends (terminal ports) of each edge.
+    /// Moves a node of `doc`, which was laid out by this engine, to `origin` (e.g. a record
+    /// dragged in an editor). Its terminal ports move with it, and only the edges which it
+    /// affects are routed again. Returns the view box of the moved layout.
     ///
-    /// The engine must build a `path` of edges.
-    fn draw_edge_path(&mut self, doc: &mut mir::Document);
+    /// By default, the node is placed at `origin` as its `preferred_origin`, and `doc` is laid
+    /// out again.
+    fn move_node(
+        &mut self,
+        doc: &mut mir::Document,
+        node_id: mir::NodeId,
+        origin: Point,
+    ) -> LayoutResult {
+        if let Some(node) = doc.get_node_mut(node_id) {
+            node.preferred_origin = Some(origin);
+        }
+        self.layout(doc)
+    }
 }
 
 type _RouteGraph = UnGraph<RouteNodeData, RouteEdgeData>;
@@ -55,10 +116,62 @@ type _RouteGraph = UnGraph<RouteNodeData, RouteEdgeData>;
 /// Represents routes in a place by graph. Every junction of two edges will be a node of the graph.
 /// Neighboring junctions are connected by edges. Each nodes neighbors four other nodes and each
 /// edge is NOT directed so shared by two junctions.
+///
+/// Nodes and edges are never removed, so `RouteNodeId` and `RouteEdgeId` are indices in insertion
+/// order. They are preserved through serialization.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct RouteGraph {
     graph: _RouteGraph,
+    #[cfg_attr(feature = "serde", serde(with = "terminal_port_entries"))]
     terminal_ports: HashMap<TerminalPortId, RouteNodeId>,
+    /// Nodes by `location_key`, which is rebuilt when it's behind the graph (e.g. after
+    /// deserialization).
+    #[cfg_attr(feature = "serde", serde(skip))]
+    locations: HashMap<(u32, u32), RouteNodeId>,
+    /// Shortest paths between two nodes around obstacles, which are dropped when nodes or
+    /// edges are added. Taking lanes doesn't change costs of segments, so it keeps paths.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    shortest_paths: RefCell<HashMap<ShortestPathKey, ShortestPath>>,
+}
+
+/// The start and end nodes, bits of obstacle rects, and bits of `LayoutOptions::bend_cost`.
+type ShortestPathKey = (RouteNodeId, RouteNodeId, Vec<[u32; 4]>, u32);
+
+/// The cost and nodes of a shortest path, or `None` if the end node is unreachable.
+type ShortestPath = Option<(RouteCost, Vec<RouteNodeId>)>;
+
+/// Serializes the terminal-port map as a list of entries sorted by port, because JSON object keys
+/// must be strings.
+#[cfg(feature = "serde")]
+mod terminal_port_entries {
+    use super::{RouteNodeId, TerminalPortId};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::collections::HashMap;
+
+    pub fn serialize<S: Serializer>(
+        map: &HashMap<TerminalPortId, RouteNodeId>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let mut entries: Vec<_> = map.iter().collect();
+
+        entries.sort();
+        entries.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<HashMap<TerminalPortId, RouteNodeId>, D::Error> {
+        let entries: Vec<(TerminalPortId, RouteNodeId)> = Vec::deserialize(deserializer)?;
+
+        Ok(entries.into_iter().collect())
+    }
+}
+
+impl Default for RouteGraph {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl RouteGraph {
@@ -68,15 +181,38 @@ impl RouteGraph {
         Self {
             graph,
             terminal_ports: HashMap::new(),
+            locations: HashMap::new(),
+            shortest_paths: RefCell::default(),
         }
     }
 
+    /// Returns a key of `location` which is equal for equal points (`-0.0` is `0.0`).
+    fn location_key(location: &Point) -> (u32, u32) {
+        ((location.x + 0.0).to_bits(), (location.y + 0.0).to_bits())
+    }
+
+    /// Returns nodes on each vertical line by the bits of `x`, and on each horizontal line by
+    /// the bits of `y`, in insertion order.
+    fn nodes_on_lines(&self) -> (HashMap<u32, Vec<RouteNodeId>>, HashMap<u32, Vec<RouteNodeId>>) {
+        let mut columns: HashMap<u32, Vec<RouteNodeId>> = HashMap::new();
+        let mut rows: HashMap<u32, Vec<RouteNodeId>> = HashMap::new();
+
+        for node_id in self.node_ids() {
+            let (x, y) = Self::location_key(self.get_node(node_id).unwrap().location());
+
+            columns.entry(x).or_default().push(node_id);
+            rows.entry(y).or_default().push(node_id);
+        }
+
+        (columns, rows)
+    }
+
     pub fn nodes(&self) -> impl Iterator<Item = &RouteNodeData> {
         self.graph.node_weights()
     }
 
     pub fn node_ids(&self) -> impl ExactSizeIterator<Item = RouteNodeId> {
-        self.graph.node_indices().map(|i| RouteNodeId(i))
+        self.graph.node_indices().map(RouteNodeId)
     }
 
     pub fn get_node(&self, id: RouteNodeId) -> Option<&RouteNodeData> {
@@ -93,8 +229,8 @@ impl RouteGraph {
 
     pub fn add_terminal_port(&mut self, terminal_port: &TerminalPort) -> RouteNodeId {
         let node_index = self._add_node(
-            terminal_port.location().clone(),
-            Some(terminal_port.orientation().clone()),
+            *terminal_port.location(),
+            Some(terminal_port.orientation()),
         );
 
         self.terminal_ports.insert(terminal_port.id(), node_index);
@@ -102,11 +238,18 @@ impl RouteGraph {
     }
 
     fn _add_node(&mut self, location: Point, orientation: Option<Orientation>) -> RouteNodeId {
+        if self.locations.len() != self.graph.node_count() {
+            self.locations = self
+                .node_ids()
+                .map(|id| (Self::location_key(self.get_node(id).unwrap().location()), id))
+                .collect();
+        }
+
+        let key = Self::location_key(&location);
         let node_index = if let Some((node_index, node)) = self
-            .graph
-            .node_indices()
-            .flat_map(|i| self.graph.node_weight(i).map(|w| (i, w)))
-            .find(|(_, w)| *w.location() == location)
+            .locations
+            .get(&key)
+            .and_then(|id| self.graph.node_weight(id.0).map(|w| (id.0, w)))
         {
             if node.orientation() != orientation {
                 panic!(
@@ -121,7 +264,11 @@ impl RouteGraph {
             node_index
         } else {
             let node = RouteNodeData::new(location, orientation);
-            self.graph.add_node(node)
+            let node_index = self.graph.add_node(node);
+
+            self.locations.insert(key, RouteNodeId(node_index));
+            self.shortest_paths.get_mut().clear();
+            node_index
         };
 
         RouteNodeId(node_index)
@@ -141,17 +288,77 @@ impl RouteGraph {
         self.graph.edge_weights_mut()
     }
 
+    /// Returns lanes of the segment between neighboring junctions `a` and `b` which are taken by
+    /// routes of edges.
+    pub fn lanes(&self, a: RouteNodeId, b: RouteNodeId) -> &[usize] {
+        self.graph
+            .find_edge(a.0, b.0)
+            .and_then(|edge_index| self.graph.edge_weight(edge_index))
+            .map_or(&[], |edge| &edge.lanes)
+    }
+
+    /// Marks `lane` of the segment between neighboring junctions `a` and `b` as taken.
+    pub fn take_lane(&mut self, a: RouteNodeId, b: RouteNodeId, lane: usize) {
+        let Some(edge_index) = self.graph.find_edge(a.0, b.0) else { return };
+        let Some(edge) = self.graph.edge_weight_mut(edge_index) else { return };
+
+        if !edge.lanes.contains(&lane) {
+            edge.lanes.push(lane);
+        }
+    }
+
+    /// Releases lanes of all segments, before routes are computed again.
+    pub fn clear_lanes(&mut self) {
+        for edge in self.graph.edge_weights_mut() {
+            edge.lanes.clear();
+        }
+    }
+
+    /// Marks `lane` of all segments on the straight line from `start` to `end` as taken, however
+    /// the line is split by junctions.
+    pub fn take_lane_along(&mut self, start: Point, end: Point, lane: usize) {
+        let (min_x, max_x) = (start.x.min(end.x), start.x.max(end.x));
+        let (min_y, max_y) = (start.y.min(end.y), start.y.max(end.y));
+        let on_line = |p: &Point| {
+            if start.y == end.y {
+                p.y == start.y && (min_x..=max_x).contains(&p.x)
+            } else {
+                p.x == start.x && (min_y..=max_y).contains(&p.y)
+            }
+        };
+        let segments: Vec<EdgeIndex> = self
+            .graph
+            .edge_references()
+            .filter(|edge| {
+                [edge.source(), edge.target()]
+                    .iter()
+                    .all(|index| on_line(self.graph[*index].location()))
+            })
+            .map(|edge| edge.id())
+            .collect();
+
+        for edge_index in segments {
+            let edge = &mut self.graph[edge_index];
+
+            if !edge.lanes.contains(&lane) {
+                edge.lanes.push(lane);
+            }
+        }
+    }
+
     pub fn add_edge(&mut self, a: RouteNodeId, b: RouteNodeId) {
         for (from, to) in [(a, b)] {
             if !self.graph.edges(from.0).any(|e| e.target() == to.0) {
                 self.graph
                     .add_edge(from.0, to.0, RouteEdgeData::new(from, to));
+                self.shortest_paths.get_mut().clear();
             }
         }
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct RouteNodeId(NodeIndex);
 
 impl fmt::Display for RouteNodeId {
@@ -161,6 +368,7 @@ impl fmt::Display for RouteNodeId {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct RouteEdgeId(EdgeIndex);
 
 impl fmt::Display for RouteEdgeId {
@@ -170,6 +378,7 @@ impl fmt::Display for RouteEdgeId {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct RouteNodeData {
     location: Point,
 
@@ -204,9 +413,15 @@ impl RouteNodeData {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct RouteEdgeData {
     source_id: RouteNodeId,
     target_id: RouteNodeId,
+
+    /// Lanes taken by routes which pass along this segment. Lane `0` is the segment itself, and
+    /// the others are offset to its sides. See `SimpleLayoutEngine::separate_edges`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    lanes: Vec<usize>,
 }
 
 impl RouteEdgeData {
@@ -214,6 +429,7 @@ impl RouteEdgeData {
         Self {
             source_id,
             target_id,
+            lanes: vec![],
         }
     }
 
@@ -224,6 +440,65 @@ impl RouteEdgeData {
     pub fn target_id(&self) -> RouteNodeId {
         self.target_id
     }
+
+    pub fn lanes(&self) -> &[usize] {
+        &self.lanes
+    }
+}
+
+/// How the route of an edge was chosen. See `SimpleLayoutEngine::explain_edge`.
+#[derive(Debug, Clone, Default)]
+pub struct RouteExplanation {
+    /// Pairs of terminal ports considered, in search order.
+    pub candidates: Vec<RouteCandidate>,
+    /// The index of the selected candidate. Ties of costs are broken by
+    /// `SimpleLayoutEngine::port_preference` of the source and then of the target.
+    pub selected: Option<usize>,
+    /// Junctions on the selected route, from the source to the target.
+    pub path: Vec<RouteNodeId>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RouteCandidate {
+    pub source: TerminalPort,
+    pub target: TerminalPort,
+    /// The length of the shortest route between the terminal ports, plus penalties of its bends
+    /// and port sides (see `LayoutOptions::bend_cost` and `LayoutOptions::port_side_cost`).
+    pub cost: u32,
+}
+
+/// Routes of edges kept across layouts by `SimpleLayoutEngine::route_cache`, e.g. by an engine
+/// which re-renders a document on each edit.
+///
+/// A route is keyed by the terminal ports of both ends of its edge, and stays valid while the
+/// shapes (records, groups and keep-out regions) around it are unchanged: its region is the
+/// bounding box of the route and both ends, extended by `LayoutOptions::record_space`. Edits
+/// elsewhere in the diagram don't invalidate it.
+#[derive(Debug, Clone, Default)]
+pub struct RouteCache {
+    routes: HashMap<u64, CachedRoute>,
+    hits: usize,
+    misses: usize,
+}
+
+#[derive(Debug, Clone)]
+struct CachedRoute {
+    region: Rect,
+    /// A hash of the shapes which intersect `region`.
+    shapes_hash: u64,
+    path: Vec<Point>,
+}
+
+impl RouteCache {
+    /// The number of routes reused by the last layout.
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+
+    /// The number of routes searched by the last layout.
+    pub fn misses(&self) -> usize {
+        self.misses
+    }
 }
 
 // Used for computing shortest path
@@ -234,152 +509,1791 @@ impl RouteCost {
     pub const MAX: Self = Self(u32::MAX);
 }
 
+/// Metrics of the grid layout. Smaller values make denser diagrams.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayoutOptions {
+    /// The space between the diagram and the edges of the view box. The body is placed at
+    /// `(margin, margin)`. Edges may be routed in the margin, so margins smaller than
+    /// `shape_junction_margin` can clip them.
+    pub margin: f32,
+    /// The height of a field row.
+    pub line_height: f32,
+    /// The (minimum) width of records.
+    pub record_width: f32,
+    /// The space between records.
+    pub record_space: f32,
+    /// The distance from records at which edges are routed around them. It should be smaller
+    /// than `record_space`, so that edges can pass between records.
+    pub shape_junction_margin: f32,
+    /// The distance between parallel routes of edges which share segments. See
+    /// `SimpleLayoutEngine::separate_edges`.
+    pub lane_spacing: f32,
+    /// Grid cells which are intentionally left empty, e.g. to separate groups of records.
+    /// Records only take them by their `preferred_cell`.
+    pub blank_cells: Vec<mir::GridCell>,
+    /// Regions which edges are routed around like records, e.g. where a legend or a note is
+    /// drawn over the diagram. Edge labels aren't placed on them either.
+    pub keep_out_regions: Vec<Rect>,
+    /// The penalty of each bend of a route, in the unit of route lengths. The route search
+    /// tracks the direction of routes, so routes with fewer bends win over slightly shorter
+    /// ones, both between junctions and between terminal ports.
+    pub bend_cost: f32,
+    /// The penalty of each terminal port of a route which faces away from the other end, or
+    /// isn't on the preferred sides (left or right, top or bottom in the vertical orientation).
+    pub port_side_cost: f32,
+}
+
+impl LayoutOptions {
+    pub const DEFAULT_MARGIN: f32 = 50.0;
+    pub const DEFAULT_LINE_HEIGHT: f32 = 35.0;
+    pub const DEFAULT_RECORD_WIDTH: f32 = 300.0;
+    pub const DEFAULT_RECORD_SPACE: f32 = 80.0;
+    pub const DEFAULT_LANE_SPACING: f32 = 8.0;
+}
+
+impl Default for LayoutOptions {
+    fn default() -> Self {
+        Self {
+            margin: Self::DEFAULT_MARGIN,
+            line_height: Self::DEFAULT_LINE_HEIGHT,
+            record_width: Self::DEFAULT_RECORD_WIDTH,
+            record_space: Self::DEFAULT_RECORD_SPACE,
+            shape_junction_margin: Self::DEFAULT_RECORD_SPACE / 2.0,
+            lane_spacing: Self::DEFAULT_LANE_SPACING,
+            blank_cells: vec![],
+            keep_out_regions: vec![],
+            bend_cost: 0.0,
+            port_side_cost: 0.0,
+        }
+    }
+}
+
+/// How routes of edges are searched.
+#[derive(Debug, Clone, Copy, Default, Hash, PartialEq, Eq, Display)]
+pub enum EdgeRouter {
+    /// On junctions around records, which are connected to their nearest neighbors.
+    #[default]
+    #[display(fmt = "junctions")]
+    Junctions,
+    /// On a grid graph of `LayoutOptions::lane_spacing` cells, where routes avoid grid lines
+    /// taken by earlier routes (see `GridGraph::route`).
+    #[display(fmt = "grid")]
+    Grid,
+}
+
+impl EdgeRouter {
+    pub const ALL: [EdgeRouter; 2] = [EdgeRouter::Junctions, EdgeRouter::Grid];
+}
+
+impl FromStr for EdgeRouter {
+    type Err = String;
+
+    /// Parses `junctions` or `grid`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|router| router.to_string() == s)
+            .ok_or_else(|| format!("unknown edge router `{}`", s))
+    }
+}
+
+/// The direction in which records fill the grid.
+#[derive(Debug, Clone, Copy, Default, Hash, PartialEq, Eq, Display)]
+pub enum LayoutOrientation {
+    /// Records fill rows from left to right, and relations mostly run horizontally.
+    #[default]
+    #[display(fmt = "horizontal")]
+    Horizontal,
+    /// Records fill columns from top to bottom, and relations mostly run vertically.
+    #[display(fmt = "vertical")]
+    Vertical,
+}
+
+impl LayoutOrientation {
+    pub const ALL: [LayoutOrientation; 2] =
+        [LayoutOrientation::Horizontal, LayoutOrientation::Vertical];
+}
+
+impl FromStr for LayoutOrientation {
+    type Err = String;
+
+    /// Parses `horizontal` or `vertical`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|orientation| orientation.to_string() == s)
+            .ok_or_else(|| format!("unknown layout orientation `{}`", s))
+    }
+}
+
+/// Where records without relations are placed.
+#[derive(Debug, Clone, Copy, Default, Hash, PartialEq, Eq, Display)]
+pub enum IsolatedRecords {
+    /// Among the other records.
+    #[default]
+    #[display(fmt = "interleaved")]
+    Interleaved,
+    /// In grid rows (columns with `LayoutOrientation::Vertical`) after the other records.
+    #[display(fmt = "trailing")]
+    Trailing,
+    /// Stacked in a column to the right of the grid.
+    #[display(fmt = "side-column")]
+    SideColumn,
+}
+
+impl IsolatedRecords {
+    pub const ALL: [IsolatedRecords; 3] = [
+        IsolatedRecords::Interleaved,
+        IsolatedRecords::Trailing,
+        IsolatedRecords::SideColumn,
+    ];
+}
+
+impl FromStr for IsolatedRecords {
+    type Err = String;
+
+    /// Parses `interleaved`, `trailing` or `side-column`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|isolated| isolated.to_string() == s)
+            .ok_or_else(|| format!("unknown placement of isolated records `{}`", s))
+    }
+}
+
 #[derive(Debug)]
 pub struct SimpleLayoutEngine {
+    pub options: LayoutOptions,
+    /// Widens records to fit their longest field (measured with `text_measure`). Records in
+    /// the same grid column share the width. Render with
+    /// `FieldColumnRatio::max_badge_width = Some(SimpleLayoutEngine::BADGE_COLUMN_WIDTH)` so
+    /// that subtitles move with the record width.
+    pub fit_record_width: bool,
+    /// Measures texts of fields for `fit_record_width`. The renderer still truncates texts by
+    /// `font::text_width`, so measures shouldn't be narrower than it.
+    pub text_measure: Box<dyn TextMeasure>,
+    /// Chooses the number of grid columns whose layout is closest to this width / height ratio
+    /// (e.g. `SimpleLayoutEngine::DEFAULT_ASPECT_RATIO`), instead of `GRID_N_COLUMNS`. The
+    /// `%columns` directive of a module takes precedence.
+    pub target_aspect_ratio: Option<f32>,
+    /// Removes grid columns (and wraps records into more rows) until the view box is at most
+    /// this wide, e.g. to embed diagrams in fixed-width documents. It takes precedence over
+    /// `target_aspect_ratio` and the `%columns` directive, but a single column may still be
+    /// wider.
+    pub max_width: Option<f32>,
+    /// Moves routes which share segments apart by `LayoutOptions::lane_spacing`, so that edges
+    /// into the same field (e.g. many foreign keys to `users.id`) can be told apart. Routes only
+    /// merge on their first and last straight runs, which lead to terminal ports.
+    pub separate_edges: bool,
+    /// Routes all edges into the same node (e.g. many foreign keys to `users.id`) to the same
+    /// terminal port, so that their last segments are bundled into a trunk, which they leave
+    /// at different junctions. See `SimpleLayoutEngine::trunk_port`. It only applies to
+    /// `EdgeRouter::Junctions`.
+    pub bundle_edges: bool,
+    /// The number of edges which each terminal port takes before edges move to other ports of
+    /// the same node (e.g. from the left to the right side of a field), so that edges don't end
+    /// at the same point. Edges take ports in insertion order. When all ports of a node are
+    /// full, the least taken ones are used. `bundle_edges` takes precedence. It only applies to
+    /// `EdgeRouter::Junctions`.
+    pub port_capacity: Option<usize>,
+    /// The grid router doesn't build the route graph, so `explain_edge` returns `None` and the
+    /// debug overlay is empty with `EdgeRouter::Grid`. `move_node` and `update_layout` route all
+    /// edges again with it.
+    pub router: EdgeRouter,
+    /// Places records which have relations with each other in adjacent grid cells, instead of
+    /// the order of definitions. See `SimpleLayoutEngine::cluster_records`.
+    pub cluster_related: bool,
+    /// Places referenced records before the records which refer to them, so that references
+    /// read from left to right (top to bottom with `LayoutOrientation::Vertical`). See
+    /// `SimpleLayoutEngine::order_by_references`. `cluster_related` starts from this order.
+    pub order_references: bool,
+    /// Breaks ties between routes of equal cost (e.g. between terminal ports on the same side)
+    /// by a pseudo-random order derived from the seed, instead of port indices. The same seed
+    /// always gives the same layout.
+    pub seed: Option<u64>,
+    /// With `LayoutOrientation::Vertical`, the grid keeps its number of columns, but records
+    /// fill each column before the next one, and terminal ports on the top or bottom side are
+    /// preferred.
+    pub orientation: LayoutOrientation,
+    /// Places each record right below the record above it in its grid column, instead of at the
+    /// top of its grid row, so that a tall record doesn't leave gaps below the short records of
+    /// its row. Columns keep their alignment.
+    pub pack_columns: bool,
+    /// Assigns records to grid columns by height instead of the order of definitions (the
+    /// tallest first, each to the lowest column so far), and stacks them like `pack_columns`,
+    /// so that columns end at similar heights and the diagram is as low as possible. It only
+    /// applies to `LayoutOrientation::Horizontal`.
+    pub balance_columns: bool,
+    /// Moves records without relations out of the grid of the others, so that the connected
+    /// records stay close. Records in groups are never isolated. It only applies to the grid
+    /// layout (not `swimlanes` or `circular`).
+    pub isolated_records: IsolatedRecords,
+    /// Places each group in a horizontal band across the diagram instead of a grid cell, e.g. a
+    /// band per service of a multi-service database map. Edges between bands are routed
+    /// through other bands.
+    pub swimlanes: bool,
+    /// Places records around a circle instead of a grid, which suits small schemas with many
+    /// relations. It takes precedence over `swimlanes` and the options of the grid.
+    pub circular: bool,
+    /// Rounds origins of records, locations of terminal ports and points of routes to
+    /// multiples of this length (e.g. `1.0` for whole pixels), so that halves of line heights
+    /// (e.g. `102.5`) don't leave sub-pixel gaps between ports and routes. Sizes of records are
+    /// kept, so ports in the middle of odd sides move off the middle. Non-positive or
+    /// non-finite lengths are ignored.
+    pub snap: Option<f32>,
+    /// Reuses routes of earlier layouts whose surroundings are unchanged, instead of searching
+    /// them again (see `RouteCache`). A reused route may be longer than a new one, if a shape
+    /// around it moved away. Routes which depend on each other (with `separate_edges`,
+    /// `bundle_edges` or `port_capacity`) and routes of `EdgeRouter::Grid` aren't cached.
+    pub route_cache: Option<RouteCache>,
+
+    /// Origins of records pinned by `pin`.
+    pins: HashMap<mir::NodeId, Point>,
+
+    /// Straight runs of the route of each edge from junction to junction with their lanes, so
+    /// that `move_node` can take them again in a new route graph.
+    edge_lanes: HashMap<mir::EdgeId, Vec<(Point, Point, usize)>>,
+    /// Warnings of the last `place_nodes`, which are found while grid cells are assigned.
+    cell_warnings: RefCell<Vec<LayoutWarning>>,
+
     // for debug
     edge_route_graph: RouteGraph,
 }
 
+impl Default for SimpleLayoutEngine {
+    fn default() -> Self {
+        Self::new(LayoutOptions::default())
+    }
+}
+
 impl SimpleLayoutEngine {
-    pub fn new() -> Self {
+    pub fn new(options: LayoutOptions) -> Self {
         Self {
+            options,
+            fit_record_width: false,
+            text_measure: Box::new(FontMetrics),
+            target_aspect_ratio: None,
+            max_width: None,
+            separate_edges: false,
+            bundle_edges: false,
+            port_capacity: None,
+            router: EdgeRouter::default(),
+            cluster_related: false,
+            order_references: false,
+            seed: None,
+            orientation: LayoutOrientation::default(),
+            pack_columns: false,
+            balance_columns: false,
+            isolated_records: IsolatedRecords::Interleaved,
+            swimlanes: false,
+            circular: false,
+            snap: None,
+            route_cache: None,
+            pins: HashMap::new(),
+            edge_lanes: HashMap::new(),
+            cell_warnings: RefCell::default(),
             edge_route_graph: RouteGraph::new(),
         }
     }
 }
 
 impl SimpleLayoutEngine {
-    const ORIGIN: Point = Point::new(50.0, 50.0);
-    const LINE_HEIGHT: f32 = 35.0;
-    const RECORD_WIDTH: f32 = 300.0;
-    const RECORD_SPACE: f32 = 80.0;
+    /// 16:9
+    pub const DEFAULT_ASPECT_RATIO: f32 = 16.0 / 9.0;
+
+    /// The width of the badge column in a record of the default width.
+    pub const BADGE_COLUMN_WIDTH: f32 = LayoutOptions::DEFAULT_RECORD_WIDTH / 5.0;
+
+    /// The width at which notes wrap, unless `NoteShape::max_width` is set.
+    pub const NOTE_MAX_WIDTH: f32 = 240.0;
 
-    // The number of columns in fixed grid.
+    /// The cost of a route segment through a group which contains neither end of the edge, so
+    /// that other edges go around groups unless there is no other way.
+    const GROUP_CROSSING_COST: u32 = 100_000;
+
+    // The default number of columns in fixed grid. It can be overridden by
+    // `BodyShape::n_columns`.
     const GRID_N_COLUMNS: usize = 3;
 
+    /// The space between the title and the first row. It leaves room for edges routed above
+    /// the first row.
+    fn title_space(&self) -> f32 {
+        self.options.record_space * 0.75
+    }
+
+    /// The origin of the body, at the top left corner inside the margin.
+    fn origin(&self) -> Point {
+        Point::new(self.options.margin, self.options.margin)
+    }
+
+    /// Pins a record at `origin`, so that later layouts place it there. The record still
+    /// takes its grid cell, which is left empty, so that the other records stay in their cells.
+    /// A pin takes precedence over `NodeData::preferred_origin`. Nodes other than records are
+    /// ignored.
+    pub fn pin(&mut self, node_id: mir::NodeId, origin: Point) {
+        self.pins.insert(node_id, origin);
+    }
+
+    /// Unpins a record and returns its pinned origin.
+    pub fn unpin(&mut self, node_id: mir::NodeId) -> Option<Point> {
+        self.pins.remove(&node_id)
+    }
+
+    pub fn pinned_origin(&self, node_id: mir::NodeId) -> Option<Point> {
+        self.pins.get(&node_id).copied()
+    }
+
+    pub fn clear_pins(&mut self) {
+        self.pins.clear();
+    }
+
+    /// The origin at which a record is placed out of the grid, if it has a preferred origin.
+    fn fixed_origin(&self, doc: &mir::Document, node_id: mir::NodeId) -> Option<Point> {
+        let preferred_origin = doc.get_node(node_id)?.preferred_origin?;
+
+        Some(self.pinned_origin(node_id).unwrap_or(preferred_origin))
+    }
+
     // for debug
     pub fn edge_route_graph(&self) -> &RouteGraph {
         &self.edge_route_graph
     }
+
+    /// Replaces the route graph with the one restored from a layout cache.
+    pub fn set_edge_route_graph(&mut self, edge_route_graph: RouteGraph) {
+        self.edge_route_graph = edge_route_graph;
+    }
+
+    /// A hash of the settings and metrics which affect layout. Layouts computed by engines of
+    /// different hashes may differ for the same document. The hash is stable across builds
+    /// (FNV-1a).
+    pub fn config_hash(&self) -> u64 {
+        let metrics = [
+            self.options.margin,
+            self.options.line_height,
+            self.options.record_width,
+            self.options.record_space,
+            self.title_space(),
+            self.options.shape_junction_margin,
+        ];
+        let bytes = metrics
+            .iter()
+            .flat_map(|metric| metric.to_le_bytes())
+            .chain(self.fit_record_width.then_some(b'f'))
+            .chain(
+                self.target_aspect_ratio
+                    .into_iter()
+                    .flat_map(|ratio| [b'a'].into_iter().chain(ratio.to_le_bytes())),
+            )
+            .chain(
+                self.max_width
+                    .into_iter()
+                    .flat_map(|width| [b'w'].into_iter().chain(width.to_le_bytes())),
+            )
+            .chain(
+                self.separate_edges
+                    .then_some(self.options.lane_spacing)
+                    .into_iter()
+                    .flat_map(|spacing| [b'l'].into_iter().chain(spacing.to_le_bytes())),
+            )
+            .chain(
+                (self.router != EdgeRouter::Junctions)
+                    .then_some([b'r', self.router as u8])
+                    .into_iter()
+                    .flatten(),
+            )
+            .chain(self.cluster_related.then_some(b'c'))
+            .chain(self.order_references.then_some(b't'))
+            .chain(
+                self.seed
+                    .into_iter()
+                    .flat_map(|seed| [b'd'].into_iter().chain(seed.to_le_bytes())),
+            )
+            .chain((self.orientation != LayoutOrientation::Horizontal).then_some(b'v'))
+            .chain(self.pack_columns.then_some(b'p'))
+            .chain(self.balance_columns.then_some(b'h'))
+            .chain(
+                (self.isolated_records != IsolatedRecords::Interleaved)
+                    .then_some([b'i', self.isolated_records as u8])
+                    .into_iter()
+                    .flatten(),
+            )
+            .chain(self.swimlanes.then_some(b's'))
+            .chain(self.circular.then_some(b'o'))
+            .chain(
+                self.snap_length()
+                    .into_iter()
+                    .flat_map(|snap| [b'g'].into_iter().chain(snap.to_le_bytes())),
+            )
+            .chain(self.bundle_edges.then_some(b'b'))
+            .chain(
+                self.port_capacity
+                    .into_iter()
+                    .flat_map(|n| [b'n'].into_iter().chain((n as u64).to_le_bytes())),
+            )
+            .chain(self.options.blank_cells.iter().flat_map(|cell| {
+                [b'x'].into_iter().chain(
+                    [cell.row, cell.column].into_iter().flat_map(|i| (i as u64).to_le_bytes()),
+                )
+            }))
+            .chain(self.options.keep_out_regions.iter().flat_map(|r| {
+                [b'k'].into_iter().chain(
+                    [r.min_x(), r.min_y(), r.width(), r.height()]
+                        .into_iter()
+                        .flat_map(f32::to_le_bytes),
+                )
+            }))
+            .chain(
+                (self.options.bend_cost != 0.0 || self.options.port_side_cost != 0.0)
+                    .then(|| {
+                        [b'e'].into_iter().chain(
+                            [self.options.bend_cost, self.options.port_side_cost]
+                                .into_iter()
+                                .flat_map(f32::to_le_bytes),
+                        )
+                    })
+                    .into_iter()
+                    .flatten(),
+            )
+            .chain(self.text_measure_bytes());
+
+        fnv1a(bytes)
+    }
+
+    /// Returns `TextMeasure::config_bytes` of the text measure for `config_hash`, tagged unless
+    /// it's empty (the default).
+    fn text_measure_bytes(&self) -> Vec<u8> {
+        let bytes = self.text_measure.config_bytes();
+
+        if bytes.is_empty() {
+            bytes
+        } else {
+            [b'm'].into_iter().chain(bytes).collect()
+        }
+    }
+
+    /// Returns the size of the view box of a grid of `n_columns` which holds `record_sizes`.
+    fn grid_layout_size(&self, record_sizes: &[Size], title_height: f32, n_columns: usize) -> Size {
+        let line_length = self.line_length(record_sizes.len(), n_columns);
+        let mut column_widths: Vec<f32> = vec![];
+        let mut row_heights: Vec<f32> = vec![];
+
+        for (i, size) in record_sizes.iter().enumerate() {
+            let (row, column) = self.grid_position(i, line_length);
+
+            if column_widths.len() <= column {
+                column_widths.resize(column + 1, 0.0);
+            }
+            if row_heights.len() <= row {
+                row_heights.resize(row + 1, 0.0);
+            }
+            column_widths[column] = column_widths[column].max(size.width);
+            row_heights[row] = row_heights[row].max(size.height);
+        }
+
+        let spaces = |n: usize| self.options.record_space * n.saturating_sub(1) as f32;
+        let width = column_widths.iter().sum::<f32>() + spaces(column_widths.len());
+        let height = row_heights.iter().sum::<f32>() + spaces(row_heights.len());
+
+        Size::new(
+            width + self.options.margin * 2.0,
+            height + title_height + self.options.margin * 2.0,
+        )
+    }
+
+    /// Returns the number of grid columns whose layout of `record_sizes` has the aspect ratio
+    /// closest to `aspect_ratio`. Fewer columns win ties.
+    fn column_count(&self, record_sizes: &[Size], title_height: f32, aspect_ratio: f32) -> usize {
+        // Ratios are compared in log scale, so that 2:1 and 1:2 are as far from 1:1.
+        let distance = |n_columns: usize| {
+            let size = self.grid_layout_size(record_sizes, title_height, n_columns);
+            ((size.width / size.height).ln() - aspect_ratio.ln()).abs()
+        };
+
+        (1..=record_sizes.len().max(1))
+            .min_by(|a, b| distance(*a).total_cmp(&distance(*b)))
+            .unwrap_or(1)
+    }
+
+    /// Returns the number of cells in each row, or in each column with
+    /// `LayoutOrientation::Vertical`, of a grid of `n_columns` which holds `n_cells`.
+    fn line_length(&self, n_cells: usize, n_columns: usize) -> usize {
+        match self.orientation {
+            LayoutOrientation::Horizontal => n_columns,
+            LayoutOrientation::Vertical => n_cells.div_ceil(n_columns).max(1),
+        }
+    }
+
+    /// Puts `records` in free cells of `cells` by first-fit decreasing: from the tallest record,
+    /// each goes to the first free cell of the column whose records are the lowest so far,
+    /// counting records which took their preferred cells. Records of the same height keep their
+    /// order, and ties of columns go to the left one.
+    fn balance_cells(
+        &self,
+        doc: &mir::Document,
+        cells: &mut Vec<Option<mir::NodeId>>,
+        mut records: Vec<mir::NodeId>,
+        blank_cells: &[usize],
+        line_length: usize,
+    ) {
+        let space = self.options.record_space;
+        let height = |item_id: mir::NodeId| self.item_size(doc, item_id).map_or(0.0, |s| s.height);
+        let mut column_heights = vec![0.0f32; line_length];
+
+        for (cell, item_id) in cells.iter().enumerate() {
+            if let Some(item_id) = item_id {
+                column_heights[cell % line_length] += height(*item_id) + space;
+            }
+        }
+
+        records.sort_by(|a, b| height(*b).total_cmp(&height(*a)));
+        for item_id in records {
+            let column = (0..line_length)
+                .min_by(|a, b| column_heights[*a].total_cmp(&column_heights[*b]))
+                .unwrap_or(0);
+            let cell = (column..)
+                .step_by(line_length)
+                .find(|cell| {
+                    cells.get(*cell).is_none_or(Option::is_none) && !blank_cells.contains(cell)
+                })
+                .unwrap_or(column);
+
+            if cells.len() <= cell {
+                cells.resize(cell + 1, None);
+            }
+            cells[cell] = Some(item_id);
+            column_heights[column] += height(item_id) + space;
+        }
+    }
+
+    /// Returns the row and the column of the `cell`-th cell of the grid whose rows (or columns
+    /// with `LayoutOrientation::Vertical`) hold `line_length` cells.
+    fn grid_position(&self, cell: usize, line_length: usize) -> (usize, usize) {
+        let (line, offset) = (cell / line_length, cell % line_length);
+
+        match self.orientation {
+            LayoutOrientation::Horizontal => (line, offset),
+            LayoutOrientation::Vertical => (offset, line),
+        }
+    }
+
+    /// Maps records, their fields, and fields of group members to the index of their owner in
+    /// `records`.
+    fn record_owners(doc: &mir::Document, records: &[mir::NodeId]) -> HashMap<mir::NodeId, usize> {
+        let mut owners: HashMap<mir::NodeId, usize> = HashMap::new();
+
+        for (i, record_id) in records.iter().enumerate() {
+            owners.insert(*record_id, i);
+            // Groups own fields of their members.
+            let Some(record_node) = doc.get_node(*record_id) else { continue };
+
+            for child_id in record_node.children() {
+                owners.insert(child_id, i);
+                if let Some(child_node) = doc.get_node(child_id) {
+                    owners.extend(child_node.children().map(|field_id| (field_id, i)));
+                }
+            }
+        }
+
+        owners
+    }
+
+    /// Orders `records` so that referenced records come before the records which refer to them
+    /// (e.g. `users` before `posts`), where edges refer by `mir::Document::edge_reference`.
+    ///
+    /// Records in a cycle of references are kept together in the order of definitions, and so
+    /// are records which don't depend on each other.
+    pub fn order_by_references(doc: &mir::Document, records: &[mir::NodeId]) -> Vec<mir::NodeId> {
+        let owners = Self::record_owners(doc, records);
+        let mut graph: DiGraph<usize, ()> = DiGraph::default();
+        let indices: Vec<NodeIndex> = (0..records.len()).map(|i| graph.add_node(i)).collect();
+
+        for edge_id in doc.edge_ids() {
+            let Some((referrer_id, referred_id)) = doc.edge_reference(edge_id) else { continue };
+            let (Some(a), Some(b)) = (owners.get(&referrer_id), owners.get(&referred_id)) else {
+                continue;
+            };
+
+            if a != b {
+                graph.update_edge(indices[*b], indices[*a], ());
+            }
+        }
+
+        // Components of cycles are sorted by references, and ties by their first record.
+        let mut components: Vec<Vec<usize>> = algo::tarjan_scc(&graph)
+            .into_iter()
+            .map(|nodes| {
+                let mut component: Vec<usize> = nodes.into_iter().map(|n| graph[n]).collect();
+                component.sort_unstable();
+                component
+            })
+            .collect();
+        components.sort_unstable_by_key(|component| component[0]);
+
+        let mut component_of: Vec<usize> = vec![0; records.len()];
+        for (c, component) in components.iter().enumerate() {
+            for i in component {
+                component_of[*i] = c;
+            }
+        }
+        let mut in_degrees: Vec<usize> = vec![0; components.len()];
+        let mut dependents: Vec<Vec<usize>> = vec![vec![]; components.len()];
+        for edge in graph.edge_references() {
+            let (a, b) = (component_of[graph[edge.source()]], component_of[graph[edge.target()]]);
+            if a != b && !dependents[a].contains(&b) {
+                dependents[a].push(b);
+                in_degrees[b] += 1;
+            }
+        }
+
+        let mut ready: BinaryHeap<Reverse<usize>> =
+            (0..components.len()).filter(|c| in_degrees[*c] == 0).map(Reverse).collect();
+        let mut ordered: Vec<mir::NodeId> = Vec::with_capacity(records.len());
+
+        while let Some(Reverse(c)) = ready.pop() {
+            ordered.extend(components[c].iter().map(|i| records[*i]));
+            for d in &dependents[c] {
+                in_degrees[*d] -= 1;
+                if in_degrees[*d] == 0 {
+                    ready.push(Reverse(*d));
+                }
+            }
+        }
+
+        ordered
+    }
+
+    /// Orders `records` for grid cells in row-major order, so that records with more relations
+    /// between them are placed in adjacent cells and edges get shorter.
+    ///
+    /// Records are related by the number of edges between them or their fields. The first
+    /// record keeps the first cell, and each next cell takes the unplaced record which is most
+    /// related to its placed neighbors (left and above, then diagonally above), then in the
+    /// connected component of the last placed record, then most related to all placed records.
+    /// Remaining ties keep the order of definitions.
+    pub fn cluster_records(
+        doc: &mir::Document,
+        records: &[mir::NodeId],
+        n_columns: usize,
+    ) -> Vec<mir::NodeId> {
+        let mut graph: UnGraph<mir::NodeId, u32> = UnGraph::default();
+        let indices: Vec<NodeIndex> = records.iter().map(|id| graph.add_node(*id)).collect();
+        let owners: HashMap<mir::NodeId, NodeIndex> = Self::record_owners(doc, records)
+            .into_iter()
+            .map(|(node_id, i)| (node_id, indices[i]))
+            .collect();
+
+        for edge_id in doc.edge_ids() {
+            let Some((source_id, target_id)) = doc.edge_endpoints(edge_id) else { continue };
+            let (Some(a), Some(b)) = (owners.get(&source_id), owners.get(&target_id)) else {
+                continue;
+            };
+
+            if a == b {
+                continue;
+            }
+            match graph.find_edge(*a, *b) {
+                Some(e) => graph[e] += 1,
+                None => {
+                    graph.add_edge(*a, *b, 1);
+                }
+            }
+        }
+
+        let weight = |a: NodeIndex, b: NodeIndex| graph.find_edge(a, b).map_or(0, |e| graph[e]);
+        let mut components: HashMap<NodeIndex, usize> = HashMap::new();
+
+        for (component, nodes) in algo::tarjan_scc(&graph).into_iter().enumerate() {
+            components.extend(nodes.into_iter().map(|node| (node, component)));
+        }
+
+        let mut cells: Vec<NodeIndex> = Vec::with_capacity(indices.len());
+        let mut unplaced: Vec<NodeIndex> = indices.clone();
+
+        while !unplaced.is_empty() {
+            let cell = cells.len();
+            let (row, column) = (cell / n_columns, cell % n_columns);
+            let at = |row: usize, column: usize| cells.get(row * n_columns + column).copied();
+            let neighbors = [
+                (column > 0).then(|| at(row, column - 1)).flatten(),
+                row.checked_sub(1).and_then(|row| at(row, column)),
+            ];
+            let diagonals = [
+                row.checked_sub(1)
+                    .filter(|_| column > 0)
+                    .and_then(|row| at(row, column - 1)),
+                row.checked_sub(1)
+                    .filter(|_| column + 1 < n_columns)
+                    .and_then(|row| at(row, column + 1)),
+            ];
+            let last = cells.last().copied();
+
+            let score = |node: NodeIndex| {
+                let related = |others: &[Option<NodeIndex>]| -> u32 {
+                    others.iter().flatten().map(|other| weight(node, *other)).sum()
+                };
+                let same_component =
+                    last.is_some_and(|last| components[&last] == components[&node]);
+                let placed: Vec<_> = cells.iter().copied().map(Some).collect();
+
+                (related(&neighbors), related(&diagonals), same_component, related(&placed))
+            };
+
+            // `max_by_key` returns the last maximum, so iterate in the reverse order to keep
+            // the order of definitions.
+            let (position, _) = unplaced
+                .iter()
+                .enumerate()
+                .rev()
+                .max_by_key(|(_, node)| score(**node))
+                .unwrap();
+
+            cells.push(unplaced.remove(position));
+        }
+
+        cells.into_iter().map(|index| graph[index]).collect()
+    }
+
+    /// Assigns `records` to grid cells in row-major order (column-major with
+    /// `LayoutOrientation::Vertical`), with `None` for empty cells. Records take their
+    /// `preferred_cell` if it's in the grid and no earlier record took it, and the others fill
+    /// the remaining cells except `LayoutOptions::blank_cells` in order (or ordered with
+    /// `order_references`, then clustered with `cluster_related`). Isolated records follow
+    /// from a new line with `IsolatedRecords::Trailing`. Preferred cells out of the grid are
+    /// reported as `LayoutWarning::CellOutOfRange`.
+    fn assign_cells(
+        &self,
+        doc: &mir::Document,
+        records: &[mir::NodeId],
+        line_length: usize,
+    ) -> Vec<Option<mir::NodeId>> {
+        let mut cells: Vec<Option<mir::NodeId>> = vec![];
+        let mut rest: Vec<mir::NodeId> = vec![];
+        // Cells are allocated up to the last taken one, so preferred lines and offsets are
+        // clamped to the number of records (see `LayoutWarning::CellOutOfRange`), and blank
+        // cells beyond all records are ignored.
+        let max_index = records.len() + self.options.blank_cells.len();
+        let line_offset = |cell: &mir::GridCell| match self.orientation {
+            LayoutOrientation::Horizontal => (cell.row, cell.column),
+            LayoutOrientation::Vertical => (cell.column, cell.row),
+        };
+        let cell_index = |(line, offset): (usize, usize)| {
+            (offset < line_length)
+                .then(|| line.checked_mul(line_length)?.checked_add(offset))
+                .flatten()
+        };
+
+        for record_id in records.iter().copied() {
+            let preferred = doc.get_node(record_id).and_then(|node| node.preferred_cell);
+            let preferred = preferred.and_then(|cell| {
+                let (line, offset) = line_offset(&cell);
+                let index = cell_index((line.min(records.len()), offset.min(records.len())));
+
+                if index.is_none() || line.max(offset) > records.len() {
+                    let warning = LayoutWarning::CellOutOfRange { record_id };
+
+                    self.cell_warnings.borrow_mut().push(warning);
+                }
+                index
+            });
+
+            match preferred {
+                Some(cell) if cells.get(cell).is_none_or(Option::is_none) => {
+                    if cells.len() <= cell {
+                        cells.resize(cell + 1, None);
+                    }
+                    cells[cell] = Some(record_id);
+                }
+                _ => rest.push(record_id),
+            }
+        }
+
+        if self.order_references {
+            rest = Self::order_by_references(doc, &rest);
+        }
+        if self.cluster_related {
+            // Clustering only looks at neighbors in the order of cells, which is transposed in
+            // the vertical orientation.
+            rest = Self::cluster_records(doc, &rest, line_length);
+        }
+
+        let mut isolated: Vec<mir::NodeId> = vec![];
+
+        if self.isolated_records == IsolatedRecords::Trailing {
+            let flags = Self::isolated_records(doc, &rest);
+            let (isolated_ids, connected_ids): (Vec<_>, Vec<_>) =
+                rest.into_iter().zip(flags).partition(|(_, isolated)| *isolated);
+
+            rest = connected_ids.into_iter().map(|(id, _)| id).collect();
+            isolated = isolated_ids.into_iter().map(|(id, _)| id).collect();
+        }
+
+        let blank_cells: Vec<usize> = self
+            .options
+            .blank_cells
+            .iter()
+            .map(line_offset)
+            .filter(|(line, offset)| *line <= max_index && *offset <= max_index)
+            .filter_map(cell_index)
+            .collect();
+        let mut rest = rest.into_iter();
+
+        if let Some(last) = blank_cells.iter().max() {
+            if cells.len() <= *last {
+                cells.resize(*last + 1, None);
+            }
+        }
+        if self.balance_columns && self.orientation == LayoutOrientation::Horizontal {
+            self.balance_cells(doc, &mut cells, rest.collect(), &blank_cells, line_length);
+        } else {
+            for (i, cell) in cells.iter_mut().enumerate() {
+                if cell.is_none() && !blank_cells.contains(&i) {
+                    *cell = rest.next();
+                }
+            }
+            cells.extend(rest.map(Some));
+        }
+        // Isolated records start a new line.
+        if !isolated.is_empty() {
+            cells.resize(cells.len().next_multiple_of(line_length), None);
+            cells.extend(isolated.into_iter().map(Some));
+        }
+        cells
+    }
+
+    /// `snap`, if it is a positive length.
+    fn snap_length(&self) -> Option<f32> {
+        self.snap.filter(|snap| snap.is_finite() && *snap > 0.0)
+    }
+
+    /// Rounds `point` to the nearest multiple of `snap`.
+    fn snap_point(&self, point: Point) -> Point {
+        let Some(snap) = self.snap_length() else { return point };
+
+        Point::new((point.x / snap).round() * snap, (point.y / snap).round() * snap)
+    }
+
+    /// Rounds points of `path` by `snap_point`. Points which meet are merged, and points which
+    /// fall in the middle of straight lines are removed.
+    fn snap_path(&self, path: Vec<Point>) -> Vec<Point> {
+        if self.snap_length().is_none() {
+            return path;
+        }
+        Self::simplify_path(path.into_iter().map(|point| self.snap_point(point)).collect())
+    }
+
+    /// Places a record and its fields at `origin` (rounded by `snap`) and returns the size of
+    /// the record.
+    fn place_record(
+        &self,
+        doc: &mut mir::Document,
+        record_id: mir::NodeId,
+        origin: Point,
+        record_width: f32,
+    ) -> Option<Size> {
+        let origin = self.snap_point(origin);
+        let record_node = doc.get_node_mut(record_id)?;
+        let ShapeKind::Record(_) = record_node.kind() else { return None };
+
+        let n_fields = record_node.children().len() as f32;
+        let size = Size::new(record_width, self.options.line_height * n_fields);
+
+        record_node.origin = Some(origin);
+        record_node.size = Some(size);
+
+        // children
+        let field_id_vec = record_node.children().collect::<Vec<_>>();
+
+        for (field_index, field_node_index) in field_id_vec.iter().copied().enumerate() {
+            let y = origin.y + self.options.line_height * field_index as f32;
+            let Some(field_node) = doc.get_node_mut(field_node_index) else { continue };
+            let ShapeKind::Field(_) = field_node.kind() else  { continue };
+
+            field_node.origin = Some(Point::new(origin.x, y));
+            field_node.size = Some(Size::new(record_width, self.options.line_height));
+        }
+
+        Some(size)
+    }
+
+    /// Returns the lines of a note whose text is wrapped at its max width (measured with
+    /// `text_measure`), and the size of the note.
+    fn wrap_note(&self, note: &mir::NoteShape) -> (Vec<String>, Size) {
+        let padding = mir::NoteShape::PADDING;
+        let max_width = note.max_width.unwrap_or(Self::NOTE_MAX_WIDTH) - padding * 2.0;
+        let lines = font::wrap_text(&note.text, max_width, self.text_measure.as_ref());
+        let text_width = lines
+            .iter()
+            .map(|line| {
+                self.text_measure.text_width(&mir::TextSpan {
+                    text: line.clone(),
+                    ..note.text.clone()
+                })
+            })
+            .fold(0.0, f32::max);
+
+        let size = Size::new(
+            text_width + padding * 2.0,
+            note.line_height() * lines.len() as f32 + padding * 2.0,
+        );
+
+        (lines, size)
+    }
+
+    /// Places notes right of their anchors, or left, below or above them if it would overlap
+    /// records or other notes. Notes without a placed anchor are stacked at the left below
+    /// `bottom` (or at `top` without records). Returns rects of the notes.
+    fn place_notes(&self, doc: &mut mir::Document, top: f32, bottom: f32) -> Vec<Rect> {
+        let space = self.options.record_space;
+        let origin = self.origin();
+        let mut occupied: Vec<Rect> = doc
+            .body()
+            .children()
+            .filter_map(|child_id| doc.get_node(child_id)?.rect())
+            .collect();
+        let mut unanchored = vec![];
+        let mut rects = vec![];
+        let note_ids: Vec<_> = doc.note_ids().collect();
+
+        for note_id in note_ids {
+            let Some(note_node) = doc.get_node_mut(note_id) else { continue };
+            let ShapeKind::Note(note) = note_node.kind_mut() else { continue };
+            let (lines, size) = self.wrap_note(note);
+
+            // Renderers draw the lines as they are measured here.
+            note.lines = lines;
+
+            let anchor_id = note.anchor;
+            let Some(anchor) = anchor_id.and_then(|anchor_id| doc.get_node(anchor_id)?.rect())
+            else {
+                unanchored.push((note_id, size));
+                continue;
+            };
+            let candidates = [
+                Point::new(anchor.max_x() + space, anchor.min_y()),
+                Point::new(anchor.min_x() - space - size.width, anchor.min_y()),
+                Point::new(anchor.min_x(), anchor.max_y() + space),
+                Point::new(anchor.min_x(), anchor.min_y() - space - size.height),
+            ];
+            let note_origin = candidates
+                .into_iter()
+                .find(|p| {
+                    let rect = Rect::new(*p, size);
+
+                    p.x >= origin.x && p.y >= top && !occupied.iter().any(|r| r.intersects(&rect))
+                })
+                .unwrap_or(candidates[0]);
+            let rect = Rect::new(self.snap_point(note_origin), size);
+
+            occupied.push(rect);
+            rects.push((note_id, rect));
+        }
+
+        let mut y = occupied
+            .iter()
+            .map(Rect::max_y)
+            .reduce(f32::max)
+            .map_or(top, |max_y| max_y.max(bottom) + space);
+
+        for (note_id, size) in unanchored {
+            let rect = Rect::new(self.snap_point(Point::new(origin.x, y)), size);
+
+            y = rect.max_y() + space;
+            rects.push((note_id, rect));
+        }
+
+        for (note_id, rect) in &rects {
+            let Some(note_node) = doc.get_node_mut(*note_id) else { continue };
+
+            note_node.origin = Some(rect.origin);
+            note_node.size = Some(rect.size);
+        }
+
+        rects.into_iter().map(|(_, rect)| rect).collect()
+    }
+
+    /// Places `records` (and their groups) in grid cells below `top`, and returns the width of
+    /// the grid, the bottom of the grid, and rects of records placed out of the grid cells.
+    fn place_grid(
+        &self,
+        doc: &mut mir::Document,
+        records: &[mir::NodeId],
+        n_columns: usize,
+        top: f32,
+    ) -> (f32, f32, Vec<Rect>) {
+        // Grouped records are laid out in their groups, which take grid cells instead.
+        let child_id_vec = Self::grid_items(doc, records);
+        let n_cells = child_id_vec.len() + self.options.blank_cells.len();
+        let line_length = self.line_length(n_cells, n_columns);
+        let cells = self.assign_cells(doc, &child_id_vec, line_length);
+        let n_columns = match self.orientation {
+            LayoutOrientation::Horizontal => n_columns,
+            LayoutOrientation::Vertical => cells.len().div_ceil(line_length).max(1),
+        };
+
+        // Records in the same grid column share the widest width, and records in the same grid
+        // row the highest height.
+        let mut column_widths = vec![self.options.record_width; n_columns];
+        let mut row_heights: Vec<f32> = vec![];
+        let mut cell_heights: Vec<Option<f32>> = vec![None; cells.len()];
+
+        for (cell, child_id) in cells.iter().enumerate() {
+            let (row, column) = self.grid_position(cell, line_length);
+
+            if row_heights.len() <= row {
+                row_heights.resize(row + 1, 0.0);
+            }
+
+            let Some(size) = child_id.and_then(|child_id| self.item_size(doc, child_id)) else {
+                continue;
+            };
+
+            column_widths[column] = column_widths[column].max(size.width);
+            row_heights[row] = row_heights[row].max(size.height);
+            cell_heights[cell] = Some(size.height);
+        }
+
+        let column_x: Vec<f32> = column_widths
+            .iter()
+            .scan(self.options.margin, |x, width| {
+                let column_x = *x;
+                *x += width + self.options.record_space;
+                Some(column_x)
+            })
+            .collect();
+        // Rows of empty cells only keep the space.
+        let row_y: Vec<f32> = row_heights
+            .iter()
+            .scan(top, |y, height| {
+                let row_y = *y;
+                *y += height + self.options.record_space;
+                Some(row_y)
+            })
+            .collect();
+        let mut cell_y: Vec<f32> = (0..cells.len())
+            .map(|cell| row_y[self.grid_position(cell, line_length).0])
+            .collect();
+        let mut bottom = row_y.last().copied().unwrap_or(top)
+            + row_heights.last().copied().unwrap_or(0.0);
+
+        let balanced =
+            self.balance_columns && self.orientation == LayoutOrientation::Horizontal;
+
+        if self.pack_columns || balanced {
+            let space = self.options.record_space;
+            let mut column_bottoms = vec![top - space; n_columns];
+            let mut packed: Vec<usize> = (0..cells.len()).collect();
+
+            packed.sort_by_key(|cell| self.grid_position(*cell, line_length));
+            for cell in packed {
+                let Some(height) = cell_heights[cell] else { continue };
+                let (_, column) = self.grid_position(cell, line_length);
+
+                cell_y[cell] = column_bottoms[column] + space;
+                column_bottoms[column] = cell_y[cell] + height;
+            }
+            bottom = column_bottoms.into_iter().fold(top, f32::max);
+        }
+
+        let mut out_of_grid: Vec<Rect> = vec![];
+
+        for (cell, child_id) in cells.iter().copied().enumerate() {
+            let Some(child_id) = child_id else { continue };
+            let (_, column) = self.grid_position(cell, line_length);
+            let record_width = column_widths[column];
+            let pinned_origin = self.pinned_origin(child_id);
+            let origin = pinned_origin.unwrap_or(Point::new(column_x[column], cell_y[cell]));
+            let Some(size) = self.place_item(doc, child_id, origin, record_width) else {
+                continue;
+            };
+
+            if pinned_origin.is_some() {
+                out_of_grid.push(Rect::new(origin, size));
+            }
+        }
+        // Pinned members are placed by their groups.
+        out_of_grid.extend(
+            self.pins
+                .keys()
+                .filter(|record_id| doc.group_of(**record_id).is_some())
+                .filter_map(|record_id| doc.get_node(*record_id)?.rect()),
+        );
+
+        let grid_width = column_widths.iter().sum::<f32>()
+            + ((n_columns - 1) as f32) * self.options.record_space;
+
+        (grid_width, bottom, out_of_grid)
+    }
+
+    /// Stacks `records` from `top` in a column to the right of a grid of `grid_width` and
+    /// `bottom`, which `place_grid` returned with `out_of_grid`. Returns the same for both.
+    fn place_side_column(
+        &self,
+        doc: &mut mir::Document,
+        records: &[mir::NodeId],
+        grid_width: f32,
+        bottom: f32,
+        mut out_of_grid: Vec<Rect>,
+        top: f32,
+    ) -> (f32, f32, Vec<Rect>) {
+        let space = self.options.record_space;
+        let x = if grid_width > 0.0 {
+            self.options.margin + grid_width + space
+        } else {
+            self.options.margin
+        };
+        let mut column_width = 0.0f32;
+        let mut column_bottom = top - space;
+
+        for record_id in records.iter().copied() {
+            let Some(record_node) = doc.get_node(record_id) else { continue };
+            let record_width = self.record_width(doc, record_node);
+            let pinned_origin = self.pinned_origin(record_id);
+            let origin = pinned_origin.unwrap_or(Point::new(x, column_bottom + space));
+            let Some(size) = self.place_record(doc, record_id, origin, record_width) else {
+                continue;
+            };
+
+            if pinned_origin.is_some() {
+                out_of_grid.push(Rect::new(origin, size));
+                continue;
+            }
+            column_width = column_width.max(size.width);
+            column_bottom = origin.y + size.height;
+        }
+
+        if column_width == 0.0 {
+            return (grid_width, bottom, out_of_grid);
+        }
+        (x - self.options.margin + column_width, bottom.max(column_bottom), out_of_grid)
+    }
+
+    /// Returns whether each of `records` (or groups) has no relations, except that members of
+    /// groups are never isolated.
+    fn isolated_records(doc: &mir::Document, records: &[mir::NodeId]) -> Vec<bool> {
+        let owners = Self::record_owners(doc, records);
+        let mut isolated: Vec<bool> =
+            records.iter().map(|record_id| doc.group_of(*record_id).is_none()).collect();
+
+        for (source_id, target_id) in doc.edge_ids().filter_map(|id| doc.edge_endpoints(id)) {
+            for node_id in [source_id, target_id] {
+                if let Some(i) = owners.get(&node_id) {
+                    isolated[*i] = false;
+                }
+            }
+        }
+
+        isolated
+    }
+
+    /// Places groups in horizontal bands (swimlanes) from top to bottom, each of which holds
+    /// its members in rows of `n_columns`, and ungrouped records below them without a band.
+    /// Grid columns are aligned across lanes. Returns the same as `place_grid`.
+    fn place_swimlanes(
+        &self,
+        doc: &mut mir::Document,
+        records: &[mir::NodeId],
+        n_columns: usize,
+        top: f32,
+    ) -> (f32, f32, Vec<Rect>) {
+        let space = self.options.record_space;
+        let padding = self.options.shape_junction_margin;
+        let mut lanes: Vec<(Option<mir::NodeId>, Vec<mir::NodeId>)> = doc
+            .group_ids()
+            .filter_map(|group_id| {
+                let members: Vec<mir::NodeId> = doc
+                    .get_node(group_id)?
+                    .children()
+                    .filter(|record_id| records.contains(record_id))
+                    .collect();
+
+                (!members.is_empty()).then_some((Some(group_id), members))
+            })
+            .collect();
+        let ungrouped: Vec<mir::NodeId> = records
+            .iter()
+            .copied()
+            .filter(|record_id| doc.group_of(*record_id).is_none())
+            .collect();
+
+        if !ungrouped.is_empty() {
+            lanes.push((None, ungrouped));
+        }
+
+        let mut column_widths = vec![self.options.record_width; n_columns];
+
+        for (_, members) in &lanes {
+            for (i, record_id) in members.iter().enumerate() {
+                let Some(size) = self.item_size(doc, *record_id) else { continue };
+                column_widths[i % n_columns] = column_widths[i % n_columns].max(size.width);
+            }
+        }
+
+        let column_x: Vec<f32> = column_widths
+            .iter()
+            .scan(self.options.margin + padding, |x, width| {
+                let column_x = *x;
+                *x += width + space;
+                Some(column_x)
+            })
+            .collect();
+        let grid_width = column_widths.iter().sum::<f32>() + ((n_columns - 1) as f32) * space;
+        let lane_width = grid_width + padding * 2.0;
+        let mut out_of_grid: Vec<Rect> = vec![];
+        let mut y = top;
+
+        for (group_id, members) in lanes {
+            let lane_top = y;
+
+            // Ungrouped records have no band, and so no header.
+            if group_id.is_some() {
+                y += self.options.line_height + padding;
+            }
+            for row in members.chunks(n_columns) {
+                let mut row_height = 0.0f32;
+
+                for (column, record_id) in row.iter().copied().enumerate() {
+                    let pinned_origin = self.pinned_origin(record_id);
+                    let origin = pinned_origin.unwrap_or(Point::new(column_x[column], y));
+                    let record_width = column_widths[column];
+                    let Some(size) = self.place_record(doc, record_id, origin, record_width) else {
+                        continue;
+                    };
+
+                    row_height = row_height.max(size.height);
+                    if pinned_origin.is_some() {
+                        out_of_grid.push(Rect::new(origin, size));
+                    }
+                }
+                y += row_height + space;
+            }
+            y -= space;
+
+            if let Some(group_node) = group_id.and_then(|group_id| doc.get_node_mut(group_id)) {
+                y += padding;
+                group_node.origin = Some(Point::new(self.options.margin, lane_top));
+                group_node.size = Some(Size::new(lane_width, y - lane_top));
+            }
+            y += space;
+        }
+
+        (lane_width, (y - space).max(top), out_of_grid)
+    }
+
+    /// Places `records` around a circle clockwise from the top, in the order of definitions (or
+    /// clustered with `cluster_related`), so that edges are routed through the interior. The
+    /// radius keeps the widest records apart by `LayoutOptions::record_space`. Groups aren't
+    /// drawn. Returns the same as `place_grid`.
+    fn place_circle(
+        &self,
+        doc: &mut mir::Document,
+        records: &[mir::NodeId],
+        top: f32,
+    ) -> (f32, f32, Vec<Rect>) {
+        let records = if self.cluster_related {
+            Self::cluster_records(doc, records, 1)
+        } else {
+            records.to_vec()
+        };
+        let sizes: Vec<(mir::NodeId, Size)> = records
+            .iter()
+            .filter_map(|record_id| Some((*record_id, self.item_size(doc, *record_id)?)))
+            .collect();
+        let n = sizes.len();
+        // Records whose centers are as far apart as their diagonals don't overlap, whatever
+        // their angles on the circle.
+        let max_diagonal = sizes
+            .iter()
+            .map(|(_, size)| size.width.hypot(size.height))
+            .fold(0.0, f32::max);
+        // Adjacent centers are `2r sin(π/n)` apart.
+        let radius = if n > 1 {
+            let chord = 2.0 * (std::f32::consts::PI / n as f32).sin();
+            (max_diagonal + self.options.record_space) / chord
+        } else {
+            0.0
+        };
+        let rects: Vec<(mir::NodeId, Rect)> = sizes
+            .into_iter()
+            .enumerate()
+            .map(|(i, (record_id, size))| {
+                let angle = std::f32::consts::TAU * i as f32 / n as f32;
+                // Rounded, so that routes between aligned records stay straight.
+                let center_x = (radius * angle.sin()).round();
+                let center_y = (-radius * angle.cos()).round();
+                let origin = Point::new(center_x - size.width / 2.0, center_y - size.height / 2.0);
+
+                (record_id, Rect::new(origin, size))
+            })
+            .collect();
+
+        // Move the circle to the top left corner of the body.
+        let min_x = rects.iter().map(|(_, rect)| rect.min_x()).fold(f32::MAX, f32::min);
+        let min_y = rects.iter().map(|(_, rect)| rect.min_y()).fold(f32::MAX, f32::min);
+        let (dx, dy) = (self.options.margin - min_x, top - min_y);
+        let mut width = 0.0f32;
+        let mut bottom = top;
+        let mut out_of_grid: Vec<Rect> = vec![];
+
+        for (record_id, rect) in rects {
+            let pinned_origin = self.pinned_origin(record_id);
+            let origin = Point::new(rect.min_x() + dx, rect.min_y() + dy);
+            let origin = pinned_origin.unwrap_or(origin);
+            let Some(size) = self.place_record(doc, record_id, origin, rect.width()) else {
+                continue;
+            };
+
+            if pinned_origin.is_some() {
+                out_of_grid.push(Rect::new(origin, size));
+                continue;
+            }
+            width = width.max(origin.x + size.width - self.options.margin);
+            bottom = bottom.max(origin.y + size.height);
+        }
+
+        (width, bottom, out_of_grid)
+    }
+
+    /// Replaces grouped records in `records` with their groups, at the first member of each.
+    fn grid_items(doc: &mir::Document, records: &[mir::NodeId]) -> Vec<mir::NodeId> {
+        let mut items: Vec<mir::NodeId> = vec![];
+
+        for record_id in records.iter().copied() {
+            let item_id = doc.group_of(record_id).unwrap_or(record_id);
+
+            if !items.contains(&item_id) {
+                items.push(item_id);
+            }
+        }
+        items
+    }
+
+    /// Returns the size of a record or a group in a grid cell.
+    fn item_size(&self, doc: &mir::Document, item_id: mir::NodeId) -> Option<Size> {
+        let node = doc.get_node(item_id)?;
+
+        match node.kind() {
+            ShapeKind::Record(_) => Some(Size::new(
+                self.record_width(doc, node),
+                self.options.line_height * node.children().len() as f32,
+            )),
+            ShapeKind::Group(_) => self.group_layout(doc, item_id).map(|(size, _)| size),
+            _ => None,
+        }
+    }
+
+    /// Places a record or a group at `origin`, and returns its size. Records are `width` wide.
+    fn place_item(
+        &self,
+        doc: &mut mir::Document,
+        item_id: mir::NodeId,
+        origin: Point,
+        width: f32,
+    ) -> Option<Size> {
+        let ShapeKind::Group(_) = doc.get_node(item_id)?.kind() else {
+            return self.place_record(doc, item_id, origin, width);
+        };
+        let (size, members) = self.group_layout(doc, item_id)?;
+
+        for (record_id, rect) in members {
+            let member_origin = self
+                .pinned_origin(record_id)
+                .unwrap_or(Point::new(origin.x + rect.min_x(), origin.y + rect.min_y()));
+
+            self.place_record(doc, record_id, member_origin, rect.width());
+        }
+
+        let group_node = doc.get_node_mut(item_id)?;
+        group_node.origin = Some(origin);
+        group_node.size = Some(size);
+        Some(size)
+    }
+
+    /// Lays out members of a group in a grid of about as many rows as columns, below the header
+    /// of `LayoutOptions::line_height`, and returns the size of the group and the rect of each
+    /// member relative to the group origin. Members with a fixed origin are placed out
+    /// of the group, and groups without members have no layout.
+    ///
+    /// Members are padded by `LayoutOptions::shape_junction_margin`, so that edges between
+    /// members are routed inside the group.
+    fn group_layout(
+        &self,
+        doc: &mir::Document,
+        group_id: mir::NodeId,
+    ) -> Option<(Size, Vec<(mir::NodeId, Rect)>)> {
+        let members: Vec<(mir::NodeId, Size)> = doc
+            .get_node(group_id)?
+            .children()
+            .filter(|record_id| self.fixed_origin(doc, *record_id).is_none())
+            .filter_map(|record_id| {
+                let record_node = doc.get_node(record_id)?;
+                let ShapeKind::Record(_) = record_node.kind() else { return None };
+
+                self.item_size(doc, record_id).map(|size| (record_id, size))
+            })
+            .collect();
+
+        if members.is_empty() {
+            return None;
+        }
+
+        let n_columns = (members.len() as f32).sqrt().ceil() as usize;
+        let space = self.options.record_space;
+        let padding = self.options.shape_junction_margin;
+        let mut column_widths = vec![0.0f32; n_columns];
+        let mut row_heights = vec![0.0f32; members.len().div_ceil(n_columns)];
+
+        for (i, (_, size)) in members.iter().enumerate() {
+            column_widths[i % n_columns] = column_widths[i % n_columns].max(size.width);
+            row_heights[i / n_columns] = row_heights[i / n_columns].max(size.height);
+        }
+
+        let offsets = |sizes: &[f32], start: f32| -> Vec<f32> {
+            sizes
+                .iter()
+                .scan(start, |offset, size| {
+                    let current = *offset;
+                    *offset += size + space;
+                    Some(current)
+                })
+                .collect()
+        };
+        let column_x = offsets(&column_widths, padding);
+        let row_y = offsets(&row_heights, self.options.line_height + padding);
+        let spaces = |n: usize| space * n.saturating_sub(1) as f32;
+        let size = Size::new(
+            padding * 2.0 + column_widths.iter().sum::<f32>() + spaces(n_columns),
+            self.options.line_height
+                + padding * 2.0
+                + row_heights.iter().sum::<f32>()
+                + spaces(row_heights.len()),
+        );
+        let members = members
+            .into_iter()
+            .enumerate()
+            .map(|(i, (record_id, size))| {
+                let (row, column) = (i / n_columns, i % n_columns);
+                let origin = Point::new(column_x[column], row_y[row]);
+
+                (record_id, Rect::new(origin, Size::new(column_widths[column], size.height)))
+            })
+            .collect();
+
+        Some((size, members))
+    }
+
+    /// Returns the width of a record, which fits its texts with `fit_record_width`.
+    pub(crate) fn record_width(&self, doc: &mir::Document, record_node: &mir::NodeData) -> f32 {
+        if !self.fit_record_width {
+            return self.options.record_width;
+        }
+
+        // The header is drawn with an icon of the entity kind.
+        let icon = match record_node.kind() {
+            ShapeKind::Record(record) if record.kind.is_some() => {
+                metrics::ICON_SIZE + metrics::ICON_GAP
+            }
+            _ => 0.0,
+        };
+
+        record_node
+            .children()
+            .enumerate()
+            .filter_map(|(i, field_id)| match doc.get_node(field_id)?.kind() {
+                ShapeKind::Field(field) if i == 0 => Some(self.field_width(field) + icon),
+                ShapeKind::Field(field) => Some(self.field_width(field)),
+                _ => None,
+            })
+            .fold(self.options.record_width, f32::max)
+    }
+
+    /// Returns the width a field row needs to render its texts and badges without overlaps.
+    fn field_width(&self, field: &mir::FieldShape) -> f32 {
+        let px = metrics::PADDING_X;
+        let ordinal = if field.ordinal.is_some() {
+            metrics::ORDINAL_WIDTH
+        } else {
+            0.0
+        };
+        let subtitle = field
+            .subtitle
+            .as_ref()
+            .map_or(0.0, |subtitle| self.text_measure.text_width(subtitle));
+        let n_badges = field.badges.len() + usize::from(field.change.is_some());
+        let badge_size = self.options.line_height - metrics::BADGE_MARGIN_Y * 2.0;
+        let badges = if n_badges > 0 {
+            px + badge_size * n_badges as f32 + metrics::BADGE_GAP * (n_badges - 1) as f32
+        } else {
+            0.0
+        };
+
+        px + ordinal
+            + self.text_measure.text_width(&field.title)
+            + metrics::TEXT_GAP
+            + subtitle
+            + badges.max(Self::BADGE_COLUMN_WIDTH)
+    }
 }
 
 impl LayoutEngine for SimpleLayoutEngine {
-    fn place_nodes(&mut self, doc: &mut mir::Document) -> Option<Rect> {
-        // Grid
-        let n_columns = Self::GRID_N_COLUMNS;
+    fn layout_with_hooks(
+        &mut self,
+        doc: &mut mir::Document,
+        hooks: &mut dyn LayoutHooks,
+    ) -> LayoutResult {
+        let view_box = self.place_nodes(doc);
+        let warnings = self.cell_warnings.take();
+
+        hooks.after_stage(LayoutStage::PlaceNodes, doc);
+        self.place_terminal_ports(doc);
+        hooks.after_stage(LayoutStage::PlaceTerminalPorts, doc);
+        self.draw_edge_path(doc);
+        hooks.after_stage(LayoutStage::DrawEdgePaths, doc);
+        self.place_edge_labels(doc);
+        hooks.after_stage(LayoutStage::PlaceEdgeLabels, doc);
+        LayoutResult { view_box, warnings }
+    }
 
-        // Iterate records
-        let child_id_vec = doc.body().children().collect::<Vec<_>>();
+    /// Only records can be moved, and their groups aren't resized. Notes anchored to the record
+    /// or its fields move along. Edges of the record and its fields, and edges whose paths cross
+    /// the record or its notes at their new places, are routed again, and the other paths stay.
+    /// The body grows to the right and bottom to hold them. The pin of a pinned record moves with
+    /// it.
+    ///
+    /// `EdgeRouter::Grid` routes all edges again, because it doesn't keep its grid.
+    fn move_node(
+        &mut self,
+        doc: &mut mir::Document,
+        node_id: mir::NodeId,
+        origin: Point,
+    ) -> LayoutResult {
+        let margin = self.options.margin;
+        // Preferred cells aren't looked at, so there are no warnings.
+        let result = |doc: &mir::Document| LayoutResult {
+            view_box: doc.body().rect().map(|body_rect| {
+                Rect::new(
+                    Point::zero(),
+                    Size::new(body_rect.max_x() + margin, body_rect.max_y() + margin),
+                )
+            }),
+            warnings: vec![],
+        };
+        let old_record_rect = doc
+            .get_node(node_id)
+            .filter(|node| matches!(node.kind(), ShapeKind::Record(_)))
+            .and_then(|node| node.rect());
+        let Some(old_record_rect) = old_record_rect else {
+            return result(doc);
+        };
+
+        self.place_record(doc, node_id, origin, old_record_rect.width());
+        if self.pins.contains_key(&node_id) {
+            self.pin(node_id, origin);
+        }
+
+        let Some(record_node) = doc.get_node(node_id) else {
+            return result(doc);
+        };
+        let Some(record_rect) = record_node.rect() else {
+            return result(doc);
+        };
+        let moved_ids: Vec<mir::NodeId> =
+            [node_id].into_iter().chain(record_node.children()).collect();
+        let (dx, dy) = (
+            record_rect.min_x() - old_record_rect.min_x(),
+            record_rect.min_y() - old_record_rect.min_y(),
+        );
+        let note_ids: Vec<mir::NodeId> = doc
+            .note_ids()
+            .filter(|note_id| {
+                let Some(ShapeKind::Note(note)) = doc.get_node(*note_id).map(|node| node.kind())
+                else {
+                    return false;
+                };
+                note.anchor.is_some_and(|anchor_id| moved_ids.contains(&anchor_id))
+            })
+            .collect();
+        let mut moved_rects = vec![record_rect];
 
-        let mut base_y = Self::ORIGIN.y;
-        let mut max_height = f32::MIN;
+        for note_id in &note_ids {
+            let Some(note_node) = doc.get_node_mut(*note_id) else { continue };
+            let Some(note_origin) = note_node.origin else { continue };
+
+            note_node.origin = Some(Point::new(note_origin.x + dx, note_origin.y + dy));
+            moved_rects.extend(note_node.rect());
+        }
 
-        for (record_index, child_id) in child_id_vec.iter().copied().enumerate() {
-            if record_index > 0 && (record_index % n_columns == 0) {
-                // Move to next row.
-                base_y += max_height + Self::RECORD_SPACE;
-                max_height = f32::MIN;
+        for moved_id in moved_ids.iter().copied() {
+            if let Some(node) = doc.get_node_mut(moved_id) {
+                node.set_terminal_ports(vec![]);
             }
+        }
+        self.place_record_terminal_ports(doc, node_id);
 
-            let Some(record_node) = doc.get_node_mut(child_id) else { continue };
-            let ShapeKind::Record(_) = record_node.kind() else  { continue };
+        if let Some(body_rect) = doc.body().rect() {
+            let max_x = moved_rects.iter().map(Rect::max_x).fold(body_rect.max_x(), f32::max);
+            let max_y = moved_rects.iter().map(Rect::max_y).fold(body_rect.max_y(), f32::max);
 
-            let n_fields = record_node.children().len() as f32;
-            let x = Self::ORIGIN.x
-                + (Self::RECORD_WIDTH + Self::RECORD_SPACE) * (record_index % n_columns) as f32;
+            doc.body_mut().size =
+                Some(Size::new(max_x - body_rect.min_x(), max_y - body_rect.min_y()));
+        }
 
-            let record_height = Self::LINE_HEIGHT * n_fields;
-            max_height = record_height.max(max_height);
+        if self.router == EdgeRouter::Grid {
+            self.draw_grid_edge_paths(doc);
+        } else {
+            // Nodes on the edges of the record and its notes are allowed.
+            let inner_rects: Vec<Rect> =
+                moved_rects.iter().map(|rect| rect.inset_by(1.0, 1.0)).collect();
+            let affected_ids: Vec<mir::EdgeId> = doc
+                .edge_ids()
+                .filter(|edge_id| {
+                    let Some(edge) = doc.edge(*edge_id) else { return false };
+
+                    moved_ids.contains(&edge.source_id())
+                        || moved_ids.contains(&edge.target_id())
+                        || edge.path_points().is_some_and(|points| {
+                            points.windows(2).any(|s| {
+                                inner_rects.iter().any(|rect| rect.intersects_line(&s[0], &s[1]))
+                            })
+                        })
+                })
+                .collect();
+
+            // Junctions around the old place of the record are dropped. The other routes keep
+            // their lanes, and the rerouted ones release theirs.
+            self.edge_route_graph = RouteGraph::new();
+            self.build_edge_route_graph(doc);
+            for edge_id in &affected_ids {
+                self.edge_lanes.remove(edge_id);
+            }
+            for (start, end, lane) in self.edge_lanes.values().flatten() {
+                self.edge_route_graph.take_lane_along(*start, *end, *lane);
+            }
+            for edge_id in affected_ids {
+                let path = self
+                    .find_shortest_edge_route(doc, edge_id)
+                    .map(|route| self.take_lanes(edge_id, &route))
+                    .map(|path| self.snap_path(path));
+
+                if let Some(edge) = doc.edge_mut(edge_id) {
+                    edge.set_path_points(path);
+                }
+            }
+        }
+        self.place_edge_labels(doc);
+
+        result(doc)
+    }
+}
 
-            record_node.origin = Some(Point::new(x, base_y));
-            record_node.size = Some(Size::new(Self::RECORD_WIDTH.into(), record_height.into()));
+impl SimpleLayoutEngine {
+    /// Places all nodes, and returns the view box.
+    fn place_nodes(&mut self, doc: &mut mir::Document) -> Option<Rect> {
+        self.cell_warnings.get_mut().clear();
 
-            // children
-            let field_id_vec = record_node.children().collect::<Vec<_>>();
+        let ShapeKind::Body(body) = doc.body().kind() else { return None };
 
-            for (field_index, field_node_index) in field_id_vec.iter().copied().enumerate() {
-                let y = base_y + Self::LINE_HEIGHT * field_index as f32;
-                let Some(field_node) = doc.get_node_mut(field_node_index) else { continue };
-                let ShapeKind::Field(_) = field_node.kind() else  { continue };
+        // The title is placed at the origin, and records below it.
+        let title_height = body.title.as_ref().map_or(0.0, |title| {
+            font::font_size_px(title.font_size.unwrap_or_default()) + self.title_space()
+        });
 
-                field_node.origin = Some(Point::new(x, y));
-                field_node.size = Some(Size::new(Self::RECORD_WIDTH, Self::LINE_HEIGHT));
+        // Iterate records
+        let child_id_vec = doc.body().children().collect::<Vec<_>>();
+
+        // Grid
+        let record_sizes = || -> Vec<Size> {
+            Self::grid_items(doc, &child_id_vec)
+                .into_iter()
+                .filter_map(|item_id| self.item_size(doc, item_id))
+                .collect()
+        };
+        let mut n_columns = match (body.n_columns, self.target_aspect_ratio) {
+            (Some(n_columns), _) => n_columns,
+            (None, Some(aspect_ratio)) => {
+                self.column_count(&record_sizes(), title_height, aspect_ratio)
             }
+            (None, None) => Self::GRID_N_COLUMNS,
         }
+        .max(1);
+
+        if let Some(max_width) = self.max_width {
+            let record_sizes = record_sizes();
+
+            while n_columns > 1
+                && self.grid_layout_size(&record_sizes, title_height, n_columns).width > max_width
+            {
+                n_columns -= 1;
+            }
+        }
+        // Records with a preferred origin are placed there, and the others in grid cells.
+        // Pinned records keep their cells empty, so that the other records don't move.
+        let (fixed_ids, child_id_vec): (Vec<_>, Vec<_>) = child_id_vec
+            .into_iter()
+            .partition(|child_id| self.fixed_origin(doc, *child_id).is_some());
+        let top = self.options.margin + title_height;
+        let (grid_width, bottom, mut out_of_grid) = if self.circular {
+            self.place_circle(doc, &child_id_vec, top)
+        } else if self.swimlanes && doc.group_ids().len() > 0 {
+            self.place_swimlanes(doc, &child_id_vec, n_columns, top)
+        } else if self.isolated_records == IsolatedRecords::SideColumn {
+            let isolated = Self::isolated_records(doc, &child_id_vec);
+            let (isolated_ids, connected_ids): (Vec<_>, Vec<_>) =
+                child_id_vec.iter().zip(isolated).partition(|(_, isolated)| *isolated);
+            let connected_ids: Vec<_> = connected_ids.into_iter().map(|(id, _)| *id).collect();
+            let (grid_width, bottom, out_of_grid) =
+                self.place_grid(doc, &connected_ids, n_columns, top);
+            let isolated_ids: Vec<_> = isolated_ids.into_iter().map(|(id, _)| *id).collect();
+
+            self.place_side_column(doc, &isolated_ids, grid_width, bottom, out_of_grid, top)
+        } else {
+            self.place_grid(doc, &child_id_vec, n_columns, top)
+        };
+
+        for child_id in fixed_ids {
+            let Some(record_node) = doc.get_node(child_id) else { continue };
+            let Some(fixed_origin) = self.fixed_origin(doc, child_id) else { continue };
+            let record_width = self.record_width(doc, record_node);
+
+            if let Some(size) = self.place_record(doc, child_id, fixed_origin, record_width) {
+                out_of_grid.push(Rect::new(fixed_origin, size));
+            }
+        }
+
+        out_of_grid.extend(self.place_notes(doc, top, bottom));
 
         // Compute view box
-        let min_width = (Self::ORIGIN.x * 2.0) // x-margin
-            + ((n_columns as f32) * Self::RECORD_WIDTH) // shape width
-            + (((n_columns - 1) as f32) * Self::RECORD_SPACE); // spaces
-        let min_height = base_y + max_height + Self::ORIGIN.y;
+        let origin = self.origin();
+        let mut min_width = (origin.x * 2.0) + grid_width;
+        let mut min_height = bottom + origin.y;
+
+        for rect in out_of_grid {
+            min_width = min_width.max(rect.max_x() + origin.x);
+            min_height = min_height.max(rect.max_y() + origin.y);
+        }
+        // Snapped records may move by up to half of `snap`.
+        if let Some(snap) = self.snap_length() {
+            min_width = ((min_width + snap / 2.0) / snap).ceil() * snap;
+            min_height = ((min_height + snap / 2.0) / snap).ceil() * snap;
+        }
+
+        // The body covers the title and records.
+        let body_node = doc.body_mut();
+        body_node.origin = Some(origin);
+        body_node.size = Some(Size::new(
+            min_width - origin.x * 2.0,
+            min_height - origin.y * 2.0,
+        ));
 
         Some(Rect::new(Point::zero(), Size::new(min_width, min_height)))
     }
 
+    /// Places terminal ports of all records and their fields.
     fn place_terminal_ports(&mut self, doc: &mut mir::Document) {
         let child_id_vec = doc.body().children().collect::<Vec<_>>();
 
-        for (_, child_id) in child_id_vec.iter().copied().enumerate() {
-            let Some(record_node) = doc.get_node_mut(child_id) else { continue };
-            let Some(record_rect) = record_node.rect() else { continue };
-
-            // In the case of a rectangle, terminal ports are placed in
-            // the center of each of the four edges.
-            for (x, y, d) in [
-                (record_rect.mid_x(), record_rect.min_y(), Orientation::Up),
-                (record_rect.max_x(), record_rect.mid_y(), Orientation::Right),
-                (record_rect.mid_x(), record_rect.max_y(), Orientation::Down),
-                (record_rect.min_x(), record_rect.mid_y(), Orientation::Left),
-            ] {
-                record_node.add_terminal_port(child_id, Point::new(x, y), d);
-            }
-
-            // For each field in a rectangle, terminal ports are placed
-            // the center of:
-            // - each of the four edges - if the number of fields is `1`.
-            // - top, left and right - for the top field
-            // - bottom, left and right - for the bottom field
-            // - left and right - for the rest
-            let field_id_vec = record_node.children().collect::<Vec<_>>();
-
-            for (field_index, field_node_index) in field_id_vec.iter().copied().enumerate() {
-                let Some(field_node) = doc.get_node_mut(field_node_index) else { continue };
-                let Some(field_rect) = field_node.rect() else { continue };
-
-                if field_id_vec.len() == 1 {
-                    for (x, y, d) in [
-                        (field_rect.mid_x(), field_rect.min_y(), Orientation::Up),
-                        (field_rect.max_x(), field_rect.mid_y(), Orientation::Right),
-                        (field_rect.mid_x(), field_rect.max_y(), Orientation::Down),
-                        (field_rect.min_x(), field_rect.mid_y(), Orientation::Left),
-                    ] {
-                        field_node.add_terminal_port(field_node_index, Point::new(x, y), d);
-                    }
-                } else if field_index == 0 {
-                    for (x, y, d) in [
-                        (field_rect.mid_x(), field_rect.min_y(), Orientation::Up),
-                        (field_rect.max_x(), field_rect.mid_y(), Orientation::Right),
-                        (field_rect.min_x(), field_rect.mid_y(), Orientation::Left),
-                    ] {
-                        field_node.add_terminal_port(field_node_index, Point::new(x, y), d);
-                    }
-                } else if field_index == (field_id_vec.len() - 1) {
-                    for (x, y, d) in [
-                        (field_rect.max_x(), field_rect.mid_y(), Orientation::Right),
-                        (field_rect.mid_x(), field_rect.max_y(), Orientation::Down),
-                        (field_rect.min_x(), field_rect.mid_y(), Orientation::Left),
-                    ] {
-                        field_node.add_terminal_port(field_node_index, Point::new(x, y), d);
-                    }
-                } else {
-                    for (x, y, d) in [
-                        (field_rect.max_x(), field_rect.mid_y(), Orientation::Right),
-                        (field_rect.min_x(), field_rect.mid_y(), Orientation::Left),
-                    ] {
-                        field_node.add_terminal_port(field_node_index, Point::new(x, y), d);
-                    }
-                }
-            }
+        for child_id in child_id_vec.iter().copied() {
+            self.place_record_terminal_ports(doc, child_id);
         }
     }
 
@@ -438,22 +2352,158 @@ impl LayoutEngine for SimpleLayoutEngine {
         //    the shape must remain.
         //
         // e. Add start/end terminal ports.
+        //
+        // COMPLEXITY
+        // ----------
+        // With `n` records, `p` terminal ports of edges, `j` distinct junctions, `k` junctions
+        // on the longest vertical or horizontal line, and `e` edges:
+        //
+        // - (a), (b): O(n)
+        // - (c): O(p * n), each port once, however many edges share it.
+        // - (d): O(j * n) after junctions at the same location are merged.
+        // - Connecting neighbors: O(j * k), where collisions are only checked against records
+        //   across the line.
+        // - Routes: a shortest path search of O(j log j) for each pair of terminal ports of both
+        //   ends of each edge, which dominates. Searches are cached in the route graph.
+        //
+        // A schema of 500 tables and 1000 relations takes a few seconds (see `benches/layout.rs`).
+        if self.router == EdgeRouter::Grid {
+            self.draw_grid_edge_paths(doc);
+            return;
+        }
+
+        self.build_edge_route_graph(doc);
+
+        // Finding shortest edge paths. Edges take lanes of segments (and terminal ports with
+        // `port_capacity`) in insertion order, so that edges don't take the same path.
+        let edge_ids: Vec<mir::EdgeId> = doc.edge_ids().collect();
+
+        self.edge_route_graph.clear_lanes();
+        self.edge_lanes.clear();
+        // Routes which take lanes or ports of earlier routes depend on them.
+        let independent =
+            !(self.separate_edges || self.bundle_edges || self.port_capacity.is_some());
+        let mut route_cache = self.route_cache.take();
+        let use_cache = independent && route_cache.is_some();
+        let mut cached_routes: HashMap<u64, CachedRoute> = HashMap::new();
+        let (mut hits, mut misses) = (0, 0);
+
+        for edge_id in edge_ids {
+            let cache_key = use_cache.then(|| self.route_cache_key(doc, edge_id)).flatten();
+            let cached_path = route_cache
+                .as_ref()
+                .zip(cache_key)
+                .and_then(|(route_cache, key)| route_cache.routes.get(&key))
+                .filter(|route| self.shapes_hash(doc, &route.region) == route.shapes_hash)
+                .map(|route| route.path.clone());
+            let path = if cached_path.is_some() {
+                hits += 1;
+                cached_path
+            } else {
+                misses += usize::from(cache_key.is_some());
+                self.find_shortest_edge_route(doc, edge_id)
+                    .map(|route| self.take_lanes(edge_id, &route))
+                    .map(|path| self.snap_path(path))
+            };
+
+            if let (Some(key), Some(path)) = (cache_key, &path) {
+                let region = self.route_region(doc, edge_id, path);
+                let shapes_hash = self.shapes_hash(doc, &region);
+
+                cached_routes.insert(key, CachedRoute { region, shapes_hash, path: path.clone() });
+            }
+            if let Some(edge) = doc.edge_mut(edge_id) {
+                edge.set_path_points(path);
+            }
+        }
+
+        // Routes of edges which no longer exist are dropped.
+        if let Some(route_cache) = route_cache.as_mut().filter(|_| use_cache) {
+            route_cache.routes = cached_routes;
+            route_cache.hits = hits;
+            route_cache.misses = misses;
+        }
+        self.route_cache = route_cache;
+    }
+
+    /// Places each label at the middle of the longest straight segment of its path where it
+    /// doesn't overlap records, keep-out regions, or labels and cardinalities placed before it,
+    /// and flips each cardinality to the other side of its edge if only that side is free.
+    /// Edges are placed in insertion order.
+    fn place_edge_labels(&mut self, doc: &mut mir::Document) {
+        let records = self.shape_rects(doc);
+        let mut placed: Vec<Rect> = vec![];
+        let edge_ids: Vec<mir::EdgeId> = doc.edge_ids().collect();
+
+        for edge_id in edge_ids {
+            let Some(edge) = doc.edge_mut(edge_id) else { continue };
+            let Some(path_points) = edge.path_points().filter(|points| points.len() >= 2) else {
+                continue;
+            };
+            let path_points = path_points.to_vec();
+            let free = |rect: &Rect, placed: &[Rect]| {
+                records.iter().chain(placed).all(|other| !other.intersects(rect))
+            };
+
+            let label_center = edge.label().and_then(|label| {
+                let size = metrics::edge_label_size(label);
+                let center =
+                    metrics::edge_label_center(&path_points, size, |r| free(r, &placed))?;
+
+                placed.push(metrics::edge_label_rect(center, size));
+                Some(center)
+            });
+
+            let n = path_points.len();
+            let ends = [
+                (edge.start_cardinality(), &path_points[0], &path_points[1]),
+                (edge.end_cardinality(), &path_points[n - 1], &path_points[n - 2]),
+            ];
+            let flips = ends.map(|(cardinality, port, next)| {
+                let Some(cardinality) = cardinality else { return false };
+                let [rect, flipped_rect] = [false, true]
+                    .map(|flip| metrics::cardinality_rect(cardinality, port, next, flip));
+                let flip = !free(&rect, &placed) && free(&flipped_rect, &placed);
+
+                placed.push(if flip { flipped_rect } else { rect });
+                flip
+            });
+
+            edge.set_label_center(label_center);
+            edge.set_cardinality_flips(flips);
+        }
+    }
+}
 
+impl SimpleLayoutEngine {
+    /// Adds junctions around shapes and of terminal ports of edges to the route graph, and
+    /// connects them (see `draw_edge_path`).
+    fn build_edge_route_graph(&mut self, doc: &mir::Document) {
         // Place junction nodes at the four corner points around each shape node.
-        let shape_junctions = self.edge_junction_nodes_around_shapes(&doc);
+        let mut shape_junctions = self.edge_junction_nodes_around_shapes(
+            doc,
+            doc.body().children().chain(doc.group_ids()).chain(doc.note_ids()),
+        );
+        shape_junctions.extend(self.edge_junction_nodes_around_keep_out_regions());
 
         // From the start/end junction point, draw a straight line horizontally or vertically until
         // it collides with another shape node, and place a new junction node at the point where it
         // intersects the junction node in a crosswise direction.
         let mut crossing_junctions: Vec<Point> = vec![];
 
+        // Ports shared by edges (e.g. of `users.id`) are only visited once.
+        let mut visited_ports: HashSet<TerminalPortId> = HashSet::new();
+
         for edge in doc.edges() {
             let Some(start_node) = doc.get_node(edge.source_id()) else { continue };
             let Some(end_node) = doc.get_node(edge.target_id()) else { continue };
 
             for pt in start_node.terminal_ports() {
+                if !visited_ports.insert(pt.id()) {
+                    continue;
+                }
                 let junctions = self.edge_junction_nodes_from_terminal_port(
-                    &doc,
+                    doc,
                     start_node,
                     pt,
                     &shape_junctions,
@@ -462,8 +2512,11 @@ impl LayoutEngine for SimpleLayoutEngine {
                 crossing_junctions.extend(junctions);
             }
             for pt in end_node.terminal_ports() {
+                if !visited_ports.insert(pt.id()) {
+                    continue;
+                }
                 let junctions = self.edge_junction_nodes_from_terminal_port(
-                    &doc,
+                    doc,
                     end_node,
                     pt,
                     &shape_junctions,
@@ -474,7 +2527,7 @@ impl LayoutEngine for SimpleLayoutEngine {
         }
 
         let edge_junctions = self.remove_overlapped_junction_nodes(
-            &doc,
+            doc,
             shape_junctions.iter().chain(crossing_junctions.iter()),
         );
 
@@ -497,37 +2550,429 @@ impl LayoutEngine for SimpleLayoutEngine {
         }
 
         self.connect_nearest_neighbor_edge_junctions(doc);
+    }
+
+    /// Returns the key of the cached route of `edge_id`: a hash of the config and the terminal
+    /// ports of both ends.
+    fn route_cache_key(&self, doc: &mir::Document, edge_id: mir::EdgeId) -> Option<u64> {
+        let (source_id, target_id) = doc.edge_endpoints(edge_id)?;
+        let mut bytes = self.config_hash().to_le_bytes().to_vec();
+
+        for node_id in [source_id, target_id] {
+            for port in doc.get_node(node_id)?.terminal_ports() {
+                bytes.extend(port.location().x.to_le_bytes());
+                bytes.extend(port.location().y.to_le_bytes());
+                bytes.push(port.orientation() as u8);
+            }
+            bytes.push(b'|');
+        }
 
-        // Finding shortest edge paths
-        let edge_ids = doc.edge_ids();
-        let mut paths: VecDeque<Vec<Point>> = VecDeque::with_capacity(edge_ids.len());
+        Some(fnv1a(bytes))
+    }
 
-        for edge_id in edge_ids {
-            if let Some(path) = self.find_shortest_edges_path(doc, edge_id) {
-                paths.push_back(path);
+    /// Returns the bounding box of `path` and both ends of `edge_id`, extended by
+    /// `LayoutOptions::record_space`.
+    fn route_region(&self, doc: &mir::Document, edge_id: mir::EdgeId, path: &[Point]) -> Rect {
+        let end_rects = doc
+            .edge_endpoints(edge_id)
+            .into_iter()
+            .flat_map(|(source_id, target_id)| [source_id, target_id])
+            .filter_map(|node_id| doc.get_node(node_id)?.rect());
+        let points = path
+            .iter()
+            .copied()
+            .chain(end_rects.flat_map(|r| [r.origin, Point::new(r.max_x(), r.max_y())]));
+        let (min, max) = points.fold(
+            (Point::new(f32::MAX, f32::MAX), Point::new(f32::MIN, f32::MIN)),
+            |(min, max), p| {
+                (
+                    Point::new(min.x.min(p.x), min.y.min(p.y)),
+                    Point::new(max.x.max(p.x), max.y.max(p.y)),
+                )
+            },
+        );
+        let space = self.options.record_space;
+
+        Rect::new(min, Size::new(max.x - min.x, max.y - min.y)).inset_by(-space, -space)
+    }
+
+    /// Returns a hash of the shapes which intersect `region`, regardless of their order.
+    fn shapes_hash(&self, doc: &mir::Document, region: &Rect) -> u64 {
+        let mut shapes: Vec<[u32; 4]> = self
+            .shape_rects(doc)
+            .into_iter()
+            .chain(doc.group_ids().filter_map(|group_id| doc.get_node(group_id)?.rect()))
+            .filter(|r| r.intersects(region))
+            .map(|r| [r.min_x(), r.min_y(), r.width(), r.height()].map(f32::to_bits))
+            .collect();
+
+        shapes.sort_unstable();
+        fnv1a(shapes.into_iter().flatten().flat_map(u32::to_le_bytes))
+    }
+
+    /// Updates the layout of `doc`, which was laid out by this engine and then extended with
+    /// records (appended to the body) or edges, and returns the view box. New records, which
+    /// have no origin, are placed in rows below the others, and only new edges, which have no
+    /// path, are routed. Records and paths laid out before stay. `doc` is laid out from
+    /// scratch if it has never been laid out.
+    ///
+    /// `EdgeRouter::Grid` routes all edges again, because it doesn't keep its grid.
+    pub fn update_layout(&mut self, doc: &mut mir::Document) -> Option<Rect> {
+        let Some(body_rect) = doc.body().rect() else {
+            return self.layout(doc).view_box;
+        };
+
+        let new_records: Vec<mir::NodeId> = doc
+            .body()
+            .children()
+            .filter(|child_id| doc.get_node(*child_id).is_some_and(|node| node.origin.is_none()))
+            .collect();
+
+        // New records are placed from left to right, and wrap at the width of the body.
+        let space = self.options.record_space;
+        let mut origin = Point::new(body_rect.min_x(), body_rect.max_y() + space);
+        let mut row_height = 0.0f32;
+        let mut max_x = body_rect.max_x();
+        let mut max_y = body_rect.max_y();
+
+        for record_id in new_records.iter().copied() {
+            let Some(record_node) = doc.get_node(record_id) else { continue };
+            let record_width = self.record_width(doc, record_node);
+
+            if origin.x > body_rect.min_x() && origin.x + record_width > body_rect.max_x() {
+                origin = Point::new(body_rect.min_x(), origin.y + row_height + space);
+                row_height = 0.0;
+            }
+
+            let Some(size) = self.place_record(doc, record_id, origin, record_width) else {
+                continue;
+            };
+
+            self.place_record_terminal_ports(doc, record_id);
+            max_x = max_x.max(origin.x + size.width);
+            max_y = max_y.max(origin.y + size.height);
+            row_height = row_height.max(size.height);
+            origin.x += size.width + space;
+        }
+
+        doc.body_mut().size = Some(Size::new(
+            max_x - body_rect.min_x(),
+            max_y - body_rect.min_y(),
+        ));
+
+        if self.router == EdgeRouter::Grid {
+            self.draw_grid_edge_paths(doc);
+        } else {
+            self.route_new_edges(doc, &new_records);
+        }
+        self.place_edge_labels(doc);
+
+        let margin = self.options.margin;
+        Some(Rect::new(Point::zero(), Size::new(max_x + margin, max_y + margin)))
+    }
+
+    /// Adds junctions around `new_records` and of terminal ports of edges which have no path
+    /// to the route graph, and routes those edges.
+    fn route_new_edges(&mut self, doc: &mut mir::Document, new_records: &[mir::NodeId]) {
+        let new_edge_ids: Vec<mir::EdgeId> = doc
+            .edge_ids()
+            .filter(|edge_id| doc.edge(*edge_id).is_some_and(|edge| edge.path_points().is_none()))
+            .collect();
+        let mut shape_junctions = self
+            .edge_junction_nodes_around_shapes(doc, doc.body().children().chain(doc.note_ids()));
+        shape_junctions.extend(self.edge_junction_nodes_around_keep_out_regions());
+        let mut junctions = self.edge_junction_nodes_around_shapes(doc, new_records.to_vec());
+        let mut terminal_ports: Vec<TerminalPort> = vec![];
+
+        for edge_id in new_edge_ids.iter().copied() {
+            let Some((source_id, target_id)) = doc.edge_endpoints(edge_id) else { continue };
+
+            for node_id in [source_id, target_id] {
+                let Some(node) = doc.get_node(node_id) else { continue };
+
+                for pt in node.terminal_ports() {
+                    junctions.extend(self.edge_junction_nodes_from_terminal_port(
+                        doc,
+                        node,
+                        pt,
+                        &shape_junctions,
+                    ));
+                    terminal_ports.push(pt.clone());
+                }
             }
         }
 
-        for edge in doc.edges_mut() {
-            edge.set_path_points(Some(paths.pop_front().unwrap()));
+        for j in self.remove_overlapped_junction_nodes(doc, junctions.iter()) {
+            self.edge_route_graph.add_node(j);
+        }
+        for pt in terminal_ports.iter() {
+            self.edge_route_graph.add_terminal_port(pt);
+        }
+        self.connect_nearest_neighbor_edge_junctions(doc);
+
+        for edge_id in new_edge_ids {
+            let path = self
+                .find_shortest_edge_route(doc, edge_id)
+                .map(|route| self.take_lanes(edge_id, &route))
+                .map(|path| self.snap_path(path));
+
+            if let Some(edge) = doc.edge_mut(edge_id) {
+                edge.set_path_points(path);
+            }
+        }
+    }
+
+    /// Places terminal ports of a record and its fields.
+    fn place_record_terminal_ports(&self, doc: &mut mir::Document, child_id: mir::NodeId) {
+        let Some(record_node) = doc.get_node_mut(child_id) else { return };
+        let Some(record_rect) = record_node.rect() else { return };
+
+        // In the case of a rectangle, terminal ports are placed in
+        // the center of each of the four edges.
+        for (x, y, d) in [
+            (record_rect.mid_x(), record_rect.min_y(), Orientation::Up),
+            (record_rect.max_x(), record_rect.mid_y(), Orientation::Right),
+            (record_rect.mid_x(), record_rect.max_y(), Orientation::Down),
+            (record_rect.min_x(), record_rect.mid_y(), Orientation::Left),
+        ] {
+            record_node.add_terminal_port(child_id, self.snap_point(Point::new(x, y)), d);
+        }
+
+        // For each field in a rectangle, terminal ports are placed
+        // the center of:
+        // - each of the four edges - if the number of fields is `1`.
+        // - top, left and right - for the top field
+        // - bottom, left and right - for the bottom field
+        // - left and right - for the rest
+        let field_id_vec = record_node.children().collect::<Vec<_>>();
+
+        for (field_index, field_node_index) in field_id_vec.iter().copied().enumerate() {
+            let Some(field_node) = doc.get_node_mut(field_node_index) else { continue };
+            let Some(field_rect) = field_node.rect() else { continue };
+
+            if field_id_vec.len() == 1 {
+                for (x, y, d) in [
+                    (field_rect.mid_x(), field_rect.min_y(), Orientation::Up),
+                    (field_rect.max_x(), field_rect.mid_y(), Orientation::Right),
+                    (field_rect.mid_x(), field_rect.max_y(), Orientation::Down),
+                    (field_rect.min_x(), field_rect.mid_y(), Orientation::Left),
+                ] {
+                    let location = self.snap_point(Point::new(x, y));
+                    field_node.add_terminal_port(field_node_index, location, d);
+                }
+            } else if field_index == 0 {
+                for (x, y, d) in [
+                    (field_rect.mid_x(), field_rect.min_y(), Orientation::Up),
+                    (field_rect.max_x(), field_rect.mid_y(), Orientation::Right),
+                    (field_rect.min_x(), field_rect.mid_y(), Orientation::Left),
+                ] {
+                    let location = self.snap_point(Point::new(x, y));
+                    field_node.add_terminal_port(field_node_index, location, d);
+                }
+            } else if field_index == (field_id_vec.len() - 1) {
+                for (x, y, d) in [
+                    (field_rect.max_x(), field_rect.mid_y(), Orientation::Right),
+                    (field_rect.mid_x(), field_rect.max_y(), Orientation::Down),
+                    (field_rect.min_x(), field_rect.mid_y(), Orientation::Left),
+                ] {
+                    let location = self.snap_point(Point::new(x, y));
+                    field_node.add_terminal_port(field_node_index, location, d);
+                }
+            } else {
+                for (x, y, d) in [
+                    (field_rect.max_x(), field_rect.mid_y(), Orientation::Right),
+                    (field_rect.min_x(), field_rect.mid_y(), Orientation::Left),
+                ] {
+                    let location = self.snap_point(Point::new(x, y));
+                    field_node.add_terminal_port(field_node_index, location, d);
+                }
+            }
         }
     }
 }
 
 impl SimpleLayoutEngine {
-    const SHAPE_JUNCTION_MARGIN: f32 = Self::RECORD_SPACE / 2.0;
+    /// Routes edges on a grid graph around records, in insertion order. Routes leave terminal
+    /// ports straight for half of `shape_junction_margin`, and join the nearest grid node.
+    fn draw_grid_edge_paths(&self, doc: &mut mir::Document) {
+        let cell_size = self.options.lane_spacing;
+        let clearance = self.options.shape_junction_margin / 2.0;
+        let shape_rects: Vec<Rect> = self
+            .shape_rects(doc)
+            .into_iter()
+            .map(|rect| rect.inset_by(-clearance, -clearance))
+            .collect();
+        let mut grid = GridGraphBuilder::new(cell_size)
+            .margin((clearance / cell_size).ceil() as usize)
+            .blocked_rects(&shape_rects)
+            .build();
+
+        let paths: Vec<Option<Vec<Point>>> = doc
+            .edge_ids()
+            .map(|edge_id| self.find_grid_edge_path(&mut grid, doc, edge_id, clearance))
+            .collect();
+
+        for (edge, path) in doc.edges_mut().zip(paths) {
+            edge.set_path_points(path.map(|path| self.snap_path(path)));
+        }
+    }
+
+    /// Finds the cheapest route on `grid` between any terminal ports of both ends of an edge,
+    /// and takes its grid edges.
+    fn find_grid_edge_path(
+        &self,
+        grid: &mut GridGraph,
+        doc: &mir::Document,
+        edge_id: mir::EdgeId,
+        clearance: f32,
+    ) -> Option<Vec<Point>> {
+        // The grid node where a route from a terminal port joins the grid.
+        let exit = |grid: &GridGraph, port: &TerminalPort| {
+            let location = port.location();
+            let (dx, dy, rounding) = match port.orientation() {
+                Orientation::Up => (0.0, -clearance, Rounding::Floor),
+                Orientation::Down => (0.0, clearance, Rounding::Ceil),
+                Orientation::Left => (-clearance, 0.0, Rounding::Floor),
+                Orientation::Right => (clearance, 0.0, Rounding::Ceil),
+            };
+            let out = Point::new(location.x + dx, location.y + dy);
+            let rounded = grid.coord_map().with_rounding(rounding).to_grid(&out);
+            let nearest = grid.coord_map().to_grid(&out);
+            let p = match port.orientation() {
+                Orientation::Up | Orientation::Down => GridPoint::new(nearest.x, rounded.y),
+                Orientation::Left | Orientation::Right => GridPoint::new(rounded.x, nearest.y),
+            };
+
+            grid.contains(p).then_some(p)
+        };
+        // The corner between a terminal port and its grid node.
+        let elbow = |port: &TerminalPort, p: &Point| match port.orientation() {
+            Orientation::Up | Orientation::Down => Point::new(port.location().x, p.y),
+            Orientation::Left | Orientation::Right => Point::new(p.x, port.location().y),
+        };
+        let exits = |node: &mir::NodeData| -> Vec<(GridPoint, TerminalPort)> {
+            let mut ports: Vec<&TerminalPort> = node.terminal_ports().collect();
+
+            ports.sort_by_key(|port| self.port_order(port));
+            ports
+                .into_iter()
+                .filter_map(|port| exit(grid, port).map(|p| (p, port.clone())))
+                .collect()
+        };
+
+        let (source_id, target_id) = doc.edge_endpoints(edge_id)?;
+        let mut sources = exits(doc.get_node(source_id)?);
+        let mut targets = exits(doc.get_node(target_id)?);
+
+        // An edge between fields of a stub leaves its preferred port for another one.
+        if source_id == target_id {
+            sources.truncate(1);
+        }
+
+        // A route must leave the grid node where it joins.
+        targets.retain(|(p, _)| sources.iter().all(|(q, _)| p != q));
+
+        let source_points: Vec<(GridPoint, u32)> = sources.iter().map(|(p, _)| (*p, 0)).collect();
+        let target_points: Vec<GridPoint> = targets.iter().map(|(p, _)| *p).collect();
+        let (_, route) = grid.route(&source_points, &target_points)?;
+        let (first, last) = (route.first()?, route.last()?);
+        let (_, source_port) = sources.iter().find(|(p, _)| p == first)?;
+        let (_, target_port) = targets.iter().find(|(p, _)| p == last)?;
+
+        let start = grid.to_world(*first);
+        let end = grid.to_world(*last);
+        let points = [*source_port.location(), elbow(source_port, &start)]
+            .into_iter()
+            .chain(route.iter().map(|p| grid.to_world(*p)))
+            .chain([elbow(target_port, &end), *target_port.location()])
+            .collect();
+
+        grid.take_route(&route);
+        Some(Self::straighten_jogs(
+            Self::simplify_path(points),
+            grid.coord_map().cell_size,
+        ))
+    }
+
+    /// Removes jogs shorter than `max_jog` (which joining grid nodes makes next to terminal
+    /// ports) by moving the straight line after a jog onto the line before it, or the line
+    /// before onto the line after if the line after ends at a terminal port.
+    ///
+    /// ```svgbob
+    ///             +--*            +--*--*
+    ///  o-----*    |         =>    |
+    ///        `----'               o-----*
+    /// ```
+    fn straighten_jogs(mut path: Vec<Point>, max_jog: f32) -> Vec<Point> {
+        let mut i = 1;
+
+        while i + 2 < path.len() {
+            let (a, b, c, d) = (path[i - 1], path[i], path[i + 1], path[i + 2]);
+            let jog = b.distance(&c);
+            let parallel = (a.x == b.x && c.x == d.x) || (a.y == b.y && c.y == d.y);
+
+            if jog >= max_jog || !parallel || (i == 1 && i + 2 == path.len() - 1) {
+                i += 1;
+                continue;
+            }
+
+            // Move the line which doesn't end at a terminal port.
+            let (from, to, onto) = if i + 2 < path.len() - 1 {
+                (i + 1, i + 2, b)
+            } else {
+                (i - 1, i, c)
+            };
+            for point in &mut path[from..=to] {
+                if a.y == b.y {
+                    point.y = onto.y;
+                } else {
+                    point.x = onto.x;
+                }
+            }
+
+            path = Self::simplify_path(path);
+            i = 1;
+        }
+
+        path
+    }
+
+    /// Removes repeated points and points in the middle of straight lines, including lines
+    /// which turn around.
+    fn simplify_path(points: Vec<Point>) -> Vec<Point> {
+        let mut path: Vec<Point> = Vec::with_capacity(points.len());
+
+        for point in points {
+            if path.last() == Some(&point) {
+                continue;
+            }
+            if let [.., a, b] = path[..] {
+                if (a.x == b.x && b.x == point.x) || (a.y == b.y && b.y == point.y) {
+                    path.pop();
+                }
+            }
+            path.push(point);
+        }
+
+        path
+    }
 
     // a. For each shape node, create a new larger, fatter shape.
     //
     // b. Place junction nodes at the four corner points of (a)
-    fn edge_junction_nodes_around_shapes(&self, doc: &mir::Document) -> Vec<Point> {
-        let margin = Self::SHAPE_JUNCTION_MARGIN;
+    fn edge_junction_nodes_around_shapes(
+        &self,
+        doc: &mir::Document,
+        records: impl IntoIterator<Item = mir::NodeId>,
+    ) -> Vec<Point> {
+        let margin = self.options.shape_junction_margin;
         let mut junctions: Vec<Point> = vec![];
 
-        for child_id in doc.body().children() {
-            let Some(record_node) = doc.get_node(child_id) else { continue };
-            let Some(record_rect) = record_node.rect() else { continue };
+        let record_rects = records
+            .into_iter()
+            .filter_map(|child_id| doc.get_node(child_id)?.rect());
 
+        for record_rect in record_rects {
             let junction_rect = record_rect.inset_by(-margin, -margin);
 
             junctions.extend([
@@ -541,6 +2986,36 @@ impl SimpleLayoutEngine {
         junctions
     }
 
+    /// Returns rects of records, notes and `LayoutOptions::keep_out_regions`, which routes
+    /// avoid.
+    fn shape_rects(&self, doc: &mir::Document) -> Vec<Rect> {
+        doc.body()
+            .children()
+            .chain(doc.note_ids())
+            .filter_map(|child_id| doc.get_node(child_id)?.rect())
+            .chain(self.options.keep_out_regions.iter().copied())
+            .collect()
+    }
+
+    /// Places junction nodes at the four corner points around each keep-out region.
+    fn edge_junction_nodes_around_keep_out_regions(&self) -> Vec<Point> {
+        let margin = self.options.shape_junction_margin;
+
+        self.options
+            .keep_out_regions
+            .iter()
+            .map(|r| r.inset_by(-margin, -margin))
+            .flat_map(|r| {
+                [
+                    r.origin,
+                    Point::new(r.max_x(), r.min_y()),
+                    Point::new(r.max_x(), r.max_y()),
+                    Point::new(r.min_x(), r.max_y()),
+                ]
+            })
+            .collect()
+    }
+
     // c. From the start/end terminal port, draw a straight line horizontally or vertically
     //    until it collides with another shape node, and place a new junction node at the point
     //    where it intersects the junction node (b) in a crosswise direction.
@@ -551,14 +3026,12 @@ impl SimpleLayoutEngine {
         terminal_port: &TerminalPort,
         other_junctions: &[Point],
     ) -> Vec<Point> {
-        let margin = Self::SHAPE_JUNCTION_MARGIN;
+        let margin = self.options.shape_junction_margin;
         let mut junctions = vec![];
 
-        let shape_rects = doc
-            .body()
-            .children()
-            .filter_map(|child_id| doc.get_node(child_id))
-            .filter_map(|node| node.rect())
+        let shape_rects = self
+            .shape_rects(doc)
+            .into_iter()
             .map(|r| r.inset_by(-margin, -margin))
             .collect::<Vec<_>>();
 
@@ -566,7 +3039,7 @@ impl SimpleLayoutEngine {
 
         match terminal_port.orientation() {
             Orientation::Left => {
-                let mut min_x = 0.0f32;
+                let mut min_x = f32::MIN;
                 let line_end = Point::new(f32::MIN, conn_pt.y);
 
                 for r in shape_rects {
@@ -614,7 +3087,7 @@ impl SimpleLayoutEngine {
                 }
             }
             Orientation::Down => {
-                let mut min_y = 0.0f32;
+                let mut min_y = f32::MIN;
                 let line_end = Point::new(conn_pt.x, f32::MIN);
 
                 for r in shape_rects {
@@ -643,21 +3116,25 @@ impl SimpleLayoutEngine {
 
         // Remove junction nodes that overlap any (fatter) shapes. However, nodes on the edge of the
         // shape must remain.
-        let shape_rects = doc
-            .body()
-            .children()
-            .filter_map(|node_index| doc.get_node(node_index))
-            .filter_map(|node| node.rect())
+        let shape_rects = self
+            .shape_rects(doc)
+            .into_iter()
             .map(|r| {
                 r.inset_by(
                     // Nodes on the edge of fatter shapes must remain. So minus 1.0 from margin.
-                    -(Self::SHAPE_JUNCTION_MARGIN - 1.0),
-                    -(Self::SHAPE_JUNCTION_MARGIN - 1.0),
+                    -(self.options.shape_junction_margin - 1.0),
+                    -(self.options.shape_junction_margin - 1.0),
                 )
             })
             .collect::<Vec<_>>();
 
+        let mut visited: HashSet<(u32, u32)> = HashSet::new();
+
         'OUTER: for j in junctions {
+            // Junctions at the same location are merged by the route graph.
+            if !visited.insert(RouteGraph::location_key(j)) {
+                continue;
+            }
             for r in &shape_rects {
                 if r.contains_point(j) {
                     continue 'OUTER;
@@ -675,33 +3152,55 @@ impl SimpleLayoutEngine {
         let mut edges: Vec<(RouteNodeId, RouteNodeId)> = Vec::new();
 
         // Collision detection
-        let shape_rects = doc
-            .body()
-            .children()
-            .filter_map(|node_index| doc.get_node(node_index).map(|node| (node_index, node)))
-            .filter_map(|(node_index, node)| {
-                node.rect().map(|r| {
-                    (
-                        node_index,
-                        // Nodes on the edge of shapes must remain. So minus 1.0.
-                        r.inset_by(1.0, 1.0),
-                    )
-                })
-            })
+        let shape_rects = self
+            .shape_rects(doc)
+            .into_iter()
+            // Nodes on the edge of shapes must remain. So minus 1.0.
+            .map(|r| r.inset_by(1.0, 1.0))
             .collect::<Vec<_>>();
 
+        // Neighbors are only looked up on the same vertical or horizontal line, and only shapes
+        // across the line can collide with segments on it.
+        let (columns, rows) = self.edge_route_graph.nodes_on_lines();
+        let shapes_across = |key: u32, vertical: bool| -> Vec<Rect> {
+            let v = f32::from_bits(key);
+
+            shape_rects
+                .iter()
+                .copied()
+                .filter(|r| {
+                    let (min, max) = if vertical {
+                        (r.min_x(), r.max_x())
+                    } else {
+                        (r.min_y(), r.max_y())
+                    };
+                    min <= v && v <= max
+                })
+                .collect()
+        };
+        let column_shapes: HashMap<u32, Vec<Rect>> =
+            columns.keys().map(|x| (*x, shapes_across(*x, true))).collect();
+        let row_shapes: HashMap<u32, Vec<Rect>> =
+            rows.keys().map(|y| (*y, shapes_across(*y, false))).collect();
+
         for a in self.edge_route_graph.node_ids() {
             let mut left: Option<(RouteNodeId, &RouteNodeData)> = None;
             let mut right: Option<(RouteNodeId, &RouteNodeData)> = None;
             let mut up: Option<(RouteNodeId, &RouteNodeData)> = None;
             let mut down: Option<(RouteNodeId, &RouteNodeData)> = None;
+            let location = self.edge_route_graph.get_node(a).unwrap().location();
+            let (x, y) = RouteGraph::location_key(location);
+            let on_lines = columns[&x].iter().chain(&rows[&y]).copied();
 
-            for b in self.edge_route_graph.node_ids() {
+            for b in on_lines {
                 let n = self.edge_route_graph.get_node(a).unwrap();
                 let m = self.edge_route_graph.get_node(b).unwrap();
                 let p = n.location();
                 let q = m.location();
-                let no_collision = || !shape_rects.iter().any(|(_, r)| r.intersects_line(p, q));
+                let no_collision = || {
+                    let shapes = if q.x == p.x { &column_shapes[&x] } else { &row_shapes[&y] };
+                    !shapes.iter().any(|r| r.intersects_line(p, q))
+                };
 
                 if q.x == p.x && q.y < p.y {
                     // vertically upward
@@ -716,7 +3215,7 @@ impl SimpleLayoutEngine {
                     // Is connectable direction?
                     if n.is_connectable(Orientation::Up) && m.is_connectable(Orientation::Down) {
                         // Is nearest neighbor?
-                        if up.is_none() || up.unwrap().1.location().y < q.y && no_collision() {
+                        if up.is_none_or(|(_, up)| up.location().y < q.y) && no_collision() {
                             up.replace((b, m));
                         }
                     }
@@ -733,7 +3232,7 @@ impl SimpleLayoutEngine {
                     // Is connectable direction?
                     if n.is_connectable(Orientation::Down) && m.is_connectable(Orientation::Up) {
                         // Is nearest neighbor?
-                        if down.is_none() || down.unwrap().1.location().y > q.y && no_collision() {
+                        if down.is_none_or(|(_, down)| down.location().y > q.y) && no_collision() {
                             down.replace((b, m));
                         }
                     }
@@ -747,7 +3246,7 @@ impl SimpleLayoutEngine {
                     // Is connectable direction?
                     if n.is_connectable(Orientation::Left) && m.is_connectable(Orientation::Right) {
                         // Is nearest neighbor?
-                        if left.is_none() || left.unwrap().1.location().x < q.x && no_collision() {
+                        if left.is_none_or(|(_, left)| left.location().x < q.x) && no_collision() {
                             left.replace((b, m));
                         }
                     }
@@ -761,7 +3260,7 @@ impl SimpleLayoutEngine {
                     // Is connectable direction?
                     if n.is_connectable(Orientation::Right) && m.is_connectable(Orientation::Left) {
                         // Is nearest neighbor?
-                        if right.is_none() || right.unwrap().1.location().x > q.x && no_collision()
+                        if right.is_none_or(|(_, right)| right.location().x > q.x) && no_collision()
                         {
                             right.replace((b, m));
                         }
@@ -782,73 +3281,525 @@ impl SimpleLayoutEngine {
 
     /// Find the shortest path between both ends of a specified `edge`.
     ///
-    /// Returns locations of each nodes (start, intermediate and end) on the shortest path.
-    fn find_shortest_edges_path(
+    /// Returns junctions (start, intermediate and end) on the shortest path.
+    fn find_shortest_edge_route(
         &self,
         doc: &mir::Document,
         edge_id: mir::EdgeId,
-    ) -> Option<Vec<Point>> {
-        let Some((source_id, target_id)) = doc.edge_endpoints(edge_id) else { return None };
+    ) -> Option<Vec<RouteNodeId>> {
+        let explanation = self.explain_edge(doc, edge_id)?;
+
+        explanation.selected?;
+        Some(explanation.path)
+    }
+
+    /// Takes the lowest free lane of each straight run of `route`, and returns locations of its
+    /// junctions. With `separate_edges`, runs are moved to the side by their lanes. The first
+    /// and last runs stay on lane `0`, because they are connected to terminal ports.
+    ///
+    /// ```svgbob
+    ///        lane 0   lane 1   lane 2
+    ///           |        |        |
+    ///    o------+        |        |
+    ///           |  o-----+        |
+    ///           |        |  o-----+
+    /// ```
+    fn take_lanes(&mut self, edge_id: mir::EdgeId, route: &[RouteNodeId]) -> Vec<Point> {
+        let graph = &mut self.edge_route_graph;
+        let mut points: Vec<Point> = route
+            .iter()
+            .map(|id| *graph.get_node(*id).unwrap().location())
+            .collect();
+
+        // Straight runs of collinear segments as ranges of junction indices.
+        let mut runs: Vec<(usize, usize)> = vec![];
+
+        for i in 1..points.len() {
+            let horizontal = points[i - 1].y == points[i].y;
+
+            match runs.last_mut() {
+                Some((start, end)) if (points[*start].y == points[*end].y) == horizontal => {
+                    *end = i;
+                }
+                _ => runs.push((i - 1, i)),
+            }
+        }
+
+        let max_offset = self.options.shape_junction_margin / 2.0;
+        let mut run_lanes = vec![];
+
+        for (run_index, (start, end)) in runs.iter().copied().enumerate() {
+            let segments = || route[start..=end].windows(2).map(|w| (w[0], w[1]));
+            let lane = if run_index == 0 || run_index == runs.len() - 1 {
+                0
+            } else {
+                (0..)
+                    .find(|lane| segments().all(|(a, b)| !graph.lanes(a, b).contains(lane)))
+                    .unwrap()
+            };
+
+            for (a, b) in segments() {
+                graph.take_lane(a, b, lane);
+            }
+            // Points may have been moved to the lane of the previous run.
+            let [start_point, end_point] =
+                [route[start], route[end]].map(|id| *graph.get_node(id).unwrap().location());
+
+            run_lanes.push((start_point, end_point, lane));
+            if !self.separate_edges || lane == 0 {
+                continue;
+            }
+
+            // Lanes alternate between both sides: 0, +1, -1, +2, -2, ...
+            let side = if lane % 2 == 1 { 1.0 } else { -1.0 };
+            let offset = (side * lane.div_ceil(2) as f32 * self.options.lane_spacing)
+                .clamp(-max_offset, max_offset);
+            let horizontal = points[start].y == points[end].y;
+
+            for point in &mut points[start..=end] {
+                if horizontal {
+                    point.y += offset;
+                } else {
+                    point.x += offset;
+                }
+            }
+        }
+
+        self.edge_lanes.insert(edge_id, run_lanes);
+        points
+    }
+
+    /// Replays the route search of an edge and returns how the route was chosen.
+    ///
+    /// It must be called after `layout`. Returns `None` with `EdgeRouter::Grid`, which
+    /// searches routes on another graph.
+    pub fn explain_edge(
+        &self,
+        doc: &mir::Document,
+        edge_id: mir::EdgeId,
+    ) -> Option<RouteExplanation> {
+        if self.router == EdgeRouter::Grid {
+            return None;
+        }
+
+        let (source_id, target_id) = doc.edge_endpoints(edge_id)?;
 
         // Run Dijkstra's algorithm for each terminal ports of the start/end node. It's
         // inefficient but more generic solution than using heuristics about the distance between
         // nodes.
-        let Some(start_node) = doc.get_node(source_id) else { return None };
-        let Some(end_node) = doc.get_node(target_id) else { return None };
-
-        let mut cost = RouteCost::MAX;
-        let mut path: Option<Vec<RouteNodeId>> = None;
+        let start_node = doc.get_node(source_id)?;
+        let end_node = doc.get_node(target_id)?;
+        let obstacles = self.route_obstacles(doc, source_id, target_id);
+        // Edges are bundled at the referred end, which is the source of a backward edge.
+        let (_, referred_id) = doc.edge_reference(edge_id)?;
+        let trunk_port = if self.bundle_edges { self.trunk_port(doc, referred_id) } else { None };
+        let [src_available, dst_available] =
+            [start_node, end_node].map(|node| self.available_ports(doc, edge_id, node));
+
+        let mut best = (RouteCost::MAX, (u8::MAX, usize::MAX), (u8::MAX, usize::MAX));
+        let mut explanation = RouteExplanation::default();
 
         for src in start_node.terminal_ports() {
             for dst in end_node.terminal_ports() {
+                // An edge between fields of a stub (e.g. `employees.manager_id o--o employees.id`)
+                // ends at both ends of the stub.
+                if src.id() == dst.id() {
+                    continue;
+                }
+                let referred_port = if referred_id == target_id { dst } else { src };
+                if trunk_port.is_some_and(|port_id| port_id != referred_port.id()) {
+                    continue;
+                }
+                if trunk_port.is_none()
+                    && !(src_available.contains(&src.id()) && dst_available.contains(&dst.id()))
+                {
+                    continue;
+                }
                 let Some(src_node) = self.edge_route_graph.get_terminal_port(src.id()) else { continue };
                 let Some(dst_node) = self.edge_route_graph.get_terminal_port(dst.id()) else { continue };
 
-                let (c, p) = self.compute_shortest_path(src_node, dst_node);
-                if c < cost {
-                    path.replace(p);
-                    cost = c;
+                let Some((c, p)) = self.compute_shortest_path(src_node, dst_node, &obstacles)
+                else {
+                    continue;
+                };
+                let c = c + RouteCost(self.port_side_penalty(src, dst));
+                let key = (c, self.port_order(src), self.port_order(dst));
+
+                if key < best {
+                    explanation.selected = Some(explanation.candidates.len());
+                    explanation.path = p;
+                    best = key;
                 }
+                explanation.candidates.push(RouteCandidate {
+                    source: src.clone(),
+                    target: dst.clone(),
+                    cost: c.0,
+                });
             }
         }
 
-        path.map(|path| {
-            path.iter()
-                .copied()
-                .map(|id| self.edge_route_graph().get_node(id).unwrap().location())
-                .copied()
-                .collect()
-        })
+        Some(explanation)
+    }
+
+    /// Returns terminal ports of `node` which `edge_id` can take by `port_capacity`: the ports
+    /// which fewer earlier edges end at than the capacity, or else the least taken ones.
+    fn available_ports(
+        &self,
+        doc: &mir::Document,
+        edge_id: mir::EdgeId,
+        node: &mir::NodeData,
+    ) -> Vec<TerminalPortId> {
+        let Some(capacity) = self.port_capacity else {
+            return node.terminal_ports().map(|port| port.id()).collect();
+        };
+        let end_points: Vec<Point> = doc
+            .edge_ids()
+            .take_while(|id| *id != edge_id)
+            .filter_map(|id| doc.edge(id)?.path_points())
+            .flat_map(|points| points.first().into_iter().chain(points.last()).copied())
+            .collect();
+        let usages: Vec<(TerminalPortId, usize)> = node
+            .terminal_ports()
+            .map(|port| {
+                let n = end_points.iter().filter(|point| *point == port.location()).count();
+                (port.id(), n)
+            })
+            .collect();
+        let least = usages.iter().map(|(_, n)| *n).min().unwrap_or(0);
+
+        usages
+            .into_iter()
+            .filter(|(_, n)| *n < capacity || *n == least)
+            .map(|(port_id, _)| port_id)
+            .collect()
+    }
+
+    /// Returns interiors of groups which contain neither `source_id` nor `target_id`, which
+    /// routes between them avoid. Junctions of members are on the borders.
+    fn route_obstacles(
+        &self,
+        doc: &mir::Document,
+        source_id: mir::NodeId,
+        target_id: mir::NodeId,
+    ) -> Vec<Rect> {
+        if self.swimlanes {
+            return vec![];
+        }
+
+        let owner_record = |node_id: mir::NodeId| {
+            doc.body()
+                .children()
+                .find(|record_id| {
+                    *record_id == node_id
+                        || doc
+                            .get_node(*record_id)
+                            .is_some_and(|record| record.children().any(|id| id == node_id))
+                })
+                .unwrap_or(node_id)
+        };
+        let end_groups = [source_id, target_id].map(|node_id| doc.group_of(owner_record(node_id)));
+
+        doc.group_ids()
+            .filter(|group_id| !end_groups.contains(&Some(*group_id)))
+            .filter_map(|group_id| doc.get_node(group_id)?.rect())
+            .map(|rect| rect.inset_by(1.0, 1.0))
+            .collect()
+    }
+
+    /// Returns the terminal port of `node_id` where edges which refer to it are bundled, if
+    /// there are two or more of them: the port whose total cost of the best routes from each
+    /// edge is the lowest, then the preferred one by `port_order`. Ports which some of the
+    /// edges can't reach aren't taken.
+    pub fn trunk_port(&self, doc: &mir::Document, node_id: mir::NodeId) -> Option<TerminalPortId> {
+        let referrer_ids: Vec<mir::NodeId> = doc
+            .edge_ids()
+            .filter_map(|edge_id| doc.edge_reference(edge_id))
+            .filter(|(_, referred_id)| *referred_id == node_id)
+            .map(|(referrer_id, _)| referrer_id)
+            .collect();
+
+        if referrer_ids.len() < 2 {
+            return None;
+        }
+
+        let graph = &self.edge_route_graph;
+        let end_node = doc.get_node(node_id)?;
+        let route_cost = |referrer_id: mir::NodeId, dst: &TerminalPort| -> Option<u64> {
+            let dst_node = graph.get_terminal_port(dst.id())?;
+            let obstacles = self.route_obstacles(doc, referrer_id, node_id);
+
+            doc.get_node(referrer_id)?
+                .terminal_ports()
+                .filter_map(|src| {
+                    let src_node = graph.get_terminal_port(src.id())?;
+                    let (c, _) = self.compute_shortest_path(src_node, dst_node, &obstacles)?;
+
+                    Some(u64::from(c.0) + u64::from(self.port_side_penalty(src, dst)))
+                })
+                .min()
+        };
+
+        end_node
+            .terminal_ports()
+            .filter_map(|dst| {
+                let total: u64 = referrer_ids
+                    .iter()
+                    .map(|referrer_id| route_cost(*referrer_id, dst))
+                    .sum::<Option<u64>>()?;
+
+                Some(((total, self.port_order(dst)), dst.id()))
+            })
+            .min()
+            .map(|(_, port_id)| port_id)
+    }
+
+    /// Orders terminal ports for routes of equal cost, lower is preferred: ports on the left or
+    /// right side come before ports on the top or bottom side, then lower port indices. The
+    /// order only depends on ports, so the chosen route doesn't change with iteration orders of
+    /// graphs or maps.
+    pub fn port_preference(port: &TerminalPort) -> (u8, usize) {
+        let side = match port.orientation() {
+            Orientation::Left | Orientation::Right => 0,
+            Orientation::Up | Orientation::Down => 1,
+        };
+
+        (side, port.id().index())
+    }
+
+    /// Returns the penalty of terminal ports `src` and `dst` of a route by
+    /// `LayoutOptions::port_side_cost`. Bends are penalized by the route search.
+    fn port_side_penalty(&self, src: &TerminalPort, dst: &TerminalPort) -> u32 {
+        let port_side_cost = self.options.port_side_cost;
+
+        if port_side_cost == 0.0 {
+            return 0;
+        }
+
+        let wrong_sides = |port: &TerminalPort, other: &TerminalPort| {
+            let (from, to) = (port.location(), other.location());
+            let faces_away = match port.orientation() {
+                Orientation::Left => to.x > from.x,
+                Orientation::Right => to.x < from.x,
+                Orientation::Up => to.y > from.y,
+                Orientation::Down => to.y < from.y,
+            };
+
+            usize::from(faces_away) + usize::from(self.port_order(port).0 > 0)
+        };
+        let n_wrong_sides = wrong_sides(src, dst) + wrong_sides(dst, src);
+
+        (port_side_cost * n_wrong_sides as f32) as u32
+    }
+
+    /// `port_preference`, except that sides are swapped by `LayoutOrientation::Vertical`, and
+    /// port indices are shuffled by `seed` if any.
+    fn port_order(&self, port: &TerminalPort) -> (u8, usize) {
+        let (side, index) = Self::port_preference(port);
+        let side = match self.orientation {
+            LayoutOrientation::Horizontal => side,
+            LayoutOrientation::Vertical => 1 - side,
+        };
+        let Some(seed) = self.seed else { return (side, index) };
+        let bytes = seed
+            .to_le_bytes()
+            .into_iter()
+            .chain((port.id().node_id().index() as u64).to_le_bytes())
+            .chain((index as u64).to_le_bytes());
+
+        (side, fnv1a(bytes) as usize)
     }
 
-    /// Run Dijkstra's algorithm to compute the shortest path between `start_node` and `end_node`.
+    /// Run Dijkstra's algorithm to compute the shortest path between `start_node` and `end_node`,
+    /// with `compute_min_bend_path` if bends cost. Segments through `obstacles` cost
+    /// `GROUP_CROSSING_COST` more. Paths are cached in the route graph until it changes.
+    ///
+    /// Returns `None` if `end_node` is unreachable, e.g. from a terminal port enclosed by
+    /// `LayoutOptions::keep_out_regions`.
     fn compute_shortest_path(
         &self,
         start_node: RouteNodeId,
         end_node: RouteNodeId,
-    ) -> (RouteCost, Vec<RouteNodeId>) {
-        let graph = &self.edge_route_graph().graph;
+        obstacles: &[Rect],
+    ) -> ShortestPath {
+        let route_graph = self.edge_route_graph();
+        let key: ShortestPathKey = (
+            start_node,
+            end_node,
+            obstacles
+                .iter()
+                .map(|r| [r.min_x(), r.min_y(), r.width(), r.height()].map(f32::to_bits))
+                .collect(),
+            self.options.bend_cost.to_bits(),
+        );
 
-        let (cost, path) = algo::astar(
-            graph,
-            start_node.0,
-            |finish| finish == end_node.0,
-            |edge| {
-                let node = graph.node_weight(edge.source()).unwrap();
-                let to_node = graph.node_weight(edge.target()).unwrap();
+        if let Some(shortest_path) = route_graph.shortest_paths.borrow().get(&key) {
+            return shortest_path.clone();
+        }
 
-                let distance = node.location().distance(to_node.location());
+        let graph = &route_graph.graph;
+        let segment_cost = |a: NodeIndex, b: NodeIndex| {
+            let (p, q) = (graph[a].location(), graph[b].location());
+            let distance = p.distance(q);
+            let crosses = |r: &Rect| {
+                p.x.min(q.x) < r.max_x()
+                    && p.x.max(q.x) > r.min_x()
+                    && p.y.min(q.y) < r.max_y()
+                    && p.y.max(q.y) > r.min_y()
+            };
+
+            if obstacles.iter().any(crosses) {
+                RouteCost(distance as u32 + Self::GROUP_CROSSING_COST)
+            } else {
                 RouteCost(distance as u32)
-            },
-            |_| RouteCost(0),
-        )
-        .unwrap_or_else(|| {
-            panic!(
-                "can't compute shortest path: {} -> {}",
-                start_node, end_node
+            }
+        };
+        let shortest_path = if self.options.bend_cost > 0.0 {
+            self.compute_min_bend_path(start_node.0, end_node.0, segment_cost)
+        } else {
+            algo::astar(
+                graph,
+                start_node.0,
+                |finish| finish == end_node.0,
+                |edge| segment_cost(edge.source(), edge.target()),
+                |_| RouteCost(0),
             )
-        });
+        };
+        let shortest_path = shortest_path
+            .map(|(cost, path)| (cost, path.into_iter().map(RouteNodeId).collect::<Vec<_>>()));
+
+        route_graph.shortest_paths.borrow_mut().insert(key, shortest_path.clone());
+        shortest_path
+    }
+
+    /// Runs Dijkstra's algorithm on states of a junction and the direction in which a route
+    /// enters it, so that each bend costs `LayoutOptions::bend_cost` on top of `segment_cost`.
+    fn compute_min_bend_path(
+        &self,
+        start: NodeIndex,
+        end: NodeIndex,
+        segment_cost: impl Fn(NodeIndex, NodeIndex) -> RouteCost,
+    ) -> Option<(RouteCost, Vec<NodeIndex>)> {
+        // States are junctions and indices of the directions in which routes enter them.
+        type State = (NodeIndex, usize);
+        const DIRECTIONS: [Option<Orientation>; 5] = [
+            None,
+            Some(Orientation::Up),
+            Some(Orientation::Right),
+            Some(Orientation::Down),
+            Some(Orientation::Left),
+        ];
+
+        let graph = &self.edge_route_graph.graph;
+        let bend_cost = RouteCost(self.options.bend_cost as u32);
+        let mut costs: HashMap<State, RouteCost> = HashMap::from([((start, 0), RouteCost(0))]);
+        let mut previous: HashMap<State, State> = HashMap::new();
+        let mut heap = BinaryHeap::from([Reverse((RouteCost(0), start, 0))]);
+
+        while let Some(Reverse((cost, node, direction))) = heap.pop() {
+            if node == end {
+                let mut path = vec![node];
+                let mut state = (node, direction);
+
+                while let Some(prev) = previous.get(&state) {
+                    path.push(prev.0);
+                    state = *prev;
+                }
+                path.reverse();
+                return Some((cost, path));
+            }
+            if costs.get(&(node, direction)).is_some_and(|c| *c < cost) {
+                continue;
+            }
+
+            for next in graph.neighbors(node) {
+                let next_direction =
+                    Some(graph[node].location().orthogonal_direction(graph[next].location()));
+                let next_index = DIRECTIONS.iter().position(|d| *d == next_direction).unwrap();
+                let mut next_cost = cost + segment_cost(node, next);
+
+                if direction != 0 && direction != next_index {
+                    next_cost = next_cost + bend_cost;
+                }
+
+                let state = (next, next_index);
+
+                if costs.get(&state).is_none_or(|c| next_cost < *c) {
+                    costs.insert(state, next_cost);
+                    previous.insert(state, (node, direction));
+                    heap.push(Reverse((next_cost, next, next_index)));
+                }
+            }
+        }
+
+        None
+    }
+}
+
+/// A defect of a layout found by `validate_layout`, e.g. caused by a bug of an engine or by
+/// input which can't be laid out as given.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display)]
+pub enum LayoutWarning {
+    /// Two records overlap.
+    #[display(fmt = "records #{} and #{} overlap", first, second)]
+    RecordOverlap {
+        first: mir::NodeId,
+        second: mir::NodeId,
+    },
+    /// The path of an edge passes through a record. Paths may run along borders.
+    #[display(fmt = "edge #{} passes through record #{}", edge_id, record_id)]
+    EdgeThroughRecord {
+        edge_id: mir::EdgeId,
+        record_id: mir::NodeId,
+    },
+    /// The preferred cell of a record is out of the grid. A row or a column beyond the number
+    /// of records is moved back to it, and the record is placed in a free cell if its cell is
+    /// still beyond the line of the grid. Reported by `LayoutEngine::layout`.
+    #[display(fmt = "preferred cell of record #{} is out of range", record_id)]
+    CellOutOfRange { record_id: mir::NodeId },
+}
+
+/// Checks that records of `doc`, which has been laid out, don't overlap each other, and that
+/// paths of edges don't pass through records. Returns warnings in the order of records, then of
+/// edges.
+pub fn validate_layout(doc: &mir::Document) -> Vec<LayoutWarning> {
+    // Shapes which only touch on their borders don't overlap.
+    let records: Vec<(mir::NodeId, Rect)> = doc
+        .body()
+        .children()
+        .filter_map(|record_id| {
+            let record = doc.get_node(record_id)?;
+            let ShapeKind::Record(_) = record.kind() else { return None };
+
+            Some((record_id, record.rect()?.inset_by(1.0, 1.0)))
+        })
+        .collect();
+    let mut warnings = vec![];
+
+    for (i, (first, a)) in records.iter().enumerate() {
+        for (second, b) in &records[i + 1..] {
+            if a.intersects(b) {
+                warnings.push(LayoutWarning::RecordOverlap { first: *first, second: *second });
+            }
+        }
+    }
+    for edge_id in doc.edge_ids() {
+        let Some(path_points) = doc.edge(edge_id).and_then(|edge| edge.path_points()) else {
+            continue;
+        };
 
-        (cost, path.iter().map(|i| RouteNodeId(*i)).collect())
+        for (record_id, r) in &records {
+            if path_points.windows(2).any(|s| r.intersects_line(&s[0], &s[1])) {
+                warnings.push(LayoutWarning::EdgeThroughRecord { edge_id, record_id: *record_id });
+            }
+        }
     }
+
+    warnings
+}
+
+/// 64-bit FNV-1a hash of `bytes`, which is stable across platforms and releases.
+pub(crate) fn fnv1a(bytes: impl IntoIterator<Item = u8>) -> u64 {
+    bytes.into_iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
 }
@@ -0,0 +1,489 @@
+//! Layout cache
+//!
+//! A layout cache stores the result of a layout engine: node placement, terminal ports, edge
+//! paths, placement of edge labels and cardinalities, and the route graph. If the structure of
+//! a document is unchanged (e.g. only colors or types are edited), re-renders can restore the
+//! cache and skip both placement and routing.
+//!
+//! A cache is stamped with the crate version and the layout config hash of the engine. Layouts
+//! of other versions or settings may differ, so such caches shouldn't be restored.
+use crate::error::LayoutCacheError;
+use crate::geometry::{Point, Rect, Size};
+use crate::layout::{RouteGraph, SimpleLayoutEngine};
+use crate::mir::{
+    self, Cardinality, FontFamily, FontSize, FontWeight, GridCell, NodeId, ShapeKind, TerminalPort,
+};
+use serde::{Deserialize, Serialize};
+use std::io;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LayoutCache {
+    /// The crate version which created the cache. Caches of older versions lack it.
+    #[serde(default)]
+    version: String,
+    /// `SimpleLayoutEngine::config_hash` of the engine which computed the layout.
+    #[serde(default)]
+    config_hash: u64,
+    key: LayoutKey,
+    view_box: Option<Rect>,
+    nodes: Vec<NodePlacement>,
+    /// Each edge in the order of edge IDs.
+    edges: Vec<EdgePlacement>,
+    route_graph: RouteGraph,
+}
+
+/// The structure of a document which affects layout. A cache can be restored only into a document
+/// which has the same key.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct LayoutKey {
+    n_columns: Option<usize>,
+    /// Whether the body has a title, which moves records down.
+    has_title: bool,
+    /// Each node in the order of node IDs.
+    nodes: Vec<NodeKey>,
+    /// Each edge in the order of edge IDs.
+    edges: Vec<EdgeKey>,
+}
+
+/// The part of a node which affects layout.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct NodeKey {
+    kind: NodeKind,
+    children: Vec<NodeId>,
+    preferred_cell: Option<GridCell>,
+    /// Bits of the coordinates, so that keys are `Eq`.
+    preferred_origin: Option<[u32; 2]>,
+    /// Bits of the width of a record measured by the engine, so that renamed fields don't reuse
+    /// the widths of old names.
+    width: Option<u32>,
+    note: Option<NoteKey>,
+}
+
+/// The part of a note which its text is wrapped by.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct NoteKey {
+    text: String,
+    font_family: Option<FontFamily>,
+    font_weight: Option<FontWeight>,
+    font_size: Option<FontSize>,
+    /// Bits of the width, so that keys are `Eq`.
+    max_width: Option<u32>,
+}
+
+/// The part of an edge which affects layout. Labels and cardinalities are placed where they
+/// don't overlap each other.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct EdgeKey {
+    source_id: NodeId,
+    target_id: NodeId,
+    label: Option<String>,
+    cardinalities: [Option<Cardinality>; 2],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+enum NodeKind {
+    Body,
+    Record,
+    Field,
+    Group,
+    Note,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct NodePlacement {
+    origin: Option<Point>,
+    size: Option<Size>,
+    terminal_ports: Vec<TerminalPort>,
+    /// The wrapped text of a note.
+    note_lines: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct EdgePlacement {
+    path_points: Option<Vec<Point>>,
+    label_center: Option<Point>,
+    cardinality_flips: [bool; 2],
+}
+
+impl LayoutCache {
+    /// Captures the layout of `doc` by `engine`. It must be called after the engine has placed
+    /// edge labels.
+    pub fn capture(
+        doc: &mir::Document,
+        view_box: Option<Rect>,
+        engine: &SimpleLayoutEngine,
+    ) -> Self {
+        let nodes = doc
+            .node_ids()
+            .filter_map(|node_id| doc.get_node(node_id))
+            .map(|node| NodePlacement {
+                origin: node.origin,
+                size: node.size,
+                terminal_ports: node.terminal_ports().cloned().collect(),
+                note_lines: match node.kind() {
+                    ShapeKind::Note(note) => note.lines.clone(),
+                    _ => vec![],
+                },
+            })
+            .collect();
+        let edges = doc
+            .edges()
+            .map(|edge| EdgePlacement {
+                path_points: edge.path_points().map(|points| points.to_vec()),
+                label_center: edge.label_center(),
+                cardinality_flips: edge.cardinality_flips(),
+            })
+            .collect();
+
+        Self {
+            version: crate::VERSION.to_string(),
+            config_hash: engine.config_hash(),
+            key: LayoutKey::new(doc, engine),
+            view_box,
+            nodes,
+            edges,
+            route_graph: engine.edge_route_graph().clone(),
+        }
+    }
+
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    /// Checks that the cache was created by this version of the crate and by an engine of
+    /// `config_hash`.
+    pub fn check_version(&self, config_hash: u64) -> Result<(), LayoutCacheError> {
+        if self.version != crate::VERSION {
+            Err(LayoutCacheError::IncompatibleVersion(self.version.clone()))
+        } else if self.config_hash != config_hash {
+            Err(LayoutCacheError::ConfigMismatch)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Returns `true` if the cache can be restored into `doc` laid out by `engine`.
+    pub fn is_applicable(&self, doc: &mir::Document, engine: &SimpleLayoutEngine) -> bool {
+        self.key == LayoutKey::new(doc, engine)
+    }
+
+    /// Restores the layout into `doc` and returns the view box. Returns `None` and leaves `doc`
+    /// untouched if the structure of `doc` differs from the cached one.
+    pub fn apply(
+        &self,
+        doc: &mut mir::Document,
+        engine: &SimpleLayoutEngine,
+    ) -> Option<Option<Rect>> {
+        if !self.is_applicable(doc, engine) {
+            return None;
+        }
+
+        let node_ids: Vec<_> = doc.node_ids().collect();
+
+        for (node_id, placement) in node_ids.into_iter().zip(self.nodes.iter()) {
+            let node = doc.get_node_mut(node_id).unwrap();
+
+            node.origin = placement.origin;
+            node.size = placement.size;
+            node.set_terminal_ports(placement.terminal_ports.clone());
+            if let ShapeKind::Note(note) = node.kind_mut() {
+                note.lines = placement.note_lines.clone();
+            }
+        }
+        for (edge, placement) in doc.edges_mut().zip(self.edges.iter()) {
+            edge.set_path_points(placement.path_points.clone());
+            edge.set_label_center(placement.label_center);
+            edge.set_cardinality_flips(placement.cardinality_flips);
+        }
+
+        Some(self.view_box)
+    }
+
+    pub fn route_graph(&self) -> &RouteGraph {
+        &self.route_graph
+    }
+
+    pub fn into_route_graph(self) -> RouteGraph {
+        self.route_graph
+    }
+
+    pub fn read_from(reader: impl io::Read) -> Result<Self, serde_json::Error> {
+        serde_json::from_reader(reader)
+    }
+
+    pub fn write_to(&self, writer: impl io::Write) -> Result<(), serde_json::Error> {
+        serde_json::to_writer(writer, self)
+    }
+}
+
+impl LayoutKey {
+    fn new(doc: &mir::Document, engine: &SimpleLayoutEngine) -> Self {
+        let (n_columns, has_title) = match doc.body().kind() {
+            ShapeKind::Body(body) => (body.n_columns, body.title.is_some()),
+            _ => (None, false),
+        };
+        let nodes = doc
+            .node_ids()
+            .filter_map(|node_id| doc.get_node(node_id))
+            .map(|node| {
+                let kind = match node.kind() {
+                    ShapeKind::Body(_) => NodeKind::Body,
+                    ShapeKind::Record(_) => NodeKind::Record,
+                    ShapeKind::Field(_) => NodeKind::Field,
+                    ShapeKind::Group(_) => NodeKind::Group,
+                    ShapeKind::Note(_) => NodeKind::Note,
+                };
+
+                NodeKey {
+                    kind,
+                    children: node.children().collect(),
+                    preferred_cell: node.preferred_cell,
+                    preferred_origin: node.preferred_origin.map(|p| [p.x, p.y].map(f32::to_bits)),
+                    width: (kind == NodeKind::Record)
+                        .then(|| engine.record_width(doc, node).to_bits()),
+                    note: match node.kind() {
+                        ShapeKind::Note(note) => Some(NoteKey {
+                            text: note.text.text.clone(),
+                            font_family: note.text.font_family,
+                            font_weight: note.text.font_weight,
+                            font_size: note.text.font_size,
+                            max_width: note.max_width.map(f32::to_bits),
+                        }),
+                        _ => None,
+                    },
+                }
+            })
+            .collect();
+        let edges = doc
+            .edges()
+            .map(|edge| EdgeKey {
+                source_id: edge.source_id(),
+                target_id: edge.target_id(),
+                label: edge.label().map(str::to_string),
+                cardinalities: [edge.start_cardinality(), edge.end_cardinality()],
+            })
+            .collect();
+
+        Self {
+            n_columns,
+            has_title,
+            nodes,
+            edges,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::{LayoutEngine, SimpleLayoutEngine};
+    use crate::parser::parse;
+
+    const SRC: &str = "erd {
+users {
+    id int PK
+}
+posts {
+    id int PK
+    user_id int FK
+}
+posts.user_id o--o users.id
+}";
+
+    fn layout(src: &str) -> (mir::Document, Option<Rect>, SimpleLayoutEngine) {
+        let (ast, _, _) = parse(src);
+        let mut doc = ast.unwrap().into_mir();
+        let mut engine = SimpleLayoutEngine::default();
+        let view_box = engine.layout(&mut doc).view_box;
+
+        (doc, view_box, engine)
+    }
+
+    #[test]
+    fn restore_layout() {
+        let (doc, view_box, engine) = layout(SRC);
+        let cache = LayoutCache::capture(&doc, view_box, &engine);
+
+        // round trip
+        let mut json = vec![];
+        cache.write_to(&mut json).unwrap();
+        let cache = LayoutCache::read_from(json.as_slice()).unwrap();
+
+        // Only a type is changed.
+        let (ast, _, _) = parse(&SRC.replace("user_id int", "user_id uuid"));
+        let mut restored = ast.unwrap().into_mir();
+
+        assert_eq!(cache.apply(&mut restored, &engine), Some(view_box));
+
+        let paths = |doc: &mir::Document| {
+            doc.edges()
+                .map(|edge| edge.path_points().map(|points| points.to_vec()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(paths(&restored), paths(&doc));
+
+        for node_id in doc.node_ids() {
+            let a = doc.get_node(node_id).unwrap();
+            let b = restored.get_node(node_id).unwrap();
+
+            assert_eq!(a.rect(), b.rect());
+            assert_eq!(a.terminal_ports().len(), b.terminal_ports().len());
+        }
+
+        // Route node IDs are stable.
+        let route_graph = engine.edge_route_graph();
+        for node_id in route_graph.node_ids() {
+            assert_eq!(
+                route_graph.get_node(node_id).unwrap().location(),
+                cache.route_graph().get_node(node_id).unwrap().location()
+            );
+        }
+        for port in doc.node_ids().flat_map(|id| {
+            doc.get_node(id)
+                .unwrap()
+                .terminal_ports()
+                .map(|p| p.id())
+                .collect::<Vec<_>>()
+        }) {
+            assert_eq!(
+                route_graph.get_terminal_port(port),
+                cache.route_graph().get_terminal_port(port)
+            );
+        }
+    }
+
+    #[test]
+    fn restore_edge_labels() {
+        let src = "erd {
+users { id int PK\nname text }
+posts { id int PK\nuser_id int FK }
+comments { id int PK\nuser_id int FK }
+posts.user_id o--o users.id [cardinality=\"N:1\"]
+comments.user_id o--o users.id [label=\"author\", cardinality=\"N:1\"]
+}";
+        let (doc, view_box, engine) = layout(src);
+        let cache = LayoutCache::capture(&doc, view_box, &engine);
+        let (ast, _, _) = parse(src);
+        let mut restored = ast.unwrap().into_mir();
+
+        assert_eq!(cache.apply(&mut restored, &engine), Some(view_box));
+
+        let labels = |doc: &mir::Document| {
+            doc.edges()
+                .map(|edge| (edge.label_center(), edge.cardinality_flips()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(labels(&restored), labels(&doc));
+        assert!(labels(&doc).iter().any(|(_, flips)| flips[1]));
+
+        // Labels are placed by their sizes.
+        let (changed, _, _) = layout(&src.replace("\"author\"", "\"written by\""));
+        assert!(!cache.is_applicable(&changed, &engine));
+    }
+
+    #[test]
+    fn reject_structural_changes() {
+        let (doc, view_box, engine) = layout(SRC);
+        let cache = LayoutCache::capture(&doc, view_box, &engine);
+
+        // A field is added.
+        let src = SRC.replace("user_id int FK", "user_id int FK\ntitle text");
+        let (mut changed, _, _) = layout(&src);
+
+        assert!(!cache.is_applicable(&changed, &engine));
+        assert_eq!(cache.apply(&mut changed, &engine), None);
+    }
+
+    #[test]
+    fn reject_moved_records() {
+        let (doc, view_box, engine) = layout(SRC);
+        let cache = LayoutCache::capture(&doc, view_box, &engine);
+
+        for attributes in ["[row=2, column=1]", "[x=100, y=400]"] {
+            let src = SRC.replace("posts {", &format!("posts {} {{", attributes));
+            let (changed, _, _) = layout(&src);
+
+            assert!(!cache.is_applicable(&changed, &engine));
+        }
+    }
+
+    #[test]
+    fn reject_renamed_fields() {
+        let (mut doc, view_box, mut engine) = layout(SRC);
+        let renamed = SRC.replace("user_id", "author_id_which_is_too_long_to_fit_the_record");
+        let (changed, _, _) = layout(&renamed);
+
+        // Records keep their widths.
+        let cache = LayoutCache::capture(&doc, view_box, &engine);
+        assert!(cache.is_applicable(&changed, &engine));
+
+        // Records fit their fields.
+        engine.fit_record_width = true;
+        let view_box = engine.layout(&mut doc).view_box;
+        let cache = LayoutCache::capture(&doc, view_box, &engine);
+        assert!(!cache.is_applicable(&changed, &engine));
+        assert!(cache.is_applicable(&doc, &engine));
+    }
+
+    #[test]
+    fn restore_notes() {
+        let with_note = |text: &str| {
+            let (ast, _, _) = parse(SRC);
+            let mut doc = ast.unwrap().into_mir();
+            let text = mir::TextSpanBuilder::default().text(text).build().unwrap();
+            let note_id =
+                doc.create_note(mir::NoteShapeBuilder::default().text(text).build().unwrap());
+
+            (doc, note_id)
+        };
+        let lines = |doc: &mir::Document, note_id| match doc.get_node(note_id).unwrap().kind() {
+            ShapeKind::Note(note) => note.lines.clone(),
+            _ => vec![],
+        };
+        let (mut doc, note_id) = with_note("Partitioned by month");
+        let mut engine = SimpleLayoutEngine::default();
+        let view_box = engine.layout(&mut doc).view_box;
+        let cache = LayoutCache::capture(&doc, view_box, &engine);
+
+        let (mut restored, _) = with_note("Partitioned by month");
+        assert_eq!(cache.apply(&mut restored, &engine), Some(view_box));
+        assert_eq!(lines(&restored, note_id), lines(&doc, note_id));
+
+        // The text is wrapped into another size.
+        let (changed, _) = with_note("Partitioned by month, and archived after a year");
+        assert!(!cache.is_applicable(&changed, &engine));
+    }
+
+    #[test]
+    fn check_version() {
+        let (doc, view_box, engine) = layout(SRC);
+        let cache = LayoutCache::capture(&doc, view_box, &engine);
+
+        assert_eq!(cache.version(), crate::VERSION);
+        assert_eq!(cache.check_version(engine.config_hash()), Ok(()));
+
+        // Layout settings are changed.
+        let mut fit = SimpleLayoutEngine::default();
+        fit.fit_record_width = true;
+        assert_ne!(fit.config_hash(), engine.config_hash());
+        assert_eq!(
+            cache.check_version(fit.config_hash()),
+            Err(LayoutCacheError::ConfigMismatch)
+        );
+
+        // A cache of another version, or without a version
+        let mut json = vec![];
+        cache.write_to(&mut json).unwrap();
+        let json = String::from_utf8(json).unwrap();
+        let stamp = format!("\"version\":\"{}\",", crate::VERSION);
+
+        for version in ["\"version\":\"0.0.0\",", ""] {
+            let old = LayoutCache::read_from(json.replace(&stamp, version).as_bytes()).unwrap();
+
+            assert!(matches!(
+                old.check_version(engine.config_hash()),
+                Err(LayoutCacheError::IncompatibleVersion(_))
+            ));
+        }
+    }
+}
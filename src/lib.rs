@@ -1,9 +1,23 @@
+/// The version of this crate, which is stamped on outputs and layout caches.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
 pub mod color;
+pub mod diff;
 pub mod erd;
 pub mod error;
 pub mod evcxr;
+pub mod font;
 pub mod geometry;
+pub mod grid;
 pub mod layout;
+#[cfg(feature = "serde")]
+pub mod layout_cache;
+pub mod lint;
+pub mod merge;
+pub(crate) mod metrics;
 pub mod mir;
 pub mod parser;
 pub mod renderer;
+pub mod theme;
+#[cfg(feature = "raster")]
+pub mod vdiff;
@@ -0,0 +1,381 @@
+//! Schema quality lint rules over the ERD AST.
+//!
+//! Lints are independent of rendering. Each rule reports diagnostics with the location in the
+//! source, so the CLI can print them like parse errors.
+use crate::erd::{EntityDefinition, EntityField, EntityFieldKey, EntityPath, EntityRelation};
+use crate::erd::{Module, ModuleEntry};
+use crate::parser::Span;
+use derive_more::Display;
+use std::collections::{HashMap, HashSet};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display)]
+pub enum Rule {
+    /// An entity has no primary key.
+    #[display(fmt = "missing-primary-key")]
+    MissingPrimaryKey,
+    /// A field used by a relation is neither a primary key nor a foreign key.
+    #[display(fmt = "fk-without-index")]
+    ForeignKeyWithoutIndex,
+    /// A name doesn't follow the naming convention used by most other names.
+    #[display(fmt = "inconsistent-naming")]
+    InconsistentNaming,
+    /// A foreign key is nullable.
+    #[display(fmt = "nullable-fk")]
+    NullableForeignKey,
+    /// An entity has no relations.
+    #[display(fmt = "orphan-table")]
+    OrphanTable,
+    /// A relation connects an entity or a field to itself.
+    #[display(fmt = "self-relation")]
+    SelfRelation,
+}
+
+impl Rule {
+    pub const ALL: [Rule; 6] = [
+        Rule::MissingPrimaryKey,
+        Rule::ForeignKeyWithoutIndex,
+        Rule::InconsistentNaming,
+        Rule::NullableForeignKey,
+        Rule::OrphanTable,
+        Rule::SelfRelation,
+    ];
+
+    /// Returns the rule identified by `id` (e.g. `"missing-primary-key"`).
+    pub fn from_id(id: &str) -> Option<Rule> {
+        Self::ALL.into_iter().find(|rule| rule.to_string() == id)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub rule: Rule,
+    pub message: String,
+    pub span: Span,
+}
+
+/// Enables or disables lint rules. All rules are enabled by default.
+#[derive(Debug, Clone, Default)]
+pub struct LintConfig {
+    disabled: HashSet<Rule>,
+}
+
+impl LintConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn enable(&mut self, rule: Rule) {
+        self.disabled.remove(&rule);
+    }
+
+    pub fn disable(&mut self, rule: Rule) {
+        self.disabled.insert(rule);
+    }
+
+    pub fn is_enabled(&self, rule: Rule) -> bool {
+        !self.disabled.contains(&rule)
+    }
+}
+
+/// Runs enabled rules over `module` and returns diagnostics in source order.
+pub fn lint(module: &Module, config: &LintConfig) -> Vec<Diagnostic> {
+    let definitions: Vec<&EntityDefinition> = module
+        .entries()
+        .filter_map(|entry| match entry {
+            ModuleEntry::EntityDefinition(definition) => Some(definition),
+            _ => None,
+        })
+        .collect();
+    let mut diagnostics = vec![];
+
+    if config.is_enabled(Rule::MissingPrimaryKey) {
+        check_missing_primary_key(&definitions, &mut diagnostics);
+    }
+    if config.is_enabled(Rule::ForeignKeyWithoutIndex) {
+        check_fk_without_index(module, &mut diagnostics);
+    }
+    if config.is_enabled(Rule::InconsistentNaming) {
+        check_inconsistent_naming(&definitions, &mut diagnostics);
+    }
+    if config.is_enabled(Rule::NullableForeignKey) {
+        check_nullable_fk(&definitions, &mut diagnostics);
+    }
+    if config.is_enabled(Rule::OrphanTable) {
+        check_orphan_table(module, &definitions, &mut diagnostics);
+    }
+    if config.is_enabled(Rule::SelfRelation) {
+        check_self_relation(module, &mut diagnostics);
+    }
+
+    diagnostics.sort_by_key(|d| (d.span.start, d.span.end));
+    diagnostics
+}
+
+fn check_missing_primary_key(definitions: &[&EntityDefinition], diagnostics: &mut Vec<Diagnostic>) {
+    for definition in definitions {
+        let has_pk = definition
+            .fields()
+            .any(|field| field.field_key() == Some(&EntityFieldKey::PrimaryKey));
+
+        if !has_pk {
+            diagnostics.push(Diagnostic {
+                rule: Rule::MissingPrimaryKey,
+                message: format!("`{}` has no primary key", definition.name()),
+                span: definition.span(),
+            });
+        }
+    }
+}
+
+fn check_fk_without_index(module: &Module, diagnostics: &mut Vec<Diagnostic>) {
+    let mut reported = HashSet::new();
+
+    for relation in relations(module) {
+        for path in [relation.start_path(), relation.end_path()] {
+            let EntityPath::Field(entity_name, field_name) = path else {
+                continue;
+            };
+            let Some(field) = find_field(module, entity_name, field_name) else {
+                continue;
+            };
+
+            if field.field_key().is_none() && reported.insert(path) {
+                diagnostics.push(Diagnostic {
+                    rule: Rule::ForeignKeyWithoutIndex,
+                    message: format!(
+                        "`{}.{}` is used by a relation but isn't marked as PK or FK",
+                        entity_name, field_name
+                    ),
+                    span: field.span(),
+                });
+            }
+        }
+    }
+}
+
+fn check_nullable_fk(definitions: &[&EntityDefinition], diagnostics: &mut Vec<Diagnostic>) {
+    for definition in definitions {
+        for field in definition.fields() {
+            if field.is_nullable() && field.field_key() == Some(&EntityFieldKey::ForeginKey) {
+                diagnostics.push(Diagnostic {
+                    rule: Rule::NullableForeignKey,
+                    message: format!(
+                        "foreign key `{}.{}` is nullable",
+                        definition.name(),
+                        field.name()
+                    ),
+                    span: field.span(),
+                });
+            }
+        }
+    }
+}
+
+fn check_orphan_table(
+    module: &Module,
+    definitions: &[&EntityDefinition],
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    // A single entity can't have relations with others.
+    if definitions.len() < 2 {
+        return;
+    }
+
+    let related: HashSet<&str> = relations(module)
+        .flat_map(|relation| [relation.start_path(), relation.end_path()])
+        .map(|path| path.entity_name())
+        .collect();
+
+    for definition in definitions {
+        if !related.contains(definition.name()) {
+            diagnostics.push(Diagnostic {
+                rule: Rule::OrphanTable,
+                message: format!("`{}` has no relations", definition.name()),
+                span: definition.span(),
+            });
+        }
+    }
+}
+
+fn check_self_relation(module: &Module, diagnostics: &mut Vec<Diagnostic>) {
+    for relation in relations(module) {
+        if relation.is_self_relation() {
+            diagnostics.push(Diagnostic {
+                rule: Rule::SelfRelation,
+                message: format!("relation connects `{}` to itself", relation.start_path()),
+                span: relation.span(),
+            });
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display)]
+enum NamingCase {
+    #[display(fmt = "snake_case")]
+    Snake,
+    #[display(fmt = "camelCase")]
+    Camel,
+    #[display(fmt = "PascalCase")]
+    Pascal,
+}
+
+impl NamingCase {
+    /// Returns `None` if the style of `name` is ambiguous (e.g. `users`) or unknown.
+    fn of(name: &str) -> Option<NamingCase> {
+        let has_underscore = name.contains('_');
+        let has_upper = name.chars().any(|c| c.is_ascii_uppercase());
+
+        match (has_underscore, has_upper) {
+            (true, false) => Some(NamingCase::Snake),
+            (false, true) if name.starts_with(|c: char| c.is_ascii_uppercase()) => {
+                Some(NamingCase::Pascal)
+            }
+            (false, true) => Some(NamingCase::Camel),
+            _ => None,
+        }
+    }
+}
+
+fn check_inconsistent_naming(definitions: &[&EntityDefinition], diagnostics: &mut Vec<Diagnostic>) {
+    // Entity names and field names often follow different conventions, so they are checked
+    // separately.
+    let entities: Vec<(&str, Span)> = definitions
+        .iter()
+        .map(|definition| (definition.name(), definition.span()))
+        .collect();
+    let fields: Vec<(&str, Span)> = definitions
+        .iter()
+        .flat_map(|definition| definition.fields())
+        .map(|field| (field.name(), field.span()))
+        .collect();
+
+    for (kind, names) in [("entity", entities), ("field", fields)] {
+        let Some(dominant) = dominant_style(&names) else {
+            continue;
+        };
+
+        for (name, span) in names {
+            let Some(style) = NamingCase::of(name) else {
+                continue;
+            };
+
+            if style != dominant {
+                diagnostics.push(Diagnostic {
+                    rule: Rule::InconsistentNaming,
+                    message: format!(
+                        "{} name `{}` is {}, but most {} names are {}",
+                        kind, name, style, kind, dominant
+                    ),
+                    span,
+                });
+            }
+        }
+    }
+}
+
+/// Returns the most common naming style. Ties are broken by the first occurrence.
+fn dominant_style(names: &[(&str, Span)]) -> Option<NamingCase> {
+    let mut counts: HashMap<NamingCase, (usize, usize)> = HashMap::new();
+
+    for (index, (name, _)) in names.iter().enumerate() {
+        if let Some(style) = NamingCase::of(name) {
+            counts.entry(style).or_insert((0, index)).0 += 1;
+        }
+    }
+
+    counts
+        .into_iter()
+        .max_by_key(|(_, (count, first))| (*count, std::cmp::Reverse(*first)))
+        .map(|(style, _)| style)
+}
+
+fn relations(module: &Module) -> impl Iterator<Item = &EntityRelation> {
+    module.entries().filter_map(|entry| match entry {
+        ModuleEntry::EntityRelation(relation) => Some(relation),
+        _ => None,
+    })
+}
+
+fn find_field<'a>(
+    module: &'a Module,
+    entity_name: &str,
+    field_name: &str,
+) -> Option<&'a EntityField> {
+    module
+        .entity(entity_name)?
+        .fields()
+        .find(|field| field.name() == field_name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+
+    fn lint_src(src: &str, config: &LintConfig) -> Vec<(Rule, String)> {
+        let (ast, _, _) = parse(src);
+
+        lint(&ast.unwrap(), config)
+            .into_iter()
+            .map(|d| (d.rule, src[d.span].to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn rules() {
+        let src = "erd {
+users {
+    id int PK
+    user_name text
+}
+posts {
+    id int PK
+    author_id int? FK
+    postedAt timestamp
+}
+tags {
+    name text
+}
+posts.author_id o--o users.user_name
+posts.id o--o posts.id
+}";
+
+        assert_eq!(
+            lint_src(src, &LintConfig::new()),
+            vec![
+                (Rule::ForeignKeyWithoutIndex, "user_name text".into()),
+                (Rule::NullableForeignKey, "author_id int? FK".into()),
+                (Rule::InconsistentNaming, "postedAt timestamp".into()),
+                (Rule::MissingPrimaryKey, "tags".into()),
+                (Rule::OrphanTable, "tags".into()),
+                (Rule::SelfRelation, "posts.id o--o posts.id".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn disabled_rules() {
+        let src = "erd {
+users { name text }
+posts { title text }
+}";
+        let mut config = LintConfig::new();
+
+        config.disable(Rule::MissingPrimaryKey);
+        assert_eq!(
+            lint_src(src, &config),
+            vec![
+                (Rule::OrphanTable, "users".into()),
+                (Rule::OrphanTable, "posts".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn rule_ids() {
+        for rule in Rule::ALL {
+            assert_eq!(Rule::from_id(&rule.to_string()), Some(rule));
+        }
+        assert_eq!(Rule::from_id("unknown"), None);
+    }
+}
@@ -1,29 +1,1365 @@
 use ariadne::{Color, Fmt, Label, Report, ReportKind, Source};
-use seiren::layout::{LayoutEngine, SimpleLayoutEngine};
-use seiren::parser::parse;
-use seiren::renderer::{Renderer, SVGRenderer};
+use clap::{Parser, Subcommand};
+use flate2::{write::GzEncoder, Compression};
+use seiren::diff::SchemaDiff;
+use seiren::erd::{
+    ConvertOptions, EntityFilter, EntityPath, KeyHighlight, Module, ModuleEntry, TextEdit,
+};
+use seiren::error::MergeError;
+use seiren::font::CharWidth;
+#[cfg(feature = "font-file")]
+use seiren::font::FontFile;
+use seiren::geometry::{Point, Rect, Size};
+use seiren::layout::{
+    validate_layout, EdgeRouter, IsolatedRecords, LayoutEngine, LayoutOptions, LayoutOrientation,
+    LayoutResult, LayoutWarning, SimpleLayoutEngine,
+};
+use seiren::lint::{lint, LintConfig, Rule};
+use seiren::merge::{merge, Conflict, MergePolicy, MergeSource};
+use seiren::mir::{Document, EdgeLayer, NodeId, TerminalPort};
+use seiren::parser::{parse_with_options, KeywordAliases, ParseOptions};
+#[cfg(feature = "serde")]
+use seiren::renderer::JsonRenderer;
+use seiren::renderer::{
+    extract_source, Background, GridPattern, HtmlRenderer, PageGrid, RenderOptions, Renderer,
+    SVGFormat, SVGRenderer, TextCharset, TextRenderer,
+};
+use seiren::theme::Theme;
+use std::collections::HashMap;
 use std::io;
-use std::{fs, io::Read};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::{fs, io::Read, io::Write};
 
-const DEBUG: bool = false;
+#[derive(Parser, Debug)]
+#[command(version, about = "Renders a Seirḗn diagram as SVG")]
+#[command(args_conflicts_with_subcommands = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
 
-fn main() -> Result<(), io::Error> {
-    let mut filename = "(stdin)".to_string();
-    let mut args = std::env::args();
+    /// Source file, or a directory of `.seiren` files to render all of them. Reads from stdin
+    /// if omitted.
+    file: Option<PathBuf>,
+
+    /// Draws undefined entities referenced by relations as ghost records.
+    #[arg(long)]
+    implicit_entity_stubs: bool,
+
+    /// Shows the column ordinal in front of each field.
+    #[arg(long)]
+    show_field_ordinals: bool,
+
+    /// Renders only entities with these names or tags (comma-separated).
+    #[arg(long, value_name = "NAMES", value_delimiter = ',')]
+    include: Vec<String>,
+
+    /// Doesn't render entities with these names or tags (comma-separated).
+    #[arg(long, value_name = "NAMES", value_delimiter = ',')]
+    exclude: Vec<String>,
+
+    /// Widens records to fit long entity and field names.
+    #[arg(long)]
+    fit_record_width: bool,
+
+    /// Estimates text widths for `--fit-record-width` as this many em per character, instead of
+    /// the metrics of the fonts.
+    #[arg(long, value_name = "EM", requires = "fit_record_width", value_parser = parse_char_width)]
+    char_width: Option<f32>,
+
+    /// Measures text widths for `--fit-record-width` with the advance widths of this TrueType or
+    /// OpenType font file (requires `font-file` feature).
+    #[cfg(feature = "font-file")]
+    #[arg(
+        long,
+        value_name = "FILE",
+        requires = "fit_record_width",
+        conflicts_with = "char_width"
+    )]
+    font: Option<PathBuf>,
+
+    /// Output file. Writes to stdout if omitted. If the source is a directory, the output
+    /// directory (defaults to the source directory).
+    #[arg(short, long, value_name = "FILE")]
+    output: Option<PathBuf>,
+
+    /// The number of files rendered in parallel if the source is a directory. Defaults to the
+    /// number of CPUs.
+    #[arg(short, long, value_name = "N")]
+    jobs: Option<usize>,
+
+    /// Compresses the output with gzip. Implied if the output file has `.svgz` extension.
+    #[arg(long)]
+    compress: bool,
+
+    /// Width of the image. The height is derived from the aspect ratio if omitted.
+    #[arg(long)]
+    width: Option<f32>,
+
+    /// Height of the image. The width is derived from the aspect ratio if omitted.
+    #[arg(long)]
+    height: Option<f32>,
+
+    /// Scales the size of the image (e.g. `0.5`).
+    #[arg(long)]
+    scale: Option<f32>,
+
+    /// Splits the diagram into a grid of pages of `COLUMNSxROWS` (e.g. `3x2`). Each page is
+    /// written to `--output` with its row and column (e.g. `schema-1-2.svg`).
+    #[arg(long, value_name = "COLUMNSxROWS", conflicts_with_all = ["html", "text"])]
+    pages: Option<PageGrid>,
+
+    /// How far each page extends into its neighbors in pixels.
+    #[arg(long, value_name = "PX", default_value_t = 40.0, requires = "pages")]
+    page_overlap: f32,
+
+    /// Centers the diagram in the image of `--width` and `--height` at its own size, instead
+    /// of fitting it into the image.
+    #[arg(long, requires_all = ["width", "height"])]
+    center: bool,
+
+    /// Chooses the number of columns so that the diagram is close to the aspect ratio `W:H`
+    /// (defaults to `16:9`), unless the source has a `%columns` directive.
+    #[arg(
+        long,
+        value_name = "W:H",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "16:9",
+        value_parser = parse_aspect_ratio
+    )]
+    auto_columns: Option<f32>,
+
+    /// Wraps entities into more rows until the diagram is at most this wide, even with a
+    /// `%columns` directive.
+    #[arg(long, value_name = "PX")]
+    max_width: Option<f32>,
+
+    /// Moves edges which share route segments apart, so that edges into the same field can be
+    /// told apart.
+    #[arg(long)]
+    separate_edges: bool,
+
+    /// Bundles edges into the same field into a trunk, which they leave at different points.
+    #[arg(long)]
+    bundle_edges: bool,
+
+    /// Moves edges to other ports of a field after N edges end at a port.
+    #[arg(long, value_name = "N")]
+    port_capacity: Option<usize>,
+
+    /// How routes of edges are searched: `junctions` around records, or `grid`, which spreads
+    /// edges over grid lines to avoid congestion. `grid` can't be used with `--explain-edge`,
+    /// `--debug-overlay`, `--bundle-edges` or `--port-capacity`.
+    #[arg(long, value_name = "ROUTER", default_value_t = EdgeRouter::Junctions)]
+    router: EdgeRouter,
+
+    /// Places related entities next to each other, instead of in the order of definitions.
+    #[arg(long)]
+    cluster: bool,
+
+    /// Places referenced entities before the entities which refer to them.
+    #[arg(long)]
+    order_references: bool,
+
+    /// Breaks ties between equally good routes by this seed. The same seed always gives the same
+    /// layout.
+    #[arg(long, value_name = "N")]
+    seed: Option<u64>,
+
+    /// The direction in which entities are placed: `horizontal` fills rows from left to right,
+    /// and `vertical` fills columns from top to bottom.
+    #[arg(long, value_name = "ORIENTATION", default_value_t = LayoutOrientation::Horizontal)]
+    orientation: LayoutOrientation,
+
+    /// Where entities without relations are placed: `interleaved` among the others, `trailing`
+    /// in rows after them, or `side-column` to the right of them.
+    #[arg(long, value_name = "PLACEMENT", default_value_t = IsolatedRecords::Interleaved)]
+    isolated: IsolatedRecords,
+
+    /// Packs entities in each column, so that a tall entity doesn't leave gaps below the short
+    /// entities next to it.
+    #[arg(long)]
+    pack_columns: bool,
+
+    /// Assigns entities to grid columns by height (the tallest first, each to the lowest column)
+    /// and stacks them, so that the diagram is as low as possible.
+    #[arg(long)]
+    balance_columns: bool,
+
+    /// Places each group of entities (`[group="..."]`) in a horizontal band across the diagram.
+    #[arg(long)]
+    swimlanes: bool,
+
+    /// Places entities around a circle instead of a grid.
+    #[arg(long)]
+    circular: bool,
+
+    /// Rounds positions of entities, ends of edges and bends to multiples of this length in
+    /// pixels (e.g. `1` for whole pixels).
+    #[arg(long, value_name = "PX", value_parser = parse_length)]
+    snap: Option<f32>,
+
+    /// The space around the diagram in pixels.
+    #[arg(long, value_name = "PX", default_value_t = LayoutOptions::DEFAULT_MARGIN)]
+    margin: f32,
+
+    /// The height of field rows in pixels.
+    #[arg(long, value_name = "PX", default_value_t = LayoutOptions::DEFAULT_LINE_HEIGHT)]
+    line_height: f32,
+
+    /// The width of records in pixels (the minimum width with `--fit-record-width`).
+    #[arg(long, value_name = "PX", default_value_t = LayoutOptions::DEFAULT_RECORD_WIDTH)]
+    record_width: f32,
+
+    /// The space between records in pixels. Edges are routed at half of it around records.
+    #[arg(long, value_name = "PX", default_value_t = LayoutOptions::DEFAULT_RECORD_SPACE)]
+    record_space: f32,
+
+    /// The distance from records at which edges are routed around them, in pixels. Defaults
+    /// to half of `--record-space`.
+    #[arg(long, value_name = "PX")]
+    junction_margin: Option<f32>,
+
+    /// The radius of rounded corners of edges in pixels. It must be smaller than half of the
+    /// junction margin.
+    #[arg(long, value_name = "PX", default_value_t = 6.0)]
+    path_radius: f32,
+
+    /// Routes edges around a region of `X,Y,WIDTH,HEIGHT` in pixels (e.g. where a legend is
+    /// drawn) like a record. Can be repeated.
+    #[arg(long, value_name = "X,Y,W,H", value_parser = parse_rect)]
+    keep_out: Vec<Rect>,
+
+    /// Penalizes each bend of an edge by this length in pixels, so that straighter routes win.
+    #[arg(long, value_name = "PX", default_value_t = 0.0)]
+    bend_cost: f32,
+
+    /// Penalizes each end of an edge facing away from the other end, or on the top or bottom
+    /// of a record, by this length in pixels.
+    #[arg(long, value_name = "PX", default_value_t = 0.0)]
+    port_side_cost: f32,
+
+    /// Background of the image (`transparent` or `#RRGGBB`). Defaults to the theme color.
+    #[arg(long)]
+    background: Option<Background>,
+
+    /// Draws a grid of `dots` or `lines` behind the diagram.
+    #[arg(long, value_name = "PATTERN")]
+    grid: Option<GridPattern>,
+
+    /// Fills the background of primary key rows (`pk`), or of all key rows (`all`).
+    #[arg(
+        long,
+        value_name = "KEYS",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "pk"
+    )]
+    highlight_keys: Option<KeyHighlight>,
+
+    /// Draws soft shadows under records.
+    #[arg(long)]
+    shadow: bool,
+
+    /// Draws edges `below` records, or `above` their labels, unless the `layer` attribute of a
+    /// relation says otherwise.
+    #[arg(long, value_name = "LAYER", default_value_t)]
+    edge_layer: EdgeLayer,
+
+    /// Follows the viewer's color scheme: colors of the theme are switched to the light theme
+    /// by `@media (prefers-color-scheme: light)`. Implies `--css-classes`.
+    #[arg(long)]
+    auto_theme: bool,
+
+    /// Writes colors and fonts as CSS classes in a `<style>` element instead of attributes.
+    #[arg(long)]
+    css_classes: bool,
+
+    /// Draws a legend of key badges and type colors.
+    #[arg(long)]
+    legend: bool,
+
+    /// Highlights the field under the pointer and its relations with an embedded script.
+    #[arg(long)]
+    interactive: bool,
+
+    /// Adds titles, descriptions and ARIA attributes for screen readers.
+    #[arg(long)]
+    accessible: bool,
+
+    /// Whitespace of SVG output (`default`, `minified` or `pretty`).
+    #[arg(long, value_name = "FORMAT", default_value = "default")]
+    svg_format: SVGFormat,
+
+    /// Prepends a prefix to IDs of clip paths and markers, so that SVGs inlined into the same
+    /// HTML page don't share them.
+    #[arg(long, value_name = "PREFIX", default_value = "")]
+    id_prefix: String,
+
+    /// Color theme (`dark`, `light`, or a path to a theme file).
+    #[arg(long, default_value = "dark")]
+    theme: String,
+
+    /// Accepts localized keyword aliases (e.g. `ja`).
+    #[arg(long)]
+    locale: Option<String>,
+
+    /// Prints how the routes of edges connected to an entity or a field (e.g. `posts.user_id`)
+    /// were chosen.
+    #[arg(long, value_name = "PATH")]
+    explain_edge: Option<EntityPath>,
+
+    /// Merges another source file into the diagram. Can be specified multiple times.
+    #[arg(long, value_name = "FILE")]
+    merge: Vec<PathBuf>,
+
+    /// How to resolve entities defined in more than one file (`error`, `first-wins` or
+    /// `union`).
+    #[arg(long, value_name = "POLICY", default_value = "error")]
+    merge_policy: MergePolicy,
+
+    /// Restores the layout from a cache file if the diagram structure is unchanged, or writes
+    /// the computed layout to it (requires `serde` feature).
+    #[cfg(feature = "serde")]
+    #[arg(long, value_name = "FILE")]
+    layout_cache: Option<PathBuf>,
+
+    /// Refuses a layout cache created by another version of seiren or with other layout
+    /// settings, instead of warning and rebuilding it (requires `serde` feature).
+    #[cfg(feature = "serde")]
+    #[arg(long, requires = "layout_cache")]
+    version_check: bool,
+
+    /// Draws the route graph of edges in a hidden group `#debug`, which can be shown by
+    /// viewers (e.g. browser devtools).
+    #[arg(long)]
+    debug_overlay: bool,
+
+    /// Shows the group of `--debug-overlay`.
+    #[arg(long, requires = "debug_overlay")]
+    show_debug: bool,
+
+    /// Omits the `<metadata>` element which records the seiren version and the layout settings.
+    #[arg(long)]
+    no_version_stamp: bool,
+
+    /// Embeds the source in the `<metadata>` element of the SVG. An SVG with an embedded source
+    /// can be given as the input instead of the source. Merged sources can't be embedded.
+    #[arg(long, conflicts_with = "merge")]
+    embed_source: bool,
+
+    /// Writes the laid-out document as JSON instead of SVG (requires `serde` feature).
+    #[cfg(feature = "serde")]
+    #[arg(long, conflicts_with_all = ["html", "text", "pages"])]
+    json: bool,
+
+    /// Writes an HTML page with records as tables instead of SVG. Edges are drawn in an SVG
+    /// overlay.
+    #[arg(long, conflicts_with = "text")]
+    html: bool,
+
+    /// Draws the diagram as text art with box-drawing characters instead of SVG.
+    #[arg(long)]
+    text: bool,
+
+    /// Characters to draw text art with: `unicode` or `ascii`.
+    #[arg(long, value_name = "CHARSET", default_value_t, requires = "text")]
+    charset: TextCharset,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Checks the schema quality of a diagram.
+    ///
+    /// Exits with status 1 if any rule reports a problem.
+    Lint {
+        /// Source file. Reads from stdin if omitted.
+        file: Option<PathBuf>,
+        /// Disables a rule (e.g. `orphan-table`). Can be specified multiple times.
+        #[arg(long, value_name = "RULE")]
+        allow: Vec<String>,
+    },
+    /// Renames an entity or a field (e.g. `users` or `users.id`) and updates relations.
+    ///
+    /// Writes the edited source to stdout unless `--write` is specified.
+    Rename {
+        file: PathBuf,
+        #[arg(value_name = "PATH")]
+        path: EntityPath,
+        new_name: String,
+        /// Overwrites the source file.
+        #[arg(long)]
+        write: bool,
+    },
+    /// Renders the new version of a diagram with fields changed since the old version marked.
+    Diff {
+        old: PathBuf,
+        new: PathBuf,
+    },
+    /// Rasterizes two SVG files and writes a pixel-diff heatmap (requires `raster` feature).
+    ///
+    /// Exits with status 1 if the images differ.
+    Vdiff {
+        a: PathBuf,
+        b: PathBuf,
+        /// Output PNG file.
+        #[arg(long)]
+        out: PathBuf,
+    },
+}
+
+fn main() -> Result<ExitCode, io::Error> {
+    let cli = Cli::parse();
+
+    match cli.command {
+        None => render(&cli),
+        Some(Command::Lint { ref file, ref allow }) => run_lint(file.as_ref(), allow),
+        Some(Command::Rename {
+            ref file,
+            ref path,
+            ref new_name,
+            write,
+        }) => rename(file, path, new_name, write),
+        Some(Command::Diff { ref old, ref new }) => diff(old, new),
+        Some(Command::Vdiff { a, b, out }) => vdiff(a, b, out),
+    }
+}
+
+fn rename(
+    file: &PathBuf,
+    path: &EntityPath,
+    new_name: &str,
+    write: bool,
+) -> Result<ExitCode, io::Error> {
+    let (filename, src) = read_source(Some(file))?;
+    let Some(mut ast) = parse_source(&filename, &src, &ParseOptions::default()) else {
+        return Ok(ExitCode::from(2));
+    };
+    let result = match path {
+        EntityPath::Entity(name) => ast.rename_entity(name, new_name),
+        EntityPath::Field(entity_name, name) => ast.rename_field(entity_name, name, new_name),
+    };
+    let report = match result {
+        Ok(report) => report,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            return Ok(ExitCode::FAILURE);
+        }
+    };
+    let renamed = TextEdit::apply_all(&src, &report.edits);
+
+    eprintln!(
+        "renamed {} definition(s) and {} relation(s)",
+        report.definitions, report.relations
+    );
+    if write {
+        fs::write(file, renamed)?;
+    } else {
+        io::stdout().write_all(renamed.as_bytes())?;
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn diff(old: &PathBuf, new: &PathBuf) -> Result<ExitCode, io::Error> {
+    let (old_filename, old_src) = read_source(Some(old))?;
+    let (new_filename, new_src) = read_source(Some(new))?;
+    let parse_options = ParseOptions::default();
+    let Some(old_ast) = parse_source(&old_filename, &old_src, &parse_options) else {
+        return Ok(ExitCode::from(2));
+    };
+    let Some(new_ast) = parse_source(&new_filename, &new_src, &parse_options) else {
+        return Ok(ExitCode::from(2));
+    };
+    let convert_options = ConvertOptions {
+        diff: Some(SchemaDiff::new(&old_ast, &new_ast)),
+        ..ConvertOptions::default()
+    };
+
+    let mut doc = new_ast.into_mir_with_options(&convert_options);
+    let mut engine = SimpleLayoutEngine::default();
+    let mut backend = SVGRenderer::new();
+
+    backend.view_box = run_layout(&mut doc, &mut engine).view_box;
+    backend
+        .render(&doc, &mut io::stdout().lock())
+        .expect("Couldn't render as SVG.");
+
+    Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(feature = "raster")]
+fn vdiff(a: PathBuf, b: PathBuf, out: PathBuf) -> Result<ExitCode, io::Error> {
+    use seiren::vdiff;
+
+    let result = (|| {
+        let a = vdiff::rasterize(&fs::read_to_string(a)?)?;
+        let b = vdiff::rasterize(&fs::read_to_string(b)?)?;
+        let d = vdiff::diff(&a, &b)?;
+
+        d.save_png(out)?;
+        Ok::<_, seiren::error::RasterError>(d)
+    })();
+
+    match result {
+        Ok(d) => {
+            eprintln!(
+                "{} of {} pixels changed ({:.2}%)",
+                d.n_changed_pixels,
+                d.n_pixels,
+                100.0 * d.n_changed_pixels as f64 / d.n_pixels.max(1) as f64
+            );
+            Ok(if d.is_identical() {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::FAILURE
+            })
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            Ok(ExitCode::from(2))
+        }
+    }
+}
+
+#[cfg(not(feature = "raster"))]
+fn vdiff(_: PathBuf, _: PathBuf, _: PathBuf) -> Result<ExitCode, io::Error> {
+    eprintln!("error: `vdiff` requires seiren to be built with `raster` feature");
+    Ok(ExitCode::from(2))
+}
+
+fn render(cli: &Cli) -> Result<ExitCode, io::Error> {
+    let mut parse_options = ParseOptions::default();
+    let Some(theme) = load_theme(&cli.theme)? else {
+        return Ok(ExitCode::from(2));
+    };
+    let convert_options = ConvertOptions {
+        implicit_entity_stubs: cli.implicit_entity_stubs,
+        show_field_ordinals: cli.show_field_ordinals,
+        entity_filter: EntityFilter::new(cli.include.clone(), cli.exclude.clone()),
+        highlight_keys: cli.highlight_keys,
+        theme,
+        ..ConvertOptions::default()
+    };
+
+    if let Some(locale) = &cli.locale {
+        let Some(aliases) = KeywordAliases::for_locale(locale) else {
+            eprintln!("error: unknown locale `{}`", locale);
+            return Ok(ExitCode::from(2));
+        };
+        parse_options.keyword_aliases = aliases;
+    }
+
+    // The grid router doesn't build the route graph, and picks terminal ports by itself.
+    if cli.router == EdgeRouter::Grid {
+        let unsupported = [
+            ("--explain-edge", cli.explain_edge.is_some()),
+            ("--debug-overlay", cli.debug_overlay),
+            ("--bundle-edges", cli.bundle_edges),
+            ("--port-capacity", cli.port_capacity.is_some()),
+        ];
+
+        if let Some((option, _)) = unsupported.iter().find(|(_, used)| *used) {
+            eprintln!("error: `{}` can't be used with `--router grid`", option);
+            return Ok(ExitCode::from(2));
+        }
+    }
+
+    if let Err(err) = render_options(cli).validate(&layout_options(cli)) {
+        eprintln!("error: {}", err);
+        return Ok(ExitCode::from(2));
+    }
+
+    #[cfg(feature = "font-file")]
+    if let Some(path) = &cli.font {
+        if let Err(err) = FontFile::new(fs::read(path)?) {
+            eprintln!("error: {}: {}", path.display(), err);
+            return Ok(ExitCode::from(2));
+        }
+    }
+
+    if let Some(dir) = cli.file.as_ref().filter(|path| path.is_dir()) {
+        return render_batch(cli, dir, &parse_options, &convert_options);
+    }
+
+    let rendered = render_file(
+        cli,
+        cli.file.as_ref(),
+        cli.output.as_ref(),
+        &parse_options,
+        &convert_options,
+    )?;
+
+    Ok(if rendered {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::from(2)
+    })
+}
+
+/// Renders a source file (or stdin) to `output` (or stdout). Returns `false` if errors in the
+/// source were reported to stderr.
+fn render_file(
+    cli: &Cli,
+    file: Option<&PathBuf>,
+    output: Option<&PathBuf>,
+    parse_options: &ParseOptions,
+    convert_options: &ConvertOptions,
+) -> Result<bool, io::Error> {
+    let (filename, src) = read_source(file)?;
+    let mut ast = parse_source(&filename, &src, parse_options);
+
+    if let Some(module) = &ast {
+        report_self_relations(&filename, &src, module);
+    }
+
+    if let (Some(module), false) = (&ast, cli.merge.is_empty()) {
+        let mut sources = vec![(filename.clone(), src.clone())];
+
+        for path in &cli.merge {
+            sources.push(read_source(Some(path))?);
+        }
+
+        ast = merge_sources(&sources, module.clone(), cli.merge_policy, parse_options);
+        if ast.is_none() {
+            return Ok(false);
+        }
+    }
+
+    let Some(ast) = ast else { return Ok(false) };
+
+    // AST -> MIR
+    let (mut doc, node_paths) = ast.into_mir_with_node_paths(convert_options);
+    let mut engine = SimpleLayoutEngine::new(layout_options(cli));
+
+    engine.fit_record_width = cli.fit_record_width;
+    if let Some(char_width) = cli.char_width {
+        engine.text_measure = Box::new(CharWidth(char_width));
+    }
+    #[cfg(feature = "font-file")]
+    if let Some(path) = &cli.font {
+        let font = FontFile::new(fs::read(path)?)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+        engine.text_measure = Box::new(font);
+    }
+    engine.target_aspect_ratio = cli.auto_columns;
+    engine.max_width = cli.max_width;
+    engine.separate_edges = cli.separate_edges;
+    engine.bundle_edges = cli.bundle_edges;
+    engine.port_capacity = cli.port_capacity;
+    engine.router = cli.router;
+    engine.cluster_related = cli.cluster;
+    engine.order_references = cli.order_references;
+    engine.seed = cli.seed;
+    engine.orientation = cli.orientation;
+    engine.isolated_records = cli.isolated;
+    engine.pack_columns = cli.pack_columns;
+    engine.balance_columns = cli.balance_columns;
+    engine.swimlanes = cli.swimlanes;
+    engine.circular = cli.circular;
+    engine.snap = cli.snap;
+
+    let Some(result) = layout(cli, &mut doc, &mut engine)? else { return Ok(false) };
+    let view_box = result.view_box;
+
+    report_layout_warnings(&filename, &doc, &node_paths, result.warnings);
+    if let Some(path) = &cli.explain_edge {
+        explain_edge(&doc, &engine, &node_paths, path);
+    }
+
+    if let Some(page_grid) = cli.pages {
+        let Some(output) = output else {
+            eprintln!("error: `--pages` requires `--output`");
+            return Ok(false);
+        };
+        let Some(view_box) = view_box else { return Ok(false) };
+        let mut backend = create_svg_backend(cli, None, &engine, convert_options, &src);
+
+        for page in page_grid.pages(&view_box, cli.page_overlap) {
+            let stem = output.file_stem().unwrap_or_default().to_string_lossy();
+            let page_output = output.with_file_name(format!(
+                "{}-{}-{}.{}",
+                stem,
+                page.row,
+                page.column,
+                output.extension().unwrap_or_default().to_string_lossy()
+            ));
+            let mut output = Output::create(Some(&page_output), cli.compress)?;
+
+            backend.view_box = Some(page.view_box);
+            backend.page_label = Some(page.label);
+            backend
+                .render(&doc, &mut output)
+                .expect("Couldn't render the document.");
+            output.finish()?;
+        }
+        return Ok(true);
+    }
+
+    let backend = create_backend(cli, view_box, &engine, convert_options, &src);
+    let mut output = Output::create(output, cli.compress)?;
+
+    backend
+        .render(&doc, &mut output)
+        .expect("Couldn't render the document.");
+    output.finish()?;
+
+    Ok(true)
+}
+
+/// Creates the backend selected by options: JSON with `--json`, HTML with `--html`, text art
+/// with `--text`, or SVG.
+fn create_backend<'e>(
+    cli: &Cli,
+    view_box: Option<Rect>,
+    engine: &'e SimpleLayoutEngine,
+    convert_options: &ConvertOptions,
+    src: &str,
+) -> Box<dyn Renderer + 'e> {
+    #[cfg(feature = "serde")]
+    if cli.json {
+        let mut backend = JsonRenderer::new();
+
+        backend.view_box = view_box;
+        backend.pretty = true;
+        return Box::new(backend);
+    }
+
+    if cli.html {
+        let mut backend = HtmlRenderer::new();
+
+        backend.view_box = view_box;
+        backend.theme = convert_options.theme.clone();
+        backend.background = cli.background.clone().unwrap_or_default();
+        return Box::new(backend);
+    }
+
+    if cli.text {
+        let mut backend = TextRenderer::new();
+
+        backend.view_box = view_box;
+        backend.charset = cli.charset;
+        return Box::new(backend);
+    }
+
+    Box::new(create_svg_backend(cli, view_box, engine, convert_options, src))
+}
+
+/// Metrics of the layout engine configured by options.
+fn layout_options(cli: &Cli) -> LayoutOptions {
+    LayoutOptions {
+        margin: cli.margin,
+        line_height: cli.line_height,
+        record_width: cli.record_width,
+        record_space: cli.record_space,
+        shape_junction_margin: cli.junction_margin.unwrap_or(cli.record_space / 2.0),
+        bend_cost: cli.bend_cost,
+        port_side_cost: cli.port_side_cost,
+        keep_out_regions: cli.keep_out.clone(),
+        ..LayoutOptions::default()
+    }
+}
+
+/// Sizes of shapes of the SVG backend configured by options.
+fn render_options(cli: &Cli) -> RenderOptions {
+    RenderOptions {
+        path_radius: cli.path_radius,
+        ..RenderOptions::default()
+    }
+}
+
+/// Creates the SVG backend configured by options.
+fn create_svg_backend<'e>(
+    cli: &Cli,
+    view_box: Option<Rect>,
+    engine: &'e SimpleLayoutEngine,
+    convert_options: &ConvertOptions,
+    src: &str,
+) -> SVGRenderer<'e> {
+    let mut backend = SVGRenderer::new();
+    backend.view_box = view_box;
+    backend.options = render_options(cli);
+    backend.width = cli.width;
+    backend.height = cli.height;
+    backend.scale = cli.scale;
+    backend.center = cli.center;
+    backend.theme = convert_options.theme.clone();
+    backend.background = cli.background.clone().unwrap_or_default();
+    backend.grid = cli.grid;
+    backend.shadow = cli.shadow;
+    backend.edge_layer = cli.edge_layer;
+    backend.css_classes = cli.css_classes;
+    backend.light_theme = cli.auto_theme.then(Theme::light);
+    backend.legend = cli.legend;
+    backend.interactive = cli.interactive;
+    backend.accessible = cli.accessible;
+    backend.id_prefix = cli.id_prefix.clone();
+    backend.format = cli.svg_format;
+    if !cli.no_version_stamp {
+        backend.version_stamp = Some(engine.config_hash());
+    }
+    if cli.embed_source {
+        backend.source = Some(src.to_string());
+    }
+    if cli.fit_record_width {
+        backend.field_column_ratio.max_badge_width =
+            Some(SimpleLayoutEngine::BADGE_COLUMN_WIDTH);
+        backend.truncate_overflow = false;
+    }
+
+    if cli.debug_overlay {
+        backend.edge_route_graph = Some(engine.edge_route_graph());
+        backend.show_debug = cli.show_debug;
+    }
+
+    backend
+}
+
+/// Renders all `.seiren` files in `dir` (not recursively) on `--jobs` threads, and prints a
+/// summary. Each output is written to the `--output` directory, or next to its source.
+///
+/// A file which fails (errors in the source, I/O errors or panics) doesn't stop the others.
+fn render_batch(
+    cli: &Cli,
+    dir: &Path,
+    parse_options: &ParseOptions,
+    convert_options: &ConvertOptions,
+) -> Result<ExitCode, io::Error> {
+    #[cfg(feature = "serde")]
+    if cli.layout_cache.is_some() {
+        eprintln!("error: `--layout-cache` can't be used with a directory");
+        return Ok(ExitCode::from(2));
+    }
+    if cli.explain_edge.is_some() {
+        eprintln!("error: `--explain-edge` can't be used with a directory");
+        return Ok(ExitCode::from(2));
+    }
+
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<_, _>>()?;
+
+    files.retain(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "seiren"));
+    files.sort();
+
+    let out_dir = cli.output.as_deref().unwrap_or(dir);
+    fs::create_dir_all(out_dir)?;
 
-    // Read the contents of a specified file or from stdio.
-    let src = if args.len() >= 2 {
-        let path = args.nth(1).unwrap();
+    let extension = if is_json(cli) {
+        "json"
+    } else if cli.html {
+        "html"
+    } else if cli.text {
+        "txt"
+    } else if cli.compress {
+        "svgz"
+    } else {
+        "svg"
+    };
+    let jobs = cli
+        .jobs
+        .or_else(|| thread::available_parallelism().ok().map(usize::from))
+        .unwrap_or(1)
+        .clamp(1, files.len().max(1));
+
+    // Workers take files in order until all files are taken.
+    let next = AtomicUsize::new(0);
+    let failed: Mutex<Vec<&PathBuf>> = Mutex::new(vec![]);
 
-        filename = path.clone();
-        fs::read_to_string(path)?
+    thread::scope(|scope| {
+        for _ in 0..jobs {
+            scope.spawn(|| {
+                while let Some(file) = files.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    let output = out_dir.join(file.file_stem().unwrap()).with_extension(extension);
+                    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                        render_file(cli, Some(file), Some(&output), parse_options, convert_options)
+                    }));
+                    let rendered = match result {
+                        Ok(Ok(rendered)) => rendered,
+                        Ok(Err(err)) => {
+                            eprintln!("error: {}: {}", file.display(), err);
+                            false
+                        }
+                        // The panic message has been printed by the panic hook.
+                        Err(_) => false,
+                    };
+
+                    if !rendered {
+                        failed.lock().unwrap().push(file);
+                    }
+                }
+            });
+        }
+    });
+
+    let mut failed = failed.into_inner().unwrap();
+    failed.sort();
+
+    eprintln!(
+        "rendered {} file(s), {} failed",
+        files.len() - failed.len(),
+        failed.len()
+    );
+    for file in &failed {
+        eprintln!("  failed: {}", file.display());
+    }
+
+    Ok(if failed.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+#[cfg(feature = "serde")]
+fn is_json(cli: &Cli) -> bool {
+    cli.json
+}
+
+#[cfg(not(feature = "serde"))]
+fn is_json(_cli: &Cli) -> bool {
+    false
+}
+
+/// The destination of a rendered document.
+enum Output {
+    Plain(Box<dyn Write>),
+    /// Compressed on the fly, so the compressed document isn't buffered either.
+    Gzip(GzEncoder<Box<dyn Write>>),
+}
+
+impl Output {
+    /// Opens `path` or stdout. The output is compressed if `compress` is true or `path` has
+    /// `.svgz` extension.
+    fn create(path: Option<&PathBuf>, compress: bool) -> Result<Self, io::Error> {
+        let inner: Box<dyn Write> = match path {
+            Some(path) => Box::new(io::BufWriter::new(fs::File::create(path)?)),
+            None => Box::new(io::stdout().lock()),
+        };
+        let compress = compress
+            || path.and_then(|path| path.extension()).is_some_and(|ext| ext == "svgz");
+
+        Ok(if compress {
+            Output::Gzip(GzEncoder::new(inner, Compression::default()))
+        } else {
+            Output::Plain(inner)
+        })
+    }
+
+    /// Writes the gzip trailer (if compressed) and flushes the output.
+    fn finish(self) -> Result<(), io::Error> {
+        match self {
+            Output::Plain(mut inner) => inner.flush(),
+            Output::Gzip(encoder) => encoder.finish()?.flush(),
+        }
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Output::Plain(inner) => inner.write(buf),
+            Output::Gzip(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Output::Plain(inner) => inner.flush(),
+            Output::Gzip(encoder) => encoder.flush(),
+        }
+    }
+}
+
+/// Parses `sources` except the first one, which is parsed as `first`, and merges them. Errors
+/// and conflicts are reported to stderr.
+fn merge_sources(
+    sources: &[(String, String)],
+    first: Module,
+    policy: MergePolicy,
+    parse_options: &ParseOptions,
+) -> Option<Module> {
+    let mut modules = vec![first];
+
+    for (filename, src) in &sources[1..] {
+        let module = parse_source(filename, src, parse_options)?;
+
+        report_self_relations(filename, src, &module);
+        modules.push(module);
+    }
+
+    let merge_sources: Vec<_> = sources
+        .iter()
+        .zip(&modules)
+        .map(|((filename, _), module)| MergeSource::new(filename, module))
+        .collect();
+
+    match merge(&merge_sources, policy) {
+        Ok(merged) => {
+            for conflict in &merged.conflicts {
+                report_conflict(conflict, ReportKind::Warning, sources);
+            }
+            Some(merged.module)
+        }
+        Err(MergeError::Conflict(conflict)) => {
+            report_conflict(&conflict, ReportKind::Error, sources);
+            None
+        }
+    }
+}
+
+/// Warns about relations which connect an entity or a field to itself, because they are not
+/// rendered.
+fn report_self_relations(filename: &str, src: &str, module: &Module) {
+    let relations = module.entries().filter_map(|entry| match entry {
+        ModuleEntry::EntityRelation(relation) if relation.is_self_relation() => Some(relation),
+        _ => None,
+    });
+
+    for relation in relations {
+        let message = format!("relation connects `{}` to itself", relation.start_path());
+
+        Report::build(ReportKind::Warning, filename, relation.span().start)
+            .with_code(Rule::SelfRelation)
+            .with_message(&message)
+            .with_label(
+                Label::new((filename, relation.span()))
+                    .with_message("this relation is not rendered")
+                    .with_color(Color::Yellow),
+            )
+            .finish()
+            .eprint((filename, Source::from(src)))
+            .unwrap();
+    }
+}
+
+fn report_conflict(conflict: &Conflict, kind: ReportKind, sources: &[(String, String)]) {
+    let first = (conflict.first.source.clone(), conflict.first.span.clone());
+    let second = (conflict.second.source.clone(), conflict.second.span.clone());
+
+    Report::build(kind, second.0.clone(), second.1.start)
+        .with_message(&conflict.message)
+        .with_label(
+            Label::new(first)
+                .with_message("first defined here")
+                .with_color(Color::Green),
+        )
+        .with_label(
+            Label::new(second)
+                .with_message("redefined here")
+                .with_color(Color::Yellow),
+        )
+        .finish()
+        .eprint(ariadne::sources(sources.iter().cloned()))
+        .unwrap();
+}
+
+/// Parses a rect of `X,Y,WIDTH,HEIGHT` (e.g. `0,0,200,100`). The size must not be negative.
+fn parse_rect(s: &str) -> Result<Rect, String> {
+    let values: Vec<f32> = s
+        .split(',')
+        .map(|v| v.trim().parse())
+        .collect::<Result<_, _>>()
+        .unwrap_or_default();
+
+    match values[..] {
+        [x, y, width, height] if width >= 0.0 && height >= 0.0 => {
+            Ok(Rect::new(Point::new(x, y), Size::new(width, height)))
+        }
+        _ => Err(format!("invalid region `{}` (expected e.g. `0,0,200,100`)", s)),
+    }
+}
+
+/// Parses a positive length in pixels.
+fn parse_length(s: &str) -> Result<f32, String> {
+    s.trim()
+        .parse::<f32>()
+        .ok()
+        .filter(|length| length.is_finite() && *length > 0.0)
+        .ok_or_else(|| format!("invalid length `{}` (expected a positive number)", s))
+}
+
+/// Parses a positive advance width in em.
+fn parse_char_width(s: &str) -> Result<f32, String> {
+    s.trim()
+        .parse::<f32>()
+        .ok()
+        .filter(|width| width.is_finite() && *width > 0.0)
+        .ok_or_else(|| format!("invalid character width `{}` (expected a positive number)", s))
+}
+
+/// Parses an aspect ratio of `W:H` (e.g. `16:9`) or a number (e.g. `1.5`).
+fn parse_aspect_ratio(s: &str) -> Result<f32, String> {
+    let ratio = match s.split_once(':') {
+        Some((width, height)) => match (width.trim().parse::<f32>(), height.trim().parse::<f32>()) {
+            (Ok(width), Ok(height)) => Some(width / height),
+            _ => None,
+        },
+        None => s.trim().parse().ok(),
+    };
+
+    ratio
+        .filter(|ratio| ratio.is_finite() && *ratio > 0.0)
+        .ok_or_else(|| format!("invalid aspect ratio `{}` (expected e.g. `16:9`)", s))
+}
+
+/// Returns a builtin theme named `name`, or loads a theme file. Errors in a theme file are
+/// reported to stderr.
+fn load_theme(name: &str) -> Result<Option<Theme>, io::Error> {
+    if let Some(theme) = Theme::from_name(name) {
+        return Ok(Some(theme));
+    }
+    if !Path::new(name).is_file() {
+        eprintln!(
+            "error: unknown theme `{}` (available: {}, or a theme file)",
+            name,
+            Theme::NAMES.join(", ")
+        );
+        return Ok(None);
+    }
+
+    match Theme::parse(&fs::read_to_string(name)?) {
+        Ok(theme) => Ok(Some(theme)),
+        Err(err) => {
+            eprintln!("error: {}: {}", name, err);
+            Ok(None)
+        }
+    }
+}
+
+/// Prints `warnings` of laying out `doc`, and defects of the layout (e.g. overlapping records),
+/// which are bugs of the layout engine, as warnings.
+fn report_layout_warnings(
+    filename: &str,
+    doc: &Document,
+    node_paths: &HashMap<EntityPath, NodeId>,
+    warnings: Vec<LayoutWarning>,
+) {
+    let name_of = |id: NodeId| {
+        node_paths
+            .iter()
+            .find(|(_, node_id)| **node_id == id)
+            .map_or_else(|| id.to_string(), |(path, _)| path.to_string())
+    };
+
+    for warning in warnings.into_iter().chain(validate_layout(doc)) {
+        let message = match warning {
+            LayoutWarning::RecordOverlap { first, second } => {
+                format!("`{}` and `{}` overlap", name_of(first), name_of(second))
+            }
+            LayoutWarning::EdgeThroughRecord { edge_id, record_id } => {
+                let Some((source_id, target_id)) = doc.edge_endpoints(edge_id) else { continue };
+
+                format!(
+                    "the edge between `{}` and `{}` passes through `{}`",
+                    name_of(source_id),
+                    name_of(target_id),
+                    name_of(record_id)
+                )
+            }
+            LayoutWarning::CellOutOfRange { record_id } => format!(
+                "the row or column of `{}` is out of the grid, so it is placed in another cell",
+                name_of(record_id)
+            ),
+        };
+
+        eprintln!("warning: {}: {}", filename, message);
+    }
+}
+
+/// Prints route explanations of edges connected to `path` to stderr.
+fn explain_edge(
+    doc: &Document,
+    engine: &SimpleLayoutEngine,
+    node_paths: &HashMap<EntityPath, NodeId>,
+    path: &EntityPath,
+) {
+    let Some(node_id) = node_paths.get(path).copied() else {
+        eprintln!("error: `{}` is not found", path);
+        return;
+    };
+    let name_of = |id: NodeId| {
+        node_paths
+            .iter()
+            .find(|(_, node_id)| **node_id == id)
+            .map_or_else(|| id.to_string(), |(path, _)| path.to_string())
+    };
+    let port = |p: &TerminalPort| format!("{} {} {}", p.id(), p.orientation(), p.location());
+    let mut n_edges = 0;
+
+    for edge_id in doc.edge_ids() {
+        let Some((source_id, target_id)) = doc.edge_endpoints(edge_id) else { continue };
+
+        if source_id != node_id && target_id != node_id {
+            continue;
+        }
+        n_edges += 1;
+
+        eprintln!(
+            "edge {} between {} and {}:",
+            edge_id,
+            name_of(source_id),
+            name_of(target_id)
+        );
+
+        // Routes are searched after layout, so replaying is always possible here.
+        let Some(explanation) = engine.explain_edge(doc, edge_id) else { continue };
+
+        eprintln!("  candidates:");
+        for (i, candidate) in explanation.candidates.iter().enumerate() {
+            eprintln!(
+                "  {} {} -> {}  cost {}",
+                if explanation.selected == Some(i) { "*" } else { " " },
+                port(&candidate.source),
+                port(&candidate.target),
+                candidate.cost
+            );
+        }
+        eprintln!(
+            "  path: {}",
+            explanation
+                .path
+                .iter()
+                .map(|id| id.to_string())
+                .collect::<Vec<_>>()
+                .join(" -> ")
+        );
+    }
+
+    if n_edges == 0 {
+        eprintln!("`{}` has no edges", path);
+    }
+}
+
+fn run_layout(doc: &mut Document, engine: &mut SimpleLayoutEngine) -> LayoutResult {
+    engine.layout(doc)
+}
+
+/// Lays out `doc`, restoring and updating `--layout-cache`. Returns `None` if the cache is
+/// refused by `--version-check`, after reporting it. A restored layout has no warnings.
+#[cfg(feature = "serde")]
+fn layout(
+    cli: &Cli,
+    doc: &mut Document,
+    engine: &mut SimpleLayoutEngine,
+) -> Result<Option<LayoutResult>, io::Error> {
+    use seiren::layout_cache::LayoutCache;
+
+    let Some(path) = &cli.layout_cache else { return Ok(Some(run_layout(doc, engine))) };
+
+    // A missing or broken cache is simply rebuilt.
+    if let Ok(cache) = fs::File::open(path).map(io::BufReader::new) {
+        if let Ok(cache) = LayoutCache::read_from(cache) {
+            match cache.check_version(engine.config_hash()) {
+                Err(err) if cli.version_check => {
+                    eprintln!("error: {}: {}", path.display(), err);
+                    return Ok(None);
+                }
+                Err(err) => {
+                    eprintln!("warning: {}: {}; rebuilding it", path.display(), err);
+                }
+                Ok(()) => {
+                    if let Some(view_box) = cache.apply(doc, engine) {
+                        engine.set_edge_route_graph(cache.into_route_graph());
+                        return Ok(Some(LayoutResult { view_box, warnings: vec![] }));
+                    }
+                }
+            }
+        }
+    }
+
+    let result = run_layout(doc, engine);
+    let cache = LayoutCache::capture(doc, result.view_box, engine);
+
+    cache.write_to(io::BufWriter::new(fs::File::create(path)?))?;
+    Ok(Some(result))
+}
+
+#[cfg(not(feature = "serde"))]
+fn layout(
+    _: &Cli,
+    doc: &mut Document,
+    engine: &mut SimpleLayoutEngine,
+) -> Result<Option<LayoutResult>, io::Error> {
+    Ok(Some(run_layout(doc, engine)))
+}
+
+fn run_lint(file: Option<&PathBuf>, allow: &[String]) -> Result<ExitCode, io::Error> {
+    let mut config = LintConfig::new();
+
+    for id in allow {
+        let Some(rule) = Rule::from_id(id) else {
+            eprintln!("error: unknown lint rule `{}`", id);
+            return Ok(ExitCode::from(2));
+        };
+        config.disable(rule);
+    }
+
+    let (filename, src) = read_source(file)?;
+    let Some(ast) = parse_source(&filename, &src, &ParseOptions::default()) else {
+        return Ok(ExitCode::from(2));
+    };
+    let diagnostics = lint(&ast, &config);
+
+    for d in diagnostics.iter() {
+        let filename = filename.as_str();
+
+        Report::build(ReportKind::Warning, filename, d.span.start)
+            .with_code(d.rule)
+            .with_message(&d.message)
+            .with_label(
+                Label::new((filename, d.span.clone()))
+                    .with_message(d.message.clone())
+                    .with_color(Color::Yellow),
+            )
+            .finish()
+            .eprint((filename, Source::from(&src)))
+            .unwrap();
+    }
+
+    Ok(if diagnostics.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+/// Reads the contents of a specified file or from stdin. Returns the filename and the contents.
+/// The source embedded in an `.svg` file is read instead of the file.
+fn read_source(file: Option<&PathBuf>) -> Result<(String, String), io::Error> {
+    let mut filename = "(stdin)".to_string();
+    let src = if let Some(path) = file {
+        filename = path.display().to_string();
+
+        let contents = fs::read_to_string(path)?;
+        if path.extension().is_some_and(|ext| ext == "svg") {
+            extract_source(&contents).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{} has no embedded source", filename),
+                )
+            })?
+        } else {
+            contents
+        }
     } else {
         let mut s = String::new();
         io::stdin().read_to_string(&mut s)?;
         s
     };
 
-    let (ast, tokenize_errs, parse_errs) = parse(&src);
+    Ok((filename, src))
+}
+
+/// Parses `src` and reports errors to stderr.
+fn parse_source(filename: &str, src: &str, parse_options: &ParseOptions) -> Option<Module> {
+    let (ast, tokenize_errs, parse_errs) = parse_with_options(src, parse_options);
 
     // Convert both errors into error::Simple<String>
     let errors = tokenize_errs
@@ -34,7 +1370,6 @@ fn main() -> Result<(), io::Error> {
 
     // Report errors
     for e in errors {
-        let filename = filename.as_str();
         let report = Report::build(ReportKind::Error, filename, e.span().start);
 
         let report = match e.reason() {
@@ -100,35 +1435,10 @@ fn main() -> Result<(), io::Error> {
 
         report
             .finish()
-            .eprint((filename, Source::from(&src)))
+            .eprint((filename, Source::from(src)))
             .unwrap();
     }
 
-    // AST -> MIR
-
-    if let Some(ast) = ast {
-        let mut doc = ast.into_mir();
-        let mut engine = SimpleLayoutEngine::new();
-
-        let view_box = engine.place_nodes(&mut doc);
-
-        engine.place_terminal_ports(&mut doc);
-        engine.draw_edge_path(&mut doc);
-
-        let mut backend = SVGRenderer::new();
-        backend.view_box = view_box;
-
-        if DEBUG {
-            backend.edge_route_graph = Some(engine.edge_route_graph());
-        }
-
-        let stdout = io::stdout();
-        let mut handle = stdout.lock();
-
-        backend
-            .render(&doc, &mut handle)
-            .expect("Couldn't render as SVG.");
-    }
 
-    Ok(())
+    ast
 }
@@ -0,0 +1,274 @@
+//! Merging modules
+//!
+//! Combines modules parsed from several sources (e.g. files) into one module. Relations, type
+//! aliases and directives are concatenated in source order, and entities defined more than once
+//! are resolved by a `MergePolicy`. Spans in the merged module still refer to their own sources.
+use crate::erd::{EntityField, EntityPath, Module, ModuleEntry};
+use crate::error::MergeError;
+use crate::parser::Span;
+use derive_more::Display;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// How to resolve an entity defined in more than one place.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Display)]
+pub enum MergePolicy {
+    /// A redefinition is an error.
+    #[default]
+    #[display(fmt = "error")]
+    Error,
+    /// The first definition is used, and redefinitions are reported as conflicts.
+    #[display(fmt = "first-wins")]
+    FirstWins,
+    /// Fields of all definitions are combined. If definitions of a field differ, the first one
+    /// is used and the others are reported as conflicts.
+    #[display(fmt = "union")]
+    Union,
+}
+
+impl MergePolicy {
+    pub const ALL: [MergePolicy; 3] = [
+        MergePolicy::Error,
+        MergePolicy::FirstWins,
+        MergePolicy::Union,
+    ];
+}
+
+impl FromStr for MergePolicy {
+    type Err = String;
+
+    /// Parses `error`, `first-wins` or `union`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|policy| policy.to_string() == s)
+            .ok_or_else(|| format!("unknown merge policy `{}`", s))
+    }
+}
+
+/// A module to be merged, with the name of its source (e.g. a file path).
+#[derive(Debug, Clone, Copy)]
+pub struct MergeSource<'a> {
+    pub name: &'a str,
+    pub module: &'a Module,
+}
+
+impl<'a> MergeSource<'a> {
+    pub fn new(name: &'a str, module: &'a Module) -> Self {
+        Self { name, module }
+    }
+}
+
+/// A location in one of merged sources.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    pub source: String,
+    pub span: Span,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.source, self.span.start)
+    }
+}
+
+/// Conflicting definitions of an entity or a field.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflict {
+    /// The entity or the field which has conflicting definitions.
+    pub path: EntityPath,
+    pub message: String,
+    /// The definition which is kept.
+    pub first: Location,
+    /// The definition which is dropped.
+    pub second: Location,
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} (first defined at {}, redefined at {})",
+            self.message, self.first, self.second
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Merged {
+    pub module: Module,
+    /// Conflicts resolved by the policy, in source order.
+    pub conflicts: Vec<Conflict>,
+}
+
+/// Merges `sources` into one module. The name of the merged module is taken from the first
+/// source.
+pub fn merge(sources: &[MergeSource], policy: MergePolicy) -> Result<Merged, MergeError> {
+    let mut entries: Vec<ModuleEntry> = vec![];
+    let mut conflicts = vec![];
+    // Entity name -> the position of the first definition in `entries` and its location
+    let mut entities: HashMap<String, (usize, Location)> = HashMap::new();
+    // (entity name, field name) -> the location of the field kept in the merged definition
+    let mut fields: HashMap<(String, String), Location> = HashMap::new();
+
+    for source in sources {
+        let location = |span: Span| Location {
+            source: source.name.to_string(),
+            span,
+        };
+
+        for entry in source.module.entries() {
+            let ModuleEntry::EntityDefinition(definition) = entry else {
+                entries.push(entry.clone());
+                continue;
+            };
+            let name = definition.name();
+
+            for field in definition.fields() {
+                fields
+                    .entry((name.to_string(), field.name().to_string()))
+                    .or_insert_with(|| location(field.span()));
+            }
+
+            let Some((index, first)) = entities.get(name) else {
+                entities.insert(
+                    name.to_string(),
+                    (entries.len(), location(definition.span())),
+                );
+                entries.push(entry.clone());
+                continue;
+            };
+
+            if policy != MergePolicy::Union {
+                let conflict = Conflict {
+                    path: EntityPath::Entity(name.to_string()),
+                    message: format!("entity `{}` is defined more than once", name),
+                    first: first.clone(),
+                    second: location(definition.span()),
+                };
+
+                if policy == MergePolicy::Error {
+                    return Err(MergeError::Conflict(Box::new(conflict)));
+                }
+                conflicts.push(conflict);
+                continue;
+            }
+
+            let ModuleEntry::EntityDefinition(merged) = &mut entries[*index] else {
+                unreachable!("entity `{}` must be a definition", name);
+            };
+
+            for field in definition.fields() {
+                let kept = merged.fields().find(|f| f.name() == field.name());
+
+                match kept {
+                    None => merged.add_field(field.clone()),
+                    Some(kept) if !is_same_field(kept, field) => {
+                        let path = EntityPath::Field(name.to_string(), field.name().to_string());
+
+                        conflicts.push(Conflict {
+                            message: format!(
+                                "field `{}` has conflicting definitions: `{}` and `{}`",
+                                path, kept, field
+                            ),
+                            path,
+                            first: fields[&(name.to_string(), field.name().to_string())].clone(),
+                            second: location(field.span()),
+                        });
+                    }
+                    Some(_) => {}
+                }
+            }
+        }
+    }
+
+    let name = sources
+        .first()
+        .and_then(|source| source.module.name())
+        .map(str::to_string);
+    let mut module = Module::new(name);
+
+    for entry in entries {
+        module.add_entry(entry);
+    }
+
+    Ok(Merged { module, conflicts })
+}
+
+/// Compares the declarations of fields, ignoring their locations.
+fn is_same_field(a: &EntityField, b: &EntityField) -> bool {
+    a.to_string() == b.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+
+    fn module(src: &str) -> Module {
+        let (ast, _, _) = parse(src);
+        ast.unwrap()
+    }
+
+    fn field_names(module: &Module, entity: &str) -> Vec<String> {
+        module
+            .entity(entity)
+            .unwrap()
+            .fields()
+            .map(|f| f.name().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn merge_policies() {
+        let a = module("erd { users { id int PK\nage int } }");
+        let b = module("erd { users { id int PK\nage text\nemail text }\nposts { id int }\nposts.id o--o users.id }");
+        let sources = [
+            MergeSource::new("a.seiren", &a),
+            MergeSource::new("b.seiren", &b),
+        ];
+
+        // error
+        let Err(MergeError::Conflict(conflict)) = merge(&sources, MergePolicy::Error) else {
+            panic!("conflict must be an error");
+        };
+        assert_eq!(conflict.path, EntityPath::Entity("users".into()));
+        assert_eq!(conflict.first.source, "a.seiren");
+        assert_eq!(conflict.second.source, "b.seiren");
+
+        // first-wins
+        let merged = merge(&sources, MergePolicy::FirstWins).unwrap();
+        assert_eq!(merged.conflicts.len(), 1);
+        assert_eq!(field_names(&merged.module, "users"), vec!["id", "age"]);
+        assert!(merged.module.entity("posts").is_some());
+        assert_eq!(merged.module.relations_of("users").count(), 1);
+
+        // union
+        let merged = merge(&sources, MergePolicy::Union).unwrap();
+        assert_eq!(
+            field_names(&merged.module, "users"),
+            vec!["id", "age", "email"]
+        );
+        assert_eq!(merged.conflicts.len(), 1);
+
+        let conflict = &merged.conflicts[0];
+        assert_eq!(
+            conflict.path,
+            EntityPath::Field("users".into(), "age".into())
+        );
+        assert_eq!(
+            conflict.message,
+            "field `users.age` has conflicting definitions: `age int` and `age text`"
+        );
+        assert_eq!(conflict.second.source, "b.seiren");
+    }
+
+    #[test]
+    fn parse_policy() {
+        for policy in MergePolicy::ALL {
+            assert_eq!(policy.to_string().parse(), Ok(policy));
+        }
+        assert!("last-wins".parse::<MergePolicy>().is_err());
+    }
+}
@@ -0,0 +1,147 @@
+//! Metrics of drawn shapes
+//!
+//! Sizes of the parts of records and edges which renderers draw and the layout engine makes room
+//! for, e.g. padding of field rows, badges, and edge labels and cardinalities.
+use crate::{
+    font,
+    geometry::{Orientation, Point, Rect, Size},
+    mir,
+};
+
+/// Horizontal padding of field rows.
+pub(crate) const PADDING_X: f32 = 12.0;
+/// Horizontal space reserved for a field ordinal in front of the title.
+pub(crate) const ORDINAL_WIDTH: f32 = 24.0;
+/// Horizontal space between stacked badges.
+pub(crate) const BADGE_GAP: f32 = 4.0;
+/// Vertical space between a badge and the row border.
+pub(crate) const BADGE_MARGIN_Y: f32 = 6.0;
+/// The minimum space between a title and a subtitle.
+pub(crate) const TEXT_GAP: f32 = 16.0;
+/// The size of entity kind icons in record headers.
+pub(crate) const ICON_SIZE: f32 = 12.0;
+/// The space between an entity kind icon and the name.
+pub(crate) const ICON_GAP: f32 = 6.0;
+/// The distance of a cardinality text from the port, along the edge.
+const CARDINALITY_OFFSET_ALONG: f32 = 20.0;
+/// The distance of a cardinality text from the edge.
+const CARDINALITY_OFFSET_BESIDE: f32 = 8.0;
+pub(crate) const EDGE_LABEL_FONT_SIZE: mir::FontSize = mir::FontSize::XSmall;
+const EDGE_LABEL_PADDING_X: f32 = 8.0;
+const EDGE_LABEL_PADDING_Y: f32 = 4.0;
+
+/// Which point of a text its origin is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TextAnchor {
+    Start,
+    Middle,
+    End,
+}
+
+pub(crate) fn cardinality_span(cardinality: mir::Cardinality) -> mir::TextSpan {
+    mir::TextSpanBuilder::default()
+        .text(cardinality.to_string())
+        .font_family(Some(mir::FontFamily::Monospace2))
+        .font_size(Some(mir::FontSize::XXSmall))
+        .build()
+        .unwrap()
+}
+
+/// Returns the origin and the anchor of a cardinality text at `port` of an edge which goes on to
+/// `next`. With `flip`, the text is below a horizontal end, or left of a vertical end.
+pub(crate) fn cardinality_origin(port: &Point, next: &Point, flip: bool) -> (Point, TextAnchor) {
+    let along = CARDINALITY_OFFSET_ALONG;
+    let beside = if flip {
+        -CARDINALITY_OFFSET_BESIDE
+    } else {
+        CARDINALITY_OFFSET_BESIDE
+    };
+    let vertical_anchor = if flip {
+        TextAnchor::End
+    } else {
+        TextAnchor::Start
+    };
+
+    match port.orthogonal_direction(next) {
+        Orientation::Left => (
+            Point::new(port.x - along, port.y - beside),
+            TextAnchor::Middle,
+        ),
+        Orientation::Right => (
+            Point::new(port.x + along, port.y - beside),
+            TextAnchor::Middle,
+        ),
+        Orientation::Up => (Point::new(port.x + beside, port.y - along), vertical_anchor),
+        Orientation::Down => (Point::new(port.x + beside, port.y + along), vertical_anchor),
+    }
+}
+
+/// Returns the bounds of the text of `cardinality` at `port`. See `cardinality_origin`.
+pub(crate) fn cardinality_rect(
+    cardinality: mir::Cardinality,
+    port: &Point,
+    next: &Point,
+    flip: bool,
+) -> Rect {
+    let span = cardinality_span(cardinality);
+    let size = Size::new(
+        font::text_width(&span),
+        font::font_size_px(mir::FontSize::XXSmall),
+    );
+    let (origin, anchor) = cardinality_origin(port, next, flip);
+    let min_x = match anchor {
+        TextAnchor::Start => origin.x,
+        TextAnchor::Middle => origin.x - size.width / 2.0,
+        TextAnchor::End => origin.x - size.width,
+    };
+
+    Rect::new(Point::new(min_x, origin.y - size.height / 2.0), size)
+}
+
+/// Returns the size of the pill-shaped background of an edge `label`.
+pub(crate) fn edge_label_size(label: &str) -> Size {
+    let span = mir::TextSpanBuilder::default()
+        .text(label)
+        .font_family(Some(mir::FontFamily::Monospace2))
+        .font_size(Some(EDGE_LABEL_FONT_SIZE))
+        .build()
+        .unwrap();
+
+    Size::new(
+        font::text_width(&span) + EDGE_LABEL_PADDING_X * 2.0,
+        font::font_size_px(EDGE_LABEL_FONT_SIZE) + EDGE_LABEL_PADDING_Y * 2.0,
+    )
+}
+
+/// Returns the rect of an edge label of `size` at `center`.
+pub(crate) fn edge_label_rect(center: Point, size: Size) -> Rect {
+    Rect::new(
+        Point::new(center.x - size.width / 2.0, center.y - size.height / 2.0),
+        size,
+    )
+}
+
+/// Returns the middle of the longest straight segment of `path_points` where a label of `size` is
+/// `free`, or of the longest segment if there is none. Ties are broken by the order along the
+/// path.
+pub(crate) fn edge_label_center(
+    path_points: &[Point],
+    size: Size,
+    free: impl Fn(&Rect) -> bool,
+) -> Option<Point> {
+    let mut segments: Vec<(Point, Point)> = path_points
+        .windows(2)
+        .map(|pair| (pair[0], pair[1]))
+        .filter(|(a, b)| a.distance(b) > 0.0)
+        .collect();
+
+    segments.sort_by(|(a1, b1), (a2, b2)| a2.distance(b2).total_cmp(&a1.distance(b1)));
+
+    let midpoint = |(a, b): &(Point, Point)| Point::new((a.x + b.x) / 2.0, (a.y + b.y) / 2.0);
+
+    segments
+        .iter()
+        .map(midpoint)
+        .find(|center| free(&edge_label_rect(*center, size)))
+        .or_else(|| segments.first().map(midpoint))
+}
@@ -15,11 +15,22 @@ use crate::geometry::{Orientation, Point, Rect, Size};
 use derive_builder::Builder;
 use derive_more::Display;
 use petgraph::graph::{EdgeIndex, NodeIndex, UnGraph};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 use std::fmt;
+use std::str::FromStr;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct NodeId(NodeIndex);
 
+impl NodeId {
+    /// The index of the node in the document, in insertion order.
+    pub fn index(&self) -> usize {
+        self.0.index()
+    }
+}
+
 impl fmt::Display for NodeId {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", self.0.index())
@@ -27,6 +38,7 @@ impl fmt::Display for NodeId {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct EdgeId(EdgeIndex);
 
 impl fmt::Display for EdgeId {
@@ -35,16 +47,50 @@ impl fmt::Display for EdgeId {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Display)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[display(fmt = "{}:{}", _0, _1)]
 pub struct TerminalPortId(NodeId, usize);
 
+impl TerminalPortId {
+    /// The node which the terminal port belongs to.
+    pub fn node_id(&self) -> NodeId {
+        self.0
+    }
+
+    /// The index of the terminal port in the node, in placement order.
+    pub fn index(&self) -> usize {
+        self.1
+    }
+}
+
+/// A cell of the grid in which records are placed. Both are 0-based.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct GridCell {
+    pub row: usize,
+    pub column: usize,
+}
+
+impl GridCell {
+    pub fn new(row: usize, column: usize) -> Self {
+        Self { row, column }
+    }
+}
+
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct NodeData {
     /// The origin (absolute in the global coordination)
     pub origin: Option<Point>,
     pub size: Option<Size>,
 
+    /// The grid cell in which the layout engine should place the node.
+    pub preferred_cell: Option<GridCell>,
+    /// The origin at which the layout engine should place the node, out of the grid. It takes
+    /// precedence over `preferred_cell`.
+    pub preferred_origin: Option<Point>,
+
     /// Points to which edges can be connected.
     terminal_ports: Vec<TerminalPort>,
     kind: ShapeKind,
@@ -59,6 +105,8 @@ impl NodeData {
             kind,
             origin: None,
             size: None,
+            preferred_cell: None,
+            preferred_origin: None,
             terminal_ports: vec![],
             children: vec![],
         }
@@ -68,9 +116,13 @@ impl NodeData {
         &self.kind
     }
 
+    pub fn kind_mut(&mut self) -> &mut ShapeKind {
+        &mut self.kind
+    }
+
     // --- Children
 
-    pub fn children(&self) -> impl ExactSizeIterator<Item = NodeId> + '_ {
+    pub fn children(&self) -> impl DoubleEndedIterator<Item = NodeId> + ExactSizeIterator + '_ {
         self.children.iter().copied()
     }
 
@@ -89,6 +141,10 @@ impl NodeData {
         self.terminal_ports.iter()
     }
 
+    pub fn set_terminal_ports(&mut self, terminal_ports: Vec<TerminalPort>) {
+        self.terminal_ports = terminal_ports;
+    }
+
     pub fn add_terminal_port(
         &mut self,
         node_id: NodeId,
@@ -104,11 +160,43 @@ impl NodeData {
 }
 
 // --- Edge
-#[derive(Debug)]
+#[derive(Debug, Builder)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct EdgeData {
     source_id: NodeId,
     target_id: NodeId,
+    #[builder(default)]
     path_points: Option<Vec<Point>>,
+    #[builder(default)]
+    start_marker: EdgeMarker,
+    #[builder(default)]
+    end_marker: EdgeMarker,
+    #[builder(default)]
+    start_cardinality: Option<Cardinality>,
+    #[builder(default)]
+    end_cardinality: Option<Cardinality>,
+    #[builder(default)]
+    label: Option<String>,
+    /// Chosen by the layout engine. Backends place the label by themselves if it's `None`.
+    #[builder(default)]
+    label_center: Option<Point>,
+    /// Whether cardinalities of the start and end are placed on the other side of the edge.
+    #[builder(default)]
+    cardinality_flips: [bool; 2],
+    #[builder(default)]
+    stroke_style: StrokeStyle,
+    /// Falls back to the default width of the backend.
+    #[builder(default)]
+    stroke_width: Option<f32>,
+    /// Falls back to the edge color of the theme.
+    #[builder(default)]
+    stroke_color: Option<WebColor>,
+    /// Falls back to the default layer of the backend.
+    #[builder(default)]
+    layer: Option<EdgeLayer>,
+    #[builder(default)]
+    #[cfg_attr(feature = "serde", serde(default))]
+    direction: EdgeDirection,
 }
 
 impl EdgeData {
@@ -117,9 +205,115 @@ impl EdgeData {
             source_id,
             target_id,
             path_points,
+            start_marker: EdgeMarker::default(),
+            end_marker: EdgeMarker::default(),
+            start_cardinality: None,
+            end_cardinality: None,
+            label: None,
+            label_center: None,
+            cardinality_flips: [false; 2],
+            stroke_style: StrokeStyle::default(),
+            stroke_width: None,
+            stroke_color: None,
+            layer: None,
+            direction: EdgeDirection::default(),
         }
     }
 
+    /// The cardinality of the source end, if known.
+    pub fn start_cardinality(&self) -> Option<Cardinality> {
+        self.start_cardinality
+    }
+
+    /// The cardinality of the target end, if known.
+    pub fn end_cardinality(&self) -> Option<Cardinality> {
+        self.end_cardinality
+    }
+
+    pub fn set_cardinalities(&mut self, start: Option<Cardinality>, end: Option<Cardinality>) {
+        self.start_cardinality = start;
+        self.end_cardinality = end;
+    }
+
+    /// The text drawn along the edge (e.g. `author`).
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    pub fn set_label(&mut self, label: Option<String>) {
+        self.label = label;
+    }
+
+    /// The center of the label, if the layout engine has placed it.
+    pub fn label_center(&self) -> Option<Point> {
+        self.label_center
+    }
+
+    pub fn set_label_center(&mut self, label_center: Option<Point>) {
+        self.label_center = label_center;
+    }
+
+    /// Whether the cardinality of the start (and the end) is placed below a horizontal end or
+    /// left of a vertical end, instead of above or right of it, so that it doesn't overlap.
+    pub fn cardinality_flips(&self) -> [bool; 2] {
+        self.cardinality_flips
+    }
+
+    pub fn set_cardinality_flips(&mut self, cardinality_flips: [bool; 2]) {
+        self.cardinality_flips = cardinality_flips;
+    }
+
+    /// The marker at the source end.
+    pub fn start_marker(&self) -> EdgeMarker {
+        self.start_marker
+    }
+
+    /// The marker at the target end.
+    pub fn end_marker(&self) -> EdgeMarker {
+        self.end_marker
+    }
+
+    pub fn set_markers(&mut self, start_marker: EdgeMarker, end_marker: EdgeMarker) {
+        self.start_marker = start_marker;
+        self.end_marker = end_marker;
+    }
+
+    pub fn stroke_style(&self) -> StrokeStyle {
+        self.stroke_style
+    }
+
+    pub fn stroke_width(&self) -> Option<f32> {
+        self.stroke_width
+    }
+
+    pub fn stroke_color(&self) -> Option<&WebColor> {
+        self.stroke_color.as_ref()
+    }
+
+    pub fn set_stroke(&mut self, style: StrokeStyle, width: Option<f32>, color: Option<WebColor>) {
+        self.stroke_style = style;
+        self.stroke_width = width;
+        self.stroke_color = color;
+    }
+
+    /// Whether the edge is drawn below records or above labels.
+    pub fn layer(&self) -> Option<EdgeLayer> {
+        self.layer
+    }
+
+    pub fn set_layer(&mut self, layer: Option<EdgeLayer>) {
+        self.layer = layer;
+    }
+
+    /// Which way the edge points, independently of its markers.
+    pub fn direction(&self) -> EdgeDirection {
+        self.direction
+    }
+
+    pub fn set_direction(&mut self, direction: EdgeDirection) {
+        self.direction = direction;
+    }
+
     pub fn source_id(&self) -> NodeId {
         self.source_id
     }
@@ -137,14 +331,230 @@ impl EdgeData {
     }
 }
 
+/// The shape drawn at an end of an edge, which denotes the cardinality of the end.
+#[derive(Debug, Clone, Copy, Default, Hash, PartialEq, Eq, Display)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum EdgeMarker {
+    #[display(fmt = "none")]
+    None,
+    /// An unspecified cardinality.
+    #[default]
+    #[display(fmt = "circle")]
+    Circle,
+    #[display(fmt = "arrow")]
+    Arrow,
+    /// A crow's foot: zero or more.
+    #[display(fmt = "crow")]
+    Crow,
+    /// Two bars: exactly one.
+    #[display(fmt = "one")]
+    One,
+    /// A bar and a crow's foot: one or more.
+    #[display(fmt = "many")]
+    Many,
+}
+
+impl EdgeMarker {
+    pub const ALL: [EdgeMarker; 6] = [
+        EdgeMarker::None,
+        EdgeMarker::Circle,
+        EdgeMarker::Arrow,
+        EdgeMarker::Crow,
+        EdgeMarker::One,
+        EdgeMarker::Many,
+    ];
+
+    /// Returns the cardinality denoted by the marker, if any.
+    pub fn cardinality(&self) -> Option<Cardinality> {
+        match self {
+            EdgeMarker::None | EdgeMarker::Circle | EdgeMarker::Arrow => None,
+            EdgeMarker::Crow => Some(Cardinality::Many),
+            EdgeMarker::One => Some(Cardinality::One),
+            EdgeMarker::Many => Some(Cardinality::OneOrMany),
+        }
+    }
+}
+
+impl FromStr for EdgeMarker {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|marker| marker.to_string() == s)
+            .ok_or_else(|| format!("unknown edge marker `{}`", s))
+    }
+}
+
+/// The line of an edge.
+#[derive(Debug, Clone, Copy, Default, Hash, PartialEq, Eq, Display)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum StrokeStyle {
+    #[default]
+    #[display(fmt = "solid")]
+    Solid,
+    #[display(fmt = "dashed")]
+    Dashed,
+    #[display(fmt = "dotted")]
+    Dotted,
+}
+
+impl StrokeStyle {
+    pub const ALL: [StrokeStyle; 3] =
+        [StrokeStyle::Solid, StrokeStyle::Dashed, StrokeStyle::Dotted];
+}
+
+impl FromStr for StrokeStyle {
+    type Err = String;
+
+    /// Parses `solid`, `dashed` or `dotted`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|style| style.to_string() == s)
+            .ok_or_else(|| format!("unknown stroke style `{}`", s))
+    }
+}
+
+/// Where an edge is drawn in the stack of shapes.
+#[derive(Debug, Clone, Copy, Default, Hash, PartialEq, Eq, Display)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum EdgeLayer {
+    /// Beneath records, so records hide crossing paths.
+    #[display(fmt = "below")]
+    Below,
+    /// Over records and beneath edge labels.
+    #[default]
+    #[display(fmt = "normal")]
+    Normal,
+    /// Over edge labels.
+    #[display(fmt = "above")]
+    Above,
+}
+
+impl EdgeLayer {
+    pub const ALL: [EdgeLayer; 3] = [EdgeLayer::Below, EdgeLayer::Normal, EdgeLayer::Above];
+}
+
+impl FromStr for EdgeLayer {
+    type Err = String;
+
+    /// Parses `below`, `normal` or `above`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|layer| layer.to_string() == s)
+            .ok_or_else(|| format!("unknown edge layer `{}`", s))
+    }
+}
+
+/// Which way an edge points. An ER relation points from a foreign key to the key it references,
+/// and flows and dependencies point from their source to their target.
+#[derive(Debug, Clone, Copy, Default, Hash, PartialEq, Eq, Display)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum EdgeDirection {
+    #[default]
+    #[display(fmt = "none")]
+    None,
+    /// From the source to the target.
+    #[display(fmt = "forward")]
+    Forward,
+    /// From the target to the source.
+    #[display(fmt = "backward")]
+    Backward,
+    #[display(fmt = "both")]
+    Both,
+}
+
+impl EdgeDirection {
+    pub const ALL: [EdgeDirection; 4] = [
+        EdgeDirection::None,
+        EdgeDirection::Forward,
+        EdgeDirection::Backward,
+        EdgeDirection::Both,
+    ];
+
+    /// Whether the edge points into its start and end, in this order.
+    pub fn points_to(&self) -> [bool; 2] {
+        match self {
+            EdgeDirection::None => [false, false],
+            EdgeDirection::Forward => [false, true],
+            EdgeDirection::Backward => [true, false],
+            EdgeDirection::Both => [true, true],
+        }
+    }
+}
+
+impl FromStr for EdgeDirection {
+    type Err = String;
+
+    /// Parses `none`, `forward`, `backward` or `both`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|direction| direction.to_string() == s)
+            .ok_or_else(|| format!("unknown edge direction `{}`", s))
+    }
+}
+
+/// The number of records which an end of an edge refers to. Shown as text near the end.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Display)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Cardinality {
+    #[display(fmt = "0..1")]
+    #[cfg_attr(feature = "serde", serde(rename = "0..1"))]
+    ZeroOrOne,
+    #[display(fmt = "1")]
+    #[cfg_attr(feature = "serde", serde(rename = "1"))]
+    One,
+    /// Zero or more.
+    #[display(fmt = "N")]
+    #[cfg_attr(feature = "serde", serde(rename = "N"))]
+    Many,
+    #[display(fmt = "1..N")]
+    #[cfg_attr(feature = "serde", serde(rename = "1..N"))]
+    OneOrMany,
+}
+
+impl Cardinality {
+    pub const ALL: [Cardinality; 4] = [
+        Cardinality::ZeroOrOne,
+        Cardinality::One,
+        Cardinality::Many,
+        Cardinality::OneOrMany,
+    ];
+}
+
+impl FromStr for Cardinality {
+    type Err = String;
+
+    /// Parses `0..1`, `1`, `N` or `1..N`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|cardinality| cardinality.to_string() == s)
+            .ok_or_else(|| format!("unknown cardinality `{}`", s))
+    }
+}
+
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum ShapeKind {
     Body(BodyShape),
     Record(RecordShape),
     Field(FieldShape),
+    /// A box around records (see `Document::create_group`).
+    Group(GroupShape),
+    /// A text box annotating the diagram (see `Document::create_note`).
+    Note(NoteShape),
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct TerminalPort {
     id: TerminalPortId,
     location: Point,
@@ -178,9 +588,18 @@ impl TerminalPort {
 type DocumentGraph = UnGraph<NodeData, EdgeData>;
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Document {
     graph: DocumentGraph,
     body_id: NodeId,
+    group_ids: Vec<NodeId>,
+    note_ids: Vec<NodeId>,
+}
+
+impl Default for Document {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Document {
@@ -192,6 +611,8 @@ impl Document {
         Self {
             graph,
             body_id: NodeId(body_index),
+            group_ids: vec![],
+            note_ids: vec![],
         }
     }
 
@@ -205,6 +626,10 @@ impl Document {
 
     // -- Get a node
 
+    pub fn node_ids(&self) -> impl ExactSizeIterator<Item = NodeId> {
+        self.graph.node_indices().map(NodeId)
+    }
+
     pub fn get_node(&self, node_id: NodeId) -> Option<&NodeData> {
         self.graph.node_weight(node_id.0)
     }
@@ -213,6 +638,28 @@ impl Document {
         self.graph.node_weight_mut(node_id.0)
     }
 
+    /// Returns laid-out nodes whose rects contain `point` (borders included), from the outermost
+    /// (e.g. a record) to the innermost (e.g. a field). Of overlapping siblings, the later one,
+    /// which is drawn above, is chosen.
+    pub fn nodes_at(&self, point: &Point) -> Vec<NodeId> {
+        let mut nodes = vec![];
+        let mut parent = self.body();
+
+        loop {
+            let hit = parent.children().rev().find(|child_id| {
+                self.get_node(*child_id)
+                    .and_then(NodeData::rect)
+                    .is_some_and(|rect| rect.contains_point(point))
+            });
+            let Some(node_id) = hit else { break };
+
+            nodes.push(node_id);
+            parent = self.get_node(node_id).unwrap();
+        }
+
+        nodes
+    }
+
     // -- Create a node
 
     pub fn create_record(&mut self, record: RecordShape) -> NodeId {
@@ -229,6 +676,44 @@ impl Document {
         NodeId(index)
     }
 
+    /// Creates a group whose children are records of the body. Groups aren't children of the
+    /// body, and records stay in the body, so that the order of records doesn't change.
+    pub fn create_group(&mut self, group: GroupShape) -> NodeId {
+        let node = NodeData::new(ShapeKind::Group(group));
+        let node_id = NodeId(self.graph.add_node(node));
+
+        self.group_ids.push(node_id);
+        node_id
+    }
+
+    /// Returns groups in the order in which they were created.
+    pub fn group_ids(&self) -> impl ExactSizeIterator<Item = NodeId> + '_ {
+        self.group_ids.iter().copied()
+    }
+
+    /// Returns the group which `record_id` belongs to.
+    pub fn group_of(&self, record_id: NodeId) -> Option<NodeId> {
+        self.group_ids().find(|group_id| {
+            self.get_node(*group_id)
+                .is_some_and(|group| group.children().any(|child_id| child_id == record_id))
+        })
+    }
+
+    /// Creates a note, which isn't a child of the body either. The layout engine places it next
+    /// to `NoteShape::anchor`.
+    pub fn create_note(&mut self, note: NoteShape) -> NodeId {
+        let node = NodeData::new(ShapeKind::Note(note));
+        let node_id = NodeId(self.graph.add_node(node));
+
+        self.note_ids.push(node_id);
+        node_id
+    }
+
+    /// Returns notes in the order in which they were created.
+    pub fn note_ids(&self) -> impl ExactSizeIterator<Item = NodeId> + '_ {
+        self.note_ids.iter().copied()
+    }
+
     // --- Edge
     pub fn edge_endpoints(&self, edge_id: EdgeId) -> Option<(NodeId, NodeId)> {
         self.graph
@@ -236,14 +721,30 @@ impl Document {
             .map(|(x, y)| (NodeId(x), NodeId(y)))
     }
 
+    /// Returns the referring and referred ends of an edge: its source and target, or the other
+    /// way around if it points backward.
+    pub fn edge_reference(&self, edge_id: EdgeId) -> Option<(NodeId, NodeId)> {
+        let (source_id, target_id) = self.edge_endpoints(edge_id)?;
+
+        match self.edge(edge_id)?.direction() {
+            EdgeDirection::Backward => Some((target_id, source_id)),
+            _ => Some((source_id, target_id)),
+        }
+    }
+
     pub fn edge(&self, edge_id: EdgeId) -> Option<&EdgeData> {
         self.graph.edge_weight(edge_id.0)
     }
 
+    pub fn edge_mut(&mut self, edge_id: EdgeId) -> Option<&mut EdgeData> {
+        self.graph.edge_weight_mut(edge_id.0)
+    }
+
     pub fn edge_ids(&self) -> impl ExactSizeIterator<Item = EdgeId> {
-        self.graph.edge_indices().map(|i| EdgeId(i))
+        self.graph.edge_indices().map(EdgeId)
     }
 
+    /// Returns edges in the order in which they were added.
     pub fn edges(&self) -> impl Iterator<Item = &EdgeData> {
         self.graph.edge_weights()
     }
@@ -261,28 +762,136 @@ impl Document {
 }
 
 #[derive(Debug, Clone, Default, Builder)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[builder(default)]
+pub struct BodyShape {
+    /// The number of columns in the grid layout. If it's `None`, the layout engine
+    /// uses its default.
+    pub n_columns: Option<usize>,
+    /// The title of the diagram drawn above records.
+    pub title: Option<TextSpan>,
+}
+
+#[derive(Debug, Clone, Default, Builder)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[builder(default)]
-pub struct BodyShape {}
+pub struct GroupShape {
+    /// Drawn in the header of the group.
+    pub title: TextSpan,
+    pub bg_color: Option<WebColor>,
+    pub border_color: Option<WebColor>,
+}
 
 #[derive(Debug, Clone, Default, Builder)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[builder(default)]
+pub struct NoteShape {
+    /// Wrapped into lines at spaces.
+    pub text: TextSpan,
+    /// Falls back to the record background of the theme.
+    pub bg_color: Option<WebColor>,
+    /// The width in pixels at which the text wraps. Falls back to the default of the layout
+    /// engine.
+    pub max_width: Option<f32>,
+    /// The node (e.g. a record) which the note is placed next to. Notes without an anchor are
+    /// placed below records.
+    pub anchor: Option<NodeId>,
+    /// `text` wrapped by the layout engine, which renderers draw as they are.
+    pub lines: Vec<String>,
+}
+
+impl NoteShape {
+    /// The space between the border and the text in pixels.
+    pub const PADDING: f32 = 10.0;
+
+    /// The distance between baselines of wrapped lines, relative to the font size.
+    pub const LINE_SPACING: f32 = 1.4;
+
+    pub fn line_height(&self) -> f32 {
+        crate::font::font_size_px(self.text.font_size.unwrap_or_default()) * Self::LINE_SPACING
+    }
+}
+
+#[derive(Debug, Clone, Default, Builder)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[builder(default)]
 pub struct RecordShape {
     pub rounded: bool,
+    /// Draws the border with a dashed line.
+    pub dashed: bool,
     pub bg_color: Option<WebColor>,
     pub border_color: Option<WebColor>,
+    /// Links the record to a page (e.g. documentation of the table).
+    pub url: Option<String>,
+    /// Shown as an icon in front of the name. Records without a kind have no icon.
+    pub kind: Option<EntityKind>,
+}
+
+/// What an entity is in the database.
+#[derive(Debug, Clone, Copy, Default, Hash, PartialEq, Eq, Display)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum EntityKind {
+    #[default]
+    #[display(fmt = "table")]
+    Table,
+    #[display(fmt = "view")]
+    View,
+    #[display(fmt = "enum")]
+    Enum,
+}
+
+impl EntityKind {
+    pub const ALL: [EntityKind; 3] = [EntityKind::Table, EntityKind::View, EntityKind::Enum];
+}
+
+impl FromStr for EntityKind {
+    type Err = String;
+
+    /// Parses `table`, `view` or `enum`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|kind| kind.to_string() == s)
+            .ok_or_else(|| format!("unknown entity kind `{}`", s))
+    }
 }
 
 #[derive(Debug, Clone, Default, Builder)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[builder(default)]
 pub struct FieldShape {
+    /// Small label in front of the title (e.g. column ordinal).
+    pub ordinal: Option<TextSpan>,
     pub title: TextSpan,
     pub subtitle: Option<TextSpan>,
-    pub badge: Option<Badge>,
+    pub default_value: Option<TextSpan>,
+    /// Badges are stacked from the right edge of the row.
+    pub badges: Vec<Badge>,
+    /// Diff metadata. A changed row is tinted and gets a change badge after other badges.
+    pub change: Option<FieldChange>,
     pub bg_color: Option<WebColor>,
     pub border_color: Option<WebColor>,
+    /// Links the field to a page. Fields without it link to the page of the record.
+    pub url: Option<String>,
+}
+
+/// How a field is changed between two versions of a schema.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Display)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum FieldChange {
+    #[display(fmt = "+")]
+    Added,
+    #[display(fmt = "\u{2212}")]
+    Removed,
+    /// The type of the field is changed.
+    #[display(fmt = "~")]
+    Modified,
 }
 
 #[derive(Debug, Clone, Default, Builder)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[builder(default)]
 pub struct TextSpan {
     #[builder(setter(into))]
@@ -294,12 +903,15 @@ pub struct TextSpan {
 }
 
 #[derive(Debug, Clone, Default, Builder)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[builder(default)]
 pub struct Badge {
     #[builder(setter(into))]
     pub text: String,
     pub color: Option<WebColor>,
     pub bg_color: Option<WebColor>,
+    /// The meaning of the badge shown in the legend (e.g. `primary key` for `PK`).
+    pub description: Option<String>,
 }
 
 impl Badge {
@@ -315,6 +927,7 @@ impl Badge {
 }
 
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Display)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum FontFamily {
     #[display(fmt = "Arial,sans-serif")]
     SansSerif1,
@@ -328,9 +941,11 @@ pub enum FontFamily {
     Monospace2,
 }
 
-#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Display)]
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Default, Display)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum FontWeight {
     #[display(fmt = "normal")]
+    #[default]
     Normal,
     #[display(fmt = "bold")]
     Bold,
@@ -340,13 +955,8 @@ pub enum FontWeight {
     Bolder,
 }
 
-impl Default for FontWeight {
-    fn default() -> Self {
-        Self::Normal
-    }
-}
-
-#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Display)]
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Default, Display)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum FontSize {
     /* <absolute-size> values */
     #[display(fmt = "xx-small")]
@@ -356,6 +966,7 @@ pub enum FontSize {
     #[display(fmt = "small")]
     Small,
     #[display(fmt = "medium")]
+    #[default]
     Medium,
     #[display(fmt = "large")]
     Large,
@@ -367,12 +978,6 @@ pub enum FontSize {
     XXXLarge,
 }
 
-impl Default for FontSize {
-    fn default() -> Self {
-        Self::Medium
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -407,4 +1012,27 @@ mod tests {
 
         assert_eq!(field.title.text, "uuid");
     }
+
+    #[test]
+    fn nodes_at() {
+        let mut doc = Document::new();
+        let title = TextSpanBuilder::default().text("id").build().unwrap();
+        let field_id = doc.create_field(FieldShapeBuilder::default().title(title).build().unwrap());
+        let record_id = doc.create_record(RecordShape::default());
+
+        doc.get_node_mut(record_id).unwrap().append_child(field_id);
+        doc.body_mut().append_child(record_id);
+
+        let record_node = doc.get_node_mut(record_id).unwrap();
+        record_node.origin = Some(Point::new(0.0, 0.0));
+        record_node.size = Some(Size::new(100.0, 70.0));
+
+        let field_node = doc.get_node_mut(field_id).unwrap();
+        field_node.origin = Some(Point::new(0.0, 35.0));
+        field_node.size = Some(Size::new(100.0, 35.0));
+
+        assert_eq!(doc.nodes_at(&Point::new(100.0, 50.0)), vec![record_id, field_id]);
+        assert_eq!(doc.nodes_at(&Point::new(50.0, 10.0)), vec![record_id]);
+        assert_eq!(doc.nodes_at(&Point::new(101.0, 50.0)), vec![]);
+    }
 }
@@ -6,26 +6,39 @@ TODO: Follow UAX31 Default Identifier <https://www.unicode.org/reports/tr31/tr31
 
 ```ebnf
 program = erd_module ;
-erd_module = PAD, "erd", PAD, [ identifier, PAD ], "{", PAD, module_entries, PAD, "}", PAD ;
+erd_module = PAD, "erd", PAD, [ ( identifier | string ), PAD ], "{", PAD, module_entries, PAD, "}", PAD ;
 module_entries = module_entry, { SEP, PAD, module_entry }
                | EMPTY ;
-module_entry = entity_definition | relation ;
-entity_definition = identifier, PAD, "{", entity_fields, "}" ;
+module_entry = type_alias | entity_definition | relation | directive ;
+directive = "%columns", number ;
+type_alias = "type", identifier, "=", entity_field_type ;
+entity_definition = identifier, [ string ], [ attributes ], PAD, "{", entity_fields, "}" ;
 entity_fields = PAD, entity_field, { SEP, PAD, entity_field }, PAD
               | EMPTY ;
-entity_field = identifier, entity_field_type, [ entity_field_type ] ;
-entity_field_type = "int" | "uuid" | "text" | "timestamp" ;
+entity_field = identifier, entity_field_type, [ "?" ], [ entity_field_key ],
+               [ entity_field_default ], [ entity_field_position ], [ attributes ] ;
+entity_field_type = "int" | "uuid" | "text" | "timestamp"
+                  | identifier, [ "(", number, { ",", number }, ")" ] ;
 entity_field_key = "PK" | "FK" ;
-relation = entity, PAD, edge, PAD, entity ;
+entity_field_default = "default", ( identifier | number | string ) ;
+entity_field_position = "@", number ;
+relation = entity, PAD, edge, PAD, entity, [ attributes ] ;
+attributes = "[", PAD, [ attribute, { ",", PAD, attribute }, [ "," ] ], PAD, "]" ;
+attribute = identifier, [ "=", attribute_value ] ;
+attribute_value = identifier | string | number | color ;
 entity = identifier, [ ".", identifier ] ;
 edge = "o", "--", "o" ;
+number = digit, { digit } ;
+color = "#", hex_digit, hex_digit, hex_digit, [ hex_digit, hex_digit, hex_digit ] ;
 identifier = identifier_start, { identifier_continue }
            | quoted_identifier ;
 identifier_start = "_" | letter ;
 identifier_continue = "_" | letter | digit ;
 quoted_identifier = "`", { ? any character or escaped character ? }, "`" ;
+string = '"', { ? any character or escaped character ? }, '"' ;
 letter = ? a-zA-Z ? ;
 digit = ? 0-9 ? ;
+hex_digit = ? 0-9a-fA-F ? ;
 whitespace = ? whitespace ? ;
 newline = "\n" | "\r\n" ;
 PAD = { whitespace | newline } ;
@@ -33,11 +46,14 @@ SEP = newline | ";" ;
 EMPTY = ? (empty) ? ;
 ```
 */
-use crate::erd::{EntityDefinition, EntityField, EntityRelation};
-use crate::erd::{EntityFieldKey, EntityFieldType, EntityPath, Module, ModuleEntry};
+use crate::color::RGBColor;
+use crate::erd::{Attribute, AttributeValue, Attributes};
+use crate::erd::{EntityDefinition, EntityField, EntityPathSpan, EntityRelation, TypeAlias};
+use crate::erd::{EntityFieldKey, EntityFieldType, EntityPath, Module, ModuleDirective, ModuleEntry};
 use chumsky::prelude::*;
 use chumsky::Stream;
 use derive_more::Display;
+use std::collections::HashMap;
 
 pub type Span = std::ops::Range<usize>;
 
@@ -49,6 +65,17 @@ pub enum Token {
     // Identifier
     #[display(fmt = "{}", _0)]
     Ident(String),
+    // Literal
+    #[display(fmt = "{}", _0)]
+    Number(String),
+    #[display(fmt = "{:?}", _0)]
+    Str(String),
+    // Hex color (e.g. `#FF0000`)
+    #[display(fmt = "{}", _0)]
+    Color(String),
+    // Directive (e.g. `%columns`)
+    #[display(fmt = "%{}", _0)]
+    Directive(String),
     // Keywords
     #[display(fmt = "erd")]
     Erd,
@@ -64,6 +91,8 @@ pub enum Token {
     PK,
     #[display(fmt = "FK")]
     FK,
+    #[display(fmt = "default")]
+    Default,
     // Control characters (delimiters, semicolons, etc.)
     #[display(fmt = "'{}'", _0)]
     Ctrl(char),
@@ -71,10 +100,91 @@ pub enum Token {
     Newline,
 }
 
+impl Token {
+    /// Returns the keyword token spelled as `s`.
+    pub fn keyword(s: &str) -> Option<Token> {
+        match s {
+            "erd" => Some(Token::Erd),
+            "int" => Some(Token::Int),
+            "uuid" => Some(Token::Uuid),
+            "text" => Some(Token::Text),
+            "timestamp" => Some(Token::Timestamp),
+            "PK" => Some(Token::PK),
+            "FK" => Some(Token::FK),
+            "default" => Some(Token::Default),
+            _ => None,
+        }
+    }
+}
+
+/// A table of localized keyword aliases (e.g. `主キー` for `PK`).
+///
+/// The tokenizer translates aliases into canonical keyword tokens, so the AST doesn't know
+/// which spelling was used.
+#[derive(Debug, Clone, Default)]
+pub struct KeywordAliases {
+    aliases: HashMap<String, Token>,
+}
+
+impl KeywordAliases {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the built-in alias table for a specified locale (e.g. `"ja"`).
+    pub fn for_locale(locale: &str) -> Option<Self> {
+        let table: &[(&str, &str)] = match locale {
+            "ja" => &[
+                ("整数", "int"),
+                ("テキスト", "text"),
+                ("文字列", "text"),
+                ("タイムスタンプ", "timestamp"),
+                ("日時", "timestamp"),
+                ("主キー", "PK"),
+                ("外部キー", "FK"),
+                ("既定値", "default"),
+            ],
+            _ => return None,
+        };
+
+        let mut aliases = Self::new();
+
+        for (alias, keyword) in table {
+            aliases.insert(*alias, keyword);
+        }
+        Some(aliases)
+    }
+
+    /// Adds `alias` for a canonical `keyword`. Returns `false` if `keyword` is not a keyword.
+    pub fn insert(&mut self, alias: impl Into<String>, keyword: &str) -> bool {
+        let Some(token) = Token::keyword(keyword) else { return false };
+
+        self.aliases.insert(alias.into(), token);
+        true
+    }
+
+    pub fn get(&self, alias: &str) -> Option<&Token> {
+        self.aliases.get(alias)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.aliases.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptions {
+    pub keyword_aliases: KeywordAliases,
+}
+
 pub type ParseResult = (Option<Module>, Vec<Simple<char>>, Vec<Simple<Token>>);
 
 pub fn parse(src: &str) -> ParseResult {
-    let (tokens, errs) = tokenizer().parse_recovery(src);
+    parse_with_options(src, &ParseOptions::default())
+}
+
+pub fn parse_with_options(src: &str, options: &ParseOptions) -> ParseResult {
+    let (tokens, errs) = tokenizer(options.keyword_aliases.clone()).parse_recovery(src);
 
     if let Some(tokens) = tokens {
         let len = src.chars().count();
@@ -89,9 +199,11 @@ pub fn parse(src: &str) -> ParseResult {
     (None, errs, vec![])
 }
 
-fn tokenizer() -> impl Parser<char, Vec<(Token, Span)>, Error = Simple<char>> {
+fn tokenizer(
+    keyword_aliases: KeywordAliases,
+) -> impl Parser<char, Vec<(Token, Span)>, Error = Simple<char>> {
     let edge = just("o--o").to(Token::Edge);
-    let ctrl = one_of("{};.").map(|c| Token::Ctrl(c));
+    let ctrl = one_of("{};.@=(),?[]").map(Token::Ctrl);
     let newline = choice((
         just("\n").to(Token::Newline),
         just("\r\n").to(Token::Newline),
@@ -104,6 +216,7 @@ fn tokenizer() -> impl Parser<char, Vec<(Token, Span)>, Error = Simple<char>> {
         text::keyword("timestamp").to(Token::Timestamp),
         text::keyword("PK").to(Token::PK),
         text::keyword("FK").to(Token::FK),
+        text::keyword("default").to(Token::Default),
     ));
 
     let escape = just('\\').ignore_then(
@@ -117,7 +230,7 @@ fn tokenizer() -> impl Parser<char, Vec<(Token, Span)>, Error = Simple<char>> {
             .or(just('r').to('\r'))
             .or(just('t').to('\t'))
             .or(just('u').ignore_then(
-                filter(|c: &char| c.is_digit(16))
+                filter(|c: &char| c.is_ascii_hexdigit())
                     .repeated()
                     .exactly(4)
                     .collect::<String>()
@@ -132,7 +245,25 @@ fn tokenizer() -> impl Parser<char, Vec<(Token, Span)>, Error = Simple<char>> {
             )),
     );
 
-    let ident = text::ident().map(|ident| Token::Ident(ident));
+    let ident = text::ident().map(Token::Ident);
+
+    // Localized keywords may contain non-ASCII letters.
+    let keyword_alias = filter(|c: &char| c.is_alphanumeric() || *c == '_')
+        .repeated()
+        .at_least(1)
+        .collect::<String>()
+        .try_map(move |word, span| {
+            keyword_aliases
+                .get(&word)
+                .cloned()
+                .ok_or_else(|| Simple::custom(span, "not a keyword alias"))
+        });
+    let number = text::int(10).map(Token::Number);
+    let directive = just('%').ignore_then(text::ident()).map(Token::Directive);
+    let color = just('#')
+        .chain(filter(|c: &char| c.is_ascii_hexdigit()).repeated().at_least(1))
+        .collect::<String>()
+        .map(Token::Color);
 
     // `...`
     let quoted_ident = just("`")
@@ -141,11 +272,23 @@ fn tokenizer() -> impl Parser<char, Vec<(Token, Span)>, Error = Simple<char>> {
         .collect::<String>()
         .map(Token::Ident);
 
+    // "..."
+    let string = just('"')
+        .ignore_then(filter(|c| *c != '\\' && *c != '"').or(escape).repeated())
+        .then_ignore(just('"'))
+        .collect::<String>()
+        .map(Token::Str);
+
     // A single token can be one of the above
     let token = edge
         .or(keyword)
+        .or(keyword_alias)
         .or(ident)
+        .or(number)
+        .or(directive)
+        .or(color)
         .or(quoted_ident)
+        .or(string)
         .or(ctrl)
         .or(newline)
         // TODO: Choose other recovery mode for better error generation.
@@ -167,16 +310,71 @@ fn tokenizer() -> impl Parser<char, Vec<(Token, Span)>, Error = Simple<char>> {
         .repeated()
 }
 
+// `Simple<Token>` is large, but it's the error type chumsky requires for `filter_map`.
+#[allow(clippy::result_large_err)]
 fn erd_module_parser() -> impl Parser<Token, Module, Error = Simple<Token>> + Clone {
     let ident = filter_map(|span, tok| match tok {
         Token::Ident(ident) => Ok(ident.clone()),
         _ => Err(Simple::expected_input_found(span, Vec::new(), Some(tok))),
     });
 
+    let number = filter_map(|span, tok| match tok {
+        Token::Number(n) => Ok(n),
+        _ => Err(Simple::expected_input_found(span, Vec::new(), Some(tok))),
+    });
+
+    let string = filter_map(|span, tok| match tok {
+        Token::Str(s) => Ok(s),
+        _ => Err(Simple::expected_input_found(span, Vec::new(), Some(tok))),
+    });
+
+    let color = filter_map(|span, tok| match tok {
+        Token::Color(c) => Ok(c),
+        _ => Err(Simple::expected_input_found(span, Vec::new(), Some(tok))),
+    });
+
     let separator = choice((just(Token::Newline).to(()), just(Token::Ctrl(';')).to(())));
 
     let pad = separator.clone().repeated();
 
+    // Attributes shared by entities, fields, and relations (e.g. `[color=#FF0000, hidden]`)
+    let attribute_value = choice((
+        ident.map(AttributeValue::Ident),
+        string.map(AttributeValue::Str),
+        number.validate(|n, span, emit| {
+            AttributeValue::Number(n.parse().unwrap_or_else(|_| {
+                emit(Simple::custom(span, "the number is too large"));
+                0
+            }))
+        }),
+        color.validate(|c, span, emit| {
+            AttributeValue::Color(RGBColor::from_hex(&c).unwrap_or_else(|| {
+                emit(Simple::custom(
+                    span,
+                    "a color must be written as #RGB or #RRGGBB",
+                ));
+                RGBColor::default()
+            }))
+        }),
+    ));
+
+    let attribute = ident
+        .then(just(Token::Ctrl('=')).ignore_then(attribute_value).or_not())
+        .map_with_span(|(name, value), span| {
+            let mut attribute = Attribute::new(name, value);
+
+            attribute.set_span(span);
+            attribute
+        });
+
+    let attributes = attribute
+        .padded_by(pad.clone())
+        .separated_by(just(Token::Ctrl(',')))
+        .allow_trailing()
+        .padded_by(pad.clone())
+        .delimited_by(just(Token::Ctrl('[')), just(Token::Ctrl(']')))
+        .map(Attributes::from_iter);
+
     // We want the compiler to check for exclusivity. However, due to the limitations of Rust and the nature of combinator typing, this could not be achieved without introducing code complexity and third-party libraries.
     //
     // - To iterate through the variants of enum, I can use the `strum` crate.
@@ -186,6 +384,19 @@ fn erd_module_parser() -> impl Parser<Token, Module, Error = Simple<Token>> + Cl
         just(Token::Uuid).to(EntityFieldType::Uuid),
         just(Token::Text).to(EntityFieldType::Text),
         just(Token::Timestamp).to(EntityFieldType::Timestamp),
+        // Other types (e.g. `decimal(12,2)`) or type aliases
+        ident
+            .then(
+                number
+                    .separated_by(just(Token::Ctrl(',')))
+                    .at_least(1)
+                    .delimited_by(just(Token::Ctrl('(')), just(Token::Ctrl(')')))
+                    .or_not(),
+            )
+            .map(|(name, params)| match params {
+                Some(params) => EntityFieldType::Named(format!("{}({})", name, params.join(","))),
+                None => EntityFieldType::Named(name),
+            }),
     ));
 
     let entity_field_key = choice((
@@ -194,19 +405,59 @@ fn erd_module_parser() -> impl Parser<Token, Module, Error = Simple<Token>> + Cl
     ));
 
     let entity = ident
-        .then(just(Token::Ctrl('.')).ignore_then(ident.or_not()))
-        .map(|(table, field)| {
-            if let Some(field) = field {
-                EntityPath::Field(table, field)
+        .map_with_span(|name, span| (name, span))
+        .then(
+            just(Token::Ctrl('.'))
+                .ignore_then(ident.map_with_span(|name, span| (name, span)).or_not()),
+        )
+        .map(|((table, table_span), field)| {
+            if let Some((field, field_span)) = field {
+                let span = EntityPathSpan::new(table_span, Some(field_span));
+                (EntityPath::Field(table, field), span)
             } else {
-                EntityPath::Entity(table)
+                (EntityPath::Entity(table), EntityPathSpan::new(table_span, None))
             }
         });
 
+    let entity_field_default = just(Token::Default).ignore_then(choice((ident, number, string)));
+
+    let entity_field_position =
+        just(Token::Ctrl('@'))
+            .ignore_then(number)
+            .validate(|n, span, emit| match n.parse::<usize>() {
+                Ok(n) if n > 0 => n,
+                _ => {
+                    emit(Simple::custom(
+                        span,
+                        "the field position must be a positive integer",
+                    ));
+                    1
+                }
+            });
+
     let entity_field = ident
-        .then(entity_field_type)
+        .map_with_span(|name, span| (name, span))
+        .then(entity_field_type.clone())
+        .then(just(Token::Ctrl('?')).or_not())
         .then(entity_field_key.or_not())
-        .map(|((name, field_type), field_key)| EntityField::new(name, field_type, field_key));
+        .then(entity_field_default.or_not())
+        .then(entity_field_position.or_not())
+        .then(attributes.clone().or_not())
+        .map_with_span(
+            |((((((name, field_type), nullable), field_key), default_value), position), attrs),
+             span| {
+                let (name, name_span) = name;
+                let mut field = EntityField::new(name, field_type, field_key);
+
+                field.set_name_span(name_span);
+                field.set_nullable(nullable.is_some());
+                field.set_default_value(default_value);
+                field.set_position(position);
+                field.set_attributes(attrs.unwrap_or_default());
+                field.set_span(span);
+                field
+            },
+        );
 
     let entity_fields = entity_field
         .clone()
@@ -219,16 +470,23 @@ fn erd_module_parser() -> impl Parser<Token, Module, Error = Simple<Token>> + Cl
         )
         .or_not()
         .padded_by(pad.clone())
-        .map(|fields| fields.unwrap_or_else(|| vec![]));
+        .map(|fields| fields.unwrap_or_default());
 
     let entity_definition = ident
+        .map_with_span(|name, span| (name, span))
+        .then(string.or_not())
+        .then(attributes.clone().or_not())
         .then_ignore(pad.clone())
         .then_ignore(just(Token::Ctrl('{')))
         .then(entity_fields)
         .then_ignore(just(Token::Ctrl('}')))
-        .map(|(name, fields)| {
+        .map(|((((name, span), display_name), attrs), fields)| {
             let mut definition = EntityDefinition::new(name);
 
+            definition.set_display_name(display_name);
+            definition.set_attributes(attrs.unwrap_or_default());
+            definition.set_span(span);
+
             for f in fields {
                 definition.add_field(f);
             }
@@ -243,11 +501,41 @@ fn erd_module_parser() -> impl Parser<Token, Module, Error = Simple<Token>> + Cl
                 .padded_by(pad.clone())
                 .ignore_then(entity.clone()),
         )
-        .map(|(a, b)| EntityRelation::new(a, b));
+        .then(attributes.or_not())
+        .map_with_span(|(((a, a_span), (b, b_span)), attrs), span| {
+            let mut relation = EntityRelation::new(a, b);
+
+            relation.set_path_spans(a_span, b_span);
+            relation.set_attributes(attrs.unwrap_or_default());
+            relation.set_span(span);
+            relation
+        });
+
+    let directive = just(Token::Directive("columns".into()))
+        .ignore_then(number)
+        .validate(|n, span, emit| match n.parse::<usize>() {
+            Ok(n) if n > 0 => ModuleDirective::Columns(n),
+            _ => {
+                emit(Simple::custom(
+                    span,
+                    "the number of columns must be a positive integer",
+                ));
+                ModuleDirective::Columns(1)
+            }
+        });
+
+    // `type` isn't a keyword because it's a common field name.
+    let type_alias = just(Token::Ident("type".into()))
+        .ignore_then(ident)
+        .then_ignore(just(Token::Ctrl('=')))
+        .then(entity_field_type)
+        .map(|(name, field_type)| TypeAlias::new(name, field_type));
 
     let module_entry = choice((
-        entity_definition.map(|d| ModuleEntry::EntityDefinition(d)),
-        relation.map(|r| ModuleEntry::EntityRelation(r)),
+        type_alias.map(ModuleEntry::TypeAlias),
+        entity_definition.map(ModuleEntry::EntityDefinition),
+        relation.map(ModuleEntry::EntityRelation),
+        directive.map(ModuleEntry::Directive),
     ));
 
     let module_entries = module_entry
@@ -260,11 +548,11 @@ fn erd_module_parser() -> impl Parser<Token, Module, Error = Simple<Token>> + Cl
                 .repeated(),
         )
         .or_not()
-        .map(|entries| entries.unwrap_or_else(|| vec![]));
+        .map(|entries| entries.unwrap_or_default());
 
     just(Token::Erd)
         .padded_by(pad.clone())
-        .ignore_then(ident.padded_by(pad.clone()).or_not())
+        .ignore_then(ident.or(string).padded_by(pad.clone()).or_not())
         .then_ignore(just(Token::Ctrl('{')))
         .then(module_entries.padded_by(pad.clone()))
         .then_ignore(just(Token::Ctrl('}')))
@@ -298,6 +586,31 @@ mod tests {
         };
     }
 
+    /// Parses `src`, and parses the printed module again. The printed modules must be the same.
+    fn round_trip(src: &str) -> Module {
+        let (ast, errs, parse_errs) = parse(src);
+        assert!(errs.is_empty());
+        assert!(parse_errs.is_empty());
+        let printed = ast.unwrap().to_string();
+
+        let (ast, errs, parse_errs) = parse(&printed);
+        assert!(errs.is_empty(), "{}", printed);
+        assert!(parse_errs.is_empty(), "{}", printed);
+        let module = ast.unwrap();
+
+        assert_diff!(&module.to_string(), &printed, "\n", 0);
+        module
+    }
+
+    fn default_values(module: &Module, entity: &str) -> Vec<Option<String>> {
+        module
+            .entity(entity)
+            .unwrap()
+            .fields()
+            .map(|field| field.default_value().map(str::to_string))
+            .collect()
+    }
+
     #[test]
     fn test_script() {
         assert_ast!(
@@ -316,13 +629,227 @@ posts {
 users.id o--o posts.created_by
 }",
             "erd G {
-    users { id int PK; uuid uuid; text text; about_html text }
+    users { id int PK; `uuid` uuid; `text` text; about_html text }
     posts { id int PK; title text; body text; created_by int FK }
     users.id o--o posts.created_by
 }"
         );
     }
 
+    #[test]
+    fn field_default_values() {
+        let module = round_trip(
+            r#"erd {
+users {
+    id int PK default 0
+    status text default `active`
+    greeting text default "say \"hi\"\n\u0001 \\ `ok`"
+    created_at timestamp
+}
+}"#,
+        );
+
+        assert_eq!(
+            default_values(&module, "users"),
+            [
+                Some("0".into()),
+                Some("active".into()),
+                Some("say \"hi\"\n\u{1} \\ `ok`".into()),
+                None
+            ]
+        );
+    }
+
+    #[test]
+    fn field_positions() {
+        let module = round_trip(
+            "erd {
+users {
+    id int PK
+    email text @1
+    name text default `-` @3
+}
+}",
+        );
+        let users = module.entity("users").unwrap();
+
+        assert_eq!(
+            users.fields().map(|field| field.position()).collect::<Vec<_>>(),
+            [None, Some(1), Some(3)]
+        );
+        assert_eq!(default_values(&module, "users")[2].as_deref(), Some("-"));
+    }
+
+    #[test]
+    fn type_aliases() {
+        assert_ast!(
+            "erd {
+type money = decimal(12,2)
+type id = uuid
+orders {
+    id id PK
+    type text
+    price money
+    tax decimal(5, 2)
+}
+}",
+            "erd {
+    type money = decimal(12,2)
+    type id = uuid
+    orders { id id PK; type text; price money; tax decimal(5,2) }
+}"
+        );
+    }
+
+    #[test]
+    fn nullable_fields() {
+        assert_ast!(
+            "erd {
+posts {
+    id int PK
+    user_id int? FK
+}
+}",
+            "erd {
+    posts { id int PK; user_id int? FK }
+}"
+        );
+    }
+
+    #[test]
+    fn spans() {
+        let src = "erd {\nusers {\n  id int PK\n}\nposts.user_id o--o users.id\n}";
+        let (ast, _, _) = parse(src);
+        let ast = ast.unwrap();
+        let mut entries = ast.entries();
+
+        let Some(ModuleEntry::EntityDefinition(users)) = entries.next() else { panic!() };
+        assert_eq!(&src[users.span()], "users");
+        assert_eq!(&src[users.fields().next().unwrap().span()], "id int PK");
+
+        let Some(ModuleEntry::EntityRelation(relation)) = entries.next() else { panic!() };
+        assert_eq!(&src[relation.span()], "posts.user_id o--o users.id");
+    }
+
+    #[test]
+    fn attributes() {
+        assert_ast!(
+            "erd {
+users \"Users\" [color=#f00, hidden] {
+    id int PK [label=\"ID\", width=2,]
+    name text []
+}
+posts.user_id o--o users.id [style=dashed]
+}",
+            "erd {
+    users \"Users\" [color=#FF0000, hidden] { id int PK [label=\"ID\", width=2]; name text }
+    posts.user_id o--o users.id [style=dashed]
+}"
+        );
+    }
+
+    #[test]
+    fn attribute_values() {
+        let src = "erd {\nusers [a=b, c=\"d\", e=3, f=#123456, g] {}\n}";
+        let (ast, _, _) = parse(src);
+        let ast = ast.unwrap();
+        let Some(ModuleEntry::EntityDefinition(users)) = ast.entries().next() else { panic!() };
+        let attributes = users.attributes();
+
+        assert_eq!(attributes.value("a").and_then(|v| v.as_str()), Some("b"));
+        assert_eq!(attributes.value("c").and_then(|v| v.as_str()), Some("d"));
+        assert_eq!(attributes.value("e").and_then(|v| v.as_number()), Some(3));
+        assert_eq!(
+            attributes.value("f").and_then(|v| v.as_color()),
+            Some(&RGBColor::new(0x12, 0x34, 0x56))
+        );
+        assert!(attributes.has("g"));
+        assert!(attributes.value("g").is_none());
+        assert_eq!(&src[attributes.get("c").unwrap().span()], "c=\"d\"");
+    }
+
+    #[test]
+    fn invalid_color_attribute() {
+        let (_, errs, parse_errs) = parse("erd { users [color=#12345] {} }");
+
+        assert!(errs.is_empty());
+        assert_eq!(parse_errs.len(), 1);
+        assert_eq!(
+            parse_errs[0].reason(),
+            &chumsky::error::SimpleReason::Custom(
+                "a color must be written as #RGB or #RRGGBB".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn string_literals() {
+        let module = round_trip(
+            r#"erd "My \"Blog\" App" {
+users "Registered\tusers" {
+    id int PK
+    status text default "not verified"
+}
+}"#,
+        );
+        let users = module.entity("users").unwrap();
+
+        assert_eq!(module.name(), Some("My \"Blog\" App"));
+        assert_eq!(users.display_name(), Some("Registered\tusers"));
+        assert_eq!(default_values(&module, "users")[1].as_deref(), Some("not verified"));
+    }
+
+    #[test]
+    fn keyword_aliases() {
+        let options = ParseOptions {
+            keyword_aliases: KeywordAliases::for_locale("ja").unwrap(),
+        };
+        let (ast, errs, parse_errs) = parse_with_options(
+            "erd {
+`ユーザー` {
+    id 整数 主キー
+    email テキスト
+    // ASCII identifiers are not affected.
+    int_value int
+}
+}",
+            &options,
+        );
+
+        assert!(errs.is_empty());
+        assert!(parse_errs.is_empty());
+        assert_eq!(
+            ast.unwrap().to_string(),
+            "erd {
+    `ユーザー` { id int PK; email text; int_value int }
+}"
+        );
+    }
+
+    #[test]
+    fn directives() {
+        assert_ast!(
+            "erd {
+%columns 2
+users {
+    id int PK
+}
+}",
+            "erd {
+    %columns 2
+    users { id int PK }
+}"
+        );
+    }
+
+    #[test]
+    fn invalid_columns_directive() {
+        let (_, errs, parse_errs) = parse("erd { %columns 0 }");
+
+        assert!(errs.is_empty());
+        assert_eq!(parse_errs.len(), 1);
+    }
+
     #[test]
     fn spaces_and_comments() {
         assert_ast!(
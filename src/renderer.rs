@@ -1,48 +1,531 @@
 //! Backends translate MIR into graphics format.
 use crate::{
     color::{RGBColor, WebColor},
-    error::BackendError,
-    geometry::{Orientation, Point, Rect},
-    layout::RouteGraph,
+    error::{BackendError, RenderOptionsError},
+    font,
+    geometry::{Orientation, Point, Rect, Size},
+    layout::{LayoutOptions, RouteGraph},
+    metrics::{self, TextAnchor},
     mir,
+    theme::Theme,
 };
+use css::StyleSheet;
+use derive_more::Display;
+use std::collections::HashMap;
 use std::io::Write;
+use std::str::FromStr;
 use svg::{node::element, Node};
+use textwriter::Escape;
 
+mod accessibility;
+mod css;
+mod format;
+mod html;
+mod interactive;
+#[cfg(feature = "serde")]
+mod json;
+mod legend;
+mod pages;
+mod text;
+
+pub mod textwriter;
+
+pub use format::SVGFormat;
+pub use html::HtmlRenderer;
+#[cfg(feature = "serde")]
+pub use json::JsonRenderer;
+pub use pages::{Page, PageGrid};
+pub use text::{TextCharset, TextRenderer};
+
+/// A backend which writes a laid-out document. The trait is object-safe, so a backend can be
+/// chosen at runtime as `Box<dyn Renderer>`.
 pub trait Renderer {
-    fn render(&self, doc: &mir::Document, writer: &mut impl Write) -> Result<(), BackendError>;
+    fn render(&self, doc: &mir::Document, writer: &mut dyn Write) -> Result<(), BackendError>;
+}
+
+/// Relative widths of the text columns in a field row.
+///
+/// ```svgbob
+/// +-------------+-------------+---------------+---------+
+/// |<-- title -->|<-subtitle-->|<-default_val->|<-badge->|
+/// +-------------+-------------+---------------+---------+
+/// ```
+///
+/// The `default_value` column is used only in wide mode.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FieldColumnRatio {
+    pub title: f32,
+    pub subtitle: f32,
+    pub default_value: f32,
+    pub badge: f32,
+    /// Caps the width of the badge column, so that text columns take the extra width of wide
+    /// records (e.g. sized by `SimpleLayoutEngine::fit_record_width`).
+    pub max_badge_width: Option<f32>,
+}
+
+impl Default for FieldColumnRatio {
+    fn default() -> Self {
+        Self {
+            title: 2.0,
+            subtitle: 2.0,
+            default_value: 2.0,
+            badge: 1.0,
+            max_badge_width: None,
+        }
+    }
+}
+
+impl FieldColumnRatio {
+    /// Checks that the ratios are finite and non-negative, and that the text columns (`title`,
+    /// `subtitle`, and `default_value` in wide mode) have a positive total, so that columns of a
+    /// field row have finite widths.
+    pub fn validate(&self, wide_mode: bool) -> Result<(), RenderOptionsError> {
+        let default_value = if wide_mode { self.default_value } else { 0.0 };
+        let ratios = [self.title, self.subtitle, default_value, self.badge];
+
+        if ratios.iter().all(|ratio| ratio.is_finite() && *ratio >= 0.0)
+            && self.title + self.subtitle + default_value > 0.0
+        {
+            Ok(())
+        } else {
+            Err(RenderOptionsError::InvalidFieldColumnRatio)
+        }
+    }
+}
+
+/// Sizes and fonts of shapes drawn by `SVGRenderer`.
+///
+/// The layout engine measures records with the default paddings, so a larger `padding_x` may
+/// need wider records (e.g. `SimpleLayoutEngine::fit_record_width`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderOptions {
+    /// Horizontal padding of field rows.
+    pub padding_x: f32,
+    /// The corner radius of records.
+    pub border_radius: f32,
+    /// The radius of rounded corners of edges. Corners between short segments are drawn with
+    /// half of the shorter segment instead. See `RenderOptions::validate`.
+    pub path_radius: f32,
+    /// The radius of circle markers at ends of edges.
+    pub circle_radius: f32,
+    /// The default width of edges, which `EdgeData::stroke_width` overrides.
+    pub edge_stroke_width: f32,
+    /// CSS font family lists which replace the defaults of `FontFamily` (e.g.
+    /// `Monospace1` → `"JetBrains Mono, monospace"`).
+    pub font_families: HashMap<mir::FontFamily, String>,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            padding_x: metrics::PADDING_X,
+            border_radius: 6.0,
+            path_radius: 6.0,
+            circle_radius: 4.0,
+            edge_stroke_width: SVGRenderer::EDGE_STROKE_WIDTH,
+            font_families: HashMap::new(),
+        }
+    }
+}
+
+impl RenderOptions {
+    /// Checks that the options fit routes laid out with `layout`. Edges are routed at
+    /// `shape_junction_margin` around records and leave terminal ports straight for half of it,
+    /// so `path_radius` must be smaller than that.
+    pub fn validate(&self, layout: &LayoutOptions) -> Result<(), RenderOptionsError> {
+        let max_radius = layout.shape_junction_margin / 2.0;
+
+        if self.path_radius >= max_radius {
+            return Err(RenderOptionsError::PathRadiusTooLarge(
+                self.path_radius,
+                layout.shape_junction_margin,
+            ));
+        }
+        Ok(())
+    }
+
+    /// The CSS font family list of `font_family`.
+    pub fn font_family(&self, font_family: mir::FontFamily) -> String {
+        self.font_families
+            .get(&font_family)
+            .cloned()
+            .unwrap_or_else(|| font_family.to_string())
+    }
+}
+
+/// How to paint the background of a diagram.
+#[derive(Debug, Clone, Default)]
+pub enum Background {
+    /// The background color of the theme.
+    #[default]
+    Theme,
+    /// No background.
+    Transparent,
+    Color(WebColor),
+}
+
+impl FromStr for Background {
+    type Err = String;
+
+    /// Parses `transparent` or a color (`#RGB` or `#RRGGBB`).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "transparent" {
+            return Ok(Background::Transparent);
+        }
+
+        RGBColor::from_hex(s)
+            .map(|color| Background::Color(WebColor::RGB(color)))
+            .ok_or_else(|| format!("invalid background `{}`", s))
+    }
+}
+
+/// A pattern drawn over the background, which shows distances in screenshots.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display)]
+pub enum GridPattern {
+    /// A dot at every intersection.
+    #[display(fmt = "dots")]
+    Dots,
+    /// Thin horizontal and vertical lines.
+    #[display(fmt = "lines")]
+    Lines,
+}
+
+impl GridPattern {
+    pub const ALL: [GridPattern; 2] = [GridPattern::Dots, GridPattern::Lines];
+}
+
+impl FromStr for GridPattern {
+    type Err = String;
+
+    /// Parses `dots` or `lines`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|pattern| pattern.to_string() == s)
+            .ok_or_else(|| format!("unknown grid pattern `{}`", s))
+    }
 }
 
 #[derive(Debug)]
 pub struct SVGRenderer<'g> {
     // SVG viewBox
     pub view_box: Option<Rect>,
+    // `width` and `height` attributes of the root element. If only one of them is given, the
+    // other is derived from the aspect ratio of `view_box`.
+    pub width: Option<f32>,
+    pub height: Option<f32>,
+    // Multiplies the size of the image. Without `width` and `height`, the size of `view_box` is
+    // scaled.
+    pub scale: Option<f32>,
+    // Centers the diagram in the canvas of `width` and `height` at its own size, instead of
+    // fitting it. The view box is widened (or cropped) around `view_box`.
+    pub center: bool,
 
-    // for debug
+    // Renders default values of fields in a third text column. Otherwise, default values are
+    // shown as tooltips of field titles.
+    pub wide_mode: bool,
+    // Ratios of the widths of field columns. Invalid ratios (see `FieldColumnRatio::validate`)
+    // are drawn as the default.
+    pub field_column_ratio: FieldColumnRatio,
+    // Truncates field titles and subtitles which would overlap with an ellipsis (`…`). The full
+    // text is shown as a tooltip.
+    pub truncate_overflow: bool,
+    // Writes colors and fonts as CSS rules of classes (e.g. `.record`, `.field-title` and
+    // `.badge-pk`) in a `<style>` element instead of attributes of each element.
+    pub css_classes: bool,
+    // Draws a legend of badges and type colors in a free corner of `view_box`.
+    pub legend: bool,
+    // Embeds a script which highlights a field and its edges under the pointer. Fields and
+    // edges are grouped with `data-node`, `data-source` and `data-target` attributes of node IDs.
+    pub interactive: bool,
+    // Whitespace between tags: as written by the `svg` crate, minified or pretty-printed.
+    pub format: SVGFormat,
+    // Prepended to IDs of clip paths and markers, so that they don't collide when more than
+    // one SVG is inlined into an HTML page.
+    pub id_prefix: String,
+    // Describes the document, records, fields and edges for screen readers with `<title>`,
+    // `<desc>`, `role` and `aria-label`. Titles are also shown as tooltips.
+    pub accessible: bool,
+    // Writes the crate version and this layout config hash (`SimpleLayoutEngine::config_hash`)
+    // in a `<metadata>` element.
+    pub version_stamp: Option<u64>,
+    // Embeds the source of the diagram in the `<metadata>` element, so that it can be restored
+    // from the SVG with `extract_source`.
+    pub source: Option<String>,
+
+    // The background and edge colors. Other colors are given by MIR.
+    pub theme: Theme,
+    // Switches colors of `theme` to colors of this theme if the viewer prefers a light color
+    // scheme. Colors are written as CSS custom properties, so `css_classes` is implied.
+    pub light_theme: Option<Theme>,
+    // Overrides the background color of the theme.
+    pub background: Background,
+    // Draws a grid of `GRID_SPACING` behind the diagram.
+    pub grid: Option<GridPattern>,
+    // Draws soft shadows in the shadow color of the theme under records.
+    pub shadow: bool,
+    // Paddings, radii and fonts.
+    pub options: RenderOptions,
+    // The layer of edges which don't have their own (`EdgeData::layer`).
+    pub edge_layer: mir::EdgeLayer,
+
+    // Drawn at the top left corner of `view_box` (e.g. the label of a `Page`).
+    pub page_label: Option<String>,
+
+    // for debug: the route graph is drawn in a hidden group `#debug` over the diagram.
     pub edge_route_graph: Option<&'g RouteGraph>,
+    // Shows the debug group.
+    pub show_debug: bool,
+}
+
+impl Default for SVGRenderer<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl SVGRenderer<'_> {
+    const CHANGE_TINT_OPACITY: f32 = 0.2;
+    const EDGE_STROKE_WIDTH: f32 = 1.5;
+    /// The distance between lines of the background grid, which is a multiple of the layout
+    /// margins.
+    pub const GRID_SPACING: f32 = 20.0;
+    /// The space between a page label and the corner of the page.
+    const PAGE_LABEL_MARGIN: f32 = 8.0;
+    /// The element in `<metadata>` which contains the embedded source.
+    const SOURCE_ELEMENT: &'static str = "seiren-source";
+    /// How far a shadow is below its record.
+    const SHADOW_OFFSET_Y: f32 = 3.0;
+    const SHADOW_BLUR: f32 = 4.0;
+    const SHADOW_OPACITY: f32 = 0.4;
+    /// Theme colors of type names.
+    const TYPE_COLOR_ROLES: [&'static str; 4] = [
+        "numeric_type_color",
+        "text_type_color",
+        "timestamp_type_color",
+        "named_type_color",
+    ];
+    /// Theme colors of diff tints, which are also the backgrounds of change badges.
+    const CHANGE_COLOR_ROLES: [&'static str; 3] =
+        ["added_color", "removed_color", "modified_color"];
+    const BADGE_BG_ROLES: [&'static str; 5] = [
+        "primary_key_bg_color",
+        "foreign_key_bg_color",
+        "added_color",
+        "removed_color",
+        "modified_color",
+    ];
+    const BADGE_TEXT_ROLES: [&'static str; 3] =
+        ["primary_key_color", "foreign_key_color", "change_badge_color"];
+
     pub fn new() -> Self {
         Self {
             view_box: None,
+            width: None,
+            height: None,
+            scale: None,
+            center: false,
+            wide_mode: false,
+            field_column_ratio: FieldColumnRatio::default(),
+            truncate_overflow: true,
+            css_classes: false,
+            legend: false,
+            interactive: false,
+            accessible: false,
+            id_prefix: String::new(),
+            format: SVGFormat::Default,
+            version_stamp: None,
+            source: None,
+            theme: Theme::default(),
+            light_theme: None,
+            background: Background::default(),
+            grid: None,
+            shadow: false,
+            options: RenderOptions::default(),
+            edge_layer: mir::EdgeLayer::default(),
+            page_label: None,
             edge_route_graph: None,
+            show_debug: false,
+        }
+    }
+}
+
+impl SVGRenderer<'_> {
+    /// Returns truncated copies of the title and the subtitle of `field` if they don't fit in
+    /// `width`. The subtitle keeps its full width as long as the title gets half of the space.
+    fn truncate_field_texts(
+        field: &mir::FieldShape,
+        width: f32,
+    ) -> (Option<mir::TextSpan>, Option<mir::TextSpan>) {
+        let title_width = font::text_width(&field.title);
+        let Some(subtitle) = &field.subtitle else {
+            return (truncate_span(&field.title, width), None);
+        };
+
+        let available = width - metrics::TEXT_GAP;
+        let subtitle_width = font::text_width(subtitle);
+
+        if title_width + subtitle_width <= available {
+            return (None, None);
+        }
+
+        let subtitle_budget = subtitle_width.min((available - title_width).max(available / 2.0));
+
+        (
+            truncate_span(&field.title, available - subtitle_budget),
+            truncate_span(subtitle, subtitle_budget),
+        )
+    }
+}
+
+impl SVGRenderer<'_> {
+    /// Returns the background color, or `None` if the background is transparent.
+    fn background_color(&self) -> Option<&WebColor> {
+        match &self.background {
+            Background::Theme => Some(&self.theme.background_color),
+            Background::Transparent => None,
+            Background::Color(color) => Some(color),
         }
     }
+
+    /// Returns `name` prefixed with `id_prefix`.
+    fn element_id(&self, name: &str) -> String {
+        format!("{}{}", self.id_prefix, name)
+    }
+
+    /// Returns the ID of the `<marker>` of `marker` drawn with `stroke` (see `edge_stroke`).
+    /// Markers of the default stroke have no suffix.
+    fn marker_id(&self, marker: mir::EdgeMarker, (color, width): &(String, f32)) -> String {
+        if *color == self.theme.edge_color.to_string() && *width == self.options.edge_stroke_width
+        {
+            return self.element_id(&format!("marker-{}", marker));
+        }
+
+        let color: String = color.chars().filter(char::is_ascii_alphanumeric).collect();
+        let width = width.to_string().replace('.', "_");
+
+        self.element_id(&format!("marker-{}-{}-{}", marker, color, width))
+    }
+
+    /// Returns the color and the width of the stroke of `edge`.
+    fn edge_stroke(&self, edge: &mir::EdgeData) -> (String, f32) {
+        (
+            edge.stroke_color().unwrap_or(&self.theme.edge_color).to_string(),
+            edge.stroke_width().unwrap_or(self.options.edge_stroke_width),
+        )
+    }
+
+    /// Returns `true` if any record or field has a URL.
+    fn has_links(doc: &mir::Document) -> bool {
+        doc.node_ids()
+            .filter_map(|node_id| doc.get_node(node_id))
+            .any(|node| match node.kind() {
+                mir::ShapeKind::Record(record) => record.url.is_some(),
+                mir::ShapeKind::Field(field) => field.url.is_some(),
+                mir::ShapeKind::Body(_) | mir::ShapeKind::Group(_) | mir::ShapeKind::Note(_) => {
+                    false
+                }
+            })
+    }
+
+    /// Returns the color painted under `point`: the background of the innermost field or record
+    /// which has one, or the background of the diagram.
+    fn fill_at(&self, doc: &mir::Document, point: &Point) -> Option<WebColor> {
+        let shape_color = doc.nodes_at(point).into_iter().rev().find_map(|node_id| {
+            match doc.get_node(node_id)?.kind() {
+                mir::ShapeKind::Field(field) => field.bg_color.clone(),
+                mir::ShapeKind::Record(record) => record.bg_color.clone(),
+                _ => None,
+            }
+        });
+
+        shape_color.or_else(|| self.background_color().cloned())
+    }
+
+    /// Returns the view box of the canvas size centered on `view_box` if `center` is set.
+    fn center_view_box(&self, view_box: Rect) -> Rect {
+        let (true, Some(width), Some(height)) = (self.center, self.width, self.height) else {
+            return view_box;
+        };
+        let origin = Point::new(
+            view_box.mid_x() - width / 2.0,
+            view_box.mid_y() - height / 2.0,
+        );
+
+        Rect::new(origin, Size::new(width, height))
+    }
+
+    /// Returns `width` and `height` attributes of the root element.
+    fn root_size(&self, view_box: Option<Rect>) -> (Option<f32>, Option<f32>) {
+        let aspect_ratio = view_box
+            .filter(|view_box| view_box.width() > 0.0)
+            .map(|view_box| view_box.height() / view_box.width());
+        let (width, height) = match (self.width, self.height) {
+            (None, None) if self.scale.is_some() => (
+                view_box.map(|view_box| view_box.width()),
+                view_box.map(|view_box| view_box.height()),
+            ),
+            (Some(width), None) => (Some(width), aspect_ratio.map(|ratio| width * ratio)),
+            (None, Some(height)) => (
+                aspect_ratio
+                    .filter(|ratio| *ratio > 0.0)
+                    .map(|ratio| height / ratio),
+                Some(height),
+            ),
+            size => size,
+        };
+        let scale = self.scale.unwrap_or(1.0);
+
+        (width.map(|w| w * scale), height.map(|h| h * scale))
+    }
 }
 
 impl Renderer for SVGRenderer<'_> {
-    fn render(&self, doc: &mir::Document, writer: &mut impl Write) -> Result<(), BackendError> {
-        let px = 12f32;
-        let border_radius = 6f32;
-        let record_clip_path_id_prefix = "record-clip-path-";
-        let background_color = WebColor::RGB(RGBColor::new(28, 28, 28));
+    /// Writes elements in a fixed order, so that the same document and options always give the
+    /// same bytes:
+    ///
+    /// 1. `<style>` (CSS rules in the order of first use) and `<metadata>`
+    /// 2. the background and the grid
+    /// 3. `<defs>`: the grid pattern, the shadow filter, then clip paths and header gradients
+    ///    in the order of records, then markers in the order of `EdgeMarker::ALL` and of first
+    ///    use of their strokes
+    /// 4. the title of the body
+    /// 5. groups (their backgrounds and titles) in the order of `mir::Document::group_ids`
+    /// 6. edges of `EdgeLayer::Below`
+    /// 7. records in the order of children of the body, and fields in the order of children
+    ///    of each record
+    /// 8. edges of `EdgeLayer::Normal`, labels of all edges, and edges of `EdgeLayer::Above`.
+    ///    Edges of a layer are in the order of `mir::Document::edges`.
+    /// 9. notes in the order of `mir::Document::note_ids`
+    /// 10. the legend, the page label, debug info, and the highlight style and script
+    ///
+    /// Attributes are sorted by name. Maps are only used for lookups, never iterated to emit
+    /// elements.
+    fn render(&self, doc: &mir::Document, writer: &mut dyn Write) -> Result<(), BackendError> {
+        let px = self.options.padding_x;
+        let border_radius = self.options.border_radius;
+        let record_clip_path_id_prefix = self.element_id("record-clip-path-");
+        let header_gradient_id_prefix = self.element_id("header-gradient-");
 
         // -- Build a SVG document
         let mut svg_doc = svg::Document::new();
         let mut svg_defs = element::Definitions::new();
 
-        if let Some(view_box) = self.view_box {
+        // The view box is extended if the legend doesn't fit in it.
+        let legend = if self.legend { self.place_legend(doc) } else { None };
+        let view_box = self.view_box.map(|view_box| match &legend {
+            Some((_, rect)) if rect.max_y() > view_box.max_y() => Rect::new(
+                *view_box.origin(),
+                Size::new(
+                    view_box.width(),
+                    rect.max_y() + Self::LEGEND_MARGIN - view_box.min_y(),
+                ),
+            ),
+            _ => view_box,
+        });
+        let view_box = view_box.map(|view_box| self.center_view_box(view_box));
+
+        if let Some(view_box) = view_box {
             svg_doc.assign(
                 "viewBox",
                 format!(
@@ -55,13 +538,88 @@ impl Renderer for SVGRenderer<'_> {
             );
         }
 
+        if Self::has_links(doc) {
+            svg_doc.assign("xmlns:xlink", "http://www.w3.org/1999/xlink");
+        }
+
+        let (width, height) = self.root_size(view_box);
+        if let Some(width) = width {
+            svg_doc.assign("width", width);
+        }
+        if let Some(height) = height {
+            svg_doc.assign("height", height);
+        }
+
+        if self.version_stamp.is_some() || self.source.is_some() {
+            let mut metadata = element::Element::new("metadata");
+
+            metadata.assign("data-seiren-version", crate::VERSION);
+            if let Some(config_hash) = self.version_stamp {
+                metadata.assign("data-seiren-layout", format!("{:016x}", config_hash));
+            }
+            if let Some(source) = &self.source {
+                let mut source_element = element::Element::new(Self::SOURCE_ELEMENT);
+
+                source_element.append(source_node(source));
+                metadata.append(source_element);
+            }
+            svg_doc.append(metadata);
+        }
+
         // -- Background
-        let background_rect = element::Rectangle::new()
-            .set("width", "100%")
-            .set("height", "100%")
-            .set("fill", background_color.to_string());
+        let mut sheet = if self.css_classes || self.light_theme.is_some() {
+            StyleSheet::classes()
+        } else {
+            StyleSheet::inline()
+        };
+        if let Some(light_theme) = &self.light_theme {
+            let light_colors = light_theme.colors();
 
-        svg_doc.append(background_rect);
+            for (key, color) in self.theme.colors() {
+                let Some((_, light_color)) = light_colors.iter().find(|(k, _)| *k == key) else {
+                    continue;
+                };
+                let (color, light_color) = (color.to_string(), light_color.to_string());
+                if color != light_color {
+                    let name = self.element_id(&key.replace('_', "-"));
+                    sheet.define_variable(key, &name, color, light_color);
+                }
+            }
+        }
+        // Rects of 100% start at the origin of the user space, not of the view box.
+        let cover = |rect: element::Rectangle| match view_box {
+            Some(view_box) if *view_box.origin() != Point::zero() => rect
+                .set("x", view_box.min_x())
+                .set("y", view_box.min_y()),
+            _ => rect,
+        };
+        if let Some(background_color) = self.background_color() {
+            let mut background_rect = cover(element::Rectangle::new())
+                .set("width", "100%")
+                .set("height", "100%");
+
+            sheet.apply(
+                &mut background_rect,
+                "background",
+                &[("fill", background_color.to_string())],
+                &["background_color"],
+            );
+            svg_doc.append(background_rect);
+        }
+        if let Some(grid) = self.grid {
+            let id = self.element_id("grid");
+            let grid_rect = cover(element::Rectangle::new())
+                .set("width", "100%")
+                .set("height", "100%")
+                .set("fill", format!("url(#{})", id));
+
+            svg_defs.append(self.draw_grid_pattern(&mut sheet, grid, &id));
+            svg_doc.append(grid_rect);
+        }
+        let shadow_filter_id = self.element_id("record-shadow");
+        if self.shadow {
+            svg_defs.append(Self::draw_shadow_filter(&shadow_filter_id));
+        }
 
         // -- Generate clip paths for record shapes.
         for (record_index, child_id) in doc.body().children().enumerate() {
@@ -83,9 +641,95 @@ impl Renderer for SVGRenderer<'_> {
             let clip_path = element::ClipPath::new().set("id", id).add(clip_path_rect);
 
             svg_defs.append(clip_path);
+
+            // A vertical gradient from the header color of the record.
+            let Some(end_color) = &self.theme.header_gradient_color else { continue };
+            let Some(mir::ShapeKind::Field(mir::FieldShape { bg_color: Some(start_color), .. })) =
+                record_node.children().next().and_then(|id| doc.get_node(id)).map(|n| n.kind())
+            else {
+                continue;
+            };
+            let stop = |offset: u8, color: &WebColor| {
+                element::Stop::new()
+                    .set("offset", offset)
+                    .set("stop-color", color.to_string())
+            };
+
+            svg_defs.append(
+                element::LinearGradient::new()
+                    .set("id", format!("{}{}", header_gradient_id_prefix, record_index))
+                    .set("x1", 0)
+                    .set("y1", 0)
+                    .set("x2", 0)
+                    .set("y2", 1)
+                    .add(stop(0, start_color))
+                    .add(stop(1, end_color)),
+            );
+        }
+
+        // -- Generate markers used by edges. Circles are drawn as shapes over edges.
+        for marker_element in self.draw_markers(&mut sheet, doc) {
+            svg_defs.append(marker_element);
         }
         svg_doc.append(svg_defs);
 
+        // -- Draw the title at the top of the body
+        if let (mir::ShapeKind::Body(body), Some(origin)) = (doc.body().kind(), doc.body().origin) {
+            if let Some(title) = &body.title {
+                let font_size = font::font_size_px(title.font_size.unwrap_or_default());
+                let title_element = self.draw_text(
+                    &mut sheet,
+                    "title",
+                    &["text_color"],
+                    title,
+                    Point::new(origin.x, origin.y + font_size / 2.0),
+                    Some(SVGAnchor::Start),
+                );
+
+                svg_doc.append(title_element);
+            }
+        }
+
+        // -- Draw groups beneath edges and records
+        for group_id in doc.group_ids() {
+            let Some(group_node) = doc.get_node(group_id) else { continue };
+            let mir::ShapeKind::Group(group) = group_node.kind() else { continue };
+            // Groups without members aren't placed.
+            let Some(group_rect) = group_node.rect() else { continue };
+
+            let mut group_bg = element::Rectangle::new()
+                .set("x", group_rect.min_x())
+                .set("y", group_rect.min_y())
+                .set("width", group_rect.width())
+                .set("height", group_rect.height())
+                .set("rx", border_radius)
+                .set("ry", border_radius);
+            let mut declarations = vec![];
+            if let Some(border_color) = &group.border_color {
+                declarations.push(("stroke", border_color.to_string()));
+            }
+            if let Some(bg_color) = &group.bg_color {
+                declarations.push(("fill", bg_color.to_string()));
+            } else {
+                declarations.push(("fill", "none".into()));
+            }
+            sheet.apply(&mut group_bg, "group", &declarations, &["record_border_color"]);
+            svg_doc.append(group_bg);
+
+            let font_size = font::font_size_px(group.title.font_size.unwrap_or_default());
+            svg_doc.append(self.draw_text(
+                &mut sheet,
+                "group-title",
+                &["secondary_text_color"],
+                &group.title,
+                Point::new(group_rect.min_x() + px, group_rect.min_y() + px + font_size / 2.0),
+                Some(SVGAnchor::Start),
+            ));
+        }
+
+        // -- Draw edges beneath shapes
+        self.draw_edges(&mut sheet, &mut svg_doc, doc, &[mir::EdgeLayer::Below])?;
+
         // -- Draw shapes
         for (record_index, child_id) in doc.body().children().enumerate() {
             let Some(record_node) = doc.get_node(child_id) else { continue };
@@ -93,6 +737,29 @@ impl Renderer for SVGRenderer<'_> {
             let Some(record_origin) = record_node.origin else { return Err(BackendError::InvalidLayout(child_id)) };
             let Some(record_size) = record_node.size else { return Err(BackendError::InvalidLayout(child_id)) };
 
+            // shadow under records which have a background
+            if self.shadow && record.bg_color.is_some() {
+                let mut shadow = element::Rectangle::new()
+                    .set("x", record_origin.x)
+                    .set("y", record_origin.y + Self::SHADOW_OFFSET_Y)
+                    .set("width", record_size.width)
+                    .set("height", record_size.height)
+                    .set("rx", border_radius)
+                    .set("ry", border_radius)
+                    .set("filter", format!("url(#{})", shadow_filter_id));
+
+                sheet.apply(
+                    &mut shadow,
+                    "record-shadow",
+                    &[
+                        ("fill", self.theme.shadow_color.to_string()),
+                        ("fill-opacity", Self::SHADOW_OPACITY.to_string()),
+                    ],
+                    &["shadow_color"],
+                );
+                svg_doc.append(shadow);
+            }
+
             // background
             let mut table_bg = element::Rectangle::new()
                 .set("x", record_origin.x)
@@ -101,13 +768,28 @@ impl Renderer for SVGRenderer<'_> {
                 .set("height", record_size.height)
                 .set("rx", border_radius)
                 .set("ry", border_radius);
+            let mut declarations = vec![];
             if let Some(border_color) = &record.border_color {
-                table_bg.assign("stroke", border_color.to_string());
+                declarations.push(("stroke", border_color.to_string()));
+            }
+            if record.dashed {
+                declarations.push(("stroke-dasharray", "4 3".into()));
             }
             if let Some(bg_color) = &record.bg_color {
-                table_bg.assign("fill", bg_color.to_string());
+                declarations.push(("fill", bg_color.to_string()));
+            } else {
+                declarations.push(("fill", "none".into()));
+            }
+            let class = if record.dashed { "record-stub" } else { "record" };
+            // Borders of stubs are gray.
+            let roles = ["record_border_color", "record_bg_color", "secondary_text_color"];
+            sheet.apply(&mut table_bg, class, &declarations, &roles);
+            let mut record_svg = element::Group::new();
+            if let Some(url) = &record.url {
+                record_svg.append(hyperlink(url).add(table_bg));
+            } else {
+                record_svg.append(table_bg);
             }
-            svg_doc.append(table_bg);
 
             // children
             let record_clip_path_id = format!("{}{}", record_clip_path_id_prefix, record_index);
@@ -119,17 +801,68 @@ impl Renderer for SVGRenderer<'_> {
 
                 let x = field_rect.min_x();
                 let y = field_rect.min_y();
+                let mut field_svg = element::Group::new();
 
                 // background color: we use a clip path to adjust border radius.
                 if let Some(bg_color) = &field.bg_color {
-                    let field_bg = element::Rectangle::new()
+                    let mut field_bg = element::Rectangle::new()
                         .set("x", x)
                         .set("y", y)
                         .set("width", field_rect.width())
                         .set("height", field_rect.height())
-                        .set("fill", bg_color.to_string())
                         .set("clip-path", format!("url(#{})", record_clip_path_id));
-                    svg_doc.append(field_bg);
+                    let (class, fill) = match field_index {
+                        0 if self.theme.header_gradient_color.is_some() => (
+                            "record-header",
+                            format!("url(#{}{})", header_gradient_id_prefix, record_index),
+                        ),
+                        0 => ("record-header", bg_color.to_string()),
+                        _ => ("field-bg", bg_color.to_string()),
+                    };
+                    let roles =
+                        ["header_bg_color", "primary_key_row_color", "foreign_key_row_color"];
+                    sheet.apply(&mut field_bg, class, &[("fill", fill)], &roles);
+                    field_svg.append(field_bg);
+                }
+
+                // zebra striping of rows without their own background
+                if let (Some(stripe_color), None) = (&self.theme.stripe_color, &field.bg_color) {
+                    if field_index > 0 && field_index % 2 == 0 {
+                        let mut stripe = element::Rectangle::new()
+                            .set("x", x)
+                            .set("y", y)
+                            .set("width", field_rect.width())
+                            .set("height", field_rect.height())
+                            .set("clip-path", format!("url(#{})", record_clip_path_id));
+                        let fill = stripe_color.to_string();
+                        let roles = ["stripe_color"];
+                        sheet.apply(&mut stripe, "field-stripe", &[("fill", fill)], &roles);
+                        field_svg.append(stripe);
+                    }
+                }
+
+                // diff tint over the background
+                if let Some(change) = &field.change {
+                    let mut tint = element::Rectangle::new()
+                        .set("x", x)
+                        .set("y", y)
+                        .set("width", field_rect.width())
+                        .set("height", field_rect.height())
+                        .set("clip-path", format!("url(#{})", record_clip_path_id));
+                    sheet.apply(
+                        &mut tint,
+                        &format!("field-{}", change_name(*change)),
+                        &[
+                            ("fill", self.theme.change_color(*change).to_string()),
+                            ("fill-opacity", Self::CHANGE_TINT_OPACITY.to_string()),
+                        ],
+                        &Self::CHANGE_COLOR_ROLES,
+                    );
+                    field_svg.append(tint);
+                }
+
+                if self.interactive {
+                    field_svg.append(Self::draw_hit_area(&field_rect));
                 }
 
                 // border
@@ -139,12 +872,15 @@ impl Renderer for SVGRenderer<'_> {
                         .set("x2", field_rect.max_x())
                         .set("y1", y)
                         .set("y2", y);
-                    if let Some(border_color) = &field.border_color {
-                        line = line
-                            .set("stroke", border_color.to_string())
-                            .set("stroke-width", 1);
-                    }
-                    svg_doc.append(line);
+                    let declarations: Vec<_> = field
+                        .border_color
+                        .iter()
+                        .flat_map(|border_color| {
+                            [("stroke", border_color.to_string()), ("stroke-width", "1".into())]
+                        })
+                        .collect();
+                    sheet.apply(&mut line, "field-border", &declarations, &["record_border_color"]);
+                    field_svg.append(line);
                 }
 
                 // Renders text elements
@@ -155,67 +891,331 @@ impl Renderer for SVGRenderer<'_> {
                 // | title       |    subtitle |  badge  |
                 // +-------------+-------------+---------+
                 // ```
-                let column_width = field_rect.width() / 5.0;
+                //
+                // In wide mode, default values are rendered in the third column:
+                //
+                // ```svgbob
+                // +-------------+-------------+-------------+---------+
+                // |<---- 2 ---->|<---- 2 ---->|<---- 2 ---->|<-- 1 -->|
+                // | title       |    subtitle |     default |  badge  |
+                // +-------------+-------------+-------------+---------+
+                // ```
+                // Invalid ratios would give infinite or NaN widths.
+                let ratio = match self.field_column_ratio.validate(self.wide_mode) {
+                    Ok(()) => self.field_column_ratio,
+                    Err(_) => FieldColumnRatio::default(),
+                };
+                let default_value_ratio = if self.wide_mode {
+                    ratio.default_value
+                } else {
+                    0.0
+                };
+                let mut column_width = field_rect.width()
+                    / (ratio.title + ratio.subtitle + default_value_ratio + ratio.badge);
+
+                if let Some(max_badge_width) = ratio.max_badge_width {
+                    if column_width * ratio.badge > max_badge_width {
+                        column_width = (field_rect.width() - max_badge_width)
+                            / (ratio.title + ratio.subtitle + default_value_ratio);
+                    }
+                }
+
+                // ordinal
+                let mut title_x = x + px;
+
+                if let Some(ordinal) = &field.ordinal {
+                    let text_element = self.draw_text(
+                        &mut sheet,
+                        "field-ordinal",
+                        &["secondary_text_color"],
+                        ordinal,
+                        Point::new(title_x, field_rect.mid_y()),
+                        Some(SVGAnchor::Start),
+                    );
+                    field_svg.append(text_element);
+                    title_x += metrics::ORDINAL_WIDTH;
+                }
+
+                // icon of the entity kind in front of the name
+                if let (0, Some(kind)) = (field_index, record.kind) {
+                    let color = field.title.color.as_ref().unwrap_or(&self.theme.text_color);
+                    let origin = Point::new(title_x, field_rect.mid_y() - metrics::ICON_SIZE / 2.0);
+
+                    field_svg.append(self.draw_entity_icon(&mut sheet, kind, origin, color));
+                    title_x += metrics::ICON_SIZE + metrics::ICON_GAP;
+                }
+
+                // title and subtitle
+                let subtitle_x = x + column_width * (ratio.title + ratio.subtitle);
+                let (title, subtitle) = if self.truncate_overflow {
+                    Self::truncate_field_texts(field, subtitle_x - title_x)
+                } else {
+                    (None, None)
+                };
+
+                let mut tooltip_lines = vec![];
+                if title.is_some() {
+                    tooltip_lines.push(field.title.text.clone());
+                }
+                if let (false, Some(default_value)) = (self.wide_mode, &field.default_value) {
+                    tooltip_lines.push(format!("default: {}", default_value.text));
+                }
 
-                // title
-                let text_element = self.draw_text(
-                    &field.title,
-                    Point::new(x + px, field_rect.mid_y()),
+                let title_class = if field_index == 0 { "record-name" } else { "field-title" };
+                let mut text_element = self.draw_text(
+                    &mut sheet,
+                    title_class,
+                    &["text_color", "secondary_text_color"],
+                    title.as_ref().unwrap_or(&field.title),
+                    Point::new(title_x, field_rect.mid_y()),
                     Some(SVGAnchor::Start),
                 );
-                svg_doc.append(text_element);
+                if !tooltip_lines.is_empty() {
+                    let tooltip = element::Title::new().add(text_node(&tooltip_lines.join("\n")));
+                    text_element.append(tooltip);
+                }
+                field_svg.append(text_element);
+
+                if let Some(full_subtitle) = &field.subtitle {
+                    let mut text_element = self.draw_text(
+                        &mut sheet,
+                        "field-subtitle",
+                        &Self::TYPE_COLOR_ROLES,
+                        subtitle.as_ref().unwrap_or(full_subtitle),
+                        Point::new(subtitle_x, field_rect.mid_y()),
+                        Some(SVGAnchor::End),
+                    );
+                    if subtitle.is_some() {
+                        let tooltip = element::Title::new().add(text_node(&full_subtitle.text));
+                        text_element.append(tooltip);
+                    }
+                    field_svg.append(text_element);
+                }
 
-                // subtitle
-                if let Some(subtitle) = &field.subtitle {
+                // default value
+                if let (true, Some(default_value)) = (self.wide_mode, &field.default_value) {
                     let text_element = self.draw_text(
-                        subtitle,
-                        Point::new(x + column_width * 4.0, field_rect.mid_y()),
+                        &mut sheet,
+                        "field-default",
+                        &["secondary_text_color"],
+                        default_value,
+                        Point::new(
+                            x + column_width
+                                * (ratio.title + ratio.subtitle + ratio.default_value),
+                            field_rect.mid_y(),
+                        ),
                         Some(SVGAnchor::End),
                     );
-                    svg_doc.append(text_element);
+                    field_svg.append(text_element);
                 }
 
-                // badge
-                if let Some(badge) = &field.badge {
-                    let rx = field_rect.max_x() - px;
-                    let cy = field_rect.mid_y();
-                    let bg_radius = (field_rect.height() / 2.0) - 6.0;
+                // badges: stacked from right to left. A change badge follows other badges.
+                let change_badge = field
+                    .change
+                    .map(|change| (self.change_badge(change), change_name(change).to_string()));
+                let badges = field
+                    .badges
+                    .iter()
+                    .map(|badge| (badge.clone(), class_name(&badge.text)))
+                    .chain(change_badge);
+                let cy = field_rect.mid_y();
+                let bg_radius = (field_rect.height() / 2.0) - metrics::BADGE_MARGIN_Y;
+                let mut rx = field_rect.max_x() - px;
+
+                for (badge, name) in badges {
+                    let class = format!("badge-{}", name);
 
                     if let Some(bg_color) = &badge.bg_color {
-                        let bg_element = element::Circle::new()
+                        let mut bg_element = element::Circle::new()
                             .set("cx", rx - bg_radius)
                             .set("cy", cy)
-                            .set("r", bg_radius)
-                            .set("fill", bg_color.to_string());
-                        svg_doc.append(bg_element);
+                            .set("r", bg_radius);
+                        let declarations = [("fill", bg_color.to_string())];
+                        sheet.apply(&mut bg_element, &class, &declarations, &Self::BADGE_BG_ROLES);
+                        field_svg.append(bg_element);
                     }
 
                     let text_element = self.draw_text(
+                        &mut sheet,
+                        &format!("{}-text", class),
+                        &Self::BADGE_TEXT_ROLES,
                         &badge.into_text_span(),
                         Point::new(rx - bg_radius, cy),
                         Some(SVGAnchor::Middle),
                     );
-                    svg_doc.append(text_element);
+                    field_svg.append(text_element);
+                    rx -= bg_radius * 2.0 + metrics::BADGE_GAP;
+                }
+
+                // A field is grouped in interactive or accessible mode, and linked to its URL or
+                // the record's.
+                if self.interactive {
+                    field_svg.assign("data-node", field_node_id.to_string());
+                }
+                if self.accessible && field_index > 0 {
+                    Self::describe_field(field).apply(&mut field_svg, "group");
+                }
+                let is_grouped = self.interactive || self.accessible;
+                let mut field_children: Vec<Box<dyn Node>> = if is_grouped {
+                    vec![Box::new(field_svg)]
+                } else {
+                    field_svg.get_children_mut().drain(..).collect()
+                };
+                if let Some(url) = field.url.as_ref().or(record.url.as_ref()) {
+                    let mut link = hyperlink(url);
+
+                    for child in field_children.drain(..) {
+                        link.append(child);
+                    }
+                    field_children.push(Box::new(link));
+                }
+                for child in field_children {
+                    record_svg.append(child);
+                }
+            }
+
+            if self.accessible {
+                Self::describe_record(doc, child_id).apply(&mut record_svg, "group");
+                svg_doc.append(record_svg);
+            } else {
+                for child in record_svg.get_children_mut().drain(..) {
+                    svg_doc.append(child);
                 }
             }
         }
 
         // -- Draw edges
-        for edge in doc.edges() {
-            let (edge_path, start_circle, end_circle) = self.draw_edge_connection(edge)?;
-            svg_doc = svg_doc.add(edge_path).add(start_circle).add(end_circle);
+        let layers = [mir::EdgeLayer::Normal, mir::EdgeLayer::Above];
+        self.draw_edges(&mut sheet, &mut svg_doc, doc, &layers)?;
+
+        // -- Draw notes above edges, which are routed around them
+        for note_id in doc.note_ids() {
+            let Some(note_node) = doc.get_node(note_id) else { continue };
+            let mir::ShapeKind::Note(note) = note_node.kind() else { continue };
+            let Some(note_rect) = note_node.rect() else {
+                return Err(BackendError::InvalidLayout(note_id));
+            };
+
+            svg_doc.append(self.draw_note(&mut sheet, note, &note_rect));
+        }
+
+        if let Some((rows, rect)) = &legend {
+            svg_doc.append(self.draw_legend(&mut sheet, rows, rect));
+        }
+
+        if let (Some(label), Some(view_box)) = (&self.page_label, view_box) {
+            svg_doc.append(self.draw_page_label(&mut sheet, label, &view_box));
         }
 
         // -- Draw debug info
         if let Some(edge_route_graph) = self.edge_route_graph {
-            svg_doc = self.draw_debug_info(svg_doc, doc, edge_route_graph);
+            svg_doc.append(self.draw_debug_info(doc, edge_route_graph));
+        }
+
+        if let Some(style) = sheet.to_style_element() {
+            svg_doc.get_children_mut().insert(0, Box::new(style));
+        }
+        if self.accessible {
+            Self::describe_document(doc).apply(&mut svg_doc, "graphics-document document");
+        }
+        if self.interactive {
+            svg_doc.append(self.highlight_style());
+            svg_doc.append(Self::highlight_script());
         }
 
-        writer.write_all(svg_doc.to_string().as_bytes())?;
+        if self.format == SVGFormat::Default {
+            // Streams the document without building the whole string.
+            write!(writer, "{}", svg_doc)?;
+        } else {
+            format::write_svg(&svg_doc.to_string(), self.format, writer)?;
+        }
         Ok(())
     }
 }
 
+/// Returns the name of a change used in class names (e.g. `badge-added`).
+fn change_name(change: mir::FieldChange) -> &'static str {
+    match change {
+        mir::FieldChange::Added => "added",
+        mir::FieldChange::Removed => "removed",
+        mir::FieldChange::Modified => "modified",
+    }
+}
+
+/// Converts `text` to a class name component (e.g. `PK` → `pk`).
+fn class_name(text: &str) -> String {
+    text.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+fn truncate_span(span: &mir::TextSpan, max_width: f32) -> Option<mir::TextSpan> {
+    font::truncate_text(span, max_width).map(|text| mir::TextSpan {
+        text,
+        ..span.clone()
+    })
+}
+
+/// Creates a link to `url`. `xlink:href` is for viewers which don't support SVG 2.
+fn hyperlink(url: &str) -> element::Link {
+    let url = Escape::XmlAttribute.apply(url);
+
+    element::Link::new()
+        .set("href", url.as_ref())
+        .set("xlink:href", url.as_ref())
+}
+
+/// Returns the source embedded in an SVG by `SVGRenderer::source`, if any.
+pub fn extract_source(svg: &str) -> Option<String> {
+    let start_tag = format!("<{}>", SVGRenderer::SOURCE_ELEMENT);
+    let end_tag = format!("</{}>", SVGRenderer::SOURCE_ELEMENT);
+    let start = svg.find(&start_tag)? + start_tag.len();
+    let end = start + svg[start..].find(&end_tag)?;
+    // Whitespace around the text is the indentation of `SVGFormat`. See `source_node`.
+    let text = svg[start..end].trim_matches(SOURCE_WHITESPACE);
+
+    // The reverse of `source_node`
+    let source = SOURCE_WHITESPACE
+        .iter()
+        .fold(text.to_string(), |text, c| {
+            text.replace(&format!("&#{};", *c as u32), &c.to_string())
+        })
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&");
+    Some(source)
+}
+
+/// Creates a text node. Unlike `svg::node::Text::new`, markup characters are escaped.
+fn text_node(text: &str) -> svg::node::Text {
+    svg::node::Text::new(Escape::Xml.apply(text))
+}
+
+/// Whitespace which `SVGFormat`s may write around the embedded source.
+const SOURCE_WHITESPACE: &[char] = &[' ', '\t', '\n', '\r'];
+
+/// Creates a text node of an embedded source. Whitespace at both ends is written as character
+/// references, so that `extract_source` can tell it from the indentation.
+fn source_node(source: &str) -> svg::node::Text {
+    let body = source.trim_matches(SOURCE_WHITESPACE);
+    let start = source.len() - source.trim_start_matches(SOURCE_WHITESPACE).len();
+    let references =
+        |s: &str| -> String { s.chars().map(|c| format!("&#{};", c as u32)).collect() };
+
+    svg::node::Text::new(format!(
+        "{}{}{}",
+        references(&source[..start]),
+        Escape::Xml.apply(body),
+        references(&source[start + body.len()..])
+    ))
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 enum SVGAnchor {
     Start,
@@ -234,9 +1234,285 @@ impl SVGAnchor {
     }
 }
 
+impl From<TextAnchor> for SVGAnchor {
+    fn from(anchor: TextAnchor) -> Self {
+        match anchor {
+            TextAnchor::Start => SVGAnchor::Start,
+            TextAnchor::Middle => SVGAnchor::Middle,
+            TextAnchor::End => SVGAnchor::End,
+        }
+    }
+}
+
 impl SVGRenderer<'_> {
+    fn change_badge(&self, change: mir::FieldChange) -> mir::Badge {
+        mir::BadgeBuilder::default()
+            .text(change.to_string())
+            .color(Some(self.theme.change_badge_color.clone()))
+            .bg_color(Some(self.theme.change_color(change).clone()))
+            .build()
+            .unwrap()
+    }
+
+    /// Creates `<marker>`s of edge ends used in `doc`, one for each stroke which they're used
+    /// with.
+    fn draw_markers(&self, sheet: &mut StyleSheet, doc: &mir::Document) -> Vec<element::Marker> {
+        let mut used_markers: Vec<(mir::EdgeMarker, (String, f32))> = vec![];
+
+        for edge in doc.edges() {
+            let stroke = self.edge_stroke(edge);
+
+            for marker in [edge.start_marker(), edge.end_marker()] {
+                let used_marker = (marker, stroke.clone());
+
+                if !used_markers.contains(&used_marker) {
+                    used_markers.push(used_marker);
+                }
+            }
+        }
+
+        mir::EdgeMarker::ALL
+            .into_iter()
+            .flat_map(|marker| used_markers.iter().filter(move |(used, _)| *used == marker))
+            .filter_map(|(marker, stroke)| self.draw_marker(sheet, *marker, stroke))
+            .collect()
+    }
+
+    /// Draws edges of `layers` from the bottom with terminal circles and cardinalities. Labels
+    /// of all edges are drawn over the normal layer.
+    fn draw_edges(
+        &self,
+        sheet: &mut StyleSheet,
+        svg_doc: &mut svg::Document,
+        doc: &mir::Document,
+        layers: &[mir::EdgeLayer],
+    ) -> Result<(), BackendError> {
+        let node_names = self.accessible.then(|| Self::node_names(doc));
+
+        for layer in layers {
+            let edges = doc
+                .edges()
+                .filter(|edge| edge.layer().unwrap_or(self.edge_layer) == *layer);
+
+            for edge in edges {
+                let (edge_path, circles) = self.draw_edge_connection(sheet, doc, edge)?;
+                let mut edge_svg = element::Group::new();
+
+                edge_svg.append(edge_path);
+                for circle in circles {
+                    edge_svg.append(circle);
+                }
+                for text in self.draw_cardinalities(sheet, edge) {
+                    edge_svg.append(text);
+                }
+                let description = node_names
+                    .as_ref()
+                    .map(|names| Self::describe_edge(names, edge));
+
+                self.append_edge_group(svg_doc, edge, edge_svg, description);
+            }
+
+            if *layer != mir::EdgeLayer::Normal {
+                continue;
+            }
+            for edge in doc.edges() {
+                let Some((pill, text)) = self.draw_edge_label(sheet, doc, edge) else { continue };
+                let edge_svg = element::Group::new().add(pill).add(text);
+
+                self.append_edge_group(svg_doc, edge, edge_svg, None);
+            }
+        }
+        Ok(())
+    }
+
+    /// Draws the icon of `kind` in a box of `ICON_SIZE` at `origin`.
+    ///
+    /// ```svgbob
+    ///  table    view     enum
+    /// +-----+  .---.   -  ----
+    /// +--+--+ (  o  )  -  ----
+    /// +--+--+  `---'   -  ----
+    /// ```
+    fn draw_entity_icon(
+        &self,
+        sheet: &mut StyleSheet,
+        kind: mir::EntityKind,
+        origin: Point,
+        color: &WebColor,
+    ) -> element::Path {
+        let d = match kind {
+            mir::EntityKind::Table => "M1 1 H11 V11 H1 Z M1 4.5 H11 M1 8 H11 M5 4.5 V11",
+            mir::EntityKind::View => {
+                "M0.5 6 Q6 0 11.5 6 Q6 12 0.5 6 Z M6 4 A2 2 0 1 1 6 8 A2 2 0 1 1 6 4 Z"
+            }
+            mir::EntityKind::Enum => {
+                "M1 2.5 H2.5 M4.5 2.5 H11 M1 6 H2.5 M4.5 6 H11 M1 9.5 H2.5 M4.5 9.5 H11"
+            }
+        };
+        let mut path = element::Path::new()
+            .set("d", d)
+            .set("transform", format!("translate({} {})", origin.x, origin.y));
+
+        sheet.apply(
+            &mut path,
+            "record-icon",
+            &[
+                ("stroke", color.to_string()),
+                ("stroke-width", "1.2".into()),
+                ("fill", "none".into()),
+            ],
+            &["text_color"],
+        );
+        path
+    }
+
+    /// A blur of record shadows. The filter region is enlarged, so the blur isn't cut at the
+    /// bounding box of a record.
+    fn draw_shadow_filter(id: &str) -> element::Filter {
+        let mut blur = element::Element::new("feGaussianBlur");
+
+        blur.assign("stdDeviation", Self::SHADOW_BLUR);
+        element::Filter::new()
+            .set("id", id)
+            .set("x", "-10%")
+            .set("y", "-10%")
+            .set("width", "120%")
+            .set("height", "120%")
+            .add(blur)
+    }
+
+    /// A tile of the background grid, in user units so the grid is aligned to the layout.
+    fn draw_grid_pattern(
+        &self,
+        sheet: &mut StyleSheet,
+        grid: GridPattern,
+        id: &str,
+    ) -> element::Pattern {
+        let spacing = Self::GRID_SPACING;
+        let color = self.theme.record_border_color.to_string();
+        let pattern = element::Pattern::new()
+            .set("id", id)
+            .set("width", spacing)
+            .set("height", spacing)
+            .set("patternUnits", "userSpaceOnUse");
+
+        match grid {
+            GridPattern::Dots => {
+                let mut dot = element::Circle::new().set("cx", 1).set("cy", 1).set("r", 1);
+
+                sheet.apply(&mut dot, "grid", &[("fill", color)], &["record_border_color"]);
+                pattern.add(dot)
+            }
+            GridPattern::Lines => {
+                let mut lines = element::Path::new()
+                    .set("d", format!("M{} 0 L0 0 L0 {}", spacing, spacing))
+                    .set("fill", "none");
+
+                sheet.apply(
+                    &mut lines,
+                    "grid",
+                    &[("stroke", color), ("stroke-width", "0.5".into())],
+                    &["record_border_color"],
+                );
+                pattern.add(lines)
+            }
+        }
+    }
+
+    /// Creates an SVG of only the edges of `doc` (with their markers), which is laid over
+    /// records drawn by another backend. A user unit is a pixel, and the origin of the view box
+    /// is at the top left corner.
+    pub(crate) fn render_edge_overlay(
+        &self,
+        doc: &mir::Document,
+        view_box: &Rect,
+    ) -> Result<svg::Document, BackendError> {
+        let mut sheet = StyleSheet::inline();
+        let mut svg_doc = svg::Document::new()
+            .set(
+                "viewBox",
+                format!(
+                    "{}, {}, {}, {}",
+                    view_box.min_x(),
+                    view_box.min_y(),
+                    view_box.width(),
+                    view_box.height()
+                ),
+            )
+            .set("width", view_box.width())
+            .set("height", view_box.height());
+        let markers = self.draw_markers(&mut sheet, doc);
+
+        if !markers.is_empty() {
+            let mut svg_defs = element::Definitions::new();
+
+            for marker in markers {
+                svg_defs.append(marker);
+            }
+            svg_doc.append(svg_defs);
+        }
+        self.draw_edges(&mut sheet, &mut svg_doc, doc, &mir::EdgeLayer::ALL)?;
+        Ok(svg_doc)
+    }
+
+    /// Draws a note with the lines which the layout engine wrapped its text into.
+    fn draw_note(
+        &self,
+        sheet: &mut StyleSheet,
+        note: &mir::NoteShape,
+        rect: &Rect,
+    ) -> element::Group {
+        let padding = mir::NoteShape::PADDING;
+        let mut note_bg = element::Rectangle::new()
+            .set("x", rect.min_x())
+            .set("y", rect.min_y())
+            .set("width", rect.width())
+            .set("height", rect.height())
+            .set("rx", self.options.border_radius)
+            .set("ry", self.options.border_radius);
+        let bg_color = note.bg_color.as_ref().unwrap_or(&self.theme.record_bg_color);
+
+        sheet.apply(
+            &mut note_bg,
+            "note",
+            &[
+                ("fill", bg_color.to_string()),
+                ("stroke", self.theme.record_border_color.to_string()),
+            ],
+            &["record_bg_color", "record_border_color"],
+        );
+
+        let mut group = element::Group::new().add(note_bg);
+        let text = mir::TextSpan {
+            color: note.text.color.clone().or_else(|| Some(self.theme.text_color.clone())),
+            ..note.text.clone()
+        };
+        for (i, line) in note.lines.iter().enumerate() {
+            let span = mir::TextSpan {
+                text: line.clone(),
+                ..text.clone()
+            };
+            let y = rect.min_y() + padding + note.line_height() * (i as f32 + 0.5);
+
+            group.append(self.draw_text(
+                sheet,
+                "note-text",
+                &["text_color"],
+                &span,
+                Point::new(rect.min_x() + padding, y),
+                Some(SVGAnchor::Start),
+            ));
+        }
+
+        group
+    }
+
+    /// Draws `span` of `class`, whose color is taken from one of the theme colors `roles`.
     fn draw_text(
         &self,
+        sheet: &mut StyleSheet,
+        class: &str,
+        roles: &[&str],
         span: &mir::TextSpan,
         origin: Point,
         text_anchor: Option<SVGAnchor>,
@@ -244,67 +1520,85 @@ impl SVGRenderer<'_> {
         let mut label = element::Text::new()
             .set("x", origin.x)
             .set("y", origin.y)
-            .set("dominant-baseline", "middle")
-            .add(svg::node::Text::new(span.text.clone()));
+            .add(text_node(&span.text));
+        let mut declarations = vec![("dominant-baseline", "middle".to_string())];
 
         if let Some(text_anchor) = text_anchor {
-            label = label.set("text-anchor", text_anchor.text_anchor());
+            declarations.push(("text-anchor", text_anchor.text_anchor()));
         }
         if let Some(text_color) = &span.color {
-            label = label.set("fill", text_color.to_string());
+            declarations.push(("fill", text_color.to_string()));
         }
         if let Some(font_family) = &span.font_family {
-            label = label.set("font-family", font_family.to_string());
+            declarations.push(("font-family", self.options.font_family(*font_family)));
         }
         if let Some(font_weight) = &span.font_weight {
-            label = label.set("font-weight", font_weight.to_string());
+            declarations.push(("font-weight", font_weight.to_string()));
         }
-
-        // position
         if let Some(font_size) = &span.font_size {
-            label = label.set("font-size", font_size.to_string());
+            declarations.push(("font-size", font_size.to_string()));
         }
 
+        sheet.apply(&mut label, class, &declarations, roles);
         label
     }
 
     fn draw_edge_connection(
         &self,
+        sheet: &mut StyleSheet,
+        doc: &mir::Document,
         edge: &mir::EdgeData,
-    ) -> Result<(element::Path, element::Circle, element::Circle), BackendError> {
-        let circle_radius = 4.0;
-        let path_radius = 6.0;
-        let stroke_width = 1.5;
-        let stroke_color = WebColor::RGB(RGBColor {
-            red: 136,
-            green: 136,
-            blue: 136,
-        });
-        let background_color = WebColor::RGB(RGBColor::new(28, 28, 28));
+    ) -> Result<(element::Path, Vec<element::Circle>), BackendError> {
+        let circle_radius = self.options.circle_radius;
+        let path_radius = self.options.path_radius;
+        let stroke_width = edge.stroke_width().unwrap_or(self.options.edge_stroke_width);
+        let stroke_color = edge.stroke_color().unwrap_or(&self.theme.edge_color);
 
         let Some(path_points) = edge.path_points() else {
             return Err(BackendError::InvalidLayout(edge.source_id()))
         };
         assert!(path_points.len() >= 2);
 
-        // Draw circles at both ends of the edge.
+        // Draw circles at ends of the edge which have circle markers.
         let start_point = path_points[0];
         let end_point = path_points.last().unwrap();
 
-        let start_circle = element::Circle::new()
-            .set("cx", start_point.x)
-            .set("cy", start_point.y)
-            .set("r", circle_radius)
-            .set("stroke", stroke_color.to_string())
-            .set("stroke-width", stroke_width)
-            .set("fill", background_color.to_string());
-        let end_circle = element::Circle::new()
-            .set("cx", end_point.x)
-            .set("cy", end_point.y)
-            .set("r", circle_radius)
-            .set("stroke", stroke_color.to_string())
-            .set("stroke-width", stroke_width)
-            .set("fill", background_color.to_string());
+        // Terminals are filled with the color under them to hide record borders.
+        let circle_declarations = |point: &Point| {
+            let fill = self
+                .fill_at(doc, point)
+                .map_or_else(|| "none".to_string(), |color| color.to_string());
+
+            [
+                ("stroke", stroke_color.to_string()),
+                ("stroke-width", stroke_width.to_string()),
+                ("fill", fill),
+            ]
+        };
+        let mut circles = vec![];
+
+        for (marker, point) in [(edge.start_marker(), &start_point), (edge.end_marker(), end_point)] {
+            if marker != mir::EdgeMarker::Circle {
+                continue;
+            }
+
+            let mut circle = element::Circle::new()
+                .set("cx", point.x)
+                .set("cy", point.y)
+                .set("r", circle_radius);
+
+            let roles = [
+                "edge_color",
+                "background_color",
+                "record_bg_color",
+                "header_bg_color",
+                "primary_key_row_color",
+                "foreign_key_row_color",
+                "stripe_color",
+            ];
+            sheet.apply(&mut circle, "edge-terminal", &circle_declarations(point), &roles);
+            circles.push(circle);
+        }
 
         // When you draw the line, trace edge's `path_points` and look at the points before and
         // after to determine the path to draw.
@@ -341,6 +1635,12 @@ impl SVGRenderer<'_> {
                 let d1 = bp.orthogonal_direction(&pt);
                 let d2 = pt.orthogonal_direction(&fp);
 
+                // Segments shared by two corners give half of their length to each, so curves
+                // of short segments don't overshoot.
+                let path_radius = path_radius
+                    .min(bp.distance(&pt) / 2.0)
+                    .min(pt.distance(&fp) / 2.0);
+
                 match (d1, d2) {
                     (Orientation::Up, Orientation::Up)
                     | (Orientation::Down, Orientation::Down)
@@ -510,22 +1810,246 @@ impl SVGRenderer<'_> {
             }
         }
 
-        let svg_path = element::Path::new()
-            .set("stroke", stroke_color.to_string())
-            .set("stroke-width", stroke_width)
-            .set("fill", "transparent")
-            .set("d", d.join(" "));
+        let mut svg_path = element::Path::new().set("d", d.join(" "));
+        let mut declarations = vec![
+            ("stroke", stroke_color.to_string()),
+            ("stroke-width", stroke_width.to_string()),
+            ("fill", "transparent".into()),
+        ];
+
+        // Dashes are proportional to the width, so they look the same on thick lines.
+        match edge.stroke_style() {
+            mir::StrokeStyle::Solid => {}
+            mir::StrokeStyle::Dashed => declarations.push((
+                "stroke-dasharray",
+                format!("{} {}", stroke_width * 4.0, stroke_width * 2.0),
+            )),
+            mir::StrokeStyle::Dotted => declarations.push((
+                "stroke-dasharray",
+                format!("{} {}", stroke_width, stroke_width * 2.0),
+            )),
+        }
+        sheet.apply(&mut svg_path, "edge", &declarations, &["edge_color"]);
+
+        for (attribute, marker) in [
+            ("marker-start", edge.start_marker()),
+            ("marker-end", edge.end_marker()),
+        ] {
+            if !matches!(marker, mir::EdgeMarker::None | mir::EdgeMarker::Circle) {
+                let marker_id = self.marker_id(marker, &self.edge_stroke(edge));
+
+                svg_path.assign(attribute, format!("url(#{})", marker_id));
+            }
+        }
+
+        Ok((svg_path, circles))
+    }
+
+    /// Creates a `<marker>` of an edge end, or returns `None` if `marker` isn't drawn as a marker.
+    ///
+    /// Markers are drawn in a 12x12 box whose right side touches a record, and
+    /// `orient="auto-start-reverse"` turns the box toward the record at both ends of an edge.
+    fn draw_marker(
+        &self,
+        sheet: &mut StyleSheet,
+        marker: mir::EdgeMarker,
+        stroke: &(String, f32),
+    ) -> Option<element::Marker> {
+        // ```svgbob
+        //  arrow   crow    one     many
+        // +-----+ +-----+ +-----+ +-----+
+        // |\    | |   / | |  | | | |  / |
+        // |  >  | |--<  | |  | | | |-<  |
+        // |/    | |   \ | |  | | | |  \ |
+        // +-----+ +-----+ +-----+ +-----+
+        // ```
+        let (d, fill) = match marker {
+            mir::EdgeMarker::None | mir::EdgeMarker::Circle => return None,
+            mir::EdgeMarker::Arrow => ("M0 1 L12 6 L0 11 Z", stroke.0.clone()),
+            mir::EdgeMarker::Crow => ("M0 6 L12 0 M0 6 L12 6 M0 6 L12 12", "none".into()),
+            mir::EdgeMarker::One => ("M4 0 L4 12 M8 0 L8 12", "none".into()),
+            mir::EdgeMarker::Many => ("M1 0 L1 12 M4 6 L12 0 M4 6 L12 6 M4 6 L12 12", "none".into()),
+        };
+        let mut path = element::Path::new().set("d", d);
+
+        sheet.apply(
+            &mut path,
+            &format!("marker-{}", marker),
+            &[
+                ("stroke", stroke.0.clone()),
+                ("stroke-width", stroke.1.to_string()),
+                ("fill", fill),
+            ],
+            &["edge_color"],
+        );
+
+        Some(
+            element::Marker::new()
+                .set("id", self.marker_id(marker, stroke))
+                .set("viewBox", "0 0 12 12")
+                .set("refX", 12)
+                .set("refY", 6)
+                .set("markerWidth", 12)
+                .set("markerHeight", 12)
+                .set("markerUnits", "userSpaceOnUse")
+                .set("orient", "auto-start-reverse")
+                .set("overflow", "visible")
+                .add(path),
+        )
+    }
+
+    /// Creates texts of the known cardinalities at ends of `edge`.
+    ///
+    /// A text is placed a little away from the port along the edge, so it doesn't cover a
+    /// marker, and beside the edge: above a horizontal end, or right of a vertical end (the
+    /// other side if the layout engine flips it).
+    ///
+    /// ```svgbob
+    ///          N
+    /// +------+ :       1
+    /// |      o-*-------*-+------+
+    /// +------+           |      |
+    ///                    +------+
+    /// ```
+    fn draw_cardinalities(&self, sheet: &mut StyleSheet, edge: &mir::EdgeData) -> Vec<element::Text> {
+        let Some(path_points) = edge.path_points().filter(|points| points.len() >= 2) else {
+            return vec![]
+        };
+        let n = path_points.len();
+        let ends = [
+            (edge.start_cardinality(), &path_points[0], &path_points[1]),
+            (edge.end_cardinality(), &path_points[n - 1], &path_points[n - 2]),
+        ];
+        let mut texts = vec![];
+
+        for ((cardinality, port, next), flip) in ends.into_iter().zip(edge.cardinality_flips()) {
+            let Some(cardinality) = cardinality else { continue };
+            let (origin, anchor) = metrics::cardinality_origin(port, next, flip);
+            let mut span = metrics::cardinality_span(cardinality);
+
+            span.color = Some(self.theme.edge_color.clone());
+            texts.push(self.draw_text(
+                sheet,
+                "edge-cardinality",
+                &["edge_color"],
+                &span,
+                origin,
+                Some(anchor.into()),
+            ));
+        }
+
+        texts
+    }
+
+    /// Creates a pill-shaped background and a text of the label of `edge`, or returns `None` if
+    /// the edge has no label.
+    ///
+    /// The label is placed at `label_center` of the edge if the layout engine has chosen it, or
+    /// else at the middle of the longest straight segment of the path which doesn't make the
+    /// label overlap records. If every segment does, the longest one is used.
+    fn draw_edge_label(
+        &self,
+        sheet: &mut StyleSheet,
+        doc: &mir::Document,
+        edge: &mir::EdgeData,
+    ) -> Option<(element::Rectangle, element::Text)> {
+        let label = edge.label()?;
+        let path_points = edge.path_points()?;
+
+        let span = mir::TextSpanBuilder::default()
+            .text(label)
+            .color(Some(self.theme.text_color.clone()))
+            .font_family(Some(mir::FontFamily::Monospace2))
+            .font_size(Some(metrics::EDGE_LABEL_FONT_SIZE))
+            .build()
+            .unwrap();
+        let size = metrics::edge_label_size(label);
+        let center = match edge.label_center() {
+            Some(center) => center,
+            None => {
+                let records: Vec<Rect> = doc
+                    .body()
+                    .children()
+                    .filter_map(|node_id| doc.get_node(node_id)?.rect())
+                    .collect();
+
+                metrics::edge_label_center(path_points, size, |pill| {
+                    records.iter().all(|rect| !rect.intersects(pill))
+                })?
+            }
+        };
+        let pill = metrics::edge_label_rect(center, size);
+
+        let mut pill_element = element::Rectangle::new()
+            .set("x", pill.min_x())
+            .set("y", pill.min_y())
+            .set("width", pill.width())
+            .set("height", pill.height())
+            .set("rx", pill.height() / 2.0)
+            .set("ry", pill.height() / 2.0);
+
+        // The pill is filled with the background, so the label stays readable over edges. It's
+        // left unfilled over a transparent background.
+        let fill = self
+            .background_color()
+            .map_or_else(|| "none".to_string(), |color| color.to_string());
+
+        sheet.apply(
+            &mut pill_element,
+            "edge-label-bg",
+            &[
+                ("fill", fill),
+                ("stroke", self.theme.edge_color.to_string()),
+                ("stroke-width", "1".to_string()),
+            ],
+            &["background_color", "edge_color"],
+        );
+
+        let roles = ["text_color"];
+        let text_element =
+            self.draw_text(sheet, "edge-label", &roles, &span, center, Some(SVGAnchor::Middle));
+
+        Some((pill_element, text_element))
+    }
+
+    /// Draws `label` in the top left corner of `view_box`.
+    fn draw_page_label(
+        &self,
+        sheet: &mut StyleSheet,
+        label: &str,
+        view_box: &Rect,
+    ) -> element::Text {
+        let span = mir::TextSpanBuilder::default()
+            .text(label)
+            .color(Some(self.theme.secondary_text_color.clone()))
+            .font_family(Some(mir::FontFamily::SansSerif3))
+            .font_size(Some(mir::FontSize::Small))
+            .build()
+            .unwrap();
+        let origin = Point::new(
+            view_box.min_x() + Self::PAGE_LABEL_MARGIN,
+            view_box.min_y()
+                + Self::PAGE_LABEL_MARGIN
+                + font::font_size_px(mir::FontSize::Small) / 2.0,
+        );
+
+        let roles = ["secondary_text_color"];
 
-        Ok((svg_path, start_circle, end_circle))
+        self.draw_text(sheet, "page-label", &roles, &span, origin, Some(SVGAnchor::Start))
     }
 
+    /// Draws the route graph, edge paths and node IDs in a group, which is hidden unless
+    /// `show_debug` is set. Each of them is a child group of its class (e.g. `debug-routes`).
     fn draw_debug_info(
         &self,
-        mut svg_doc: svg::Document,
         doc: &mir::Document,
         edge_route_graph: &RouteGraph,
-    ) -> svg::Document {
+    ) -> element::Group {
         let circle_radius = 4.0;
+        let mut routes = element::Group::new().set("class", "debug-routes");
+        let mut junctions = element::Group::new().set("class", "debug-junctions");
+        let mut paths = element::Group::new().set("class", "debug-paths");
+        let mut node_ids = element::Group::new().set("class", "debug-node-ids");
 
         // Draw route edges with direction
         for edge in edge_route_graph.edges() {
@@ -585,8 +2109,8 @@ impl SVGRenderer<'_> {
                     .join(" "),
             );
 
-            svg_doc.append(line);
-            svg_doc.append(arrow);
+            routes.append(line);
+            routes.append(arrow);
         }
 
         // Draw junction nodes
@@ -609,7 +2133,7 @@ impl SVGRenderer<'_> {
                 .set("font-family", "monospace")
                 .add(svg::node::Text::new(junction_id.to_string()));
 
-            svg_doc = svg_doc.add(circle).add(label);
+            junctions = junctions.add(circle).add(label);
         }
 
         // Draw shortest paths
@@ -624,7 +2148,7 @@ impl SVGRenderer<'_> {
                     .set("stroke", "white")
                     .set("stroke-width", 1)
                     .set("fill", "orange");
-                svg_doc.append(circle);
+                paths.append(circle);
             }
         }
 
@@ -643,9 +2167,15 @@ impl SVGRenderer<'_> {
                 .set("font-size", 12)
                 .set("font-family", "monospace")
                 .add(svg::node::Text::new(id.to_string()));
-            svg_doc.append(label);
+            node_ids.append(label);
         }
 
-        svg_doc
+        element::Group::new()
+            .set("id", self.element_id("debug"))
+            .set("visibility", if self.show_debug { "visible" } else { "hidden" })
+            .add(routes)
+            .add(junctions)
+            .add(paths)
+            .add(node_ids)
     }
 }
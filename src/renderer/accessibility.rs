@@ -0,0 +1,236 @@
+//! Accessibility metadata
+//!
+//! In accessible mode, the document, records, fields and edges are given `<title>` and `<desc>`
+//! elements and `role`/`aria-label` attributes, so screen readers can read the diagram as a set
+//! of entities, their fields and relations instead of a bag of shapes and texts.
+use super::{change_name, text_node, textwriter::Escape, SVGRenderer};
+use crate::mir;
+use std::collections::HashMap;
+use svg::{node::element, Node};
+
+/// A short name and a longer description of an element.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct Description {
+    pub title: String,
+    pub desc: Option<String>,
+}
+
+impl Description {
+    fn new(title: impl Into<String>, desc: Option<String>) -> Self {
+        Self {
+            title: title.into(),
+            desc,
+        }
+    }
+
+    /// Labels `element` with `role`, and inserts `<title>` and `<desc>` as its first children.
+    pub fn apply(&self, element: &mut element::Element, role: &str) {
+        let mut children: Vec<Box<dyn Node>> =
+            vec![Box::new(element::Title::new().add(text_node(&self.title)))];
+
+        if let Some(desc) = &self.desc {
+            children.push(Box::new(element::Description::new().add(text_node(desc))));
+        }
+
+        element.assign("role", role);
+        element.assign("aria-label", Escape::XmlAttribute.apply(&self.title).as_ref());
+        element.get_children_mut().splice(0..0, children);
+    }
+}
+
+impl SVGRenderer<'_> {
+    /// Describes the whole diagram: its title, and the numbers of entities and relations.
+    pub(super) fn describe_document(doc: &mir::Document) -> Description {
+        let title = match doc.body().kind() {
+            mir::ShapeKind::Body(mir::BodyShape {
+                title: Some(title), ..
+            }) => title.text.clone(),
+            _ => "Entity relationship diagram".to_string(),
+        };
+        let names: Vec<&str> = doc
+            .body()
+            .children()
+            .filter_map(|record_id| Self::record_name(doc, record_id))
+            .collect();
+        let n_edges = doc.edges().count();
+        let mut desc = plural(names.len(), "entity", "entities");
+
+        if !names.is_empty() {
+            desc.push_str(&format!(" ({})", names.join(", ")));
+        }
+        desc.push_str(&format!(
+            " and {}.",
+            plural(n_edges, "relation", "relations")
+        ));
+
+        Description::new(title, Some(desc))
+    }
+
+    pub(super) fn describe_record(doc: &mir::Document, record_id: mir::NodeId) -> Description {
+        let name = Self::record_name(doc, record_id).unwrap_or_default();
+        let n_fields = doc
+            .get_node(record_id)
+            .map_or(0, |node| node.children().len().saturating_sub(1));
+
+        Description::new(
+            format!("Entity {}", name),
+            Some(format!("{}.", plural(n_fields, "field", "fields"))),
+        )
+    }
+
+    /// Describes a field with its type, badges, default value and change.
+    pub(super) fn describe_field(field: &mir::FieldShape) -> Description {
+        let badges = field.badges.iter().map(|badge| {
+            badge
+                .description
+                .clone()
+                .unwrap_or_else(|| badge.text.clone())
+        });
+        let details: Vec<String> = field
+            .subtitle
+            .iter()
+            .map(|subtitle| subtitle.text.clone())
+            .chain(badges)
+            .chain(
+                field
+                    .default_value
+                    .iter()
+                    .map(|value| format!("default {}", value.text)),
+            )
+            .chain(field.change.map(|change| change_name(change).to_string()))
+            .collect();
+        let desc = (!details.is_empty()).then(|| details.join(", "));
+
+        Description::new(format!("Field {}", field.title.text), desc)
+    }
+
+    /// Describes a relation by qualified names of its ends (`names`), its label and
+    /// cardinalities.
+    pub(super) fn describe_edge(
+        names: &HashMap<mir::NodeId, String>,
+        edge: &mir::EdgeData,
+    ) -> Description {
+        let name = |node_id| names.get(&node_id).map_or("?", |name| &name[..]);
+        let title = format!(
+            "Relation from {} to {}",
+            name(edge.source_id()),
+            name(edge.target_id())
+        );
+        let cardinalities = match (edge.start_cardinality(), edge.end_cardinality()) {
+            (None, None) => None,
+            (start, end) => Some(format!(
+                "{} to {}",
+                start.map_or("?".to_string(), |c| c.to_string()),
+                end.map_or("?".to_string(), |c| c.to_string())
+            )),
+        };
+        let details: Vec<String> = edge
+            .label()
+            .map(str::to_string)
+            .into_iter()
+            .chain(cardinalities)
+            .collect();
+        let desc = (!details.is_empty()).then(|| details.join(", "));
+
+        Description::new(title, desc)
+    }
+
+    /// Qualified names of records (`users`) and fields (`users.id`), which are ends of edges.
+    pub(super) fn node_names(doc: &mir::Document) -> HashMap<mir::NodeId, String> {
+        let mut names = HashMap::new();
+
+        for record_id in doc.body().children() {
+            let Some(record_name) = Self::record_name(doc, record_id) else {
+                continue;
+            };
+            let Some(record_node) = doc.get_node(record_id) else {
+                continue;
+            };
+
+            for (index, field_id) in record_node.children().enumerate() {
+                let Some(mir::ShapeKind::Field(field)) = doc.get_node(field_id).map(|n| n.kind())
+                else {
+                    continue;
+                };
+                let name = if index == 0 {
+                    record_name.to_string()
+                } else {
+                    format!("{}.{}", record_name, field.title.text)
+                };
+
+                names.insert(field_id, name);
+            }
+            names.insert(record_id, record_name.to_string());
+        }
+        names
+    }
+
+    /// The name of a record, which is the title of its header field.
+    fn record_name(doc: &mir::Document, record_id: mir::NodeId) -> Option<&str> {
+        let header_id = doc.get_node(record_id)?.children().next()?;
+
+        match doc.get_node(header_id)?.kind() {
+            mir::ShapeKind::Field(header) => Some(&header.title.text),
+            _ => None,
+        }
+    }
+}
+
+fn plural(n: usize, singular: &str, plural: &str) -> String {
+    format!("{} {}", n, if n == 1 { singular } else { plural })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+
+    #[test]
+    fn describe() {
+        let (ast, _, _) = parse(
+            "erd Blog {
+users { id int PK }
+posts {
+    id int PK
+    user_id int FK
+    title text default \"untitled\"
+}
+posts.user_id o--o users.id [label=\"author\", cardinality=\"N:1\"]
+}",
+        );
+        let doc = ast.unwrap().into_mir();
+        let names = SVGRenderer::node_names(&doc);
+
+        assert_eq!(
+            SVGRenderer::describe_document(&doc),
+            Description::new(
+                "Blog",
+                Some("2 entities (users, posts) and 1 relation.".to_string())
+            )
+        );
+
+        let posts_id = doc.body().children().nth(1).unwrap();
+        assert_eq!(
+            SVGRenderer::describe_record(&doc, posts_id),
+            Description::new("Entity posts", Some("3 fields.".to_string()))
+        );
+
+        let title_id = doc.get_node(posts_id).unwrap().children().last().unwrap();
+        let mir::ShapeKind::Field(title) = doc.get_node(title_id).unwrap().kind() else {
+            panic!()
+        };
+        assert_eq!(
+            SVGRenderer::describe_field(title),
+            Description::new("Field title", Some("text, default untitled".to_string()))
+        );
+
+        let edge = doc.edges().next().unwrap();
+        assert_eq!(
+            SVGRenderer::describe_edge(&names, edge),
+            Description::new(
+                "Relation from posts.user_id to users.id",
+                Some("author, N to 1".to_string())
+            )
+        );
+    }
+}
@@ -0,0 +1,270 @@
+//! Presentation styles of SVG elements
+//!
+//! `StyleSheet` writes presentation properties (colors, fonts, strokes) of elements either as
+//! attributes, or as CSS rules of classes in a `<style>` element. In the latter mode, the first
+//! element of each class defines the rule, and later elements of the class only carry inline
+//! `style` declarations which differ from the rule (e.g. colors of type names).
+//!
+//! Class rules can refer to CSS custom properties instead of colors, which are switched by
+//! `@media (prefers-color-scheme: light)`, so that a single SVG follows the viewer's theme.
+//! Properties are keyed by theme colors (e.g. `edge_color`), because a theme can use the same
+//! color for roles which another theme tells apart.
+use svg::{node::element, Node};
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct StyleSheet {
+    /// `None` if properties are written as attributes.
+    rules: Option<Vec<Rule>>,
+    variables: Vec<Variable>,
+}
+
+/// A custom property which replaces values equal to `value` in class rules of elements drawn
+/// with the theme color `role`.
+#[derive(Debug, Clone)]
+struct Variable {
+    role: &'static str,
+    name: String,
+    value: String,
+    light_value: String,
+}
+
+#[derive(Debug, Clone)]
+struct Rule {
+    class: String,
+    declarations: Vec<(&'static str, String)>,
+}
+
+impl StyleSheet {
+    /// Creates a style sheet which writes properties as attributes.
+    pub fn inline() -> Self {
+        Self {
+            rules: None,
+            variables: vec![],
+        }
+    }
+
+    /// Creates a style sheet which collects properties into class rules.
+    pub fn classes() -> Self {
+        Self {
+            rules: Some(vec![]),
+            variables: vec![],
+        }
+    }
+
+    /// Defines a custom property `--{name}` of the theme color `role` of `value`, or
+    /// `light_value` if the viewer prefers a light color scheme. Values of declarations equal to
+    /// `value` are written as `var(--{name})` in class mode, if they are applied with `role`.
+    /// The first variable of a role wins.
+    pub fn define_variable(
+        &mut self,
+        role: &'static str,
+        name: &str,
+        value: String,
+        light_value: String,
+    ) {
+        if self.variables.iter().any(|variable| variable.role == role) {
+            return;
+        }
+        self.variables.push(Variable {
+            role,
+            name: name.to_string(),
+            value,
+            light_value,
+        });
+    }
+
+    /// Applies `declarations` (pairs of a property and a value) to `node` of `class`. `roles`
+    /// are the theme colors which the values may be taken from.
+    pub fn apply<N: Node>(
+        &mut self,
+        node: &mut N,
+        class: &str,
+        declarations: &[(&'static str, String)],
+        roles: &[&str],
+    ) {
+        let Some(rules) = &mut self.rules else {
+            for (property, value) in declarations {
+                node.assign(*property, value.clone());
+            }
+            return;
+        };
+
+        node.assign("class", class);
+
+        let declarations: Vec<_> = declarations
+            .iter()
+            .map(|(property, value)| {
+                let value = match self
+                    .variables
+                    .iter()
+                    .find(|variable| roles.contains(&variable.role) && variable.value == *value)
+                {
+                    Some(variable) => format!("var(--{})", variable.name),
+                    None => value.clone(),
+                };
+                (*property, value)
+            })
+            .collect();
+        let Some(rule) = rules.iter().find(|rule| rule.class == class) else {
+            rules.push(Rule {
+                class: class.to_string(),
+                declarations,
+            });
+            return;
+        };
+
+        // Presentation attributes lose to class rules, so overrides are written as inline styles.
+        // Properties of the rule which the element doesn't have are reset.
+        let overrides: Vec<String> = declarations
+            .iter()
+            .filter(|declaration| !rule.declarations.contains(declaration))
+            .map(|(property, value)| format!("{}: {}", property, value))
+            .chain(
+                rule.declarations
+                    .iter()
+                    .filter(|(property, _)| declarations.iter().all(|(p, _)| p != property))
+                    .map(|(property, _)| format!("{}: initial", property)),
+            )
+            .collect();
+
+        if !overrides.is_empty() {
+            node.assign("style", overrides.join("; "));
+        }
+    }
+
+    /// Returns a `<style>` element of the collected rules, or `None` in attribute mode.
+    pub fn to_style_element(&self) -> Option<element::Style> {
+        let rules = self.rules.as_ref()?;
+        let mut css: Vec<String> = vec![];
+
+        if !self.variables.is_empty() {
+            let properties = |light: bool| -> String {
+                self.variables
+                    .iter()
+                    .map(|variable| {
+                        let value = if light {
+                            &variable.light_value
+                        } else {
+                            &variable.value
+                        };
+                        format!(" --{}: {};", variable.name, value)
+                    })
+                    .collect()
+            };
+
+            css.push(format!("svg {{{} }}", properties(false)));
+            css.push(format!(
+                "@media (prefers-color-scheme: light) {{ svg {{{} }} }}",
+                properties(true)
+            ));
+        }
+        css.extend(rules.iter().map(|rule| {
+            let declarations: String = rule
+                .declarations
+                .iter()
+                .map(|(property, value)| format!(" {}: {};", property, value))
+                .collect();
+            format!(".{} {{{} }}", rule.class, declarations)
+        }));
+
+        Some(element::Style::new(css.join("\n")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn class_rules() {
+        let mut sheet = StyleSheet::classes();
+        let mut a = element::Text::new();
+        let mut b = element::Text::new();
+        let mut c = element::Text::new();
+
+        sheet.apply(
+            &mut a,
+            "title",
+            &[("fill", "white".into()), ("font-size", "small".into())],
+            &[],
+        );
+        sheet.apply(
+            &mut b,
+            "title",
+            &[("fill", "white".into()), ("font-size", "small".into())],
+            &[],
+        );
+        sheet.apply(&mut c, "title", &[("fill", "#FF0000".into())], &[]);
+
+        assert_eq!(a.to_string(), r#"<text class="title"/>"#);
+        assert_eq!(b.to_string(), r#"<text class="title"/>"#);
+        assert_eq!(
+            c.to_string(),
+            r#"<text class="title" style="fill: #FF0000; font-size: initial"/>"#
+        );
+        assert_eq!(
+            sheet.to_style_element().unwrap().to_string(),
+            "<style>\n.title { fill: white; font-size: small; }\n</style>"
+        );
+    }
+
+    #[test]
+    fn color_variables() {
+        let mut sheet = StyleSheet::classes();
+        let mut a = element::Text::new();
+        let mut b = element::Text::new();
+        let mut c = element::Circle::new();
+
+        sheet.define_variable("text_color", "text-color", "white".into(), "#1C1C1C".into());
+        sheet.define_variable(
+            "badge_color",
+            "badge-color",
+            "white".into(),
+            "#FF0000".into(),
+        );
+        sheet.apply(
+            &mut a,
+            "title",
+            &[("fill", "white".into())],
+            &["text_color"],
+        );
+        sheet.apply(
+            &mut b,
+            "title",
+            &[("fill", "#00FF00".into())],
+            &["text_color"],
+        );
+        sheet.apply(
+            &mut c,
+            "badge",
+            &[("fill", "white".into())],
+            &["badge_color"],
+        );
+
+        assert_eq!(
+            b.to_string(),
+            r#"<text class="title" style="fill: #00FF00"/>"#
+        );
+        assert_eq!(
+            sheet.to_style_element().unwrap().to_string(),
+            "<style>\n\
+             svg { --text-color: white; --badge-color: white; }\n\
+             @media (prefers-color-scheme: light) { svg { --text-color: #1C1C1C; \
+             --badge-color: #FF0000; } }\n\
+             .title { fill: var(--text-color); }\n\
+             .badge { fill: var(--badge-color); }\n\
+             </style>"
+        );
+    }
+
+    #[test]
+    fn inline_attributes() {
+        let mut sheet = StyleSheet::inline();
+        let mut a = element::Text::new();
+
+        sheet.apply(&mut a, "title", &[("fill", "white".into())], &[]);
+
+        assert_eq!(a.to_string(), r#"<text fill="white"/>"#);
+        assert!(sheet.to_style_element().is_none());
+    }
+}
@@ -0,0 +1,201 @@
+//! Whitespace of SVG output
+//!
+//! The `svg` crate writes each element and text node in its own line without indentation.
+//! `SVGFormat` rewrites the whitespace between tags: minified output is smaller for embedding,
+//! and pretty-printed output is indented for reviewing diffs. Attributes are always sorted by
+//! name, so all formats are stable.
+use derive_more::Display;
+use std::io::{self, Write};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Display)]
+pub enum SVGFormat {
+    /// An element or a text per line, as written by the `svg` crate.
+    #[default]
+    #[display(fmt = "default")]
+    Default,
+    /// No whitespace between tags.
+    #[display(fmt = "minified")]
+    Minified,
+    /// Children are indented by two spaces, and elements which contain only a text are written
+    /// in a line (e.g. `<text x="0" y="0">PK</text>`).
+    #[display(fmt = "pretty")]
+    Pretty,
+}
+
+impl SVGFormat {
+    pub const ALL: [SVGFormat; 3] = [SVGFormat::Default, SVGFormat::Minified, SVGFormat::Pretty];
+}
+
+impl FromStr for SVGFormat {
+    type Err = String;
+
+    /// Parses `default`, `minified` or `pretty`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|format| format.to_string() == s)
+            .ok_or_else(|| format!("unknown SVG format `{}`", s))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Token<'a> {
+    Open(&'a str),
+    Close(&'a str),
+    Empty(&'a str),
+    Text(&'a str),
+}
+
+/// Splits `svg` written by the `svg` crate into tags and texts. Newlines which the crate puts
+/// around texts are removed, and whitespace between tags is dropped.
+fn tokenize(svg: &str) -> Vec<Token<'_>> {
+    let mut tokens = vec![];
+    let mut rest = svg;
+
+    while !rest.is_empty() {
+        let Some(start) = rest.find('<') else {
+            push_text(&mut tokens, rest);
+            break;
+        };
+        push_text(&mut tokens, &rest[..start]);
+
+        // `>` in quoted attribute values doesn't close the tag.
+        let mut quote = None;
+        let end = rest[start..]
+            .char_indices()
+            .find(|&(_, c)| {
+                match (quote, c) {
+                    (None, '"' | '\'') => quote = Some(c),
+                    (Some(q), c) if q == c => quote = None,
+                    (None, '>') => return true,
+                    _ => {}
+                }
+                false
+            })
+            .map_or(rest.len(), |(i, _)| start + i + 1);
+        let tag = &rest[start..end];
+
+        tokens.push(if tag.starts_with("</") {
+            Token::Close(tag)
+        } else if tag.ends_with("/>") {
+            Token::Empty(tag)
+        } else {
+            Token::Open(tag)
+        });
+        rest = &rest[end..];
+    }
+
+    tokens
+}
+
+fn push_text<'a>(tokens: &mut Vec<Token<'a>>, text: &'a str) {
+    let text = text.strip_prefix('\n').unwrap_or(text);
+    let text = text.strip_suffix('\n').unwrap_or(text);
+
+    if !text.trim().is_empty() {
+        tokens.push(Token::Text(text));
+    }
+}
+
+/// Writes `svg` written by the `svg` crate in `format`.
+pub(crate) fn write_svg(svg: &str, format: SVGFormat, writer: &mut dyn Write) -> io::Result<()> {
+    let tokens = match format {
+        SVGFormat::Default => return writer.write_all(svg.as_bytes()),
+        SVGFormat::Minified | SVGFormat::Pretty => tokenize(svg),
+    };
+
+    if format == SVGFormat::Minified {
+        for token in tokens {
+            let (Token::Open(s) | Token::Close(s) | Token::Empty(s) | Token::Text(s)) = token;
+            writer.write_all(s.as_bytes())?;
+        }
+        return Ok(());
+    }
+
+    let mut depth = 0;
+    let mut i = 0;
+
+    while i < tokens.len() {
+        let indent = "  ".repeat(depth);
+
+        match tokens[i..] {
+            [Token::Open(open), Token::Text(text), Token::Close(close), ..] => {
+                writeln!(writer, "{}{}{}{}", indent, open, text, close)?;
+                i += 3;
+                continue;
+            }
+            [Token::Open(open), ..] => {
+                writeln!(writer, "{}{}", indent, open)?;
+                depth += 1;
+            }
+            [Token::Close(close), ..] => {
+                depth = depth.saturating_sub(1);
+                writeln!(writer, "{}{}", "  ".repeat(depth), close)?;
+            }
+            [Token::Empty(s) | Token::Text(s), ..] => writeln!(writer, "{}{}", indent, s)?,
+            [] => unreachable!(),
+        }
+        i += 1;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SVG: &str = "<svg viewBox=\"0, 0, 10, 10\">
+<g>
+<rect height=\"1\" width=\"1\"/>
+<text font-family=\"a>b\" x=\"1\">
+x &lt; y
+<title>
+line 1
+line 2
+</title>
+</text>
+</g>
+</svg>";
+
+    fn write(format: SVGFormat) -> String {
+        let mut bytes = vec![];
+
+        write_svg(SVG, format, &mut bytes).unwrap();
+        String::from_utf8(bytes).unwrap()
+    }
+
+    #[test]
+    fn formats() {
+        assert_eq!(write(SVGFormat::Default), SVG);
+        assert_eq!(
+            write(SVGFormat::Minified),
+            "<svg viewBox=\"0, 0, 10, 10\"><g><rect height=\"1\" width=\"1\"/>\
+             <text font-family=\"a>b\" x=\"1\">x &lt; y<title>line 1\nline 2</title></text>\
+             </g></svg>"
+        );
+        assert_eq!(
+            write(SVGFormat::Pretty),
+            "<svg viewBox=\"0, 0, 10, 10\">
+  <g>
+    <rect height=\"1\" width=\"1\"/>
+    <text font-family=\"a>b\" x=\"1\">
+      x &lt; y
+      <title>line 1
+line 2</title>
+    </text>
+  </g>
+</svg>
+"
+        );
+    }
+
+    #[test]
+    fn parse_format() {
+        for format in SVGFormat::ALL {
+            assert_eq!(format.to_string().parse(), Ok(format));
+        }
+        assert!("compact".parse::<SVGFormat>().is_err());
+    }
+}
@@ -0,0 +1,427 @@
+//! HTML backend
+//!
+//! Renders each record as a `<table>` positioned absolutely at its laid-out rectangle, and edges
+//! as an SVG laid over the tables. Unlike SVG texts, table cells can be selected and copied, and
+//! browsers expose them to assistive technologies as tables.
+//!
+//! ```svgbob
+//! +- div.seiren ---------------------------+
+//! |  +- table ------+      +- table ------+ |
+//! |  | users        |      | posts        | |
+//! |  | id   int  PK |<-----| user_id  FK  | |
+//! |  +--------------+      +--------------+ |
+//! |  (svg: edges, pointer-events: none)     |
+//! +-----------------------------------------+
+//! ```
+use super::{textwriter::Escape, textwriter::TextWriter, Background, Renderer, SVGRenderer};
+use crate::{
+    error::BackendError,
+    font,
+    geometry::{Point, Rect},
+    mir,
+    theme::Theme,
+};
+use std::io::Write;
+
+const STYLE: &str = "\
+.seiren { position: relative; overflow: hidden; }
+.seiren > .title { position: absolute; white-space: nowrap; }
+.seiren > table { position: absolute; table-layout: fixed; border-collapse: separate; \
+border-spacing: 0; overflow: hidden; }
+.seiren th, .seiren td { padding: 0 12px; overflow: hidden; white-space: nowrap; \
+text-overflow: ellipsis; }
+.seiren th { text-align: left; }
+.seiren .type, .seiren .badges { text-align: right; }
+.seiren .ordinal { display: inline-block; width: 24px; }
+.seiren .badge { display: inline-block; min-width: 23px; height: 23px; margin-left: 4px; \
+border-radius: 12px; line-height: 23px; text-align: center; font-family: Trebuchet MS,sans-serif; \
+font-size: xx-small; }
+.seiren > svg { position: absolute; left: 0; top: 0; pointer-events: none; }";
+
+#[derive(Debug, Default)]
+pub struct HtmlRenderer {
+    /// The area of the diagram. Defaults to the rectangle of the body.
+    pub view_box: Option<Rect>,
+    /// Writes only the diagram (`<style>` and `<div>`) for embedding into another page.
+    pub fragment: bool,
+    pub theme: Theme,
+    pub background: Background,
+}
+
+impl HtmlRenderer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn background_color(&self) -> Option<String> {
+        match &self.background {
+            Background::Theme => Some(self.theme.background_color.to_string()),
+            Background::Transparent => None,
+            Background::Color(color) => Some(color.to_string()),
+        }
+    }
+}
+
+impl Renderer for HtmlRenderer {
+    fn render(&self, doc: &mir::Document, writer: &mut dyn Write) -> Result<(), BackendError> {
+        let view_box = self
+            .view_box
+            .or_else(|| doc.body().rect())
+            .unwrap_or_else(Rect::zero);
+        let title = match doc.body().kind() {
+            mir::ShapeKind::Body(body) => body.title.as_ref(),
+            _ => None,
+        };
+        let mut w = TextWriter::new(writer).with_indent_unit("  ");
+
+        if self.fragment {
+            self.write_diagram(&mut w, doc, &view_box)?;
+            return Ok(());
+        }
+
+        w.line("<!DOCTYPE html>")?;
+        w.line("<html>")?;
+        w.line("<head>")?;
+        {
+            let mut w = w.indent();
+
+            w.line("<meta charset=\"utf-8\">")?;
+            if let Some(title) = title {
+                w.line(format_args!(
+                    "<title>{}</title>",
+                    Escape::Xml.apply(&title.text)
+                ))?;
+            }
+        }
+        w.line("</head>")?;
+        w.line("<body>")?;
+        self.write_diagram(&mut w.indent(), doc, &view_box)?;
+        w.line("</body>")?;
+        w.line("</html>")?;
+        Ok(())
+    }
+}
+
+impl HtmlRenderer {
+    /// Writes `<style>` and the `<div>` of the diagram.
+    fn write_diagram<W: Write>(
+        &self,
+        w: &mut TextWriter<W>,
+        doc: &mir::Document,
+        view_box: &Rect,
+    ) -> Result<(), BackendError> {
+        w.line("<style>")?;
+        for rule in STYLE.lines() {
+            w.line(rule)?;
+        }
+        w.line("</style>")?;
+
+        let mut container_style = format!(
+            "width: {}px; height: {}px;",
+            view_box.width(),
+            view_box.height()
+        );
+        if let Some(color) = self.background_color() {
+            container_style.push_str(&format!(" background: {};", color));
+        }
+        w.line(format_args!(
+            "<div class=\"seiren\" style=\"{}\">",
+            container_style
+        ))?;
+        {
+            let mut w = w.indent();
+
+            let body = doc.body();
+
+            if let (mir::ShapeKind::Body(body), Some(origin)) = (body.kind(), body.origin) {
+                if let Some(title) = &body.title {
+                    let offset = offset(view_box, &origin);
+                    let font_size = font::font_size_px(title.font_size.unwrap_or_default());
+
+                    w.line(format_args!(
+                        "<div class=\"title\" style=\"left: {}px; top: {}px; line-height: {}px; \
+                         {}\">{}</div>",
+                        offset.x,
+                        offset.y,
+                        font_size,
+                        text_style(title),
+                        Escape::Xml.apply(&title.text)
+                    ))?;
+                }
+            }
+
+            for record_id in doc.body().children() {
+                self.write_record(&mut w, doc, record_id, view_box)?;
+            }
+
+            // Edges are drawn over records.
+            let overlay = SVGRenderer {
+                view_box: Some(*view_box),
+                theme: self.theme.clone(),
+                background: self.background.clone(),
+                ..SVGRenderer::new()
+            };
+            for line in overlay
+                .render_edge_overlay(doc, view_box)?
+                .to_string()
+                .lines()
+            {
+                w.line(line)?;
+            }
+        }
+        w.line("</div>")?;
+        Ok(())
+    }
+
+    fn write_record<W: Write>(
+        &self,
+        w: &mut TextWriter<W>,
+        doc: &mir::Document,
+        record_id: mir::NodeId,
+        view_box: &Rect,
+    ) -> Result<(), BackendError> {
+        let Some(record_node) = doc.get_node(record_id) else {
+            return Ok(());
+        };
+        let mir::ShapeKind::Record(record) = record_node.kind() else {
+            return Ok(());
+        };
+        let rect = record_node
+            .rect()
+            .ok_or(BackendError::InvalidLayout(record_id))?;
+        let offset = offset(view_box, rect.origin());
+
+        let mut style = format!(
+            "left: {}px; top: {}px; width: {}px; height: {}px;",
+            offset.x,
+            offset.y,
+            rect.width(),
+            rect.height()
+        );
+        if let Some(bg_color) = &record.bg_color {
+            style.push_str(&format!(" background: {};", bg_color));
+        }
+        if let Some(border_color) = &record.border_color {
+            // Outlines don't take space from rows.
+            let line = if record.dashed { "dashed" } else { "solid" };
+            style.push_str(&format!(
+                " outline: 1px {} {}; outline-offset: -1px;",
+                line, border_color
+            ));
+        }
+        if record.rounded {
+            style.push_str(" border-radius: 6px;");
+        }
+
+        w.line(format_args!("<table style=\"{}\">", style))?;
+        {
+            let mut w = w.indent();
+
+            w.line(
+                "<colgroup><col style=\"width: 40%\"><col style=\"width: 40%\">\
+                 <col style=\"width: 20%\"></colgroup>",
+            )?;
+            for (index, field_id) in record_node.children().enumerate() {
+                let Some(field_node) = doc.get_node(field_id) else {
+                    continue;
+                };
+                let mir::ShapeKind::Field(field) = field_node.kind() else {
+                    continue;
+                };
+                let field_rect = field_node
+                    .rect()
+                    .ok_or(BackendError::InvalidLayout(field_id))?;
+                let url = field.url.as_ref().or(record.url.as_ref());
+
+                if index == 0 {
+                    w.line("<thead>")?;
+                    w.indent().line(format_args!(
+                        "<tr style=\"height: {}px\">\
+                         <th colspan=\"3\" style=\"{}{}\">{}</th></tr>",
+                        field_rect.height(),
+                        field
+                            .bg_color
+                            .as_ref()
+                            .map(|color| format!("background: {}; ", color))
+                            .unwrap_or_default(),
+                        text_style(&field.title),
+                        linked(&field.title.text, url)
+                    ))?;
+                    w.line("</thead>")?;
+                    w.line("<tbody>")?;
+                } else {
+                    self.write_field(&mut w.indent(), field, &field_rect, url)?;
+                }
+            }
+            if record_node.children().len() > 0 {
+                w.line("</tbody>")?;
+            }
+        }
+        w.line("</table>")?;
+        Ok(())
+    }
+
+    fn write_field<W: Write>(
+        &self,
+        w: &mut TextWriter<W>,
+        field: &mir::FieldShape,
+        rect: &Rect,
+        url: Option<&String>,
+    ) -> Result<(), BackendError> {
+        let mut row_style = format!("height: {}px;", rect.height());
+
+        if let Some(change) = field.change {
+            row_style.push_str(&format!(
+                " background: color-mix(in srgb, {} 20%, transparent);",
+                self.theme.change_color(change)
+            ));
+        } else if let Some(bg_color) = &field.bg_color {
+            row_style.push_str(&format!(" background: {};", bg_color));
+        }
+        let cell_style = field
+            .border_color
+            .as_ref()
+            .map(|color| format!("border-top: 1px solid {}; ", color))
+            .unwrap_or_default();
+
+        w.line(format_args!("<tr style=\"{}\">", row_style))?;
+        {
+            let mut w = w.indent();
+            let ordinal = field
+                .ordinal
+                .as_ref()
+                .map(|ordinal| {
+                    format!(
+                        "<span class=\"ordinal\" style=\"{}\">{}</span>",
+                        text_style(ordinal),
+                        Escape::Xml.apply(&ordinal.text)
+                    )
+                })
+                .unwrap_or_default();
+            let tooltip = field
+                .default_value
+                .as_ref()
+                .map(|value| {
+                    format!(
+                        " title=\"{}\"",
+                        Escape::XmlAttribute.apply(&format!("default: {}", value.text))
+                    )
+                })
+                .unwrap_or_default();
+
+            w.line(format_args!(
+                "<td class=\"name\" style=\"{}{}\"{}>{}{}</td>",
+                cell_style,
+                text_style(&field.title),
+                tooltip,
+                ordinal,
+                linked(&field.title.text, url)
+            ))?;
+            w.line(format_args!(
+                "<td class=\"type\" style=\"{}{}\">{}</td>",
+                cell_style,
+                field.subtitle.as_ref().map(text_style).unwrap_or_default(),
+                field
+                    .subtitle
+                    .as_ref()
+                    .map(|subtitle| Escape::Xml.apply(&subtitle.text).into_owned())
+                    .unwrap_or_default()
+            ))?;
+
+            let badges: String = field
+                .badges
+                .iter()
+                .map(|badge| {
+                    let mut style = String::new();
+
+                    if let Some(color) = &badge.color {
+                        style.push_str(&format!("color: {};", color));
+                    }
+                    if let Some(bg_color) = &badge.bg_color {
+                        style.push_str(&format!(" background: {};", bg_color));
+                    }
+                    let title = badge
+                        .description
+                        .as_ref()
+                        .map(|text| format!(" title=\"{}\"", Escape::XmlAttribute.apply(text)))
+                        .unwrap_or_default();
+
+                    format!(
+                        "<span class=\"badge\" style=\"{}\"{}>{}</span>",
+                        style.trim_start(),
+                        title,
+                        Escape::Xml.apply(&badge.text)
+                    )
+                })
+                .collect();
+            w.line(format_args!(
+                "<td class=\"badges\" style=\"{}\">{}</td>",
+                cell_style.trim_end(),
+                badges
+            ))?;
+        }
+        w.line("</tr>")?;
+        Ok(())
+    }
+}
+
+/// The position of `point` relative to the top left corner of `view_box`.
+fn offset(view_box: &Rect, point: &Point) -> Point {
+    Point::new(point.x - view_box.min_x(), point.y - view_box.min_y())
+}
+
+/// CSS declarations of the color and the font of `span`.
+fn text_style(span: &mir::TextSpan) -> String {
+    let mut style = String::new();
+
+    if let Some(color) = &span.color {
+        style.push_str(&format!("color: {}; ", color));
+    }
+    if let Some(font_family) = &span.font_family {
+        style.push_str(&format!("font-family: {}; ", font_family));
+    }
+    if let Some(font_weight) = &span.font_weight {
+        style.push_str(&format!("font-weight: {}; ", font_weight));
+    }
+    if let Some(font_size) = &span.font_size {
+        style.push_str(&format!("font-size: {}; ", font_size));
+    }
+    style.trim_end().to_string()
+}
+
+/// Escapes `text`, and wraps it in a link to `url` if any.
+fn linked(text: &str, url: Option<&String>) -> String {
+    let text = Escape::Xml.apply(text);
+
+    match url {
+        Some(url) => format!(
+            "<a href=\"{}\" style=\"color: inherit\">{}</a>",
+            Escape::XmlAttribute.apply(url),
+            text
+        ),
+        None => text.into_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::{NamedColor, WebColor};
+
+    #[test]
+    fn styles() {
+        let span = mir::TextSpan {
+            text: "id".to_string(),
+            color: Some(WebColor::Named(NamedColor::White)),
+            ..mir::TextSpan::default()
+        };
+
+        assert_eq!(text_style(&span), "color: white;");
+        assert_eq!(text_style(&mir::TextSpan::default()), "");
+        assert_eq!(linked("a < b", None), "a &lt; b");
+        assert_eq!(
+            linked("users", Some(&"/users?a&b".to_string())),
+            "<a href=\"/users?a&amp;b\" style=\"color: inherit\">users</a>"
+        );
+    }
+}
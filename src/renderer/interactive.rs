@@ -0,0 +1,95 @@
+//! Hover highlighting
+//!
+//! In interactive mode, elements of each field are grouped with a `data-node` attribute, and
+//! elements of each edge (the path, terminal circles, cardinalities and the label) with
+//! `data-source` and `data-target` attributes of the node IDs of its ends. A script adds the
+//! `highlight` class to the field under the pointer and to edges connected to it, and the style
+//! recolors them. Scripts run when the SVG is opened directly or inlined in HTML, but not when
+//! it's loaded by `<img>`.
+use super::{accessibility::Description, SVGRenderer};
+use crate::{geometry::Rect, mir};
+use svg::{node::element, Document, Node};
+
+const HIGHLIGHT_SCRIPT: &str = r#"
+(function () {
+  var root = document.currentScript.ownerSVGElement || document.documentElement;
+  var edges = root.querySelectorAll('[data-source]');
+  root.querySelectorAll('[data-node]').forEach(function (field) {
+    var id = field.getAttribute('data-node');
+    var targets = [field];
+    edges.forEach(function (edge) {
+      if (edge.getAttribute('data-source') === id || edge.getAttribute('data-target') === id) {
+        targets.push(edge);
+      }
+    });
+    field.addEventListener('mouseenter', function () {
+      targets.forEach(function (target) { target.classList.add('highlight'); });
+    });
+    field.addEventListener('mouseleave', function () {
+      targets.forEach(function (target) { target.classList.remove('highlight'); });
+    });
+  });
+})();
+"#;
+
+impl SVGRenderer<'_> {
+    const HIGHLIGHT_FILL_OPACITY: f32 = 0.2;
+    const HIGHLIGHT_STROKE_WIDTH: f32 = 3.0;
+
+    /// Creates a transparent rectangle over a field, so the field receives pointer events
+    /// between its texts.
+    pub(super) fn draw_hit_area(rect: &Rect) -> element::Rectangle {
+        element::Rectangle::new()
+            .set("class", "hit-area")
+            .set("x", rect.min_x())
+            .set("y", rect.min_y())
+            .set("width", rect.width())
+            .set("height", rect.height())
+            .set("fill", "transparent")
+    }
+
+    /// Appends elements of `edge` to `svg_doc`, as a group linked to its ends in interactive
+    /// mode, or labeled with `description` in accessible mode.
+    pub(super) fn append_edge_group(
+        &self,
+        svg_doc: &mut Document,
+        edge: &mir::EdgeData,
+        mut edge_svg: element::Group,
+        description: Option<Description>,
+    ) {
+        if self.interactive {
+            edge_svg.assign("data-source", edge.source_id().to_string());
+            edge_svg.assign("data-target", edge.target_id().to_string());
+        }
+        if let Some(description) = &description {
+            description.apply(&mut edge_svg, "img");
+        }
+        if self.interactive || description.is_some() {
+            svg_doc.append(edge_svg);
+        } else {
+            for child in edge_svg.get_children_mut().drain(..) {
+                svg_doc.append(child);
+            }
+        }
+    }
+
+    /// Rules of highlighted fields and edges. Presentation attributes lose to them.
+    pub(super) fn highlight_style(&self) -> element::Style {
+        let color = &self.theme.highlight_color;
+
+        element::Style::new(format!(
+            "[data-node].highlight .hit-area {{ fill: {}; fill-opacity: {}; }}\n\
+             [data-source].highlight path {{ stroke: {}; stroke-width: {}; }}\n\
+             [data-source].highlight circle {{ stroke: {}; }}",
+            color,
+            Self::HIGHLIGHT_FILL_OPACITY,
+            color,
+            Self::HIGHLIGHT_STROKE_WIDTH,
+            color
+        ))
+    }
+
+    pub(super) fn highlight_script() -> element::Script {
+        element::Script::new(HIGHLIGHT_SCRIPT.trim())
+    }
+}
@@ -0,0 +1,267 @@
+//! JSON backend
+//!
+//! Serializes a laid-out document, so external tools (e.g. web viewers, tests) can consume the
+//! layout without parsing SVG. Colors are written in CSS notation.
+use super::Renderer;
+use crate::error::BackendError;
+use crate::geometry::{Point, Rect, Size};
+use crate::mir::{
+    self, Cardinality, EdgeId, EdgeLayer, EdgeMarker, EntityKind, FieldChange, NodeId, ShapeKind,
+    StrokeStyle, TerminalPort,
+};
+use serde::Serialize;
+use std::io::{self, Write};
+
+#[derive(Debug, Default)]
+pub struct JsonRenderer {
+    pub view_box: Option<Rect>,
+    /// Writes indented JSON.
+    pub pretty: bool,
+}
+
+impl JsonRenderer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Renderer for JsonRenderer {
+    fn render(&self, doc: &mir::Document, writer: &mut dyn Write) -> Result<(), BackendError> {
+        let nodes = doc
+            .node_ids()
+            .filter_map(|node_id| {
+                doc.get_node(node_id)
+                    .map(|node| NodeJson::new(node_id, node))
+            })
+            .collect();
+        let edges = doc
+            .edge_ids()
+            .filter_map(|edge_id| EdgeJson::new(doc, edge_id))
+            .collect();
+        let json = DocumentJson {
+            view_box: self.view_box,
+            nodes,
+            edges,
+        };
+
+        if self.pretty {
+            serde_json::to_writer_pretty(writer, &json).map_err(io::Error::from)?;
+        } else {
+            serde_json::to_writer(writer, &json).map_err(io::Error::from)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize)]
+struct DocumentJson<'a> {
+    view_box: Option<Rect>,
+    nodes: Vec<NodeJson<'a>>,
+    edges: Vec<EdgeJson<'a>>,
+}
+
+#[derive(Serialize)]
+struct NodeJson<'a> {
+    id: NodeId,
+    #[serde(flatten)]
+    shape: ShapeJson<'a>,
+    origin: Option<Point>,
+    size: Option<Size>,
+    children: Vec<NodeId>,
+    terminal_ports: Vec<&'a TerminalPort>,
+}
+
+impl<'a> NodeJson<'a> {
+    fn new(id: NodeId, node: &'a mir::NodeData) -> Self {
+        let shape = match node.kind() {
+            ShapeKind::Body(body) => ShapeJson::Body {
+                n_columns: body.n_columns,
+                title: body.title.as_ref().map(TextJson::new),
+            },
+            ShapeKind::Record(record) => ShapeJson::Record {
+                rounded: record.rounded,
+                dashed: record.dashed,
+                bg_color: record.bg_color.as_ref().map(|c| c.to_string()),
+                border_color: record.border_color.as_ref().map(|c| c.to_string()),
+                url: record.url.as_deref(),
+                entity_kind: record.kind,
+            },
+            ShapeKind::Group(group) => ShapeJson::Group {
+                title: TextJson::new(&group.title),
+                bg_color: group.bg_color.as_ref().map(|c| c.to_string()),
+                border_color: group.border_color.as_ref().map(|c| c.to_string()),
+            },
+            ShapeKind::Field(field) => ShapeJson::Field {
+                ordinal: field.ordinal.as_ref().map(TextJson::new),
+                title: TextJson::new(&field.title),
+                subtitle: field.subtitle.as_ref().map(TextJson::new),
+                default_value: field.default_value.as_ref().map(TextJson::new),
+                badges: field.badges.iter().map(|badge| &badge.text[..]).collect(),
+                change: field.change,
+                bg_color: field.bg_color.as_ref().map(|c| c.to_string()),
+                border_color: field.border_color.as_ref().map(|c| c.to_string()),
+                url: field.url.as_deref(),
+            },
+            ShapeKind::Note(note) => ShapeJson::Note {
+                text: TextJson::new(&note.text),
+                bg_color: note.bg_color.as_ref().map(|c| c.to_string()),
+                max_width: note.max_width,
+                anchor: note.anchor,
+                lines: &note.lines,
+            },
+        };
+
+        Self {
+            id,
+            shape,
+            origin: node.origin,
+            size: node.size,
+            children: node.children().collect(),
+            terminal_ports: node.terminal_ports().collect(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+enum ShapeJson<'a> {
+    Body {
+        n_columns: Option<usize>,
+        title: Option<TextJson<'a>>,
+    },
+    Record {
+        rounded: bool,
+        dashed: bool,
+        bg_color: Option<String>,
+        border_color: Option<String>,
+        url: Option<&'a str>,
+        entity_kind: Option<EntityKind>,
+    },
+    Group {
+        title: TextJson<'a>,
+        bg_color: Option<String>,
+        border_color: Option<String>,
+    },
+    Note {
+        text: TextJson<'a>,
+        bg_color: Option<String>,
+        max_width: Option<f32>,
+        anchor: Option<NodeId>,
+        lines: &'a [String],
+    },
+    Field {
+        ordinal: Option<TextJson<'a>>,
+        title: TextJson<'a>,
+        subtitle: Option<TextJson<'a>>,
+        default_value: Option<TextJson<'a>>,
+        badges: Vec<&'a str>,
+        change: Option<FieldChange>,
+        bg_color: Option<String>,
+        border_color: Option<String>,
+        url: Option<&'a str>,
+    },
+}
+
+#[derive(Serialize)]
+struct TextJson<'a> {
+    text: &'a str,
+    color: Option<String>,
+}
+
+impl<'a> TextJson<'a> {
+    fn new(span: &'a mir::TextSpan) -> Self {
+        Self {
+            text: &span.text,
+            color: span.color.as_ref().map(|c| c.to_string()),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct EdgeJson<'a> {
+    id: EdgeId,
+    source: NodeId,
+    target: NodeId,
+    path: Option<&'a [Point]>,
+    start_marker: EdgeMarker,
+    end_marker: EdgeMarker,
+    start_cardinality: Option<Cardinality>,
+    end_cardinality: Option<Cardinality>,
+    label: Option<&'a str>,
+    stroke_style: StrokeStyle,
+    stroke_width: Option<f32>,
+    stroke_color: Option<String>,
+    layer: Option<EdgeLayer>,
+}
+
+impl<'a> EdgeJson<'a> {
+    fn new(doc: &'a mir::Document, edge_id: EdgeId) -> Option<Self> {
+        let edge = doc.edge(edge_id)?;
+
+        Some(Self {
+            id: edge_id,
+            source: edge.source_id(),
+            target: edge.target_id(),
+            path: edge.path_points(),
+            start_marker: edge.start_marker(),
+            end_marker: edge.end_marker(),
+            start_cardinality: edge.start_cardinality(),
+            end_cardinality: edge.end_cardinality(),
+            label: edge.label(),
+            stroke_style: edge.stroke_style(),
+            stroke_width: edge.stroke_width(),
+            stroke_color: edge.stroke_color().map(|c| c.to_string()),
+            layer: edge.layer(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::{LayoutEngine, SimpleLayoutEngine};
+    use crate::parser::parse;
+    use serde_json::Value;
+
+    #[test]
+    fn render_json() {
+        let (ast, _, _) = parse(
+            "erd {
+users { id int PK }
+posts { user_id int FK }
+posts.user_id o--o users.id
+}",
+        );
+        let mut doc = ast.unwrap().into_mir();
+        let mut engine = SimpleLayoutEngine::default();
+        let mut backend = JsonRenderer::new();
+
+        backend.view_box = engine.layout(&mut doc).view_box;
+
+        let mut bytes = vec![];
+        backend.render(&doc, &mut bytes).unwrap();
+        let json: Value = serde_json::from_slice(&bytes).unwrap();
+
+        let nodes = json["nodes"].as_array().unwrap();
+        let kinds: Vec<_> = nodes.iter().map(|n| n["kind"].as_str().unwrap()).collect();
+        assert_eq!(kinds.iter().filter(|k| **k == "record").count(), 2);
+        assert_eq!(kinds.iter().filter(|k| **k == "field").count(), 4);
+
+        let id_field = nodes.iter().find(|n| n["title"]["text"] == "id").unwrap();
+        assert_eq!(id_field["subtitle"]["text"], "int");
+        assert_eq!(id_field["badges"][0], "PK");
+        assert!(id_field["url"].is_null());
+        assert!(id_field["size"]["width"].as_f64().unwrap() > 0.0);
+
+        let edges = json["edges"].as_array().unwrap();
+        assert_eq!(edges.len(), 1);
+        assert!(edges[0]["path"].as_array().unwrap().len() >= 2);
+        assert_eq!(edges[0]["start_marker"], "circle");
+        assert_eq!(edges[0]["stroke_style"], "solid");
+        assert!(edges[0]["stroke_color"].is_null());
+        assert!(edges[0]["layer"].is_null());
+        assert!(edges[0]["label"].is_null());
+        assert!(edges[0]["start_cardinality"].is_null());
+        assert!(json["view_box"].is_object());
+    }
+}
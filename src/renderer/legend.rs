@@ -0,0 +1,324 @@
+//! Legend of badges and type colors
+//!
+//! The legend lists badges used in a document with their meanings, and colors of types shown
+//! as subtitles of fields. It's placed in a corner of the view box which no records, edges or
+//! the title occupy. If every corner is occupied, the view box is extended at the bottom.
+use super::{class_name, css::StyleSheet, SVGAnchor, SVGRenderer};
+use crate::{
+    color::WebColor,
+    font,
+    geometry::{Point, Rect, Size},
+    metrics, mir,
+};
+use svg::{node::element, Node};
+
+/// What a row of the legend explains.
+#[derive(Debug, Clone)]
+pub(super) enum LegendSample {
+    Badge(mir::Badge),
+    /// A color of type names.
+    Color(WebColor),
+}
+
+#[derive(Debug, Clone)]
+pub(super) struct LegendRow {
+    sample: LegendSample,
+    text: String,
+}
+
+impl SVGRenderer<'_> {
+    const LEGEND_ROW_HEIGHT: f32 = 35.0;
+    const LEGEND_PADDING_Y: f32 = 8.0;
+    /// The space between the legend and edges of the view box.
+    pub(super) const LEGEND_MARGIN: f32 = 16.0;
+    const LEGEND_SWATCH_RADIUS: f32 = 5.0;
+
+    /// Returns rows of the legend and its rectangle, or `None` if there is nothing to explain
+    /// or the view box is unknown.
+    pub(super) fn place_legend(&self, doc: &mir::Document) -> Option<(Vec<LegendRow>, Rect)> {
+        let view_box = self.view_box?;
+        let rows = self.legend_rows(doc);
+
+        if rows.is_empty() {
+            return None;
+        }
+
+        let size = self.legend_size(&rows);
+        let mut obstacles: Vec<Rect> = doc
+            .body()
+            .children()
+            .filter_map(|node_id| doc.get_node(node_id)?.rect())
+            .collect();
+        if let (mir::ShapeKind::Body(body), Some(origin)) = (doc.body().kind(), doc.body().origin) {
+            if let Some(title) = &body.title {
+                let font_size = font::font_size_px(title.font_size.unwrap_or_default());
+                obstacles.push(Rect::new(
+                    origin,
+                    Size::new(font::text_width(title), font_size),
+                ));
+            }
+        }
+        let segments: Vec<(Point, Point)> = doc
+            .edges()
+            .filter_map(|edge| edge.path_points())
+            .flat_map(|points| points.windows(2).map(|pair| (pair[0], pair[1])))
+            .collect();
+
+        let origin = legend_origin(&view_box, &size, &obstacles, &segments).unwrap_or_else(|| {
+            // Below everything
+            Point::new(view_box.min_x() + Self::LEGEND_MARGIN, view_box.max_y())
+        });
+
+        Some((rows, Rect::new(origin, size)))
+    }
+
+    /// Badges in the order of appearance, and colors of types used by fields.
+    fn legend_rows(&self, doc: &mir::Document) -> Vec<LegendRow> {
+        let fields: Vec<&mir::FieldShape> = doc
+            .node_ids()
+            .filter_map(|node_id| match doc.get_node(node_id)?.kind() {
+                mir::ShapeKind::Field(field) => Some(field),
+                _ => None,
+            })
+            .collect();
+        let mut rows: Vec<LegendRow> = vec![];
+
+        for badge in fields.iter().flat_map(|field| field.badges.iter()) {
+            let is_listed = rows.iter().any(|row| {
+                matches!(&row.sample, LegendSample::Badge(listed) if listed.text == badge.text)
+            });
+
+            if !is_listed {
+                rows.push(LegendRow {
+                    sample: LegendSample::Badge(badge.clone()),
+                    text: badge
+                        .description
+                        .clone()
+                        .unwrap_or_else(|| badge.text.clone()),
+                });
+            }
+        }
+
+        let theme = &self.theme;
+        let type_colors = [
+            (&theme.numeric_type_color, "numeric"),
+            (&theme.text_type_color, "text"),
+            (&theme.timestamp_type_color, "timestamp"),
+            (&theme.named_type_color, "other types"),
+        ];
+
+        for (color, text) in type_colors {
+            let is_used = fields.iter().any(|field| {
+                field
+                    .subtitle
+                    .as_ref()
+                    .and_then(|subtitle| subtitle.color.as_ref())
+                    .is_some_and(|c| c.to_string() == color.to_string())
+            });
+
+            if is_used {
+                rows.push(LegendRow {
+                    sample: LegendSample::Color(color.clone()),
+                    text: text.to_string(),
+                });
+            }
+        }
+
+        rows
+    }
+
+    fn legend_text(&self, text: &str) -> mir::TextSpan {
+        mir::TextSpanBuilder::default()
+            .text(text)
+            .color(Some(self.theme.text_color.clone()))
+            .font_family(Some(mir::FontFamily::Monospace2))
+            .font_size(Some(mir::FontSize::Small))
+            .build()
+            .unwrap()
+    }
+
+    /// The radius of badges in the legend, which is the same as in fields.
+    fn legend_badge_radius() -> f32 {
+        Self::LEGEND_ROW_HEIGHT / 2.0 - metrics::BADGE_MARGIN_Y
+    }
+
+    fn legend_size(&self, rows: &[LegendRow]) -> Size {
+        let text_width = rows
+            .iter()
+            .map(|row| font::text_width(&self.legend_text(&row.text)))
+            .fold(0.0, f32::max);
+
+        Size::new(
+            metrics::PADDING_X * 3.0 + Self::legend_badge_radius() * 2.0 + text_width,
+            Self::LEGEND_PADDING_Y * 2.0 + Self::LEGEND_ROW_HEIGHT * rows.len() as f32,
+        )
+    }
+
+    /// Creates the legend in `rect`.
+    ///
+    /// ```svgbob
+    /// +-----------------+
+    /// | (PK) primary key|
+    /// | (FK) foreign key|
+    /// |  o   numeric    |
+    /// +-----------------+
+    /// ```
+    pub(super) fn draw_legend(
+        &self,
+        sheet: &mut StyleSheet,
+        rows: &[LegendRow],
+        rect: &Rect,
+    ) -> element::Group {
+        let mut group = element::Group::new();
+        let mut background = element::Rectangle::new()
+            .set("x", rect.min_x())
+            .set("y", rect.min_y())
+            .set("width", rect.width())
+            .set("height", rect.height())
+            .set("rx", self.options.border_radius)
+            .set("ry", self.options.border_radius);
+
+        sheet.apply(
+            &mut background,
+            "legend",
+            &[
+                ("fill", self.theme.record_bg_color.to_string()),
+                ("stroke", self.theme.record_border_color.to_string()),
+            ],
+            &["record_bg_color", "record_border_color"],
+        );
+        group.append(background);
+
+        let radius = Self::legend_badge_radius();
+        let cx = rect.min_x() + metrics::PADDING_X + radius;
+        let text_x = cx + radius + metrics::PADDING_X;
+
+        for (i, row) in rows.iter().enumerate() {
+            let cy =
+                rect.min_y() + Self::LEGEND_PADDING_Y + Self::LEGEND_ROW_HEIGHT * (i as f32 + 0.5);
+
+            match &row.sample {
+                LegendSample::Badge(badge) => {
+                    let class = format!("badge-{}", class_name(&badge.text));
+
+                    if let Some(bg_color) = &badge.bg_color {
+                        let mut circle = element::Circle::new()
+                            .set("cx", cx)
+                            .set("cy", cy)
+                            .set("r", radius);
+
+                        let declarations = [("fill", bg_color.to_string())];
+                        sheet.apply(&mut circle, &class, &declarations, &Self::BADGE_BG_ROLES);
+                        group.append(circle);
+                    }
+                    group.append(self.draw_text(
+                        sheet,
+                        &format!("{}-text", class),
+                        &Self::BADGE_TEXT_ROLES,
+                        &badge.into_text_span(),
+                        Point::new(cx, cy),
+                        Some(SVGAnchor::Middle),
+                    ));
+                }
+                LegendSample::Color(color) => {
+                    let mut swatch = element::Circle::new()
+                        .set("cx", cx)
+                        .set("cy", cy)
+                        .set("r", Self::LEGEND_SWATCH_RADIUS);
+
+                    let declarations = [("fill", color.to_string())];
+                    sheet.apply(
+                        &mut swatch,
+                        "legend-swatch",
+                        &declarations,
+                        &Self::TYPE_COLOR_ROLES,
+                    );
+                    group.append(swatch);
+                }
+            }
+
+            group.append(self.draw_text(
+                sheet,
+                "legend-text",
+                &["text_color"],
+                &self.legend_text(&row.text),
+                Point::new(text_x, cy),
+                Some(SVGAnchor::Start),
+            ));
+        }
+
+        group
+    }
+}
+
+/// Returns the origin of a box of `size` in the first free corner of `view_box`: top right,
+/// bottom right, bottom left, then top left. A corner is free if the box overlaps neither
+/// `obstacles` nor `segments`.
+fn legend_origin(
+    view_box: &Rect,
+    size: &Size,
+    obstacles: &[Rect],
+    segments: &[(Point, Point)],
+) -> Option<Point> {
+    let margin = SVGRenderer::LEGEND_MARGIN;
+    let (left, right) = (
+        view_box.min_x() + margin,
+        view_box.max_x() - margin - size.width,
+    );
+    let (top, bottom) = (
+        view_box.min_y() + margin,
+        view_box.max_y() - margin - size.height,
+    );
+
+    [(right, top), (right, bottom), (left, bottom), (left, top)]
+        .into_iter()
+        .map(|(x, y)| Rect::new(Point::new(x, y), *size))
+        .filter(|rect| {
+            rect.min_x() >= view_box.min_x()
+                && rect.min_y() >= view_box.min_y()
+                && rect.max_x() <= view_box.max_x()
+                && rect.max_y() <= view_box.max_y()
+        })
+        .find(|rect| {
+            obstacles.iter().all(|obstacle| !obstacle.intersects(rect))
+                && segments.iter().all(|(a, b)| !rect.intersects_line(a, b))
+        })
+        .map(|rect| *rect.origin())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn free_corner() {
+        let view_box = Rect::new(Point::zero(), Size::new(400.0, 300.0));
+        let size = Size::new(100.0, 50.0);
+        let record = |x: f32, y: f32| Rect::new(Point::new(x, y), Size::new(150.0, 150.0));
+
+        // top right
+        assert_eq!(
+            legend_origin(&view_box, &size, &[record(0.0, 0.0)], &[]),
+            Some(Point::new(284.0, 16.0))
+        );
+        // bottom right, since an edge passes through the top right corner
+        let edge = (Point::new(200.0, 40.0), Point::new(400.0, 40.0));
+        assert_eq!(
+            legend_origin(&view_box, &size, &[record(0.0, 0.0)], &[edge]),
+            Some(Point::new(284.0, 234.0))
+        );
+        // no free corners
+        let records = [
+            record(0.0, 0.0),
+            record(250.0, 0.0),
+            record(0.0, 150.0),
+            record(250.0, 150.0),
+        ];
+        assert_eq!(legend_origin(&view_box, &size, &records, &[]), None);
+        // too large
+        assert_eq!(
+            legend_origin(&view_box, &Size::new(500.0, 50.0), &[], &[]),
+            None
+        );
+    }
+}
@@ -0,0 +1,122 @@
+//! Tiled pages
+//!
+//! Splits the view box of a large diagram into a grid of pages, so that it can be printed or
+//! reviewed piecewise. Each page is rendered with its own view box, and neighboring pages
+//! overlap, so edges crossing the border of pages can be followed.
+use crate::geometry::{Point, Rect, Size};
+use derive_more::Display;
+use std::str::FromStr;
+
+/// The number of pages in each direction (e.g. `3x2` is 3 columns and 2 rows).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display)]
+#[display(fmt = "{}x{}", columns, rows)]
+pub struct PageGrid {
+    pub columns: usize,
+    pub rows: usize,
+}
+
+/// A part of the view box of a diagram.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Page {
+    /// 1-based
+    pub row: usize,
+    /// 1-based
+    pub column: usize,
+    pub view_box: Rect,
+    /// Drawn at the top left corner of the page (e.g. `2/6 (row 1, column 2)`).
+    pub label: String,
+}
+
+impl PageGrid {
+    pub fn new(columns: usize, rows: usize) -> Self {
+        Self { columns, rows }
+    }
+
+    /// Splits `view_box` into pages in row-major order. Each page is extended by `overlap`
+    /// into its neighbors, but not beyond `view_box`.
+    pub fn pages(&self, view_box: &Rect, overlap: f32) -> Vec<Page> {
+        let n_pages = self.columns * self.rows;
+        let tile_width = view_box.width() / self.columns as f32;
+        let tile_height = view_box.height() / self.rows as f32;
+        let mut pages = Vec::with_capacity(n_pages);
+
+        for row in 0..self.rows {
+            for column in 0..self.columns {
+                let min_x =
+                    (view_box.min_x() + tile_width * column as f32 - overlap).max(view_box.min_x());
+                let min_y =
+                    (view_box.min_y() + tile_height * row as f32 - overlap).max(view_box.min_y());
+                let max_x = (view_box.min_x() + tile_width * (column + 1) as f32 + overlap)
+                    .min(view_box.max_x());
+                let max_y = (view_box.min_y() + tile_height * (row + 1) as f32 + overlap)
+                    .min(view_box.max_y());
+
+                pages.push(Page {
+                    row: row + 1,
+                    column: column + 1,
+                    view_box: Rect::new(
+                        Point::new(min_x, min_y),
+                        Size::new(max_x - min_x, max_y - min_y),
+                    ),
+                    label: format!(
+                        "{}/{} (row {}, column {})",
+                        pages.len() + 1,
+                        n_pages,
+                        row + 1,
+                        column + 1
+                    ),
+                });
+            }
+        }
+
+        pages
+    }
+}
+
+impl FromStr for PageGrid {
+    type Err = String;
+
+    /// Parses `{columns}x{rows}` (e.g. `3x2`). Both must be positive.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid page grid `{}` (expected e.g. `3x2`)", s);
+        let (columns, rows) = s.split_once('x').ok_or_else(invalid)?;
+        let columns: usize = columns.trim().parse().map_err(|_| invalid())?;
+        let rows: usize = rows.trim().parse().map_err(|_| invalid())?;
+
+        if columns == 0 || rows == 0 {
+            return Err(invalid());
+        }
+        Ok(Self::new(columns, rows))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_page_grid() {
+        assert_eq!("3x2".parse(), Ok(PageGrid::new(3, 2)));
+        assert_eq!(PageGrid::new(3, 2).to_string(), "3x2");
+        assert!("3".parse::<PageGrid>().is_err());
+        assert!("0x2".parse::<PageGrid>().is_err());
+    }
+
+    #[test]
+    fn split_pages() {
+        let view_box = Rect::new(Point::zero(), Size::new(300.0, 200.0));
+        let pages = PageGrid::new(3, 2).pages(&view_box, 10.0);
+
+        assert_eq!(pages.len(), 6);
+        assert_eq!((pages[1].row, pages[1].column), (1, 2));
+        assert_eq!(pages[1].label, "2/6 (row 1, column 2)");
+        assert_eq!(
+            pages[0].view_box,
+            Rect::new(Point::zero(), Size::new(110.0, 110.0))
+        );
+        assert_eq!(
+            pages[4].view_box,
+            Rect::new(Point::new(90.0, 90.0), Size::new(120.0, 110.0))
+        );
+    }
+}
@@ -0,0 +1,513 @@
+//! Text-art backend
+//!
+//! Draws records and edges with box-drawing characters on a grid of character cells, for
+//! terminals, commit messages and code comments. Positions are taken from the computed layout,
+//! scaled by `TextRenderer::CELL_WIDTH` and `TextRenderer::CELL_HEIGHT`.
+//!
+//! ```text
+//! ╭─────────────────────────────╮       ╭─────────────────────────────╮
+//! │ users                       │       │ posts                       │
+//! ├─────────────────────────────┤       ├─────────────────────────────┤
+//! │ id                   int PK ├○──┐   │ id                   int PK │
+//! ╰─────────────────────────────╯   │   │                             │
+//!                                   └──○┤ user_id              int FK │
+//!                                       ╰─────────────────────────────╯
+//! ```
+use super::Renderer;
+use crate::{
+    error::BackendError,
+    font,
+    geometry::{Orientation, Point, Rect},
+    mir,
+};
+use derive_more::Display;
+use std::io::Write;
+use std::str::FromStr;
+
+/// Characters to draw lines with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Display)]
+pub enum TextCharset {
+    /// Box-drawing characters (e.g. `┌`, `─` and `┤`).
+    #[default]
+    #[display(fmt = "unicode")]
+    Unicode,
+    /// `+`, `-` and `|`, for fonts and tools without box-drawing characters.
+    #[display(fmt = "ascii")]
+    Ascii,
+}
+
+impl TextCharset {
+    pub const ALL: [TextCharset; 2] = [TextCharset::Unicode, TextCharset::Ascii];
+}
+
+impl FromStr for TextCharset {
+    type Err = String;
+
+    /// Parses `unicode` or `ascii`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|charset| charset.to_string() == s)
+            .ok_or_else(|| format!("unknown charset `{}`", s))
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct TextRenderer {
+    /// The area of the diagram. Defaults to the rectangle of the body.
+    pub view_box: Option<Rect>,
+    pub charset: TextCharset,
+}
+
+impl TextRenderer {
+    /// The width of a character cell in the layout coordinates.
+    pub const CELL_WIDTH: f32 = 10.0;
+    /// The height of a character cell. A field row is two cells high, so texts are drawn in
+    /// every other line.
+    pub const CELL_HEIGHT: f32 = 17.5;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Draws `doc` on a canvas which covers `view_box`.
+    fn draw(&self, doc: &mir::Document, view_box: &Rect) -> Canvas {
+        let cell = |point: &Point| {
+            (
+                ((point.y - view_box.min_y()) / Self::CELL_HEIGHT).round() as isize,
+                ((point.x - view_box.min_x()) / Self::CELL_WIDTH).round() as isize,
+            )
+        };
+        let mut canvas = Canvas::new(
+            (view_box.height() / Self::CELL_HEIGHT).ceil() as usize + 1,
+            (view_box.width() / Self::CELL_WIDTH).ceil() as usize + 1,
+        );
+
+        if let (mir::ShapeKind::Body(body), Some(origin)) = (doc.body().kind(), doc.body().origin) {
+            if let Some(title) = &body.title {
+                let font_size = font::font_size_px(title.font_size.unwrap_or_default());
+                let (row, col) = cell(&Point::new(origin.x, origin.y + font_size / 2.0));
+
+                canvas.text(row, col, &title.text);
+            }
+        }
+
+        for record_id in doc.body().children() {
+            let Some(record_node) = doc.get_node(record_id) else {
+                continue;
+            };
+            let mir::ShapeKind::Record(record) = record_node.kind() else {
+                continue;
+            };
+            let Some(rect) = record_node.rect() else {
+                continue;
+            };
+            let (top, left) = cell(rect.origin());
+            let (bottom, right) = cell(&Point::new(rect.max_x(), rect.max_y()));
+            let style = if record.dashed {
+                LineStyle::Dashed
+            } else if record.rounded {
+                LineStyle::Rounded
+            } else {
+                LineStyle::Solid
+            };
+
+            canvas.rect(top, left, bottom, right, style);
+
+            for (index, field_id) in record_node.children().enumerate() {
+                let Some(field_node) = doc.get_node(field_id) else {
+                    continue;
+                };
+                let mir::ShapeKind::Field(field) = field_node.kind() else {
+                    continue;
+                };
+                let Some(field_rect) = field_node.rect() else {
+                    continue;
+                };
+                let (row, _) = cell(&Point::new(field_rect.min_x(), field_rect.mid_y()));
+                let width = (right - left - 3).max(0) as usize;
+
+                if index == 0 {
+                    canvas.text(
+                        row,
+                        left + 2,
+                        &truncate(&field.title.text, width, self.charset),
+                    );
+                    if bottom > row + 1 {
+                        canvas.line(row + 1, left, row + 1, right, style.unrounded());
+                    }
+                    continue;
+                }
+                if let Some(change) = field.change {
+                    let mark = match (change, self.charset) {
+                        (mir::FieldChange::Removed, TextCharset::Ascii) => "-".to_string(),
+                        _ => change.to_string(),
+                    };
+                    canvas.text(row, left + 1, &mark);
+                }
+                canvas.text(row, left + 2, &self.field_text(field, width));
+            }
+        }
+
+        for edge in doc.edges() {
+            let Some(path_points) = edge.path_points() else {
+                continue;
+            };
+            let cells: Vec<(isize, isize)> = path_points.iter().map(cell).collect();
+            let style = match edge.stroke_style() {
+                mir::StrokeStyle::Solid => LineStyle::Solid,
+                mir::StrokeStyle::Dashed | mir::StrokeStyle::Dotted => LineStyle::Dashed,
+            };
+
+            for pair in cells.windows(2) {
+                canvas.line(pair[0].0, pair[0].1, pair[1].0, pair[1].1, style);
+            }
+            let reversed: Vec<_> = cells.iter().rev().copied().collect();
+
+            self.draw_marker(&mut canvas, &cells, edge.start_marker());
+            self.draw_marker(&mut canvas, &reversed, edge.end_marker());
+
+            // A label is written over the middle of the longest segment.
+            let Some(label) = edge.label() else { continue };
+            let Some((a, b)) = cells
+                .windows(2)
+                .map(|pair| (pair[0], pair[1]))
+                .max_by_key(|(a, b)| (a.0 - b.0).abs() + (a.1 - b.1).abs())
+            else {
+                continue;
+            };
+            let n_chars = label.chars().count() as isize;
+
+            canvas.text((a.0 + b.0) / 2, (a.1 + b.1) / 2 - n_chars / 2, label);
+        }
+
+        canvas
+    }
+
+    /// The name of a field on the left, and its type and badges on the right of `width` cells.
+    fn field_text(&self, field: &mir::FieldShape, width: usize) -> String {
+        let right: Vec<&str> = field
+            .subtitle
+            .iter()
+            .map(|subtitle| &subtitle.text[..])
+            .chain(field.badges.iter().map(|badge| &badge.text[..]))
+            .collect();
+        let right = right.join(" ");
+        let right_width = right.chars().count();
+
+        if right_width + 2 > width {
+            return truncate(&field.title.text, width, self.charset);
+        }
+
+        let name = truncate(&field.title.text, width - right_width - 1, self.charset);
+        let padding = width - right_width - name.chars().count();
+
+        format!("{}{}{}", name, " ".repeat(padding), right)
+    }
+
+    /// Draws the marker of the end of an edge at `cells[0]`, in the cell next to the end.
+    fn draw_marker(&self, canvas: &mut Canvas, cells: &[(isize, isize)], marker: mir::EdgeMarker) {
+        let Some(&(row, col)) = cells.first() else {
+            return;
+        };
+        let Some(direction) = cells
+            .iter()
+            .skip(1)
+            .find_map(|&next| direction(row, col, next))
+        else {
+            return;
+        };
+        let (dy, dx) = match direction {
+            Orientation::Up => (-1, 0),
+            Orientation::Down => (1, 0),
+            Orientation::Left => (0, -1),
+            Orientation::Right => (0, 1),
+        };
+        let horizontal = dy == 0;
+        let ascii = self.charset == TextCharset::Ascii;
+        // A crow's foot opens toward the record, and an arrow points to it.
+        let crow = match direction {
+            Orientation::Right => '>',
+            Orientation::Left => '<',
+            Orientation::Down if ascii => 'V',
+            Orientation::Down => '∨',
+            Orientation::Up if ascii => '^',
+            Orientation::Up => '∧',
+        };
+        let glyphs: &[char] = match (marker, ascii) {
+            (mir::EdgeMarker::None, _) => &[],
+            (mir::EdgeMarker::Circle, false) => &['○'],
+            (mir::EdgeMarker::Circle, true) => &['o'],
+            (mir::EdgeMarker::Arrow, _) => match (direction, ascii) {
+                (Orientation::Right, false) => &['◀'],
+                (Orientation::Left, false) => &['▶'],
+                (Orientation::Down, false) => &['▲'],
+                (Orientation::Up, false) => &['▼'],
+                (Orientation::Right, true) => &['<'],
+                (Orientation::Left, true) => &['>'],
+                (Orientation::Down, true) => &['^'],
+                (Orientation::Up, true) => &['v'],
+            },
+            (mir::EdgeMarker::Crow, _) => &[crow],
+            (mir::EdgeMarker::One, false) if horizontal => &['╫'],
+            (mir::EdgeMarker::One, false) => &['╪'],
+            (mir::EdgeMarker::One, true) if horizontal => &['|'],
+            (mir::EdgeMarker::One, true) => &['='],
+            (mir::EdgeMarker::Many, false) => &[crow, '┼'],
+            (mir::EdgeMarker::Many, true) => &[crow, '+'],
+        };
+
+        for (i, glyph) in glyphs.iter().enumerate() {
+            let step = i as isize + 1;
+
+            canvas.glyph(row + dy * step, col + dx * step, *glyph);
+        }
+    }
+}
+
+impl Renderer for TextRenderer {
+    fn render(&self, doc: &mir::Document, writer: &mut dyn Write) -> Result<(), BackendError> {
+        let view_box = self
+            .view_box
+            .or_else(|| doc.body().rect())
+            .unwrap_or_else(Rect::zero);
+
+        for line in self.draw(doc, &view_box).lines(self.charset) {
+            writeln!(writer, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+/// The direction from `(row, col)` to `next`, if they are in a row or a column.
+fn direction(row: isize, col: isize, next: (isize, isize)) -> Option<Orientation> {
+    match (next.0 - row, next.1 - col) {
+        (0, 0) => None,
+        (0, dx) if dx > 0 => Some(Orientation::Right),
+        (0, _) => Some(Orientation::Left),
+        (dy, 0) if dy > 0 => Some(Orientation::Down),
+        (_, 0) => Some(Orientation::Up),
+        _ => None,
+    }
+}
+
+/// Truncates `text` to `width` characters with an ellipsis.
+fn truncate(text: &str, width: usize, charset: TextCharset) -> String {
+    if text.chars().count() <= width {
+        return text.to_string();
+    }
+    if width == 0 {
+        return String::new();
+    }
+
+    let ellipsis = match charset {
+        TextCharset::Unicode => '…',
+        TextCharset::Ascii => '~',
+    };
+    text.chars().take(width - 1).chain([ellipsis]).collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LineStyle {
+    Solid,
+    Dashed,
+    Rounded,
+}
+
+impl LineStyle {
+    fn unrounded(self) -> Self {
+        match self {
+            LineStyle::Rounded => LineStyle::Solid,
+            style => style,
+        }
+    }
+}
+
+const UP: u8 = 1;
+const DOWN: u8 = 2;
+const LEFT: u8 = 4;
+const RIGHT: u8 = 8;
+
+/// A cell has lines to some of its sides, or a character which hides them.
+#[derive(Debug, Clone, Copy)]
+struct Cell {
+    sides: u8,
+    style: LineStyle,
+    glyph: Option<char>,
+}
+
+#[derive(Debug)]
+struct Canvas {
+    n_cols: usize,
+    cells: Vec<Cell>,
+}
+
+impl Canvas {
+    fn new(n_rows: usize, n_cols: usize) -> Self {
+        let cell = Cell {
+            sides: 0,
+            style: LineStyle::Solid,
+            glyph: None,
+        };
+
+        Self {
+            n_cols,
+            cells: vec![cell; n_rows * n_cols],
+        }
+    }
+
+    fn cell_mut(&mut self, row: isize, col: isize) -> Option<&mut Cell> {
+        if row < 0 || col < 0 || col as usize >= self.n_cols {
+            return None;
+        }
+        self.cells
+            .get_mut(row as usize * self.n_cols + col as usize)
+    }
+
+    fn glyph(&mut self, row: isize, col: isize, glyph: char) {
+        if let Some(cell) = self.cell_mut(row, col) {
+            cell.glyph = Some(glyph);
+        }
+    }
+
+    fn text(&mut self, row: isize, col: isize, text: &str) {
+        for (i, c) in text.chars().enumerate() {
+            self.glyph(row, col + i as isize, c);
+        }
+    }
+
+    /// Draws a line from `(row0, col0)` to `(row1, col1)`. A diagonal line is drawn
+    /// horizontally, then vertically.
+    fn line(&mut self, row0: isize, col0: isize, row1: isize, col1: isize, style: LineStyle) {
+        let mut connect = |row: isize, col: isize, sides: u8| {
+            if let Some(cell) = self.cell_mut(row, col) {
+                if cell.sides == 0 || style != LineStyle::Solid {
+                    cell.style = style;
+                }
+                cell.sides |= sides;
+            }
+        };
+
+        for col in col0.min(col1)..col0.max(col1) {
+            connect(row0, col, RIGHT);
+            connect(row0, col + 1, LEFT);
+        }
+        for row in row0.min(row1)..row0.max(row1) {
+            connect(row, col1, DOWN);
+            connect(row + 1, col1, UP);
+        }
+    }
+
+    fn rect(&mut self, top: isize, left: isize, bottom: isize, right: isize, style: LineStyle) {
+        self.line(top, left, top, right, style);
+        self.line(bottom, left, bottom, right, style);
+        self.line(top, left, bottom, left, style);
+        self.line(top, right, bottom, right, style);
+    }
+
+    /// Lines without trailing spaces. Empty lines at the top and the bottom, and the margin on
+    /// the left are removed.
+    fn lines(&self, charset: TextCharset) -> Vec<String> {
+        let mut lines: Vec<String> = self
+            .cells
+            .chunks(self.n_cols.max(1))
+            .map(|row| {
+                let line: String = row.iter().map(|cell| cell_char(cell, charset)).collect();
+                line.trim_end().to_string()
+            })
+            .skip_while(|line| line.is_empty())
+            .collect();
+
+        while lines.last().is_some_and(|line| line.is_empty()) {
+            lines.pop();
+        }
+
+        let margin = lines
+            .iter()
+            .filter(|line| !line.is_empty())
+            .map(|line| line.chars().take_while(|c| *c == ' ').count())
+            .min()
+            .unwrap_or(0);
+        lines
+            .into_iter()
+            .map(|line| line.chars().skip(margin).collect())
+            .collect()
+    }
+}
+
+fn cell_char(cell: &Cell, charset: TextCharset) -> char {
+    if let Some(glyph) = cell.glyph {
+        return glyph;
+    }
+
+    let horizontal = cell.sides & (UP | DOWN) == 0;
+    let vertical = cell.sides & (LEFT | RIGHT) == 0;
+
+    match (charset, cell.style) {
+        (_, _) if cell.sides == 0 => ' ',
+        (TextCharset::Ascii, LineStyle::Dashed) if horizontal => '.',
+        (TextCharset::Ascii, LineStyle::Dashed) if vertical => ':',
+        (TextCharset::Ascii, _) if horizontal => '-',
+        (TextCharset::Ascii, _) if vertical => '|',
+        (TextCharset::Ascii, _) => '+',
+        (TextCharset::Unicode, LineStyle::Dashed) if horizontal => '┄',
+        (TextCharset::Unicode, LineStyle::Dashed) if vertical => '┆',
+        (TextCharset::Unicode, style) => match cell.sides {
+            s if s == DOWN | RIGHT && style == LineStyle::Rounded => '╭',
+            s if s == DOWN | LEFT && style == LineStyle::Rounded => '╮',
+            s if s == UP | RIGHT && style == LineStyle::Rounded => '╰',
+            s if s == UP | LEFT && style == LineStyle::Rounded => '╯',
+            s if s == DOWN | RIGHT => '┌',
+            s if s == DOWN | LEFT => '┐',
+            s if s == UP | RIGHT => '└',
+            s if s == UP | LEFT => '┘',
+            s if s == UP | DOWN | RIGHT => '├',
+            s if s == UP | DOWN | LEFT => '┤',
+            s if s == LEFT | RIGHT | DOWN => '┬',
+            s if s == LEFT | RIGHT | UP => '┴',
+            _ if horizontal => '─',
+            _ if vertical => '│',
+            _ => '┼',
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn canvas() {
+        let mut canvas = Canvas::new(4, 8);
+
+        canvas.rect(0, 0, 3, 6, LineStyle::Rounded);
+        canvas.line(1, 6, 1, 7, LineStyle::Solid);
+        canvas.line(1, 0, 1, 6, LineStyle::Rounded.unrounded());
+        canvas.text(2, 2, "id");
+
+        assert_eq!(
+            canvas.lines(TextCharset::Unicode),
+            ["╭─────╮", "├─────┼─", "│ id  │", "╰─────╯"]
+        );
+        assert_eq!(
+            canvas.lines(TextCharset::Ascii),
+            ["+-----+", "+-----+-", "| id  |", "+-----+"]
+        );
+    }
+
+    #[test]
+    fn truncate_text() {
+        assert_eq!(truncate("users", 5, TextCharset::Unicode), "users");
+        assert_eq!(truncate("users", 4, TextCharset::Unicode), "use…");
+        assert_eq!(truncate("users", 4, TextCharset::Ascii), "use~");
+        assert_eq!(truncate("users", 0, TextCharset::Ascii), "");
+    }
+
+    #[test]
+    fn parse_charset() {
+        for charset in TextCharset::ALL {
+            assert_eq!(charset.to_string().parse(), Ok(charset));
+        }
+        assert!("ebcdic".parse::<TextCharset>().is_err());
+    }
+}
@@ -0,0 +1,180 @@
+//! Utilities for text backends
+//!
+//! `TextWriter` streams indented lines into an `io::Write`, and `Escape` escapes text for each
+//! target format, so that exporters (e.g. DOT, Mermaid, HTML) share the same rules.
+use std::borrow::Cow;
+use std::fmt;
+use std::io::{self, Write};
+use std::ops::{Deref, DerefMut};
+
+/// Escaping rules of target formats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Escape {
+    /// XML/HTML text content: `&`, `<` and `>`.
+    Xml,
+    /// XML/HTML attribute values in double quotes: `Xml` plus `"`.
+    XmlAttribute,
+    /// Double-quoted strings with backslash escapes (e.g. DOT, PlantUML, DBML).
+    Quoted,
+    /// Mermaid labels: `"` and markup characters are written as entity codes (e.g. `#quot;`).
+    Mermaid,
+}
+
+impl Escape {
+    pub fn apply(self, text: &str) -> Cow<'_, str> {
+        if !text.chars().any(|c| self.replacement(c).is_some()) {
+            return Cow::Borrowed(text);
+        }
+
+        let mut escaped = String::with_capacity(text.len() + 8);
+
+        for c in text.chars() {
+            match self.replacement(c) {
+                Some(s) => escaped.push_str(s),
+                None => escaped.push(c),
+            }
+        }
+        Cow::Owned(escaped)
+    }
+
+    fn replacement(self, c: char) -> Option<&'static str> {
+        match (self, c) {
+            (Escape::Xml | Escape::XmlAttribute, '&') => Some("&amp;"),
+            (Escape::Xml | Escape::XmlAttribute, '<') => Some("&lt;"),
+            (Escape::Xml | Escape::XmlAttribute, '>') => Some("&gt;"),
+            (Escape::XmlAttribute, '"') => Some("&quot;"),
+            (Escape::Quoted, '\\') => Some("\\\\"),
+            (Escape::Quoted, '"') => Some("\\\""),
+            (Escape::Quoted, '\n') => Some("\\n"),
+            (Escape::Mermaid, '"') => Some("#quot;"),
+            (Escape::Mermaid, '<') => Some("#lt;"),
+            (Escape::Mermaid, '>') => Some("#gt;"),
+            _ => None,
+        }
+    }
+}
+
+/// Writes lines with the current indentation.
+#[derive(Debug)]
+pub struct TextWriter<W: Write> {
+    inner: W,
+    indent_unit: String,
+    depth: usize,
+}
+
+impl<W: Write> TextWriter<W> {
+    /// Creates a writer which indents with four spaces.
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            indent_unit: "    ".into(),
+            depth: 0,
+        }
+    }
+
+    pub fn with_indent_unit(mut self, indent_unit: impl Into<String>) -> Self {
+        self.indent_unit = indent_unit.into();
+        self
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Writes `content` in a line. Use `format_args!` to write formatted text without
+    /// allocations.
+    pub fn line(&mut self, content: impl fmt::Display) -> io::Result<()> {
+        for _ in 0..self.depth {
+            self.inner.write_all(self.indent_unit.as_bytes())?;
+        }
+        writeln!(self.inner, "{}", content)
+    }
+
+    /// Writes an empty line without indentation.
+    pub fn blank_line(&mut self) -> io::Result<()> {
+        writeln!(self.inner)
+    }
+
+    /// Increases the indentation until the returned guard is dropped.
+    pub fn indent(&mut self) -> Indent<'_, W> {
+        self.depth += 1;
+        Indent { writer: self }
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+/// A guard returned by `TextWriter::indent`.
+#[derive(Debug)]
+pub struct Indent<'a, W: Write> {
+    writer: &'a mut TextWriter<W>,
+}
+
+impl<W: Write> Deref for Indent<'_, W> {
+    type Target = TextWriter<W>;
+
+    fn deref(&self) -> &Self::Target {
+        self.writer
+    }
+}
+
+impl<W: Write> DerefMut for Indent<'_, W> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.writer
+    }
+}
+
+impl<W: Write> Drop for Indent<'_, W> {
+    fn drop(&mut self) {
+        self.writer.depth -= 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn indent_guard() {
+        let mut w = TextWriter::new(vec![]).with_indent_unit("  ");
+
+        w.line("digraph {").unwrap();
+        {
+            let mut w = w.indent();
+
+            w.line(format_args!("{} -> {};", "a", "b")).unwrap();
+            {
+                let mut w = w.indent();
+                w.line("x").unwrap();
+            }
+            w.blank_line().unwrap();
+            assert_eq!(w.depth(), 1);
+        }
+        w.line("}").unwrap();
+
+        assert_eq!(
+            String::from_utf8(w.into_inner()).unwrap(),
+            "digraph {\n  a -> b;\n    x\n\n}\n"
+        );
+    }
+
+    #[test]
+    fn escape() {
+        let s = r#"<a & "b"> \c"#;
+
+        assert_eq!(Escape::Xml.apply(s), r#"&lt;a &amp; "b"&gt; \c"#);
+        assert_eq!(
+            Escape::XmlAttribute.apply(s),
+            r#"&lt;a &amp; &quot;b&quot;&gt; \c"#
+        );
+        assert_eq!(Escape::Quoted.apply(s), r#"<a & \"b\"> \\c"#);
+        assert_eq!(Escape::Mermaid.apply(s), r#"#lt;a & #quot;b#quot;#gt; \c"#);
+        assert!(matches!(Escape::Xml.apply("users"), Cow::Borrowed(_)));
+    }
+}
@@ -0,0 +1,340 @@
+//! Color themes
+//!
+//! A theme is shared by the AST→MIR conversion (records, fields and badges) and backends
+//! (background, edges and diff marks), so that all colors in a diagram change together.
+//!
+//! Besides the builtin themes, a theme can be loaded from a theme file which overrides colors
+//! of a builtin theme:
+//!
+//! ```text
+//! // Lines starting with `//` are comments.
+//! base = light
+//! edge_color = #FF0000
+//! text_color = #333
+//! ```
+use crate::color::{NamedColor, RGBColor, WebColor};
+use crate::error::ThemeError;
+use crate::mir::FieldChange;
+
+#[derive(Debug, Clone)]
+pub struct Theme {
+    pub background_color: WebColor,
+    pub record_bg_color: WebColor,
+    pub record_border_color: WebColor,
+    /// The background color of entity name rows.
+    pub header_bg_color: WebColor,
+    /// Fades the background of entity name rows from `header_bg_color` at the top to this
+    /// color at the bottom.
+    pub header_gradient_color: Option<WebColor>,
+    /// The background color of every other field row. Rows are not striped if not set.
+    pub stripe_color: Option<WebColor>,
+    /// The color of drop shadows under records.
+    pub shadow_color: WebColor,
+    pub text_color: WebColor,
+    /// Used for default values, ordinals and entity stubs.
+    pub secondary_text_color: WebColor,
+    pub edge_color: WebColor,
+    /// Fields and edges under the pointer in interactive SVGs.
+    pub highlight_color: WebColor,
+
+    // Column types
+    pub numeric_type_color: WebColor,
+    pub text_type_color: WebColor,
+    pub timestamp_type_color: WebColor,
+    /// User-defined types which aren't resolved to a builtin type.
+    pub named_type_color: WebColor,
+
+    // Key badges
+    pub primary_key_color: WebColor,
+    pub primary_key_bg_color: WebColor,
+    pub foreign_key_color: WebColor,
+    pub foreign_key_bg_color: WebColor,
+    /// The background colors of key rows with `ConvertOptions::highlight_keys`.
+    pub primary_key_row_color: WebColor,
+    pub foreign_key_row_color: WebColor,
+
+    // Diff marks
+    pub added_color: WebColor,
+    pub removed_color: WebColor,
+    pub modified_color: WebColor,
+    /// The text color of change badges.
+    pub change_badge_color: WebColor,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
+
+impl Theme {
+    pub const NAMES: [&'static str; 2] = ["dark", "light"];
+
+    /// Returns the theme named `name` (`"dark"` or `"light"`).
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "dark" => Some(Self::dark()),
+            "light" => Some(Self::light()),
+            _ => None,
+        }
+    }
+
+    pub fn dark() -> Self {
+        Self {
+            background_color: rgb(28, 28, 28),
+            record_bg_color: rgb(33, 33, 33),
+            record_border_color: rgb(73, 73, 73),
+            header_bg_color: rgb(73, 73, 73),
+            header_gradient_color: None,
+            stripe_color: None,
+            shadow_color: rgb(0, 0, 0),
+            text_color: WebColor::Named(NamedColor::White),
+            secondary_text_color: rgb(136, 136, 136),
+            edge_color: rgb(136, 136, 136),
+            highlight_color: rgb(17, 112, 251),
+            numeric_type_color: rgb(236, 199, 0),
+            text_type_color: rgb(214, 105, 5),
+            timestamp_type_color: rgb(6, 182, 151),
+            named_type_color: rgb(136, 136, 136),
+            primary_key_color: WebColor::Named(NamedColor::White),
+            primary_key_bg_color: rgb(55, 55, 55),
+            foreign_key_color: rgb(17, 112, 251),
+            foreign_key_bg_color: rgb(32, 41, 55),
+            primary_key_row_color: rgb(42, 42, 42),
+            foreign_key_row_color: rgb(29, 36, 46),
+            added_color: rgb(6, 182, 151),
+            removed_color: rgb(229, 72, 77),
+            modified_color: rgb(236, 199, 0),
+            change_badge_color: rgb(33, 33, 33),
+        }
+    }
+
+    pub fn light() -> Self {
+        Self {
+            background_color: WebColor::Named(NamedColor::White),
+            record_bg_color: rgb(250, 250, 250),
+            record_border_color: rgb(208, 208, 208),
+            header_bg_color: rgb(229, 229, 229),
+            header_gradient_color: None,
+            stripe_color: None,
+            shadow_color: rgb(96, 96, 96),
+            text_color: rgb(28, 28, 28),
+            secondary_text_color: rgb(115, 115, 115),
+            edge_color: rgb(140, 140, 140),
+            highlight_color: rgb(17, 112, 251),
+            numeric_type_color: rgb(163, 122, 0),
+            text_type_color: rgb(194, 65, 12),
+            timestamp_type_color: rgb(15, 138, 115),
+            named_type_color: rgb(115, 115, 115),
+            primary_key_color: rgb(28, 28, 28),
+            primary_key_bg_color: rgb(224, 224, 224),
+            foreign_key_color: rgb(17, 112, 251),
+            foreign_key_bg_color: rgb(220, 232, 253),
+            primary_key_row_color: rgb(238, 238, 238),
+            foreign_key_row_color: rgb(235, 242, 254),
+            added_color: rgb(15, 138, 115),
+            removed_color: rgb(209, 36, 47),
+            modified_color: rgb(191, 135, 0),
+            change_badge_color: WebColor::Named(NamedColor::White),
+        }
+    }
+
+    /// Parses a theme file. Colors which are not specified are taken from the `base` theme
+    /// (`dark` by default), and `base` must be specified before colors.
+    pub fn parse(src: &str) -> Result<Self, ThemeError> {
+        let mut theme = Self::default();
+        let mut has_colors = false;
+
+        for (index, line) in src.lines().enumerate() {
+            let line_number = index + 1;
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with("//") {
+                continue;
+            }
+
+            let Some((key, value)) = line.split_once('=') else {
+                return Err(ThemeError::InvalidLine(line_number));
+            };
+            let (key, value) = (key.trim(), value.trim());
+
+            if key == "base" {
+                if has_colors {
+                    return Err(ThemeError::LateBase(line_number));
+                }
+                theme = Self::from_name(value)
+                    .ok_or_else(|| ThemeError::UnknownTheme(value.to_string()))?;
+                continue;
+            }
+
+            let color = RGBColor::from_hex(value)
+                .ok_or_else(|| ThemeError::InvalidColor(line_number, value.to_string()))?;
+
+            theme.set_color(key, WebColor::RGB(color))?;
+            has_colors = true;
+        }
+
+        Ok(theme)
+    }
+
+    /// Sets a color by its name in theme files (e.g. `edge_color`).
+    pub fn set_color(&mut self, key: &str, color: WebColor) -> Result<(), ThemeError> {
+        let optional_slot = match key {
+            "header_gradient_color" => Some(&mut self.header_gradient_color),
+            "stripe_color" => Some(&mut self.stripe_color),
+            _ => None,
+        };
+        if let Some(slot) = optional_slot {
+            *slot = Some(color);
+            return Ok(());
+        }
+
+        let slot = match key {
+            "background_color" => &mut self.background_color,
+            "record_bg_color" => &mut self.record_bg_color,
+            "record_border_color" => &mut self.record_border_color,
+            "header_bg_color" => &mut self.header_bg_color,
+            "shadow_color" => &mut self.shadow_color,
+            "text_color" => &mut self.text_color,
+            "secondary_text_color" => &mut self.secondary_text_color,
+            "edge_color" => &mut self.edge_color,
+            "highlight_color" => &mut self.highlight_color,
+            "numeric_type_color" => &mut self.numeric_type_color,
+            "text_type_color" => &mut self.text_type_color,
+            "timestamp_type_color" => &mut self.timestamp_type_color,
+            "named_type_color" => &mut self.named_type_color,
+            "primary_key_color" => &mut self.primary_key_color,
+            "primary_key_bg_color" => &mut self.primary_key_bg_color,
+            "foreign_key_color" => &mut self.foreign_key_color,
+            "foreign_key_bg_color" => &mut self.foreign_key_bg_color,
+            "primary_key_row_color" => &mut self.primary_key_row_color,
+            "foreign_key_row_color" => &mut self.foreign_key_row_color,
+            "added_color" => &mut self.added_color,
+            "removed_color" => &mut self.removed_color,
+            "modified_color" => &mut self.modified_color,
+            "change_badge_color" => &mut self.change_badge_color,
+            _ => return Err(ThemeError::UnknownKey(key.to_string())),
+        };
+
+        *slot = color;
+        Ok(())
+    }
+
+    /// Returns colors by their names in theme files, in the order of fields. Unset optional
+    /// colors are omitted.
+    pub fn colors(&self) -> Vec<(&'static str, &WebColor)> {
+        let mut colors = vec![
+            ("background_color", &self.background_color),
+            ("record_bg_color", &self.record_bg_color),
+            ("record_border_color", &self.record_border_color),
+            ("header_bg_color", &self.header_bg_color),
+        ];
+
+        if let Some(color) = &self.header_gradient_color {
+            colors.push(("header_gradient_color", color));
+        }
+        if let Some(color) = &self.stripe_color {
+            colors.push(("stripe_color", color));
+        }
+        colors.extend([
+            ("shadow_color", &self.shadow_color),
+            ("text_color", &self.text_color),
+            ("secondary_text_color", &self.secondary_text_color),
+            ("edge_color", &self.edge_color),
+            ("highlight_color", &self.highlight_color),
+            ("numeric_type_color", &self.numeric_type_color),
+            ("text_type_color", &self.text_type_color),
+            ("timestamp_type_color", &self.timestamp_type_color),
+            ("named_type_color", &self.named_type_color),
+            ("primary_key_color", &self.primary_key_color),
+            ("primary_key_bg_color", &self.primary_key_bg_color),
+            ("foreign_key_color", &self.foreign_key_color),
+            ("foreign_key_bg_color", &self.foreign_key_bg_color),
+            ("primary_key_row_color", &self.primary_key_row_color),
+            ("foreign_key_row_color", &self.foreign_key_row_color),
+            ("added_color", &self.added_color),
+            ("removed_color", &self.removed_color),
+            ("modified_color", &self.modified_color),
+            ("change_badge_color", &self.change_badge_color),
+        ]);
+        colors
+    }
+
+    pub fn change_color(&self, change: FieldChange) -> &WebColor {
+        match change {
+            FieldChange::Added => &self.added_color,
+            FieldChange::Removed => &self.removed_color,
+            FieldChange::Modified => &self.modified_color,
+        }
+    }
+}
+
+fn rgb(red: u8, green: u8, blue: u8) -> WebColor {
+    WebColor::RGB(RGBColor::new(red, green, blue))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_theme_file() {
+        let theme = Theme::parse(
+            "// custom
+base = light
+
+edge_color = #f00
+text_color = #123456
+stripe_color = #eeeeee
+",
+        )
+        .unwrap();
+
+        assert_eq!(theme.edge_color.to_string(), "#FF0000");
+        assert_eq!(theme.text_color.to_string(), "#123456");
+        assert_eq!(theme.stripe_color.unwrap().to_string(), "#EEEEEE");
+        assert!(theme.header_gradient_color.is_none());
+        assert_eq!(
+            theme.background_color.to_string(),
+            Theme::light().background_color.to_string()
+        );
+    }
+
+    #[test]
+    fn color_names() {
+        let mut theme = Theme::light();
+
+        let dark = Theme::dark();
+        let keys = dark.colors().into_iter().map(|(key, _)| key);
+        for key in keys.chain(["header_gradient_color", "stripe_color"]) {
+            theme
+                .set_color(key, WebColor::Named(NamedColor::White))
+                .unwrap();
+        }
+        assert!(theme
+            .colors()
+            .iter()
+            .all(|(_, color)| color.to_string() == "white"));
+        assert_eq!(theme.colors().len(), dark.colors().len() + 2);
+    }
+
+    #[test]
+    fn invalid_theme_file() {
+        assert!(matches!(
+            Theme::parse("edge = #fff"),
+            Err(ThemeError::UnknownKey(key)) if key == "edge"
+        ));
+        assert!(matches!(
+            Theme::parse("edge_color = red"),
+            Err(ThemeError::InvalidColor(1, _))
+        ));
+        assert!(matches!(
+            Theme::parse("edge_color = #fff\nbase = light"),
+            Err(ThemeError::LateBase(2))
+        ));
+        assert!(matches!(
+            Theme::parse("base"),
+            Err(ThemeError::InvalidLine(1))
+        ));
+    }
+}
@@ -0,0 +1,140 @@
+//! Headless image diff for visual regression
+//!
+//! Rasterizes two SVG documents and produces a pixel-diff heatmap, so that layout changes can be
+//! reviewed without eyeballing both images side by side.
+use crate::error::RasterError;
+use resvg::tiny_skia::{Pixmap, PremultipliedColorU8};
+use resvg::usvg;
+use std::path::Path;
+
+/// Result of comparing two images.
+#[derive(Debug)]
+pub struct ImageDiff {
+    /// Unchanged pixels are drawn as a faded grayscale copy of the first image, changed pixels
+    /// from yellow (slightly different) to red (completely different).
+    pub heatmap: Pixmap,
+    pub n_changed_pixels: usize,
+    pub n_pixels: usize,
+}
+
+impl ImageDiff {
+    pub fn is_identical(&self) -> bool {
+        self.n_changed_pixels == 0
+    }
+
+    pub fn save_png(&self, path: impl AsRef<Path>) -> Result<(), RasterError> {
+        self.heatmap
+            .save_png(path)
+            .map_err(|e| RasterError::EncodingFailure(e.to_string()))
+    }
+}
+
+/// Rasterizes a SVG document at its intrinsic size.
+pub fn rasterize(svg: &str) -> Result<Pixmap, RasterError> {
+    let mut options = usvg::Options::default();
+    options.fontdb_mut().load_system_fonts();
+
+    let tree =
+        usvg::Tree::from_str(svg, &options).map_err(|e| RasterError::InvalidSVG(e.to_string()))?;
+    let size = tree.size().to_int_size();
+    let mut pixmap = Pixmap::new(size.width(), size.height())
+        .ok_or(RasterError::InvalidImageSize(size.width(), size.height()))?;
+
+    resvg::render(&tree, Default::default(), &mut pixmap.as_mut());
+    Ok(pixmap)
+}
+
+/// Compares two images pixel by pixel.
+///
+/// If the images differ in size, the heatmap covers both of them and pixels outside of either
+/// image are treated as completely different.
+pub fn diff(a: &Pixmap, b: &Pixmap) -> Result<ImageDiff, RasterError> {
+    let width = a.width().max(b.width());
+    let height = a.height().max(b.height());
+    let mut heatmap =
+        Pixmap::new(width, height).ok_or(RasterError::InvalidImageSize(width, height))?;
+    let mut n_changed_pixels = 0;
+
+    for y in 0..height {
+        for x in 0..width {
+            let pa = a.pixel(x, y).map(|c| c.demultiply());
+            let pb = b.pixel(x, y).map(|c| c.demultiply());
+
+            let color = match (pa, pb) {
+                (Some(ca), Some(cb)) => {
+                    let delta = [
+                        ca.red().abs_diff(cb.red()),
+                        ca.green().abs_diff(cb.green()),
+                        ca.blue().abs_diff(cb.blue()),
+                        ca.alpha().abs_diff(cb.alpha()),
+                    ]
+                    .into_iter()
+                    .max()
+                    .unwrap();
+
+                    if delta == 0 {
+                        let luma = (ca.red() as u32 * 299
+                            + ca.green() as u32 * 587
+                            + ca.blue() as u32 * 114)
+                            / 1000;
+                        let v = (191 + luma / 4) as u8;
+                        PremultipliedColorU8::from_rgba(v, v, v, 255).unwrap()
+                    } else {
+                        n_changed_pixels += 1;
+                        heat(delta)
+                    }
+                }
+                _ => {
+                    n_changed_pixels += 1;
+                    heat(u8::MAX)
+                }
+            };
+
+            heatmap.pixels_mut()[(y * width + x) as usize] = color;
+        }
+    }
+
+    Ok(ImageDiff {
+        heatmap,
+        n_changed_pixels,
+        n_pixels: (width * height) as usize,
+    })
+}
+
+/// Maps the magnitude of a difference to a color from yellow to red.
+fn heat(delta: u8) -> PremultipliedColorU8 {
+    PremultipliedColorU8::from_rgba(255, 255 - delta, 0, 255).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn svg(fill: &str) -> String {
+        format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"4\" height=\"4\">\
+             <rect width=\"2\" height=\"4\" fill=\"{}\"/></svg>",
+            fill
+        )
+    }
+
+    #[test]
+    fn identical_images() {
+        let a = rasterize(&svg("black")).unwrap();
+        let b = rasterize(&svg("black")).unwrap();
+        let d = diff(&a, &b).unwrap();
+
+        assert!(d.is_identical());
+        assert_eq!(d.n_pixels, 16);
+    }
+
+    #[test]
+    fn changed_pixels() {
+        let a = rasterize(&svg("black")).unwrap();
+        let b = rasterize(&svg("white")).unwrap();
+        let d = diff(&a, &b).unwrap();
+
+        assert_eq!(d.n_changed_pixels, 8);
+        assert_eq!(d.heatmap.pixel(0, 0), Some(heat(255)));
+    }
+}
@@ -1,20 +1,62 @@
 use std::fs;
 use seiren::{
-    layout::{LayoutEngine, SimpleLayoutEngine},
+    diff::SchemaDiff,
+    error::RenderOptionsError,
+    font::{CharWidth, FontMetrics, TextMeasure},
+    geometry::{Orientation, Point, Rect, Size},
+    layout::{
+        self, EdgeRouter, IsolatedRecords, LayoutEngine, LayoutHooks, LayoutOptions,
+        LayoutOrientation, LayoutResult, LayoutStage, LayoutWarning, RouteCache, RouteExplanation,
+        SimpleLayoutEngine,
+    },
     parser::{parse},
-    mir::Document,
-    erd::{Module, EntityDefinition, EntityPath, EntityField, EntityFieldType, EntityFieldKey, EntityRelation},
-    renderer::{Renderer, SVGRenderer},
+    mir::{
+        Document, EdgeData, EdgeDataBuilder, EdgeDirection, EdgeLayer, EdgeMarker,
+        FieldShapeBuilder, FontFamily, GridCell, NoteShapeBuilder, RecordShapeBuilder, ShapeKind,
+        TextSpan, TextSpanBuilder,
+    },
+    erd::{
+        ConvertOptions, EntityFilter, KeyHighlight, Module, EntityDefinition, EntityPath,
+        EntityField, EntityFieldType, EntityFieldKey, EntityRelation,
+    },
+    renderer::{
+        extract_source, Background, FieldColumnRatio, GridPattern, HtmlRenderer, PageGrid,
+        RenderOptions, Renderer, SVGFormat, SVGRenderer, TextCharset, TextRenderer,
+    },
+    theme::Theme,
 };
 use difference::assert_diff;
 
+/// Renders `src`, laid out by the default engine, by an `SVGRenderer` set up by `configure`.
+fn render_svg<'g>(src: &str, configure: impl FnOnce(&mut SVGRenderer<'g>)) -> String {
+    render_svg_with(src, &ConvertOptions::default(), configure)
+}
+
+/// `render_svg` with `options` of the conversion into MIR.
+fn render_svg_with<'g>(
+    src: &str,
+    options: &ConvertOptions,
+    configure: impl FnOnce(&mut SVGRenderer<'g>),
+) -> String {
+    let (ast, _, _) = parse(src);
+    let mut doc = ast.unwrap().into_mir_with_options(options);
+    let mut engine = SimpleLayoutEngine::default();
+    let mut backend = SVGRenderer::new();
+    let mut bytes: Vec<u8> = vec![];
+
+    backend.view_box = engine.layout(&mut doc).view_box;
+    configure(&mut backend);
+    backend.render(&doc, &mut bytes).expect("generate SVG");
+    String::from_utf8(bytes).unwrap()
+}
+
 #[test]
 fn empty_doc() {
     let diagram = Module::new(None);
     let mut doc = diagram.into_mir();
-    let mut engine = SimpleLayoutEngine::new();
+    let mut engine = SimpleLayoutEngine::default();
 
-    engine.place_nodes(&mut doc);
+    engine.layout(&mut doc);
 
     let backend = SVGRenderer::new();
     let mut bytes: Vec<u8> = vec![];
@@ -94,11 +136,9 @@ fn demo_erd() -> Document {
     ));
 
     let mut doc = diagram.into_mir();
-    let mut engine = SimpleLayoutEngine::new();
+    let mut engine = SimpleLayoutEngine::default();
 
-    engine.place_nodes(&mut doc);
-    engine.place_terminal_ports(&mut doc);
-    engine.draw_edge_path(&mut doc);
+    engine.layout(&mut doc);
 
     doc
 }
@@ -227,8 +267,8 @@ int
 FK
 </text>
 <path d=\"M430 277.5 L396 277.5 Q390 277.5 390 271.5 L390 108.5 Q390 102.5 384 102.5 L350 102.5\" fill=\"transparent\" stroke=\"#888888\" stroke-width=\"1.5\"/>
-<circle cx=\"430\" cy=\"277.5\" fill=\"#1C1C1C\" r=\"4\" stroke=\"#888888\" stroke-width=\"1.5\"/>
-<circle cx=\"350\" cy=\"102.5\" fill=\"#1C1C1C\" r=\"4\" stroke=\"#888888\" stroke-width=\"1.5\"/>
+<circle cx=\"430\" cy=\"277.5\" fill=\"#212121\" r=\"4\" stroke=\"#888888\" stroke-width=\"1.5\"/>
+<circle cx=\"350\" cy=\"102.5\" fill=\"#212121\" r=\"4\" stroke=\"#888888\" stroke-width=\"1.5\"/>
 </svg>", "\n", 0);
 }
 
@@ -251,28 +291,3034 @@ fn example_files() {
         let expected_svg = expected_svg.unwrap();
         let src = fs::read_to_string(path).unwrap();
 
-        let (ast, errs, parse_errs) = parse(&src);
+        let (_, errs, parse_errs) = parse(&src);
 
         assert_eq!(errs, vec![], "file:{}", file_name);
         assert_eq!(parse_errs, vec![], "file:{}", file_name);
 
+        let svg = render_svg(&src, |_| {});
+        assert_diff!(svg.as_str(), expected_svg.as_str(), "\n", 0);
+    }        
+}
+
+#[test]
+fn columns_directive() {
+    let src = "erd {
+%columns 1
+users { id int PK }
+posts { id int PK }
+}";
+    let (ast, errs, parse_errs) = parse(src);
+
+    assert!(errs.is_empty());
+    assert!(parse_errs.is_empty());
+
+    let mut doc = ast.unwrap().into_mir();
+    let mut engine = SimpleLayoutEngine::default();
+
+    let view_box = engine.layout(&mut doc).view_box.unwrap();
+    let origins = doc
+        .body()
+        .children()
+        .map(|child_id| doc.get_node(child_id).unwrap().origin.unwrap())
+        .collect::<Vec<_>>();
+
+    // Both records are stacked in a single column.
+    assert_eq!(origins.len(), 2);
+    assert_eq!(origins[0].x, origins[1].x);
+    assert!(origins[0].y < origins[1].y);
+    assert_eq!(view_box.width(), 400.0);
+}
+
+#[test]
+fn field_default_values() {
+    let src = "erd { users { id int PK default 0 } }";
+
+    // Normal width: default values are shown as tooltips.
+    let svg = render_svg(src, |_| {});
+    assert!(svg.contains("<title>\ndefault: 0\n</title>"));
+
+    // Wide mode: default values are rendered in the third column.
+    let svg = render_svg(src, |backend| backend.wide_mode = true);
+    assert!(!svg.contains("<title>"));
+    assert!(svg.contains(">\n0\n</text>"));
+}
+
+#[test]
+fn field_column_ratio() {
+    let render = |field_column_ratio: FieldColumnRatio| {
+        render_svg("erd { users { id int PK default 0 } }", |backend| {
+            backend.field_column_ratio = field_column_ratio;
+        })
+    };
+
+    // The default value column only counts in wide mode.
+    let text_only = FieldColumnRatio {
+        title: 0.0,
+        subtitle: 0.0,
+        default_value: 1.0,
+        ..FieldColumnRatio::default()
+    };
+    assert!(FieldColumnRatio::default().validate(false).is_ok());
+    assert!(text_only.validate(true).is_ok());
+    assert_eq!(text_only.validate(false), Err(RenderOptionsError::InvalidFieldColumnRatio));
+
+    // Invalid ratios are drawn as the default, instead of dividing by zero.
+    let default_svg = render(FieldColumnRatio::default());
+    for ratio in [0.0, -1.0, f32::NAN] {
+        let invalid = FieldColumnRatio {
+            title: ratio,
+            subtitle: 0.0,
+            default_value: 0.0,
+            badge: 0.0,
+            max_badge_width: None,
+        };
+
+        assert_eq!(invalid.validate(false), Err(RenderOptionsError::InvalidFieldColumnRatio));
+        assert_eq!(render(invalid), default_svg);
+    }
+}
+
+#[test]
+fn escape_text() {
+    let svg = render_svg("erd { users \"<Users & Groups>\" { id int PK } }", |_| {});
+    assert!(svg.contains(">\n&lt;Users &amp; Groups&gt;\n</text>"));
+}
+
+#[test]
+fn implicit_entity_stubs() {
+    let src = "erd {
+users { id int PK }
+audit_logs.user_id o--o users.id
+}";
+    let (ast, _, _) = parse(src);
+    let ast = ast.unwrap();
+
+    // Relations to undefined entities are dropped by default.
+    let doc = ast.into_mir();
+    assert_eq!(doc.body().children().len(), 1);
+    assert_eq!(doc.edges().count(), 0);
+
+    let options = ConvertOptions {
+        implicit_entity_stubs: true,
+        ..ConvertOptions::default()
+    };
+    let doc = ast.into_mir_with_options(&options);
+    assert_eq!(doc.body().children().len(), 2);
+    assert_eq!(doc.edges().count(), 1);
+
+    let svg = render_svg_with(src, &options, |_| {});
+    assert!(svg.contains("stroke-dasharray=\"4 3\""));
+    assert!(svg.contains(">\naudit_logs\n</text>"));
+}
+
+#[test]
+fn implicit_entity_stub_fields() {
+    let src = "erd {
+users { id int PK }
+posts { id int PK }
+audit_logs.user_id o--o users.id
+audit_logs.post_id o--o posts.id
+}";
+    let (ast, _, _) = parse(src);
+    let options = ConvertOptions {
+        implicit_entity_stubs: true,
+        ..ConvertOptions::default()
+    };
+    let (doc, node_paths) = ast.unwrap().into_mir_with_node_paths(&options);
+    let stub_id = node_paths[&EntityPath::Entity("audit_logs".into())];
+
+    // One stub, which both relations end at.
+    assert_eq!(doc.body().children().len(), 3);
+    assert_eq!(doc.edges().count(), 2);
+    assert!(doc.edges().all(|edge| edge.source_id() == stub_id));
+}
+
+#[test]
+fn implicit_entity_stub_self_relations() {
+    let src = "erd {
+employees.manager_id o--o employees.id
+employees.id o--o employees.id
+}";
+    let (ast, _, _) = parse(src);
+    let options = ConvertOptions {
+        implicit_entity_stubs: true,
+        ..ConvertOptions::default()
+    };
+    let (mut doc, node_paths) = ast.unwrap().into_mir_with_node_paths(&options);
+    let stub_id = node_paths[&EntityPath::Entity("employees".into())];
+
+    // Different fields of the stub are related, but a field isn't related to itself.
+    assert_eq!(doc.edges().count(), 1);
+    assert!(doc.edges().all(|edge| edge.source_id() == stub_id && edge.target_id() == stub_id));
+
+    for router in EdgeRouter::ALL {
+        let mut engine = SimpleLayoutEngine::default();
+
+        engine.router = router;
+        engine.layout(&mut doc);
+
+        // The edge goes around the stub between two of its ports.
+        let points = doc.edges().next().unwrap().path_points().unwrap().to_vec();
+        assert_ne!(points.first(), points.last());
+        assert!(layout::validate_layout(&doc).is_empty());
+    }
+}
+
+#[test]
+fn field_ordinals() {
+    let src = "erd {
+users {
+    id int PK
+    name text
+    email text @1
+}
+}";
+    let options = ConvertOptions {
+        show_field_ordinals: true,
+        ..ConvertOptions::default()
+    };
+    let svg = render_svg_with(src, &options, |_| {});
+
+    // `email` is displayed first but keeps its original ordinal.
+    let email = svg.find(">\nemail\n</text>").unwrap();
+    let id = svg.find(">\nid\n</text>").unwrap();
+    assert!(email < id);
+    assert!(svg.contains(">\n3\n</text>"));
+}
+
+#[test]
+fn explain_edge() {
+    let src = fs::read_to_string("example/posts.seiren").unwrap();
+    let (ast, _, _) = parse(&src);
+    let (mut doc, node_paths) = ast.unwrap().into_mir_with_node_paths(&ConvertOptions::default());
+    let mut engine = SimpleLayoutEngine::default();
+
+    engine.layout(&mut doc);
+
+    let path = EntityPath::Field("posts".into(), "created_by".into());
+    let node_id = node_paths[&path];
+    let edge_id = doc.edge_ids().next().unwrap();
+    assert_eq!(doc.edge_endpoints(edge_id).unwrap().1, node_id);
+
+    let explanation = engine.explain_edge(&doc, edge_id).unwrap();
+    let selected = &explanation.candidates[explanation.selected.unwrap()];
+    let min_cost = explanation.candidates.iter().map(|c| c.cost).min().unwrap();
+    assert_eq!(selected.cost, min_cost);
+
+    // The replayed route is the drawn one.
+    let route_graph = engine.edge_route_graph();
+    let points: Vec<_> = explanation
+        .path
+        .iter()
+        .map(|id| *route_graph.get_node(*id).unwrap().location())
+        .collect();
+    assert_eq!(doc.edge(edge_id).unwrap().path_points(), Some(points.as_slice()));
+
+    // The grid router searches routes on its own grid.
+    engine.router = EdgeRouter::Grid;
+    engine.layout(&mut doc);
+    assert!(engine.explain_edge(&doc, edge_id).is_none());
+}
+
+#[test]
+fn route_penalties() {
+    let src = fs::read_to_string("example/posts.seiren").unwrap();
+    let explain = |options: LayoutOptions| {
+        let (ast, _, _) = parse(&src);
         let mut doc = ast.unwrap().into_mir();
-        let mut engine = SimpleLayoutEngine::new();
+        let mut engine = SimpleLayoutEngine::new(options);
 
-        let view_box = engine.place_nodes(&mut doc);
-        engine.place_terminal_ports(&mut doc);
-        engine.draw_edge_path(&mut doc);
+        engine.layout(&mut doc);
 
-        let mut backend = SVGRenderer::new();
-        backend.view_box = view_box;
+        let edge_id = doc.edge_ids().next().unwrap();
+        engine.explain_edge(&doc, edge_id).unwrap()
+    };
+    let cost = |explanation: &RouteExplanation, source: Orientation, target: Orientation| {
+        explanation
+            .candidates
+            .iter()
+            .find(|c| c.source.orientation() == source && c.target.orientation() == target)
+            .map(|c| c.cost)
+            .unwrap()
+    };
+
+    // `users.id` (left) -> `posts.created_by` (right)
+    let plain = explain(LayoutOptions::default());
+    let penalized = explain(LayoutOptions {
+        port_side_cost: 1000.0,
+        ..LayoutOptions::default()
+    });
+    let (left, right) = (Orientation::Left, Orientation::Right);
+
+    assert_eq!(cost(&penalized, right, left), cost(&plain, right, left));
+    // Both ends face away from each other.
+    assert_eq!(cost(&penalized, left, right), cost(&plain, left, right) + 2000);
+
+    // Bends make routes longer, and fewer bends are preferred.
+    let bends = |options: LayoutOptions| {
+        let src = fs::read_to_string("example/comments.seiren").unwrap();
+        let (ast, _, _) = parse(&src);
+        let mut doc = ast.unwrap().into_mir();
+        let mut engine = SimpleLayoutEngine::new(options);
+
+        engine.layout(&mut doc);
+        doc.edges().map(|edge| edge.path_points().unwrap().len() - 2).sum::<usize>()
+    };
+
+    assert!(
+        bends(LayoutOptions {
+            bend_cost: 200.0,
+            ..LayoutOptions::default()
+        }) < bends(LayoutOptions::default())
+    );
+
+    // The route search counts bends of the selected route in its cost.
+    let (ast, _, _) = parse(&src);
+    let mut doc = ast.unwrap().into_mir();
+    let mut engine = SimpleLayoutEngine::new(LayoutOptions {
+        bend_cost: 200.0,
+        ..LayoutOptions::default()
+    });
+
+    engine.layout(&mut doc);
+
+    let explanation = engine.explain_edge(&doc, doc.edge_ids().next().unwrap()).unwrap();
+    let graph = engine.edge_route_graph();
+    let points: Vec<Point> = explanation
+        .path
+        .iter()
+        .map(|id| *graph.get_node(*id).unwrap().location())
+        .collect();
+    let length: f32 = points.windows(2).map(|w| w[0].distance(&w[1])).sum();
+    let n_bends = points
+        .windows(3)
+        .filter(|w| (w[0].x == w[1].x) != (w[1].x == w[2].x))
+        .count();
+    let selected = &explanation.candidates[explanation.selected.unwrap()];
+
+    assert_eq!(selected.cost, length as u32 + 200 * n_bends as u32);
+}
+
+#[test]
+fn diff_badges() {
+    let new_src = "erd {\nusers {\n    id uuid PK\n    age int\n    email text\n}\n}";
+    let (old, _, _) = parse("erd {\nusers {\n    id int PK\n    name text\n    age int\n}\n}");
+    let (new, _, _) = parse(new_src);
+    let options = ConvertOptions {
+        diff: Some(SchemaDiff::new(&old.unwrap(), &new.unwrap())),
+        ..ConvertOptions::default()
+    };
+    let svg = render_svg_with(new_src, &options, |_| {});
+
+    // The removed field is rendered at the bottom.
+    let email = svg.find(">\nemail\n</text>").unwrap();
+    let name = svg.find(">\nname\n</text>").unwrap();
+    assert!(email < name);
+
+    for badge in ["+", "\u{2212}", "~"] {
+        assert_eq!(svg.matches(&format!(">\n{}\n</text>", badge)).count(), 1, "{}", badge);
+    }
+    assert_eq!(svg.matches("fill-opacity").count(), 3);
+}
+
+#[test]
+fn light_theme() {
+    let src = fs::read_to_string("example/posts.seiren").unwrap();
+    let options = ConvertOptions {
+        theme: Theme::light(),
+        ..ConvertOptions::default()
+    };
+    let svg = render_svg_with(&src, &options, |backend| backend.theme = Theme::light());
+
+    // No colors of the dark theme remain.
+    let dark = Theme::dark();
+    for color in [
+        &dark.record_bg_color,
+        &dark.record_border_color,
+        &dark.primary_key_bg_color,
+    ] {
+        assert!(!svg.contains(&format!("\"{}\"", color)), "{}", color);
+    }
+    assert!(svg.contains(&format!("fill=\"{}\"", Theme::light().background_color)));
+}
+
+#[test]
+fn fit_record_width() {
+    let src = "erd {
+users {
+    id int PK
+    a_very_long_column_name_which_overflows_the_record timestamp
+}
+tags { id int PK }
+}";
+    let (ast, _, _) = parse(src);
+    let mut doc = ast.unwrap().into_mir();
+    let mut engine = SimpleLayoutEngine::default();
+
+    engine.fit_record_width = true;
+    let view_box = engine.layout(&mut doc).view_box.unwrap();
+
+    let records: Vec<_> = doc
+        .body()
+        .children()
+        .map(|id| doc.get_node(id).unwrap().rect().unwrap())
+        .collect();
+    assert!(records[0].width() > 300.0);
+    assert_eq!(records[1].width(), 300.0);
+    // The next record is moved right.
+    assert_eq!(records[1].min_x(), records[0].max_x() + 80.0);
+    assert!(view_box.width() > 1160.0);
+}
+
+#[test]
+fn custom_text_measure() {
+    // Pretends that every text is as wide as 20px per character.
+    #[derive(Debug)]
+    struct Measured;
+
+    impl TextMeasure for Measured {
+        fn text_width(&self, span: &TextSpan) -> f32 {
+            span.text.chars().count() as f32 * 20.0
+        }
+    }
+
+    let src = "erd {
+%columns 1
+users { id int PK\nname_of_the_user text }
+tags { id int PK }
+}";
+    let (ast, _, _) = parse(src);
+    let mut doc = ast.unwrap().into_mir();
+    let mut engine = SimpleLayoutEngine::default();
+
+    engine.fit_record_width = true;
+    engine.text_measure = Box::new(Measured);
+    engine.layout(&mut doc).view_box.unwrap();
+
+    let records: Vec<_> = doc
+        .body()
+        .children()
+        .map(|id| doc.get_node(id).unwrap().rect().unwrap())
+        .collect();
+    // `name_of_the_user` alone is 320px, and both records in the column share the width.
+    assert!(records[0].width() > 320.0);
+    assert_eq!(records[1].width(), records[0].width());
+}
+
+#[test]
+fn text_measure_config_hash() {
+    let hash = |measure: Box<dyn TextMeasure>| {
+        let mut engine = SimpleLayoutEngine::default();
+
+        engine.text_measure = measure;
+        engine.config_hash()
+    };
+
+    // The built-in metrics are the default, and other measures lay out text differently.
+    let default = SimpleLayoutEngine::default().config_hash();
+    assert_eq!(hash(Box::new(FontMetrics)), default);
+    assert_ne!(hash(Box::new(CharWidth(0.5))), default);
+    assert_ne!(hash(Box::new(CharWidth(0.5))), hash(Box::new(CharWidth(0.6))));
+}
+
+#[test]
+fn auto_column_count() {
+    let src: String = std::iter::once("erd {\n".to_string())
+        .chain((0..30).map(|i| format!("t{} {{\nid int PK\nname text\n}}\n", i)))
+        .chain(["}".to_string()])
+        .collect();
+    let place = |target_aspect_ratio: Option<f32>, src: &str| {
+        let (ast, _, _) = parse(src);
+        let mut doc = ast.unwrap().into_mir();
+        let mut engine = SimpleLayoutEngine::default();
+
+        engine.target_aspect_ratio = target_aspect_ratio;
+        engine.layout(&mut doc).view_box.unwrap()
+    };
+    let aspect_ratio = |view_box: Rect| view_box.width() / view_box.height();
+
+    // 3 columns by default
+    assert!(aspect_ratio(place(None, &src)) < 1.0);
+    // 30 records of 300x105 in 5 columns
+    let view_box = place(Some(SimpleLayoutEngine::DEFAULT_ASPECT_RATIO), &src);
+    assert_eq!(view_box.width(), 50.0 * 2.0 + 300.0 * 5.0 + 80.0 * 4.0);
+    assert!((aspect_ratio(view_box) - 16.0 / 9.0).abs() < 0.2);
+    assert!(aspect_ratio(place(Some(0.5), &src)) < 1.0);
+
+    // The `%columns` directive wins.
+    let src = src.replacen("erd {\n", "erd {\n%columns 2\n", 1);
+    assert_eq!(place(Some(2.0), &src).width(), 50.0 * 2.0 + 300.0 * 2.0 + 80.0);
+}
+
+#[test]
+fn separate_edges() {
+    let src = "erd {
+users {
+    id int PK
+    name text
+}
+posts { id int PK\nuser_id int FK }
+comments { id int PK\nuser_id int FK }
+likes { id int PK\nuser_id int FK }
+tags { id int PK\nuser_id int FK }
+users.id o--o posts.user_id
+users.id o--o comments.user_id
+users.id o--o likes.user_id
+users.id o--o tags.user_id
+}";
+    let corners = |separate_edges: bool| {
+        let (ast, _, _) = parse(src);
+        let mut doc = ast.unwrap().into_mir();
+        let mut engine = SimpleLayoutEngine::default();
+
+        engine.separate_edges = separate_edges;
+        engine.layout(&mut doc);
+
+        // All edges leave `users.id` rightward, and turn at the first corner.
+        let corners: Vec<f32> = doc.edges().map(|edge| edge.path_points().unwrap()[1].x).collect();
+        (corners, engine)
+    };
+
+    let (overlapped, _) = corners(false);
+    assert_eq!(overlapped, vec![390.0; 4]);
+
+    // The first edge keeps its route, and the others run in lanes to its sides.
+    let (separated, engine) = corners(true);
+    assert_eq!(separated, vec![390.0, 398.0, 382.0, 406.0]);
+    assert!(engine.edge_route_graph().edges().any(|edge| edge.lanes().len() == 4));
+    assert_ne!(engine.config_hash(), SimpleLayoutEngine::default().config_hash());
+}
+
+#[test]
+fn grid_router() {
+    let src = "erd {
+users {
+    id int PK
+    name text
+}
+posts { id int PK\nuser_id int FK }
+comments { id int PK\nuser_id int FK }
+likes { id int PK\nuser_id int FK }
+tags { id int PK\nuser_id int FK }
+users.id o--o posts.user_id
+users.id o--o comments.user_id
+users.id o--o likes.user_id
+users.id o--o tags.user_id
+}";
+    let (ast, _, _) = parse(src);
+    let mut doc = ast.unwrap().into_mir();
+    let mut engine = SimpleLayoutEngine::default();
+
+    engine.router = "grid".parse().unwrap();
+    assert_eq!(engine.router, EdgeRouter::Grid);
+    engine.layout(&mut doc);
+
+    let records: Vec<Rect> = doc
+        .body()
+        .children()
+        .filter_map(|child_id| doc.get_node(child_id).unwrap().rect())
+        .collect();
+    let mut corners: Vec<f32> = vec![];
+
+    for edge in doc.edges() {
+        let points = edge.path_points().unwrap();
+
+        // Orthogonal lines, which don't cross records.
+        for w in points.windows(2) {
+            assert!(w[0].x == w[1].x || w[0].y == w[1].y, "{:?}", points);
+            for r in &records {
+                assert!(!r.inset_by(1.0, 1.0).intersects_line(&w[0], &w[1]), "{:?}", points);
+            }
+        }
+        corners.push(points[1].x);
+    }
 
+    // Edges from `users.id` turn on different grid lines.
+    corners.sort_by(f32::total_cmp);
+    corners.dedup();
+    assert_eq!(corners.len(), 4);
+    assert_ne!(engine.config_hash(), SimpleLayoutEngine::default().config_hash());
+}
+
+#[test]
+fn cluster_related_records() {
+    let src = "erd {
+a { id int PK }
+u1 { id int PK }
+u2 { id int PK }
+u3 { id int PK }
+b { id int PK\na_id int FK }
+c { id int PK\nb_id int FK }
+b.a_id o--o a.id
+c.b_id o--o b.id
+}";
+    let origins = |cluster_related: bool| {
+        let (ast, _, _) = parse(src);
+        let mut doc = ast.unwrap().into_mir();
+        let mut engine = SimpleLayoutEngine::default();
+
+        engine.cluster_related = cluster_related;
+        engine.layout(&mut doc);
+        doc.body()
+            .children()
+            .map(|child_id| doc.get_node(child_id).unwrap().origin.unwrap())
+            .collect::<Vec<_>>()
+    };
+
+    // a, b and c are scattered in definition order.
+    let scattered = origins(false);
+    assert_eq!(scattered[4].y, scattered[5].y);
+    assert_ne!(scattered[0].y, scattered[4].y);
+
+    // b takes the cell next to a, c the cell next to b, and the others follow in order.
+    let clustered = origins(true);
+    assert_eq!(clustered[0], scattered[0]);
+    assert_eq!(clustered[4], scattered[1]);
+    assert_eq!(clustered[5], scattered[2]);
+    for (u, column) in clustered[1..4].iter().zip(&scattered[0..3]) {
+        assert_eq!(u.x, column.x);
+        assert!(u.y > clustered[0].y);
+    }
+}
+
+#[test]
+fn preferred_positions() {
+    let src = "erd {
+a { id int PK }
+b [row=2, column=3] { id int PK }
+c [x=1000, y=600] { id int PK }
+d { id int PK }
+e [row=1, column=1] { id int PK }
+}";
+    let (ast, _, _) = parse(src);
+    let mut doc = ast.unwrap().into_mir();
+    let mut engine = SimpleLayoutEngine::default();
+    let view_box = engine.layout(&mut doc).view_box.unwrap();
+    let origins: Vec<Point> = doc
+        .body()
+        .children()
+        .map(|child_id| doc.get_node(child_id).unwrap().origin.unwrap())
+        .collect();
+    let column_x = |column: f32| 50.0 + (300.0 + 80.0) * column;
+    let row_y = |row: f32| 50.0 + (70.0 + 80.0) * row;
+
+    // `e` takes the first cell before `a`, which wasn't preferred.
+    assert_eq!(origins[4], Point::new(column_x(0.0), row_y(0.0)));
+    assert_eq!(origins[0], Point::new(column_x(1.0), row_y(0.0)));
+    assert_eq!(origins[3], Point::new(column_x(2.0), row_y(0.0)));
+    assert_eq!(origins[1], Point::new(column_x(2.0), row_y(1.0)));
+
+    // Out of the grid, and the view box covers it.
+    assert_eq!(origins[2], Point::new(1000.0, 600.0));
+    assert_eq!(view_box.width(), 1000.0 + 300.0 + 50.0);
+    assert_eq!(view_box.height(), 600.0 + 70.0 + 50.0);
+}
+
+#[test]
+fn preferred_cells_out_of_range() {
+    let src = "erd {
+a [row=18446744073709551615, column=1] { id int PK }
+b [row=50000000, column=2] { id int PK }
+c { id int PK }
+}";
+    let (ast, _, _) = parse(src);
+    let (mut doc, node_paths) =
+        ast.unwrap().into_mir_with_node_paths(&ConvertOptions::default());
+    let result = SimpleLayoutEngine::default().layout(&mut doc);
+    let view_box = result.view_box.unwrap();
+    let [a, b, c] = ["a", "b", "c"].map(|name| node_paths[&EntityPath::Entity(name.into())]);
+    let origin = |node_id| doc.get_node(node_id).unwrap().origin.unwrap();
+
+    // Rows are clamped to the number of records, which is the last row of the grid.
+    assert_eq!(origin(c), Point::new(50.0, 50.0));
+    assert_eq!(origin(a).x, 50.0);
+    assert!(origin(a).y > origin(c).y);
+    assert_eq!(origin(b), Point::new(50.0 + 300.0 + 80.0, origin(a).y));
+    assert_eq!(view_box.height(), origin(a).y + 70.0 + 50.0);
+    assert_eq!(
+        result.warnings,
+        vec![
+            LayoutWarning::CellOutOfRange { record_id: a },
+            LayoutWarning::CellOutOfRange { record_id: b },
+        ]
+    );
+    assert_eq!(layout::validate_layout(&doc), vec![]);
+
+    // A column beyond the line of the grid, but not beyond the number of records
+    let src = "erd {
+%columns 2
+a [row=1, column=3] { id int PK }
+b { id int PK }
+c { id int PK }
+}";
+    let (ast, _, _) = parse(src);
+    let (mut doc, node_paths) =
+        ast.unwrap().into_mir_with_node_paths(&ConvertOptions::default());
+    let result = SimpleLayoutEngine::default().layout(&mut doc);
+    let a = node_paths[&EntityPath::Entity("a".into())];
+
+    // `a` is placed in the first free cell.
+    assert_eq!(doc.get_node(a).unwrap().origin, Some(Point::new(50.0, 50.0)));
+    assert_eq!(result.warnings, vec![LayoutWarning::CellOutOfRange { record_id: a }]);
+}
+
+#[test]
+fn pinned_records() {
+    let src = fs::read_to_string("example/tags.seiren").unwrap();
+    let (ast, _, _) = parse(&src);
+    let mut doc = ast.unwrap().into_mir();
+    let mut engine = SimpleLayoutEngine::default();
+    let origins = |doc: &Document| -> Vec<Point> {
+        doc.body()
+            .children()
+            .map(|child_id| doc.get_node(child_id).unwrap().origin.unwrap())
+            .collect()
+    };
+
+    let view_box = engine.layout(&mut doc).view_box.unwrap();
+    let before = origins(&doc);
+    let first_id = doc.body().children().next().unwrap();
+
+    engine.pin(first_id, Point::new(2000.0, 100.0));
+    let pinned_view_box = engine.layout(&mut doc).view_box.unwrap();
+    let after = origins(&doc);
+
+    // Only the pinned record moves, and the view box covers it.
+    assert_eq!(after[0], Point::new(2000.0, 100.0));
+    assert_eq!(after[1..], before[1..]);
+    assert!(pinned_view_box.width() > view_box.width());
+    assert_eq!(pinned_view_box.height(), view_box.height());
+
+    assert_eq!(engine.unpin(first_id), Some(Point::new(2000.0, 100.0)));
+    engine.layout(&mut doc);
+    assert_eq!(origins(&doc), before);
+}
+
+#[test]
+fn update_layout() {
+    let src = fs::read_to_string("example/posts.seiren").unwrap();
+    let (ast, _, _) = parse(&src);
+    let (mut doc, node_paths) =
+        ast.unwrap().into_mir_with_node_paths(&ConvertOptions::default());
+    let mut engine = SimpleLayoutEngine::default();
+    let view_box = engine.update_layout(&mut doc).unwrap();
+    let records: Vec<_> = doc.body().children().collect();
+    let rects: Vec<Rect> = records
+        .iter()
+        .map(|id| doc.get_node(*id).unwrap().rect().unwrap())
+        .collect();
+    let paths: Vec<Vec<Point>> = doc
+        .edges()
+        .map(|edge| edge.path_points().unwrap().to_vec())
+        .collect();
+
+    // Add a record `tags { post_id }` and a relation to `posts.id`.
+    let field = |title: &str| {
+        let title = TextSpanBuilder::default().text(title).build().unwrap();
+        FieldShapeBuilder::default().title(title).build().unwrap()
+    };
+    let record_id = doc.create_record(RecordShapeBuilder::default().build().unwrap());
+    let header_id = doc.create_field(field("tags"));
+    let field_id = doc.create_field(field("post_id"));
+    let posts_id = node_paths[&EntityPath::Field("posts".into(), "id".into())];
+
+    doc.get_node_mut(record_id).unwrap().append_child(header_id);
+    doc.get_node_mut(record_id).unwrap().append_child(field_id);
+    doc.body_mut().append_child(record_id);
+    doc.add_edge(EdgeData::new(field_id, posts_id, None));
+
+    let updated_view_box = engine.update_layout(&mut doc).unwrap();
+
+    // Old records and paths stay, and the new record is placed in a row below them.
+    for (id, rect) in records.iter().zip(&rects) {
+        assert_eq!(doc.get_node(*id).unwrap().rect(), Some(*rect));
+    }
+    for (edge, path) in doc.edges().zip(&paths) {
+        assert_eq!(edge.path_points().unwrap(), &path[..]);
+    }
+    let new_rect = doc.get_node(record_id).unwrap().rect().unwrap();
+    let bottom = rects[0].max_y().max(rects[1].max_y());
+    assert_eq!(new_rect.origin(), &Point::new(50.0, bottom + 80.0));
+    assert_eq!(updated_view_box.width(), view_box.width());
+    assert_eq!(updated_view_box.height(), new_rect.max_y() + 50.0);
+
+    let new_path = doc.edges().last().unwrap().path_points().unwrap();
+    assert!(new_path.len() >= 2);
+    assert!(new_path.windows(2).all(|w| w[0].x == w[1].x || w[0].y == w[1].y));
+
+    // Junctions added to the route graph are merged with old ones at the same location.
+    let mut locations: Vec<Point> =
+        engine.edge_route_graph().nodes().map(|node| *node.location()).collect();
+    let n_locations = locations.len();
+    locations.sort_by(|a, b| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+    locations.dedup();
+    assert_eq!(locations.len(), n_locations);
+}
+
+#[test]
+fn truncate_overflow() {
+    let src = "erd {
+users {
+    id int PK
+    a_very_long_column_name_which_overflows_the_record timestamp
+}
+}";
+    let svg = render_svg(src, |_| {});
+
+    assert!(svg.contains("\na_very_long_c…\n"));
+    assert!(svg.contains("<title>\na_very_long_column_name_which_overflows_the_record\n</title>"));
+    assert!(svg.contains("\ntimestamp\n"));
+
+    let svg = render_svg(src, |backend| backend.truncate_overflow = false);
+
+    assert!(!svg.contains('…'));
+    assert!(!svg.contains("<title>"));
+}
+
+#[test]
+fn css_classes() {
+    let doc = demo_erd();
+    let mut backend = SVGRenderer::new();
+    let mut bytes: Vec<u8> = vec![];
+
+    backend.css_classes = true;
+    backend.render(&doc, &mut bytes).unwrap();
+
+    let svg = String::from_utf8(bytes).unwrap();
+
+    assert!(svg.contains("<style>\n.background { fill: #1C1C1C; }\n"));
+    assert!(svg.contains(".record { stroke: #494949; fill: #212121; }"));
+    assert!(svg.contains("<rect class=\"record\" height=\"210\""));
+    assert!(svg.contains("<text class=\"field-title\" x=\"62\" y=\"102.5\">"));
+    assert!(svg.contains("class=\"badge-pk\""));
+    // Colors and fonts are not written as attributes.
+    assert!(!svg.contains(" fill=\""));
+    assert!(!svg.contains(" font-family=\""));
+}
+
+#[test]
+fn root_size() {
+    let render = |width: Option<f32>, height: Option<f32>, scale: Option<f32>| {
+        let mut backend = SVGRenderer::new();
         let mut bytes: Vec<u8> = vec![];
 
-        backend
-            .render(&doc, &mut bytes)
-            .expect("cannot generate SVG");
+        backend.view_box = Some(Rect::new(Point::zero(), Size::new(800.0, 400.0)));
+        backend.width = width;
+        backend.height = height;
+        backend.scale = scale;
+        backend.render(&demo_erd(), &mut bytes).unwrap();
 
         let svg = String::from_utf8(bytes).unwrap();
-        assert_diff!(svg.as_str(), expected_svg.as_str(), "\n", 0);
-    }        
+        svg.lines().next().unwrap().to_string()
+    };
+
+    assert_eq!(
+        render(None, None, None),
+        r#"<svg viewBox="0, 0, 800, 400" xmlns="http://www.w3.org/2000/svg">"#
+    );
+    assert_eq!(
+        render(None, None, Some(0.5)),
+        r#"<svg height="200" viewBox="0, 0, 800, 400" width="400" xmlns="http://www.w3.org/2000/svg">"#
+    );
+    assert_eq!(
+        render(Some(600.0), None, None),
+        r#"<svg height="300" viewBox="0, 0, 800, 400" width="600" xmlns="http://www.w3.org/2000/svg">"#
+    );
+    assert_eq!(
+        render(None, Some(100.0), Some(2.0)),
+        r#"<svg height="200" viewBox="0, 0, 800, 400" width="400" xmlns="http://www.w3.org/2000/svg">"#
+    );
+    assert_eq!(
+        render(Some(10.0), Some(20.0), None),
+        r#"<svg height="20" viewBox="0, 0, 800, 400" width="10" xmlns="http://www.w3.org/2000/svg">"#
+    );
+}
+
+#[test]
+fn background() {
+    let src = "erd {
+posts {
+    id int PK
+    user_id int FK
+}
+posts.user_id o--o users. [label=\"author\"]
+}";
+    let options = ConvertOptions {
+        implicit_entity_stubs: true,
+        ..ConvertOptions::default()
+    };
+    let render = |background: Background| {
+        render_svg_with(src, &options, |backend| backend.background = background)
+    };
+
+    // The terminal on `posts` is filled with the record background, and the one on the stub
+    // (which has no background) with the canvas background.
+    let svg = render(Background::Transparent);
+    assert!(!svg.contains("height=\"100%\""));
+    assert!(svg.contains("fill=\"#212121\" r=\"4\""));
+    assert!(svg.contains("fill=\"none\" r=\"4\""));
+
+    // The label pill is filled with the canvas background, or not at all.
+    let pill = |svg: &str| svg.lines().find(|line| line.contains("rx=\"9\"")).unwrap().to_owned();
+    assert!(pill(&svg).contains("fill=\"none\""));
+
+    let svg = render("#ffffff".parse().unwrap());
+    assert!(svg.contains("<rect fill=\"#FFFFFF\" height=\"100%\" width=\"100%\"/>"));
+    assert!(svg.contains("fill=\"#212121\" r=\"4\""));
+    assert!(svg.contains("fill=\"#FFFFFF\" r=\"4\""));
+    assert!(pill(&svg).contains("fill=\"#FFFFFF\""));
+
+    assert!("white".parse::<Background>().is_err());
+}
+
+#[test]
+fn edge_markers() {
+    let src = "erd {
+users { id int PK }
+posts {
+    id int PK
+    user_id int FK
+}
+posts.user_id o--o users.id [start=crow, end=one]
+}";
+    let svg = render_svg(src, |_| {});
+
+    assert!(svg.contains("<marker id=\"marker-crow\""));
+    assert!(svg.contains("<marker id=\"marker-one\""));
+    assert!(!svg.contains("<marker id=\"marker-arrow\""));
+    assert!(svg.contains("marker-end=\"url(#marker-one)\" marker-start=\"url(#marker-crow)\""));
+    // Terminal circles are drawn only for circle markers.
+    assert!(!svg.contains("r=\"4\""));
+}
+
+#[test]
+fn edge_marker_strokes() {
+    let src = "erd {
+users { id int PK }
+posts {
+    id int PK
+    user_id int FK
+    editor_id int FK
+}
+posts.user_id o--o users.id [color=#FF0000, width=3, end=crow, start=arrow]
+posts.editor_id o--o users.id [end=crow]
+}";
+    let svg = render_svg(src, |_| {});
+    let marker = |id: &str| {
+        let start = svg.find(&format!("<marker id=\"{}\"", id)).unwrap();
+        svg[start..start + svg[start..].find("</marker>").unwrap()].to_owned()
+    };
+
+    // Markers follow the color and the width of their edges.
+    assert!(marker("marker-arrow-FF0000-3")
+        .contains("fill=\"#FF0000\" stroke=\"#FF0000\" stroke-width=\"3\""));
+    assert!(marker("marker-crow-FF0000-3").contains("stroke=\"#FF0000\" stroke-width=\"3\""));
+    assert!(marker("marker-crow").contains("stroke=\"#888888\" stroke-width=\"1.5\""));
+    assert!(!svg.contains("<marker id=\"marker-arrow\""));
+    assert!(svg.contains(
+        "marker-end=\"url(#marker-crow-FF0000-3)\" marker-start=\"url(#marker-arrow-FF0000-3)\""
+    ));
+    assert!(svg.contains("marker-end=\"url(#marker-crow)\""));
+}
+
+#[test]
+fn edge_strokes() {
+    let src = "erd {
+users { id int PK }
+posts {
+    id int PK
+    user_id int FK
+    editor_id int FK
+    reviewer_id int FK
+}
+posts.user_id o--o users.id [style=dashed, width=3, color=#FF0000]
+posts.editor_id o--o users.id [style=dotted]
+posts.reviewer_id o--o users.id [style=wavy]
+}";
+    let svg = render_svg(src, |_| {});
+
+    assert!(svg.contains(" stroke=\"#FF0000\" stroke-dasharray=\"12 6\" stroke-width=\"3\"/>"));
+    assert!(svg.contains(" stroke=\"#888888\" stroke-dasharray=\"1.5 3\" stroke-width=\"1.5\"/>"));
+    // Terminal circles follow the color and the width of the edge.
+    assert!(svg.contains("r=\"4\" stroke=\"#FF0000\" stroke-width=\"3\"/>"));
+    // An unknown style is drawn solid.
+    assert_eq!(svg.matches("stroke-dasharray").count(), 2);
+}
+
+#[test]
+fn edge_layers() {
+    let src = "erd {
+users { id int PK }
+posts {
+    id int PK
+    user_id int FK
+    editor_id int FK
+}
+posts.user_id o--o users.id [layer=below, color=#FF0000, label=\"author\"]
+posts.editor_id o--o users.id [layer=above, color=#00FF00]
+}";
+    let render =
+        |edge_layer: EdgeLayer| render_svg(src, |backend| backend.edge_layer = edge_layer);
+
+    let svg = render(EdgeLayer::Normal);
+    let below = svg.find("stroke=\"#FF0000\"").unwrap();
+    let above = svg.find("stroke=\"#00FF00\"").unwrap();
+    let record = svg.find("<rect fill=\"#212121\"").unwrap();
+    let label = svg.find("author").unwrap();
+
+    assert!(below < record);
+    assert!(record < label && label < above);
+    // The attribute of a relation overrides the default layer.
+    assert_eq!(render(EdgeLayer::Below).find("stroke=\"#00FF00\"").unwrap(), above);
+    assert_eq!("above".parse(), Ok(EdgeLayer::Above));
+}
+
+#[test]
+fn self_relation() {
+    let src = "erd {
+users {
+    id int PK
+}
+users.id o--o users.id
+users. o--o users.
+ghosts. o--o ghosts.
+}";
+    let (ast, _, _) = parse(src);
+    let options = ConvertOptions {
+        implicit_entity_stubs: true,
+        ..ConvertOptions::default()
+    };
+    let doc = ast.unwrap().into_mir_with_options(&options);
+
+    // Self relations are skipped instead of drawing zero-length paths.
+    assert_eq!(doc.edges().count(), 0);
+
+    let svg = render_svg_with(src, &options, |_| {});
+    assert!(!svg.contains("<path"));
+    assert!(svg.contains("ghosts"));
+}
+
+#[test]
+fn edge_labels() {
+    let src = "erd {
+users {
+    id int PK
+}
+posts {
+    id int PK
+    author_id int FK
+}
+posts.author_id o--o users.id [label=\"<author>\"]
+posts.id o--o users.id
+}";
+    let (ast, _, _) = parse(src);
+    let mut doc = ast.unwrap().into_mir();
+    let mut engine = SimpleLayoutEngine::default();
+    let backend = SVGRenderer::new();
+    let mut bytes: Vec<u8> = vec![];
+
+    engine.layout(&mut doc);
+
+    let labels: Vec<Option<&str>> = doc.edges().map(|edge| edge.label()).collect();
+    assert_eq!(labels, vec![Some("<author>"), None]);
+
+    backend.render(&doc, &mut bytes).unwrap();
+
+    let svg = String::from_utf8(bytes).unwrap();
+    assert_eq!(svg.matches("\n&lt;author&gt;\n").count(), 1);
+    assert_eq!(svg.matches("rx=\"9\"").count(), 1);
+
+    // The pill is placed between records.
+    let records: Vec<Rect> = doc
+        .body()
+        .children()
+        .filter_map(|node_id| doc.get_node(node_id)?.rect())
+        .collect();
+    let pill = svg.lines().find(|line| line.contains("rx=\"9\"")).unwrap();
+    let attr = |name: &str| -> f32 {
+        let start = pill.find(&format!(" {}=\"", name)).unwrap() + name.len() + 3;
+        let end = start + pill[start..].find('"').unwrap();
+        pill[start..end].parse().unwrap()
+    };
+    let pill = Rect::new(
+        Point::new(attr("x"), attr("y")),
+        Size::new(attr("width"), attr("height")),
+    );
+    assert!(records.iter().all(|rect| !rect.intersects(&pill)));
+}
+
+#[test]
+fn cardinalities() {
+    let src = "erd {
+users {
+    id int PK
+}
+posts {
+    id int PK
+    author_id int FK
+}
+posts.author_id o--o users.id [start=crow, end=one]
+}";
+    let (ast, _, _) = parse(src);
+    let mut doc = ast.unwrap().into_mir();
+    let mut engine = SimpleLayoutEngine::default();
+    let backend = SVGRenderer::new();
+    let mut bytes: Vec<u8> = vec![];
+
+    engine.layout(&mut doc);
+    backend.render(&doc, &mut bytes).unwrap();
+
+    let svg = String::from_utf8(bytes).unwrap();
+    let edge = doc.edges().next().unwrap();
+    let path_points = edge.path_points().unwrap();
+    let (start, end) = (path_points[0], path_points[path_points.len() - 1]);
+
+    // Texts are placed beside the edge, away from the ports.
+    let text_at = |x: f32, y: f32, text: &str| {
+        svg.contains(&format!("text-anchor=\"middle\" x=\"{}\" y=\"{}\">\n{}\n", x, y, text))
+    };
+    assert!(text_at(start.x - 20.0, start.y - 8.0, "N"));
+    assert!(text_at(end.x + 20.0, end.y - 8.0, "1"));
+}
+
+#[test]
+fn route_tie_breaking() {
+    let mut ties = 0;
+
+    for name in ["comments", "posts", "tags"] {
+        let src = fs::read_to_string(format!("example/{}.seiren", name)).unwrap();
+        let (ast, _, _) = parse(&src);
+        let mut doc = ast.unwrap().into_mir();
+        let mut engine = SimpleLayoutEngine::default();
+
+        engine.layout(&mut doc);
+
+        for edge_id in doc.edge_ids() {
+            let explanation = engine.explain_edge(&doc, edge_id).unwrap();
+            let key = |c: &seiren::layout::RouteCandidate| {
+                (
+                    c.cost,
+                    SimpleLayoutEngine::port_preference(&c.source),
+                    SimpleLayoutEngine::port_preference(&c.target),
+                )
+            };
+            let best = explanation.candidates.iter().map(key).min().unwrap();
+            ties += explanation.candidates.iter().filter(|c| c.cost == best.0).count() - 1;
+
+            // Among routes of the lowest cost, horizontal ports and then lower indices win.
+            assert_eq!(key(&explanation.candidates[explanation.selected.unwrap()]), best);
+        }
+    }
+
+    // `tags.seiren` has an edge with equal-cost routes.
+    assert!(ties > 0);
+}
+
+#[test]
+fn deterministic_output() {
+    let src = fs::read_to_string("example/posts.seiren").unwrap();
+    let render = || {
+        render_svg(&src, |backend| {
+            backend.css_classes = true;
+            backend.legend = true;
+            backend.interactive = true;
+            backend.accessible = true;
+            backend.shadow = true;
+            backend.grid = Some(GridPattern::Dots);
+            backend.version_stamp = Some(SimpleLayoutEngine::default().config_hash());
+            backend.options.font_families.insert(FontFamily::Monospace1, "Menlo".into());
+            backend.options.font_families.insert(FontFamily::SansSerif3, "Arial".into());
+        })
+    };
+
+    let svg = render();
+    for _ in 0..4 {
+        assert_eq!(render(), svg);
+    }
+
+    // Sections are written in the documented order.
+    let sections = ["<style>", "<metadata", "<rect", "<defs>", "<g", "<path", "<script"];
+    let positions: Vec<_> = sections
+        .iter()
+        .map(|tag| svg.find(tag).unwrap_or_else(|| panic!("{} is missing", tag)))
+        .collect();
+    assert!(positions.windows(2).all(|pair| pair[0] < pair[1]), "{:?}", positions);
+}
+
+#[test]
+fn margin_and_center() {
+    let render = |margin: f32, size: Option<(f32, f32)>| {
+        let src = fs::read_to_string("example/posts.seiren").unwrap();
+        let (ast, _, _) = parse(&src);
+        let mut doc = ast.unwrap().into_mir();
+        let mut engine = SimpleLayoutEngine::default();
+        let mut backend = SVGRenderer::new();
+        let mut bytes: Vec<u8> = vec![];
+
+        engine.options.margin = margin;
+        backend.view_box = engine.layout(&mut doc).view_box;
+        backend.width = size.map(|(width, _)| width);
+        backend.height = size.map(|(_, height)| height);
+        backend.center = true;
+        backend.render(&doc, &mut bytes).unwrap();
+
+        (backend.view_box.unwrap(), String::from_utf8(bytes).unwrap())
+    };
+
+    let (view_box, svg) = render(LayoutOptions::DEFAULT_MARGIN, None);
+    let (small_view_box, _) = render(10.0, None);
+    assert!(svg.starts_with(&format!(
+        "<svg viewBox=\"0, 0, {}, {}\"",
+        view_box.width(),
+        view_box.height()
+    )));
+    assert_eq!(small_view_box.width(), view_box.width() - 80.0);
+    assert_eq!(small_view_box.height(), view_box.height() - 80.0);
+
+    // The diagram keeps its size in the middle of the canvas.
+    let (_, svg) = render(LayoutOptions::DEFAULT_MARGIN, Some((2000.0, 1000.0)));
+    let x = (view_box.width() - 2000.0) / 2.0;
+    let y = (view_box.height() - 1000.0) / 2.0;
+    assert!(svg.starts_with(&format!(
+        "<svg height=\"1000\" viewBox=\"{}, {}, 2000, 1000\" width=\"2000\"",
+        x, y
+    )));
+    assert!(svg.contains(&format!(
+        "<rect fill=\"#1C1C1C\" height=\"100%\" width=\"100%\" x=\"{}\" y=\"{}\"/>",
+        x, y
+    )));
+}
+
+#[test]
+fn layout_options() {
+    let place = |options: LayoutOptions| {
+        let src = fs::read_to_string("example/posts.seiren").unwrap();
+        let (ast, _, _) = parse(&src);
+        let mut doc = ast.unwrap().into_mir();
+        let mut engine = SimpleLayoutEngine::new(options);
+        let view_box = engine.layout(&mut doc).view_box.unwrap();
+
+        (view_box, engine.config_hash())
+    };
+
+    let (view_box, hash) = place(LayoutOptions::default());
+    let (dense_view_box, dense_hash) = place(LayoutOptions {
+        line_height: 25.0,
+        record_width: 200.0,
+        record_space: 40.0,
+        shape_junction_margin: 20.0,
+        ..LayoutOptions::default()
+    });
+
+    // Records in 3 grid columns, whether they are filled or not.
+    assert_eq!(view_box.width(), 50.0 * 2.0 + 300.0 * 3.0 + 80.0 * 2.0);
+    assert_eq!(dense_view_box.width(), 50.0 * 2.0 + 200.0 * 3.0 + 40.0 * 2.0);
+    // 7 rows of the header and fields, and the space below the title.
+    assert_eq!(
+        view_box.height() - dense_view_box.height(),
+        (35.0 - 25.0) * 7.0 + (80.0 - 40.0) * 0.75
+    );
+    assert_ne!(hash, dense_hash);
+    assert_eq!(hash, SimpleLayoutEngine::default().config_hash());
+}
+
+#[test]
+fn title() {
+    let render = |src: &str| {
+        let mut view_box = None;
+        let svg = render_svg(src, |backend| view_box = backend.view_box);
+
+        (view_box.unwrap(), svg)
+    };
+
+    let (untitled_box, svg) = render("erd { users { id int PK } }");
+    assert!(!svg.contains("font-size=\"large\""));
+
+    let (titled_box, svg) = render("erd MyApp { users { id int PK } }");
+    assert!(svg.contains("font-size=\"large\" font-weight=\"bold\" text-anchor=\"start\" x=\"50\" y=\"59\">\nMyApp\n"));
+    // The view box is extended by the title and the space below it.
+    assert_eq!(titled_box.width(), untitled_box.width());
+    assert_eq!(titled_box.height(), untitled_box.height() + 18.0 + 60.0);
+}
+
+#[test]
+fn legend() {
+    let render = |src: &str, legend: bool| render_svg(src, |backend| backend.legend = legend);
+
+    let svg = render(
+        "erd {
+users { id int PK }
+posts { user_id int FK }
+}",
+        true,
+    );
+    assert!(svg.starts_with("<svg viewBox=\"0, 0, 1160, "));
+    assert!(svg.contains("\nprimary key\n"));
+    assert!(svg.contains("\nforeign key\n"));
+    assert!(svg.contains("\nnumeric\n"));
+    assert!(!svg.contains("\ntext\n"));
+
+    // The single column leaves no free corner, so the view box is extended for the legend.
+    let src = "erd {\n%columns 1\nusers { id int PK }\n}";
+    assert!(render(src, false).starts_with("<svg viewBox=\"0, 0, 400, 170\""));
+    let svg = render(src, true);
+    assert!(svg.starts_with("<svg viewBox=\"0, 0, 400, 272\""));
+    assert!(svg.contains("\nprimary key\n"));
+
+    // Nothing to explain
+    let src = "erd { users {} }";
+    assert_eq!(render(src, true), render(src, false));
+}
+
+#[test]
+fn version_stamp() {
+    let mut engine = SimpleLayoutEngine::default();
+    let svg = render_svg("erd { users { id int PK } }", |backend| {
+        backend.version_stamp = Some(engine.config_hash());
+    });
+    assert!(svg.contains(&format!(
+        "<metadata data-seiren-layout=\"{:016x}\" data-seiren-version=\"{}\"/>",
+        engine.config_hash(),
+        seiren::VERSION
+    )));
+
+    // The hash depends on layout settings.
+    engine.fit_record_width = true;
+    assert!(!svg.contains(&format!("{:016x}", engine.config_hash())));
+}
+
+#[test]
+fn embedded_source() {
+    let src = "erd {\n// a < b && c > d\nusers { id int PK }\n}\n";
+    let render = |source: &str, format: SVGFormat| {
+        render_svg(src, |backend| {
+            backend.source = Some(source.to_string());
+            backend.format = format;
+        })
+    };
+
+    for format in SVGFormat::ALL {
+        let svg = render(src, format);
+        assert!(svg.contains(&format!("<metadata data-seiren-version=\"{}\">", seiren::VERSION)));
+        assert!(svg.contains("// a &lt; b &amp;&amp; c &gt; d"));
+        assert_eq!(extract_source(&svg).as_deref(), Some(src));
+    }
+
+    // Whitespace at the ends of the source is kept in every format.
+    for src in ["erd {}", "\nerd {}\n\n", " erd {}\t", "\r\nerd {}\r\n"] {
+        for format in SVGFormat::ALL {
+            let svg = render(src, format);
+            assert_eq!(extract_source(&svg).as_deref(), Some(src));
+        }
+    }
+
+    assert_eq!(extract_source("<svg/>"), None);
+}
+
+#[test]
+fn interactive() {
+    let (ast, _, _) = parse(
+        "erd {
+users { id int PK }
+posts { user_id int FK }
+posts.user_id o--o users.id [label=\"writes\"]
+}",
+    );
+    let mut doc = ast.unwrap().into_mir();
+    let mut engine = SimpleLayoutEngine::default();
+    let mut backend = SVGRenderer::new();
+    let mut bytes: Vec<u8> = vec![];
+
+    backend.view_box = engine.layout(&mut doc).view_box;
+    backend.interactive = true;
+    backend.render(&doc, &mut bytes).unwrap();
+
+    let svg = String::from_utf8(bytes).unwrap();
+    let edge = doc.edges().next().unwrap();
+
+    // Every field is a group, which the edge refers to.
+    assert_eq!(svg.matches("<g data-node=").count(), 4);
+    for node_id in [edge.source_id(), edge.target_id()] {
+        assert!(svg.contains(&format!("<g data-node=\"{}\">", node_id)));
+    }
+    // The path and the label
+    let edge_group = format!(
+        "<g data-source=\"{}\" data-target=\"{}\">",
+        edge.source_id(),
+        edge.target_id()
+    );
+    assert_eq!(svg.matches(&edge_group).count(), 2);
+    assert_eq!(svg.matches("class=\"hit-area\"").count(), 4);
+    assert!(svg.contains(".highlight"));
+    assert!(svg.contains("<script>"));
+}
+
+#[test]
+fn hyperlinks() {
+    let render = |src: &str| render_svg(src, |_| {});
+
+    let svg = render(
+        "erd {
+users [url=\"https://example.com/users?tab=columns&lang=en\"] {
+    id int PK
+    email text [url=\"https://example.com/users#email\"]
+}
+posts { id int PK }
+}",
+    );
+    let users = "<a href=\"https://example.com/users?tab=columns&amp;lang=en\" \
+                 xlink:href=\"https://example.com/users?tab=columns&amp;lang=en\">";
+    let email = "<a href=\"https://example.com/users#email\" \
+                 xlink:href=\"https://example.com/users#email\">";
+
+    assert!(svg.contains("xmlns:xlink=\"http://www.w3.org/1999/xlink\""));
+    // The record background, the header and `id` link to the record's page.
+    assert_eq!(svg.matches(users).count(), 3);
+    assert_eq!(svg.matches(email).count(), 1);
+    // `posts` has no links.
+    assert_eq!(svg.matches("<a ").count(), 4);
+
+    let svg = render("erd { users { id int PK } }");
+    assert!(!svg.contains("<a ") && !svg.contains("xmlns:xlink"));
+}
+
+#[test]
+fn accessibility() {
+    let src = "erd Blog {
+users [url=\"https://example.com/users\"] { id int PK }
+posts { user_id int FK }
+posts.user_id o--o users.id
+}";
+    let svg = render_svg(src, |backend| {
+        backend.accessible = true;
+        backend.interactive = true;
+    });
+
+    // The title and the description are the first children of the root.
+    assert!(svg.starts_with("<svg aria-label=\"Blog\" role=\"graphics-document document\""));
+    assert!(svg.contains(
+        "\">\n<title>\nBlog\n</title>\n<desc>\n2 entities (users, posts) and 1 relation.\n</desc>\n"
+    ));
+    assert!(svg.contains(
+        "<g aria-label=\"Entity users\" role=\"group\">\n<title>\nEntity users\n</title>\n\
+         <desc>\n1 field.\n</desc>"
+    ));
+    // Headers aren't fields.
+    assert_eq!(svg.matches("aria-label=\"Field ").count(), 2);
+    assert!(svg.contains("<desc>\nint, primary key\n</desc>"));
+    assert!(svg.contains("aria-label=\"Relation from posts.user_id to users.id\""));
+    assert!(svg.contains("role=\"img\""));
+}
+
+#[test]
+fn accessibility_escape() {
+    let src = "erd `Blog & \"<Posts>\"` {
+`<users>` { `a&\"b\"` int PK }
+}";
+    let svg = render_svg(src, |backend| backend.accessible = true);
+
+    assert!(svg.starts_with("<svg aria-label=\"Blog &amp; &quot;&lt;Posts&gt;&quot;\""));
+    assert!(svg.contains("aria-label=\"Entity &lt;users&gt;\""));
+    assert!(svg.contains("aria-label=\"Field a&amp;&quot;b&quot;\""));
+}
+
+#[test]
+fn id_prefix() {
+    let src = "erd {
+users { id int PK }
+posts { user_id int FK }
+posts.user_id o--o users.id [start=crow, end=one]
+}";
+    let svg = render_svg(src, |backend| backend.id_prefix = "erd1-".to_string());
+
+    // clip paths of two records and two markers
+    assert_eq!(svg.matches(" id=\"").count(), 4);
+    assert_eq!(svg.matches(" id=\"erd1-").count(), 4);
+    assert!(svg.contains("id=\"erd1-marker-crow\""));
+    assert_eq!(svg.matches("url(#").count(), svg.matches("url(#erd1-").count());
+    assert!(svg.contains("url(#erd1-record-clip-path-0)"));
+}
+
+#[test]
+fn svg_formats() {
+    let render = |format: SVGFormat| {
+        render_svg("erd { users { id int PK } }", |backend| backend.format = format)
+    };
+
+    let default = render(SVGFormat::Default);
+    let minified = render(SVGFormat::Minified);
+    let pretty = render(SVGFormat::Pretty);
+
+    assert!(!minified.contains('\n'));
+    assert!(minified.contains(">PK</text>"));
+    assert!(minified.len() < default.len());
+    assert!(pretty.contains("\n  <defs>\n    <clipPath id=\"record-clip-path-0\">\n      <rect "));
+    assert!(pretty.contains(">PK</text>\n"));
+    // Only whitespace between tags differs.
+    let strip = |svg: &str| svg.split_whitespace().collect::<String>();
+    assert_eq!(strip(&minified), strip(&default));
+    assert_eq!(strip(&pretty), strip(&default));
+}
+
+#[test]
+fn dyn_renderer() {
+    let (ast, _, _) = parse("erd { users { id int PK } }");
+    let mut doc = ast.unwrap().into_mir();
+    let mut engine = SimpleLayoutEngine::default();
+    let view_box = engine.layout(&mut doc).view_box;
+
+    // Backends are chosen at runtime.
+    let backends: Vec<(Box<dyn Renderer>, &str)> = vec![
+        (Box::new(SVGRenderer { view_box, ..SVGRenderer::new() }), "<svg"),
+        (
+            Box::new(SVGRenderer {
+                view_box,
+                format: SVGFormat::Pretty,
+                ..SVGRenderer::new()
+            }),
+            "<svg",
+        ),
+        (Box::new(HtmlRenderer { view_box, ..HtmlRenderer::new() }), "<!DOCTYPE html>"),
+        (Box::new(TextRenderer { view_box, ..TextRenderer::new() }), "╭─"),
+    ];
+
+    for (backend, prefix) in backends {
+        let mut bytes: Vec<u8> = vec![];
+
+        backend.render(&doc, &mut bytes).unwrap();
+        assert!(String::from_utf8(bytes).unwrap().starts_with(prefix));
+    }
+}
+
+#[test]
+fn html_renderer() {
+    let (ast, _, _) = parse(
+        "erd Blog {
+users { id int PK }
+posts [url=\"https://example.com/posts?a=1&b=2\"] {
+    id int PK
+    user_id int FK
+}
+posts.user_id o--o users.id
+}",
+    );
+    let mut doc = ast.unwrap().into_mir();
+    let mut engine = SimpleLayoutEngine::default();
+    let mut backend = HtmlRenderer::new();
+    let mut bytes: Vec<u8> = vec![];
+
+    backend.view_box = engine.layout(&mut doc).view_box;
+    backend.render(&doc, &mut bytes).unwrap();
+
+    let html = String::from_utf8(bytes).unwrap();
+    assert!(html.starts_with("<!DOCTYPE html>\n"));
+    assert!(html.contains("<title>Blog</title>"));
+    assert_eq!(html.matches("<table ").count(), 2);
+    assert!(html.contains("<table style=\"left: 50px; top: 128px; width: 300px; "));
+    assert!(html.contains("\">users</th></tr>"));
+    assert!(html.contains("title=\"primary key\">PK</span>"));
+    assert!(html.contains("<a href=\"https://example.com/posts?a=1&amp;b=2\""));
+    // Edges are drawn in an SVG overlay.
+    assert!(html.contains("<svg height="));
+    assert!(html.contains("<path d=\"M"));
+    assert!(html.trim_end().ends_with("</html>"));
+
+    let mut bytes: Vec<u8> = vec![];
+    backend.fragment = true;
+    backend.render(&doc, &mut bytes).unwrap();
+    assert!(String::from_utf8(bytes).unwrap().starts_with("<style>\n"));
+}
+
+#[test]
+fn text_renderer() {
+    let render = |charset: TextCharset| {
+        let (ast, _, _) = parse(
+            "erd {
+users { id int PK }
+posts {
+    id int PK
+    user_id int FK
+}
+posts.user_id o--o users.id
+}",
+        );
+        let mut doc = ast.unwrap().into_mir();
+        let mut engine = SimpleLayoutEngine::default();
+        let mut backend = TextRenderer::new();
+        let mut bytes: Vec<u8> = vec![];
+
+        backend.view_box = engine.layout(&mut doc).view_box;
+        backend.charset = charset;
+        backend.render(&doc, &mut bytes).unwrap();
+
+        String::from_utf8(bytes).unwrap()
+    };
+
+    assert_eq!(
+        render(TextCharset::Unicode),
+        "\
+╭─────────────────────────────╮       ╭─────────────────────────────╮
+│ users                       │       │ posts                       │
+├─────────────────────────────┤       ├─────────────────────────────┤
+│ id                   int PK ├○──┐   │ id                   int PK │
+╰─────────────────────────────╯   │   │                             │
+                                  └──○┤ user_id              int FK │
+                                      ╰─────────────────────────────╯
+"
+    );
+    assert_eq!(
+        render(TextCharset::Ascii),
+        "\
++-----------------------------+       +-----------------------------+
+| users                       |       | posts                       |
++-----------------------------+       +-----------------------------+
+| id                   int PK +o--+   | id                   int PK |
++-----------------------------+   |   |                             |
+                                  +--o+ user_id              int FK |
+                                      +-----------------------------+
+"
+    );
+}
+
+#[test]
+fn background_grid() {
+    let render = |grid: Option<GridPattern>, css_classes: bool| {
+        render_svg("erd { users { id int PK } }", |backend| {
+            backend.grid = grid;
+            backend.css_classes = css_classes;
+            backend.id_prefix = "a-".to_string();
+        })
+    };
+
+    let dots = render(Some(GridPattern::Dots), false);
+    assert!(dots.contains("<rect fill=\"url(#a-grid)\" height=\"100%\" width=\"100%\"/>"));
+    assert!(dots.contains(
+        "<pattern height=\"20\" id=\"a-grid\" patternUnits=\"userSpaceOnUse\" width=\"20\">"
+    ));
+    assert!(dots.contains("<circle cx=\"1\" cy=\"1\" fill=\"#494949\" r=\"1\"/>"));
+    // The grid is drawn over the background, and under records.
+    assert!(dots.find("fill=\"#1C1C1C\"") < dots.find("url(#a-grid)"));
+    assert!(dots.find("url(#a-grid)") < dots.find("<text"));
+
+    let lines = render(Some(GridPattern::Lines), true);
+    assert!(lines.contains("<path class=\"grid\" d=\"M20 0 L0 0 L0 20\" fill=\"none\"/>"));
+    assert!(lines.contains(".grid {"));
+
+    assert!(!render(None, false).contains("grid"));
+    for grid in GridPattern::ALL {
+        assert_eq!(grid.to_string().parse(), Ok(grid));
+    }
+}
+
+#[test]
+fn record_shadows() {
+    let src = "erd {\nusers { id int PK }\nposts { id int PK }\n}";
+    let render = |shadow: bool, theme: Theme| {
+        let options = ConvertOptions {
+            theme: theme.clone(),
+            ..ConvertOptions::default()
+        };
+
+        render_svg_with(src, &options, |backend| {
+            backend.shadow = shadow;
+            backend.theme = theme;
+        })
+    };
+
+    let svg = render(true, Theme::dark());
+    assert!(svg.contains("<filter height=\"120%\" id=\"record-shadow\" width=\"120%\""));
+    assert!(svg.contains("<feGaussianBlur stdDeviation=\"4\"/>"));
+    assert_eq!(svg.matches("filter=\"url(#record-shadow)\"").count(), 2);
+    // A shadow is drawn under its record.
+    let shadow = svg
+        .find("<rect fill=\"#000000\" fill-opacity=\"0.4\" filter=\"url(#record-shadow)\"")
+        .unwrap();
+    assert!(shadow < svg.find("<rect fill=\"#212121\"").unwrap());
+
+    let theme = Theme::parse("base = light\nshadow_color = #336699").unwrap();
+    assert!(render(true, theme).contains("<rect fill=\"#336699\" fill-opacity=\"0.4\""));
+
+    assert!(!render(false, Theme::dark()).contains("shadow"));
+}
+
+#[test]
+fn header_gradients() {
+    let render = |theme: Theme| {
+        render_svg("erd {\nusers { id int PK }\nposts { id int PK }\n}", |backend| {
+            backend.theme = theme;
+        })
+    };
+
+    let theme = Theme::parse("header_gradient_color = #101010").unwrap();
+    assert_eq!(theme.header_gradient_color.as_ref().unwrap().to_string(), "#101010");
+
+    let svg = render(theme);
+    assert!(svg.contains(
+        "<linearGradient id=\"header-gradient-1\" x1=\"0\" x2=\"0\" y1=\"0\" y2=\"1\">\n\
+         <stop offset=\"0\" stop-color=\"#494949\"/>\n\
+         <stop offset=\"1\" stop-color=\"#101010\"/>\n\
+         </linearGradient>"
+    ));
+    assert!(svg.contains("fill=\"url(#header-gradient-0)\""));
+    assert!(svg.contains("fill=\"url(#header-gradient-1)\""));
+
+    assert!(!render(Theme::dark()).contains("gradient"));
+}
+
+#[test]
+fn entity_kind_icons() {
+    let src = "erd {
+users { id int PK }
+active_users [kind=view] { id int }
+roles [kind=enum] { admin text }
+}";
+    let svg = render_svg(src, |_| {});
+    // An icon for each record with a kind, drawn in the color of the name
+    assert_eq!(svg.matches("<path d=\"M0.5 6 Q6 0").count(), 1);
+    assert_eq!(svg.matches("<path d=\"M1 2.5 H2.5").count(), 1);
+    assert_eq!(svg.matches("<path d=\"M1 1 H11 V11").count(), 0);
+    assert_eq!(svg.matches("fill=\"none\" stroke=\"white\" stroke-width=\"1.2\"").count(), 2);
+}
+
+#[test]
+fn striped_rows() {
+    let src = "erd {\nusers {\nid int PK\nname text\nemail text\nage int\n}\n}";
+    let render = |theme: Theme| render_svg(src, |backend| backend.theme = theme);
+
+    let svg = render(Theme::parse("stripe_color = #2a2a2a").unwrap());
+    // The 2nd and 4th fields are striped.
+    assert_eq!(svg.matches("fill=\"#2A2A2A\"").count(), 2);
+    assert!(svg.contains(
+        "<rect clip-path=\"url(#record-clip-path-0)\" fill=\"#2A2A2A\" height=\"35\" \
+         width=\"300\" x=\"50\" y=\"120\"/>"
+    ));
+
+    assert!(!render(Theme::dark()).contains("#2A2A2A"));
+}
+
+#[test]
+fn highlighted_key_rows() {
+    let options = ConvertOptions {
+        highlight_keys: Some(KeyHighlight::AllKeys),
+        ..ConvertOptions::default()
+    };
+    let svg = render_svg_with(
+        "erd {\nposts {\nid int PK\nuser_id int FK\ntitle text\n}\n}",
+        &options,
+        |_| {},
+    );
+    // Key rows are filled inside the rounded corners of the record.
+    assert!(svg.contains(
+        "<rect clip-path=\"url(#record-clip-path-0)\" fill=\"#2A2A2A\" height=\"35\" \
+         width=\"300\" x=\"50\" y=\"85\"/>"
+    ));
+    assert!(svg.contains(
+        "<rect clip-path=\"url(#record-clip-path-0)\" fill=\"#1D242E\" height=\"35\" \
+         width=\"300\" x=\"50\" y=\"120\"/>"
+    ));
+    assert!(!svg.contains("y=\"155\"/>"));
+}
+
+#[test]
+fn auto_theme() {
+    let svg = render_svg("erd {\nusers { id int PK }\n}", |backend| {
+        backend.light_theme = Some(Theme::light());
+    });
+    assert!(svg.contains("svg { --background-color: #1C1C1C; --record-bg-color: #212121;"));
+    assert!(svg.contains(
+        "@media (prefers-color-scheme: light) { svg { --background-color: white; \
+         --record-bg-color: #FAFAFA;"
+    ));
+    assert!(svg.contains(
+        ".record { stroke: var(--record-border-color); fill: var(--record-bg-color); }"
+    ));
+    // No colors are left in attributes.
+    assert!(!svg.contains(" fill=\"#"));
+}
+
+#[test]
+fn auto_theme_shared_colors() {
+    let src = "erd {
+users { id int PK\nname citext }
+posts { id int PK\nuser_id int FK }
+posts.user_id o--o users.id
+}";
+    let svg = render_svg(src, |backend| backend.light_theme = Some(Theme::light()));
+
+    // The dark theme uses the same gray for edges and other types, and the same color for
+    // borders and headers, but the light theme doesn't.
+    assert!(svg.contains("--record-border-color: #D0D0D0; --header-bg-color: #E5E5E5;"));
+    assert!(svg.contains("--edge-color: #8C8C8C;"));
+    assert!(svg.contains("--named-type-color: #737373;"));
+    assert!(svg.contains(".record { stroke: var(--record-border-color);"));
+    assert!(svg.contains(".record-header { fill: var(--header-bg-color); }"));
+    assert!(svg.contains(".edge { stroke: var(--edge-color);"));
+    assert!(svg.contains("style=\"fill: var(--named-type-color)\""));
+}
+
+#[test]
+fn debug_overlay() {
+    let src = fs::read_to_string("example/posts.seiren").unwrap();
+    let (ast, _, _) = parse(&src);
+    let mut doc = ast.unwrap().into_mir();
+    let mut engine = SimpleLayoutEngine::default();
+    let view_box = engine.layout(&mut doc).view_box;
+
+    let render = |show_debug: bool| {
+        let mut backend = SVGRenderer::new();
+        let mut bytes: Vec<u8> = vec![];
+
+        backend.view_box = view_box;
+        backend.edge_route_graph = Some(engine.edge_route_graph());
+        backend.show_debug = show_debug;
+        backend.render(&doc, &mut bytes).unwrap();
+
+        String::from_utf8(bytes).unwrap()
+    };
+
+    let svg = render(false);
+    let debug = svg.find("<g id=\"debug\" visibility=\"hidden\">").unwrap();
+    // The debug group is drawn over the diagram, and contains all debug elements.
+    assert!(svg.rfind("<text").unwrap() > debug);
+    assert!(svg[..debug].rfind("fill=\"red\"").is_none());
+    for class in ["debug-routes", "debug-junctions", "debug-paths", "debug-node-ids"] {
+        assert!(svg[debug..].contains(&format!("<g class=\"{}\">", class)));
+    }
+
+    assert!(render(true).contains("<g id=\"debug\" visibility=\"visible\">"));
+}
+
+#[test]
+fn tiled_pages() {
+    let src = fs::read_to_string("example/posts.seiren").unwrap();
+    let (ast, _, _) = parse(&src);
+    let mut doc = ast.unwrap().into_mir();
+    let mut engine = SimpleLayoutEngine::default();
+    let view_box = engine.layout(&mut doc).view_box.unwrap();
+    let mut backend = SVGRenderer::new();
+
+    let pages = PageGrid::new(2, 1).pages(&view_box, 40.0);
+    assert_eq!(pages.len(), 2);
+
+    let svgs: Vec<String> = pages
+        .into_iter()
+        .map(|page| {
+            let mut bytes: Vec<u8> = vec![];
+
+            backend.view_box = Some(page.view_box);
+            backend.page_label = Some(page.label);
+            backend.render(&doc, &mut bytes).unwrap();
+            String::from_utf8(bytes).unwrap()
+        })
+        .collect();
+
+    let half = view_box.width() / 2.0;
+    assert!(svgs[0].starts_with(&format!(
+        "<svg viewBox=\"0, 0, {}, {}\"",
+        half + 40.0,
+        view_box.height()
+    )));
+    assert!(svgs[1].starts_with(&format!(
+        "<svg viewBox=\"{}, 0, {}, {}\"",
+        half - 40.0,
+        half + 40.0,
+        view_box.height()
+    )));
+    assert!(svgs[0].contains(">\n1/2 (row 1, column 1)\n</text>"));
+    assert!(svgs[1].contains(">\n2/2 (row 1, column 2)\n</text>"));
+    // The background covers the page.
+    assert!(svgs[1].contains(&format!("x=\"{}\" y=\"0\"/>", half - 40.0)));
+}
+
+#[test]
+fn entity_filter() {
+    let (ast, _, _) = parse(
+        "erd {
+users { id int PK }
+posts {
+    id int PK
+    user_id int FK
+}
+comments [tags=\"discussion\"] {
+    id int PK
+    post_id int FK
+    user_id int FK
+}
+posts.user_id o--o users.id
+comments.post_id o--o posts.id
+comments.user_id o--o users.id
+}",
+    );
+    let options = ConvertOptions {
+        implicit_entity_stubs: true,
+        entity_filter: EntityFilter::new(vec![], vec!["users".to_string()]),
+        ..ConvertOptions::default()
+    };
+    let doc = ast.unwrap().into_mir_with_options(&options);
+
+    // `users` isn't drawn as a stub, and relations to it are dropped.
+    assert_eq!(doc.body().children().count(), 2);
+    assert_eq!(doc.edges().count(), 1);
+}
+
+#[test]
+fn render_options() {
+    let src = "erd {
+users { id int PK }
+posts {
+    id int PK
+    user_id int FK
+}
+posts.user_id o--o users.id
+}";
+    let render = |options: RenderOptions| render_svg(src, |backend| backend.options = options);
+
+    let default = render(RenderOptions::default());
+    let mut options = RenderOptions {
+        padding_x: 20.0,
+        border_radius: 0.0,
+        circle_radius: 5.0,
+        edge_stroke_width: 2.0,
+        ..RenderOptions::default()
+    };
+    options
+        .font_families
+        .insert(FontFamily::Monospace1, "JetBrains Mono,monospace".to_string());
+    let custom = render(options);
+
+    assert!(default.contains("rx=\"6\""));
+    assert!(!custom.contains("rx=\"6\""));
+    assert!(custom.contains("rx=\"0\""));
+    assert!(custom.contains("r=\"5\""));
+    assert!(custom.contains("stroke-width=\"2\""));
+    assert!(!custom.contains("stroke-width=\"1.5\""));
+    // Field titles are indented by the padding.
+    assert!(default.contains("x=\"62\""));
+    assert!(custom.contains("x=\"70\""));
+    assert!(custom.contains("font-family=\"JetBrains Mono,monospace\""));
+    assert!(!custom.contains("Monaco"));
+    assert!(custom.contains("Courier New"));
+}
+
+#[test]
+fn path_radius() {
+    let (ast, _, _) = parse(
+        "erd {
+users { id int PK }
+posts {
+    id int PK
+    user_id int FK
+}
+posts.user_id o--o users.id
+}",
+    );
+    let mut doc = ast.unwrap().into_mir();
+    let mut engine = SimpleLayoutEngine::default();
+    let mut backend = SVGRenderer::new();
+
+    backend.view_box = engine.layout(&mut doc).view_box;
+    // A jog of 4px between two corners
+    let edge = doc.edges_mut().next().unwrap();
+    edge.set_path_points(Some(vec![
+        Point::new(0.0, 0.0),
+        Point::new(50.0, 0.0),
+        Point::new(50.0, 4.0),
+        Point::new(100.0, 4.0),
+    ]));
+
+    let mut bytes: Vec<u8> = vec![];
+    backend.render(&doc, &mut bytes).unwrap();
+    let svg = String::from_utf8(bytes).unwrap();
+
+    // Both corners are drawn with the radius of 2px instead of 6px.
+    assert!(svg.contains("d=\"M0 0 L48 0 Q50 0 50 2 L50 2 Q50 4 52 4 L100 4\""));
+
+    let layout = LayoutOptions::default();
+    assert!(RenderOptions::default().validate(&layout).is_ok());
+    let options = RenderOptions {
+        path_radius: layout.shape_junction_margin / 2.0,
+        ..RenderOptions::default()
+    };
+    assert_eq!(
+        options.validate(&layout),
+        Err(RenderOptionsError::PathRadiusTooLarge(20.0, 40.0))
+    );
+}
+
+#[test]
+fn layout_seed() {
+    let src = "erd {
+%columns 1
+users { id int PK }
+posts { id int PK\nuser_id int FK\neditor_id int FK }
+posts.user_id o--o users.id
+posts.editor_id o--o users.id
+}";
+    let layout = |seed: Option<u64>| {
+        let (ast, _, _) = parse(src);
+        let mut doc = ast.unwrap().into_mir();
+        let mut engine = SimpleLayoutEngine::default();
+
+        engine.seed = seed;
+        engine.layout(&mut doc);
+
+        let paths: Vec<Vec<Point>> =
+            doc.edges().map(|edge| edge.path_points().unwrap().to_vec()).collect();
+        (paths, engine.config_hash())
+    };
+
+    let (default_paths, default_hash) = layout(None);
+
+    // The same seed always gives the same layout.
+    assert_eq!(layout(Some(1)), layout(Some(1)));
+    assert_ne!(layout(Some(1)).1, default_hash);
+    assert_eq!(
+        SimpleLayoutEngine::default().config_hash(),
+        default_hash,
+        "no seed keeps the default layout"
+    );
+    // Routes around the left and the right side of the column cost the same, and seeds choose
+    // between them.
+    assert!((0..8).any(|seed| layout(Some(seed)).0 != default_paths));
+}
+
+#[test]
+fn vertical_orientation() {
+    let src = "erd {
+%columns 2
+a { id int PK }
+b { id int PK\na_id int FK }
+c { id int PK }
+d { id int PK }
+b.a_id o--o a.id
+}";
+    let (ast, _, _) = parse(src);
+    let mut doc = ast.unwrap().into_mir();
+    let mut engine = SimpleLayoutEngine::default();
+
+    assert_eq!("vertical".parse(), Ok(LayoutOrientation::Vertical));
+    assert!("diagonal".parse::<LayoutOrientation>().is_err());
+
+    engine.orientation = LayoutOrientation::Vertical;
+    let view_box = engine.layout(&mut doc).view_box.unwrap();
+
+    let rects: Vec<Rect> = doc
+        .body()
+        .children()
+        .map(|child_id| doc.get_node(child_id).unwrap().rect().unwrap())
+        .collect();
+
+    // a and b fill the first column, c and d the second.
+    assert_eq!(rects[0].min_x(), rects[1].min_x());
+    assert_eq!(rects[1].min_y(), rects[0].max_y() + LayoutOptions::DEFAULT_RECORD_SPACE);
+    assert_eq!(rects[2].min_y(), rects[0].min_y());
+    assert_eq!(rects[3].min_y(), rects[1].min_y());
+    assert!(rects[2].min_x() > rects[0].max_x());
+    assert_eq!(view_box.max_y(), rects[1].max_y() + LayoutOptions::DEFAULT_MARGIN);
+    assert!(doc.edges().all(|edge| edge.path_points().is_some()));
+    assert_ne!(engine.config_hash(), SimpleLayoutEngine::default().config_hash());
+}
+
+#[test]
+fn blank_cells() {
+    let src = "erd {
+a { id int PK }
+b { id int PK }
+c { id int PK }
+}";
+    let (ast, _, _) = parse(src);
+    let mut doc = ast.unwrap().into_mir();
+    let mut engine = SimpleLayoutEngine::new(LayoutOptions {
+        blank_cells: vec![GridCell::new(0, 1)],
+        ..LayoutOptions::default()
+    });
+
+    engine.layout(&mut doc).view_box.unwrap();
+
+    let origins: Vec<Point> = doc
+        .body()
+        .children()
+        .map(|child_id| doc.get_node(child_id).unwrap().origin.unwrap())
+        .collect();
+    let column_x = |column: f32| 50.0 + (300.0 + 80.0) * column;
+
+    // b skips the blank cell in the middle of the first row.
+    assert_eq!(origins[0].x, column_x(0.0));
+    assert_eq!(origins[1], Point::new(column_x(2.0), origins[0].y));
+    assert_eq!(origins[2].x, column_x(0.0));
+    assert!(origins[2].y > origins[0].y);
+    assert_ne!(engine.config_hash(), SimpleLayoutEngine::default().config_hash());
+}
+
+#[test]
+fn pack_columns() {
+    let src = "erd {
+%columns 2
+tall { a int\nb int\nc int\nd int\ne int\nf int }
+short { id int PK }
+c { id int PK }
+d { id int PK }
+}";
+    let place = |pack_columns: bool| {
+        let (ast, _, _) = parse(src);
+        let mut doc = ast.unwrap().into_mir();
+        let mut engine = SimpleLayoutEngine::default();
+
+        engine.pack_columns = pack_columns;
+        let view_box = engine.layout(&mut doc).view_box.unwrap();
+        let rects: Vec<Rect> = doc
+            .body()
+            .children()
+            .map(|child_id| doc.get_node(child_id).unwrap().rect().unwrap())
+            .collect();
+        (rects, view_box)
+    };
+    let space = LayoutOptions::DEFAULT_RECORD_SPACE;
+
+    // d is aligned with c at the top of the second row.
+    let (rects, _) = place(false);
+    assert_eq!(rects[3].min_y(), rects[2].min_y());
+    assert_eq!(rects[3].min_y(), rects[0].max_y() + space);
+
+    // d moves up right below short, and columns keep their x.
+    let (packed, view_box) = place(true);
+    assert_eq!(packed[3].min_y(), packed[1].max_y() + space);
+    assert_eq!(packed[3].min_x(), packed[1].min_x());
+    assert_eq!(packed[2], rects[2]);
+    assert_eq!(view_box.max_y(), packed[2].max_y() + LayoutOptions::DEFAULT_MARGIN);
+}
+
+#[test]
+fn group_layout() {
+    let src = "erd {
+users { id int PK }
+invoices [group=\"Billing\"] { id int PK\nuser_id int FK }
+payments [group=\"Billing\"] { id int PK\ninvoice_id int FK }
+posts { id int PK\nuser_id int FK }
+invoices.user_id o--o users.id
+payments.invoice_id o--o invoices.id
+posts.user_id o--o users.id
+}";
+    let (ast, _, _) = parse(src);
+    let (mut doc, node_paths) =
+        ast.unwrap().into_mir_with_node_paths(&ConvertOptions::default());
+    let mut engine = SimpleLayoutEngine::default();
+
+    engine.layout(&mut doc).view_box.unwrap();
+
+    let rect = |name: &str| {
+        let node_id = node_paths[&EntityPath::Entity(name.into())];
+        doc.get_node(node_id).unwrap().rect().unwrap()
+    };
+    let group_id = doc.group_ids().next().unwrap();
+    let group_rect = doc.get_node(group_id).unwrap().rect().unwrap();
+    let padding = LayoutOptions::DEFAULT_RECORD_SPACE / 2.0;
+
+    // The group takes the second grid cell, and members are padded below the header.
+    assert_eq!(group_rect.min_x(), rect("users").max_x() + LayoutOptions::DEFAULT_RECORD_SPACE);
+    assert_eq!(rect("invoices").min_x(), group_rect.min_x() + padding);
+    assert_eq!(
+        rect("invoices").min_y(),
+        group_rect.min_y() + LayoutOptions::DEFAULT_LINE_HEIGHT + padding
+    );
+    assert_eq!(rect("payments").min_y(), rect("invoices").min_y());
+    assert_eq!(group_rect.max_x(), rect("payments").max_x() + padding);
+    assert_eq!(rect("posts").min_x(), group_rect.max_x() + LayoutOptions::DEFAULT_RECORD_SPACE);
+
+    // `posts -> users` goes around the group, edges into members enter it.
+    let crosses = |points: &[Point]| {
+        let inner = group_rect.inset_by(1.0, 1.0);
+        points.windows(2).any(|segment| {
+            let (p, q) = (segment[0], segment[1]);
+            p.x.min(q.x) < inner.max_x()
+                && p.x.max(q.x) > inner.min_x()
+                && p.y.min(q.y) < inner.max_y()
+                && p.y.max(q.y) > inner.min_y()
+        })
+    };
+    let paths: Vec<&[Point]> = doc.edges().map(|edge| edge.path_points().unwrap()).collect();
+    assert!(crosses(paths[0]));
+    assert!(!crosses(paths[2]));
+
+    let mut svg = vec![];
+    SVGRenderer::new().render(&doc, &mut svg).unwrap();
+    assert!(String::from_utf8(svg).unwrap().contains("Billing"));
+}
+
+#[test]
+fn swimlanes() {
+    let src = "erd {
+users [group=\"Accounts\"] { id int PK }
+audit { id int PK\nuser_id int FK }
+invoices [group=\"Billing\"] { id int PK\nuser_id int FK }
+sessions [group=\"Accounts\"] { id int PK\nuser_id int FK }
+sessions.user_id o--o users.id
+invoices.user_id o--o users.id
+audit.user_id o--o users.id
+}";
+    let (ast, _, _) = parse(src);
+    let (mut doc, node_paths) =
+        ast.unwrap().into_mir_with_node_paths(&ConvertOptions::default());
+    let mut engine = SimpleLayoutEngine::default();
+
+    engine.swimlanes = true;
+    let view_box = engine.layout(&mut doc).view_box.unwrap();
+
+    let rect = |name: &str| {
+        let node_id = node_paths[&EntityPath::Entity(name.into())];
+        doc.get_node(node_id).unwrap().rect().unwrap()
+    };
+    let lanes: Vec<Rect> = doc
+        .group_ids()
+        .map(|group_id| doc.get_node(group_id).unwrap().rect().unwrap())
+        .collect();
+    let margin = LayoutOptions::DEFAULT_MARGIN;
+
+    // Lanes span the grid from top to bottom in the order of groups, and ungrouped records
+    // follow them.
+    assert_eq!(lanes.len(), 2);
+    assert_eq!(lanes[0].width(), lanes[1].width());
+    assert_eq!(lanes[0].min_x(), margin);
+    assert_eq!(lanes[0].max_x(), view_box.max_x() - margin);
+    assert!(lanes[0].max_y() < lanes[1].min_y());
+    assert!(lanes[0].contains_point(rect("users").origin()));
+    assert_eq!(rect("sessions").min_y(), rect("users").min_y());
+    assert!(lanes[1].contains_point(rect("invoices").origin()));
+    assert!(rect("audit").min_y() > lanes[1].max_y());
+    // Columns are aligned across lanes.
+    assert_eq!(rect("invoices").min_x(), rect("users").min_x());
+    assert_eq!(rect("audit").min_x(), rect("users").min_x());
+    // Edges between lanes are routed.
+    assert!(doc.edges().all(|edge| edge.path_points().is_some()));
+}
+
+#[test]
+fn circular_layout() {
+    let src = "erd {
+users { id int PK }
+posts { id int PK\nuser_id int FK }
+comments { id int PK\npost_id int FK\nuser_id int FK }
+likes { id int PK\npost_id int FK\nuser_id int FK }
+posts.user_id o--o users.id
+comments.post_id o--o posts.id
+comments.user_id o--o users.id
+likes.post_id o--o posts.id
+likes.user_id o--o users.id
+}";
+    let (ast, _, _) = parse(src);
+    let (mut doc, node_paths) =
+        ast.unwrap().into_mir_with_node_paths(&ConvertOptions::default());
+    let mut engine = SimpleLayoutEngine::default();
+
+    engine.circular = true;
+    let view_box = engine.layout(&mut doc).view_box.unwrap();
+
+    let rect = |name: &str| {
+        let node_id = node_paths[&EntityPath::Entity(name.into())];
+        doc.get_node(node_id).unwrap().rect().unwrap()
+    };
+    let margin = LayoutOptions::DEFAULT_MARGIN;
+
+    // Records go clockwise from the top, and opposite records are centered on the same axis.
+    assert_eq!(rect("users").center().x, rect("comments").center().x);
+    assert_eq!(rect("posts").center().y, rect("likes").center().y);
+    assert!(rect("users").max_y() < rect("posts").min_y());
+    assert!(rect("posts").min_x() > rect("likes").max_x());
+    assert!(rect("likes").min_x() >= margin);
+    assert!(rect("posts").max_x() <= view_box.max_x() - margin);
+    assert!(doc.edges().all(|edge| edge.path_points().is_some()));
+}
+
+#[test]
+fn circular_layout_tall_records() {
+    let fields: String = (0..10).map(|i| format!("f{} int\n", i)).collect();
+    let src = format!(
+        "erd {{\na {{ id int PK\n{0}}}\nb {{ id int PK\na_id int FK\n{0}}}\n\
+         c {{ id int PK\n{0}}}\nd {{ id int PK\n{0}}}\nb.a_id o--o a.id\n}}",
+        fields
+    );
+    let (ast, _, _) = parse(&src);
+    let mut doc = ast.unwrap().into_mir();
+    let mut engine = SimpleLayoutEngine::default();
+
+    engine.circular = true;
+    engine.layout(&mut doc);
+
+    assert!(doc.edges().all(|edge| edge.path_points().is_some()));
+    assert_eq!(layout::validate_layout(&doc), vec![]);
+}
+
+#[test]
+fn order_by_references() {
+    let src = "erd {
+comments { id int PK\npost_id int FK\nuser_id int FK }
+posts { id int PK\nuser_id int FK }
+tags { id int PK }
+users { id int PK\nbest_friend_id int FK }
+friends { id int PK\nuser_id int FK }
+comments.post_id o--o posts.id
+comments.user_id o--o users.id
+posts.user_id o--o users.id
+users.best_friend_id o--o friends.id
+friends.user_id o--o users.id
+}";
+    let (ast, _, _) = parse(src);
+    let (doc, node_paths) = ast.unwrap().into_mir_with_node_paths(&ConvertOptions::default());
+    let names = ["comments", "posts", "tags", "users", "friends"];
+    let records: Vec<_> =
+        names.iter().map(|name| node_paths[&EntityPath::Entity((*name).into())]).collect();
+    let ordered = SimpleLayoutEngine::order_by_references(&doc, &records);
+    let name_of = |node_id| names[records.iter().position(|id| *id == node_id).unwrap()];
+
+    // `users` and `friends` refer to each other, so they stay together in the order of
+    // definitions. `tags` has no references, and keeps its place before `users`.
+    assert_eq!(
+        ordered.into_iter().map(name_of).collect::<Vec<_>>(),
+        ["tags", "users", "friends", "posts", "comments"]
+    );
+}
+
+#[test]
+fn order_by_references_from_keys() {
+    let src = "erd {
+posts { id int PK\ncreated_by int FK }
+users { id int PK }
+users.id o--o posts.created_by
+}";
+    let (ast, _, _) = parse(src);
+    let (doc, node_paths) = ast.unwrap().into_mir_with_node_paths(&ConvertOptions::default());
+    let [posts, users] =
+        ["posts", "users"].map(|name| node_paths[&EntityPath::Entity(name.into())]);
+
+    // `posts` refers to `users` by its foreign key, although the relation is written the other
+    // way around.
+    assert_eq!(SimpleLayoutEngine::order_by_references(&doc, &[posts, users]), [users, posts]);
+}
+
+#[test]
+fn layout_pipeline() {
+    let src = fs::read_to_string("example/posts.seiren").unwrap();
+    let (ast, _, _) = parse(&src);
+    let module = ast.unwrap();
+    let mut doc = module.into_mir();
+    let mut plain_doc = module.into_mir();
+    let mut plain_engine = SimpleLayoutEngine::default();
+    let view_box = plain_engine.layout(&mut plain_doc).view_box;
+
+    let mut stages: Vec<(LayoutStage, bool)> = vec![];
+    let mut hooks = |stage: LayoutStage, doc: &mut Document| {
+        let has_paths = doc.edges().all(|edge| edge.path_points().is_some());
+        stages.push((stage, has_paths));
+    };
+    let result = SimpleLayoutEngine::default().layout_with_hooks(&mut doc, &mut hooks);
+
+    assert_eq!(result, LayoutResult { view_box, warnings: vec![] });
+    assert_eq!(
+        stages,
+        [
+            (LayoutStage::PlaceNodes, false),
+            (LayoutStage::PlaceTerminalPorts, false),
+            (LayoutStage::DrawEdgePaths, true),
+            (LayoutStage::PlaceEdgeLabels, true),
+        ]
+    );
+    assert_eq!(
+        doc.edges().map(|edge| edge.path_points().unwrap().to_vec()).collect::<Vec<_>>(),
+        plain_doc.edges().map(|edge| edge.path_points().unwrap().to_vec()).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn bundle_edges() {
+    let src = "erd {
+posts { id int PK\nuser_id int FK }
+users { id int PK }
+comments { id int PK\nuser_id int FK }
+likes { id int PK\nuser_id int FK }
+posts.user_id o--o users.id
+comments.user_id o--o users.id
+likes.user_id o--o users.id
+}";
+    let end_points = |bundle_edges: bool| {
+        let (ast, _, _) = parse(src);
+        let mut doc = ast.unwrap().into_mir();
+        let mut engine = SimpleLayoutEngine::default();
+
+        engine.bundle_edges = bundle_edges;
+        engine.layout(&mut doc);
+
+        let mut points: Vec<Point> =
+            doc.edges().map(|edge| *edge.path_points().unwrap().last().unwrap()).collect();
+        points.dedup();
+        points
+    };
+
+    // Edges from both sides of `users` end at different ports, unless they are bundled.
+    assert!(end_points(false).len() > 1);
+    assert_eq!(end_points(true).len(), 1);
+}
+
+#[test]
+fn bundle_edges_by_referred_field() {
+    let src = "erd {
+posts { id int PK\nuser_id int FK }
+users { id int PK }
+comments { id int PK\nuser_id int FK }
+likes { id int PK\nuser_id int FK }
+posts.user_id o--o users.id
+users.id o--o comments.user_id
+likes.user_id o--o users.id
+}";
+    let (ast, _, _) = parse(src);
+    let mut doc = ast.unwrap().into_mir();
+    let mut engine = SimpleLayoutEngine::default();
+
+    engine.bundle_edges = true;
+    engine.layout(&mut doc);
+
+    // Relations point from foreign keys to `users.id`, however they are written.
+    let directions: Vec<EdgeDirection> = doc.edges().map(|edge| edge.direction()).collect();
+    assert_eq!(
+        directions,
+        [EdgeDirection::Forward, EdgeDirection::Backward, EdgeDirection::Forward]
+    );
+
+    let mut points: Vec<Point> = doc
+        .edges()
+        .map(|edge| {
+            let points = edge.path_points().unwrap();
+            match edge.direction() {
+                EdgeDirection::Backward => points[0],
+                _ => *points.last().unwrap(),
+            }
+        })
+        .collect();
+    points.dedup();
+    assert_eq!(points.len(), 1);
+}
+
+#[test]
+fn port_capacity() {
+    let src = "erd {
+users { id int PK\nname text }
+posts { id int PK\nuser_id int FK }
+comments { id int PK\nuser_id int FK }
+posts.user_id o--o users.id
+comments.user_id o--o users.id
+}";
+    let (ast, _, _) = parse(src);
+    let (mut doc, node_paths) =
+        ast.unwrap().into_mir_with_node_paths(&ConvertOptions::default());
+    let mut engine = SimpleLayoutEngine::default();
+
+    engine.port_capacity = Some(1);
+    engine.layout(&mut doc);
+
+    // Both edges into `users.id` prefer its right port, but the second one takes another.
+    let users_id = node_paths[&EntityPath::Field("users".into(), "id".into())];
+    let ports: Vec<Point> = doc
+        .get_node(users_id)
+        .unwrap()
+        .terminal_ports()
+        .map(|port| *port.location())
+        .collect();
+    let end_points: Vec<Point> =
+        doc.edges().map(|edge| *edge.path_points().unwrap().last().unwrap()).collect();
+
+    assert!(end_points.iter().all(|point| ports.contains(point)));
+    assert_ne!(end_points[0], end_points[1]);
+    assert_eq!(
+        engine.explain_edge(&doc, doc.edge_ids().last().unwrap()).unwrap().path.len(),
+        doc.edges().last().unwrap().path_points().unwrap().len()
+    );
+}
+
+#[test]
+fn config_hash_tags_fields() {
+    let hash = |configure: fn(&mut SimpleLayoutEngine)| {
+        let mut engine = SimpleLayoutEngine::default();
+
+        configure(&mut engine);
+        engine.config_hash()
+    };
+
+    assert_ne!(
+        hash(|engine| engine.seed = Some(3)),
+        hash(|engine| engine.port_capacity = Some(3))
+    );
+    assert_ne!(
+        hash(|engine| engine.target_aspect_ratio = Some(8.0)),
+        hash(|engine| engine.separate_edges = true)
+    );
+}
+
+#[test]
+fn keep_out_regions() {
+    let src = "erd {
+users { id int PK }
+posts { id int PK\nuser_id int FK }
+posts.user_id o--o users.id [label=\"author\"]
+}";
+    let layout = |keep_out_regions: Vec<Rect>| {
+        let (ast, _, _) = parse(src);
+        let mut doc = ast.unwrap().into_mir();
+        let mut engine = SimpleLayoutEngine::new(LayoutOptions {
+            keep_out_regions,
+            ..LayoutOptions::default()
+        });
+
+        engine.layout(&mut doc);
+        let edge = doc.edges().next().unwrap();
+        (edge.path_points().unwrap().to_vec(), edge.label_center().unwrap())
+    };
+    let crosses = |path: &[Point], region: &Rect| {
+        path.windows(2).any(|segment| region.intersects_line(&segment[0], &segment[1]))
+    };
+
+    // Covers the gap between both records, where the edge is routed by default.
+    let region = Rect::new(Point::new(360.0, 100.0), Size::new(60.0, 300.0));
+    let (default_path, _) = layout(vec![]);
+    let (path, label_center) = layout(vec![region]);
+
+    assert!(crosses(&default_path, &region));
+    assert!(!crosses(&path, &region));
+    assert!(!region.contains_point(&label_center));
+}
+
+#[test]
+fn snap() {
+    let src = "erd {
+users { id int PK\nname text }
+posts { id int PK\nuser_id int FK }
+comments { id int PK\npost_id int FK\nuser_id int FK }
+posts.user_id o--o users.id
+comments.post_id o--o posts.id
+comments.user_id o--o users.id
+}";
+    let (ast, _, _) = parse(src);
+    let mut doc = ast.unwrap().into_mir();
+    let mut engine = SimpleLayoutEngine::default();
+
+    engine.separate_edges = true;
+    engine.snap = Some(2.0);
+    engine.layout(&mut doc);
+
+    let on_grid = |point: &Point| point.x % 2.0 == 0.0 && point.y % 2.0 == 0.0;
+    let records: Vec<_> = doc.body().children().filter_map(|id| doc.get_node(id)).collect();
+    let ports: Vec<Point> = records
+        .iter()
+        .flat_map(|record| {
+            record.children().filter_map(|id| doc.get_node(id)).chain([*record])
+        })
+        .flat_map(|node| node.terminal_ports().map(|port| *port.location()))
+        .collect();
+
+    assert!(records.iter().all(|record| on_grid(&record.rect().unwrap().origin)));
+    assert!(ports.iter().all(on_grid));
+    for edge in doc.edges() {
+        let path = edge.path_points().unwrap();
+
+        assert!(path.iter().all(on_grid));
+        assert!(ports.contains(&path[0]) && ports.contains(path.last().unwrap()));
+    }
+    assert_ne!(engine.config_hash(), SimpleLayoutEngine::default().config_hash());
+
+    // Invalid lengths are ignored.
+    for snap in [0.0, -1.0, f32::NAN, f32::INFINITY] {
+        let (ast, _, _) = parse(src);
+        let mut snapped = ast.unwrap().into_mir();
+
+        engine.snap = Some(snap);
+        engine.layout(&mut snapped);
+        assert!(snapped.edges().all(|edge| edge.path_points().is_some()));
+
+        let hash = engine.config_hash();
+        engine.snap = None;
+        assert_eq!(hash, engine.config_hash());
+    }
+}
+
+#[test]
+fn route_cache() {
+    let src = |extra_field: &str| {
+        format!(
+            "erd {{
+%columns 2
+a {{ id int PK }}
+b {{ id int PK\na_id int FK }}
+c {{ id int PK }}
+d {{ id int PK }}
+e {{ id int PK }}
+f {{ id int PK\ne_id int FK{} }}
+b.a_id o--o a.id
+f.e_id o--o e.id
+}}",
+            extra_field
+        )
+    };
+    let mut engine = SimpleLayoutEngine::default();
+
+    engine.route_cache = Some(RouteCache::default());
+    let mut layout = |src: &str| {
+        let (ast, _, _) = parse(src);
+        let mut doc = ast.unwrap().into_mir();
+
+        engine.layout(&mut doc);
+        let paths: Vec<Vec<Point>> =
+            doc.edges().map(|edge| edge.path_points().unwrap().to_vec()).collect();
+        let cache = engine.route_cache.as_ref().unwrap();
+
+        (paths, cache.hits(), cache.misses())
+    };
+
+    let (paths, hits, misses) = layout(&src(""));
+    assert_eq!((hits, misses), (0, 2));
+
+    let (cached_paths, hits, misses) = layout(&src(""));
+    assert_eq!((hits, misses), (2, 0));
+    assert_eq!(cached_paths, paths);
+
+    // `f` grows at the bottom of the diagram, which only changes the surroundings of `f.e_id`.
+    let (_, hits, misses) = layout(&src("\nname text"));
+    assert_eq!((hits, misses), (1, 1));
+}
+
+#[test]
+fn move_node() {
+    let src = "erd {
+%columns 2
+a { id int PK }
+b { id int PK\na_id int FK }
+c { id int PK }
+d { id int PK\nc_id int FK }
+b.a_id o--o a.id
+d.c_id o--o c.id
+}";
+    let (ast, _, _) = parse(src);
+    let (mut doc, node_paths) =
+        ast.unwrap().into_mir_with_node_paths(&ConvertOptions::default());
+    let mut engine = SimpleLayoutEngine::default();
+
+    engine.layout(&mut doc);
+
+    let a = node_paths[&EntityPath::Entity("a".into())];
+    let a_id = node_paths[&EntityPath::Field("a".into(), "id".into())];
+    let paths = |doc: &Document| -> Vec<Vec<Point>> {
+        doc.edges().map(|edge| edge.path_points().unwrap().to_vec()).collect()
+    };
+    let old_paths = paths(&doc);
+    let n_ports = doc.get_node(a_id).unwrap().terminal_ports().len();
+    let result = engine.move_node(&mut doc, a, Point::new(1000.0, 50.0));
+    let new_paths = paths(&doc);
+    let ports: Vec<Point> =
+        doc.get_node(a_id).unwrap().terminal_ports().map(|port| *port.location()).collect();
+
+    assert_eq!(doc.get_node(a).unwrap().rect().unwrap().origin, Point::new(1000.0, 50.0));
+    // Below the header row
+    assert_eq!(doc.get_node(a_id).unwrap().rect().unwrap().origin, Point::new(1000.0, 85.0));
+    assert_eq!(ports.len(), n_ports);
+    assert!(ports.iter().all(|port| port.x >= 1000.0));
+    // Only the edge into `a` is routed again.
+    assert!(ports.contains(new_paths[0].last().unwrap()));
+    assert_ne!(new_paths[0], old_paths[0]);
+    assert_eq!(new_paths[1], old_paths[1]);
+    assert_eq!(result.view_box.unwrap().width(), 1000.0 + 300.0 + 50.0);
+
+    // The edge which stays keeps its lanes in the new route graph.
+    let graph = engine.edge_route_graph();
+    let on_path = |point: &Point| {
+        new_paths[1].windows(2).any(|s| {
+            let (a, b) = (s[0], s[1]);
+
+            (a.x.min(b.x)..=a.x.max(b.x)).contains(&point.x)
+                && (a.y.min(b.y)..=a.y.max(b.y)).contains(&point.y)
+        })
+    };
+    assert!(graph.edges().any(|edge| {
+        let [a, b] = [edge.source_id(), edge.target_id()]
+            .map(|id| *graph.get_node(id).unwrap().location());
+
+        edge.lanes().contains(&0) && on_path(&a) && on_path(&b)
+    }));
+}
+
+#[test]
+fn default_move_node() {
+    // An engine which only implements the pipeline.
+    struct Pipeline(SimpleLayoutEngine);
+
+    impl LayoutEngine for Pipeline {
+        fn layout_with_hooks(
+            &mut self,
+            doc: &mut Document,
+            hooks: &mut dyn LayoutHooks,
+        ) -> LayoutResult {
+            self.0.layout_with_hooks(doc, hooks)
+        }
+    }
+
+    let src = "erd {
+a { id int PK }
+b { id int PK\na_id int FK }
+b.a_id o--o a.id
+}";
+    let (ast, _, _) = parse(src);
+    let (mut doc, node_paths) =
+        ast.unwrap().into_mir_with_node_paths(&ConvertOptions::default());
+    let mut engine = Pipeline(SimpleLayoutEngine::default());
+
+    engine.layout(&mut doc);
+
+    // The document is laid out again with the record at its new place.
+    let a = node_paths[&EntityPath::Entity("a".into())];
+    let result = engine.move_node(&mut doc, a, Point::new(1000.0, 50.0));
+
+    assert_eq!(doc.get_node(a).unwrap().rect().unwrap().origin, Point::new(1000.0, 50.0));
+    assert!(result.view_box.unwrap().width() > 1000.0);
+    assert!(doc.edges().all(|edge| edge.path_points().is_some()));
+}
+
+#[test]
+fn validate_layout() {
+    let src = "erd {
+a { id int PK }
+b { id int PK\na_id int FK }
+c { id int PK }
+b.a_id o--o a.id
+}";
+    let (ast, _, _) = parse(src);
+    let (mut doc, node_paths) =
+        ast.unwrap().into_mir_with_node_paths(&ConvertOptions::default());
+    let mut engine = SimpleLayoutEngine::default();
+
+    engine.layout(&mut doc);
+    assert_eq!(layout::validate_layout(&doc), vec![]);
+
+    // The edge is drawn across `c`.
+    let [a, c] = ["a", "c"].map(|name| node_paths[&EntityPath::Entity(name.into())]);
+    let c_rect = doc.get_node(c).unwrap().rect().unwrap();
+    let edge_id = doc.edge_ids().next().unwrap();
+
+    doc.edge_mut(edge_id).unwrap().set_path_points(Some(vec![
+        Point::new(c_rect.min_x() - 10.0, c_rect.mid_y()),
+        Point::new(c_rect.max_x() + 10.0, c_rect.mid_y()),
+    ]));
+    assert_eq!(
+        layout::validate_layout(&doc),
+        vec![LayoutWarning::EdgeThroughRecord { edge_id, record_id: c }]
+    );
+
+    // `c` is pinned onto `a`.
+    let a_rect = doc.get_node(a).unwrap().rect().unwrap();
+
+    engine.pin(c, Point::new(a_rect.mid_x(), a_rect.min_y()));
+    engine.layout(&mut doc);
+    assert!(layout::validate_layout(&doc)
+        .contains(&LayoutWarning::RecordOverlap { first: a, second: c }));
+}
+
+#[test]
+fn balance_columns() {
+    let src = "erd {
+%columns 2
+a { id int PK }
+b { id int PK }
+c { id int PK\nname text\nemail text\nbio text\ncreated_at timestamp }
+}";
+    let layout = |balance_columns: bool| {
+        let (ast, _, _) = parse(src);
+        let (mut doc, node_paths) =
+            ast.unwrap().into_mir_with_node_paths(&ConvertOptions::default());
+        let mut engine = SimpleLayoutEngine::default();
+
+        engine.balance_columns = balance_columns;
+        let view_box = engine.layout(&mut doc).view_box.unwrap();
+        let origins: Vec<Point> = ["a", "b", "c"]
+            .map(|name| node_paths[&EntityPath::Entity(name.into())])
+            .iter()
+            .map(|id| doc.get_node(*id).unwrap().rect().unwrap().origin)
+            .collect();
+
+        assert_eq!(layout::validate_layout(&doc), vec![]);
+        (view_box.height(), origins)
+    };
+
+    let (height, _) = layout(false);
+    let (balanced_height, origins) = layout(true);
+
+    // The tall `c` takes the left column alone, and `a` and `b` are stacked on the right.
+    assert!(balanced_height < height);
+    assert_eq!(origins[2], Point::new(50.0, 50.0));
+    assert_eq!(origins[0].x, origins[1].x);
+    assert!(origins[0].y < origins[1].y);
+}
+
+#[test]
+fn place_edge_labels() {
+    let src = "erd {
+users { id int PK\nname text }
+posts { id int PK\nuser_id int FK }
+comments { id int PK\nuser_id int FK }
+posts.user_id o--o users.id [cardinality=\"N:1\"]
+comments.user_id o--o users.id [label=\"author\", cardinality=\"N:1\"]
+}";
+    let (ast, _, _) = parse(src);
+    let mut doc = ast.unwrap().into_mir();
+    let mut engine = SimpleLayoutEngine::default();
+
+    engine.layout(&mut doc);
+
+    let edges: Vec<&EdgeData> = doc.edges().collect();
+    let end_point = |edge: &EdgeData| *edge.path_points().unwrap().last().unwrap();
+
+    // Both edges end at the same port, so the second cardinality moves to the other side.
+    assert_eq!(end_point(edges[0]), end_point(edges[1]));
+    assert!(!edges[0].cardinality_flips()[1]);
+    assert!(edges[1].cardinality_flips()[1]);
+    assert_eq!(edges[0].label_center(), None);
+
+    let mut svg = vec![];
+    SVGRenderer::new().render(&doc, &mut svg).unwrap();
+    let svg = String::from_utf8(svg).unwrap();
+    let center = edges[1].label_center().unwrap();
+
+    assert!(svg.contains(&format!("x=\"{}\" y=\"{}\">\nauthor", center.x, center.y)));
+}
+
+#[test]
+fn max_width() {
+    let src = "erd {
+%columns 4
+a { id int PK }
+b { id int PK }
+c { id int PK }
+d { id int PK }
+e { id int PK }
+}";
+    let layout = |max_width: Option<f32>| {
+        let (ast, _, _) = parse(src);
+        let mut doc = ast.unwrap().into_mir();
+        let mut engine = SimpleLayoutEngine::default();
+
+        engine.max_width = max_width;
+        engine.layout(&mut doc).view_box.unwrap()
+    };
+
+    // 4 columns of 300 wide records, 80 apart, don't fit in 1000, but 2 columns do.
+    assert_eq!(layout(None).width(), 50.0 * 2.0 + 300.0 * 4.0 + 80.0 * 3.0);
+    assert_eq!(layout(Some(1000.0)).width(), 50.0 * 2.0 + 300.0 * 2.0 + 80.0);
+    assert!(layout(Some(1000.0)).height() > layout(None).height());
+    // A single column may be wider.
+    assert_eq!(layout(Some(100.0)).width(), 50.0 * 2.0 + 300.0);
+}
+
+#[test]
+fn isolated_records() {
+    let src = "erd {
+%columns 2
+users { id int PK }
+settings { id int PK }
+posts { id int PK\nuser_id int FK }
+logs { id int PK }
+comments { id int PK\npost_id int FK }
+posts.user_id o--o users.id
+comments.post_id o--o posts.id
+}";
+    let layout = |isolated_records: IsolatedRecords| {
+        let (ast, _, _) = parse(src);
+        let (mut doc, node_paths) =
+            ast.unwrap().into_mir_with_node_paths(&ConvertOptions::default());
+        let mut engine = SimpleLayoutEngine::default();
+
+        engine.isolated_records = isolated_records;
+        let view_box = engine.layout(&mut doc).view_box.unwrap();
+        let names = ["users", "settings", "posts", "logs", "comments"];
+        let origins: Vec<Point> = names
+            .iter()
+            .map(|name| {
+                let node_id = node_paths[&EntityPath::Entity((*name).into())];
+                *doc.get_node(node_id).unwrap().rect().unwrap().origin()
+            })
+            .collect();
+
+        (view_box, origins)
+    };
+    let (x0, x1) = (50.0, 50.0 + 300.0 + 80.0);
+
+    // Connected records fill the grid, and isolated ones start the next row.
+    let (_, origins) = layout(IsolatedRecords::Trailing);
+    assert_eq!(origins.iter().map(|o| o.x).collect::<Vec<_>>(), [x0, x0, x1, x1, x0]);
+    assert_eq!(origins[0].y, origins[2].y);
+    assert!(origins[4].y > origins[0].y);
+    assert!(origins[1].y > origins[4].y);
+    assert_eq!(origins[1].y, origins[3].y);
+
+    // Isolated records are stacked right of the grid.
+    let (view_box, origins) = layout(IsolatedRecords::SideColumn);
+    let x2 = x1 + 300.0 + 80.0;
+    assert_eq!(origins.iter().map(|o| o.x).collect::<Vec<_>>(), [x0, x2, x1, x2, x0]);
+    assert_eq!(origins[1].y, origins[0].y);
+    assert!(origins[3].y > origins[1].y);
+    assert_eq!(view_box.width(), x2 + 300.0 + 50.0);
+
+    assert_eq!("side-column".parse(), Ok(IsolatedRecords::SideColumn));
+    assert!("side".parse::<IsolatedRecords>().is_err());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_round_trip() {
+    let src = "erd {
+users { id int PK; name text }
+posts { id int PK; user_id int FK }
+posts.user_id o--o users.id
+}";
+    let (ast, _, _) = parse(src);
+    let module = ast.unwrap();
+
+    // AST
+    let json = serde_json::to_string(&module).expect("serialize module");
+    let restored: Module = serde_json::from_str(&json).expect("deserialize module");
+    assert_eq!(restored.to_string(), module.to_string());
+    assert_eq!(restored.entity("posts").map(|e| e.fields().len()), Some(2));
+
+    // Laid out MIR
+    let mut doc = module.into_mir();
+    SimpleLayoutEngine::default().layout(&mut doc);
+
+    let json = serde_json::to_string(&doc).expect("serialize document");
+    let restored: Document = serde_json::from_str(&json).expect("deserialize document");
+    let render = |doc: &Document| {
+        let mut bytes: Vec<u8> = vec![];
+        SVGRenderer::new().render(doc, &mut bytes).expect("generate SVG");
+        String::from_utf8(bytes).unwrap()
+    };
+    assert_eq!(render(&restored), render(&doc));
+}
+
+#[test]
+fn edge_data_builder() {
+    let mut doc = Document::new();
+    let source_id = doc.create_record(RecordShapeBuilder::default().build().unwrap());
+    let target_id = doc.create_record(RecordShapeBuilder::default().build().unwrap());
+
+    let edge = EdgeDataBuilder::default()
+        .source_id(source_id)
+        .target_id(target_id)
+        .end_marker(EdgeMarker::Arrow)
+        .label(Some("depends on".into()))
+        .layer(Some(EdgeLayer::Above))
+        .direction(EdgeDirection::Forward)
+        .build()
+        .unwrap();
+
+    assert_eq!((edge.source_id(), edge.target_id()), (source_id, target_id));
+    assert_eq!(edge.start_marker(), EdgeMarker::default());
+    assert_eq!(edge.end_marker(), EdgeMarker::Arrow);
+    assert_eq!(edge.label(), Some("depends on"));
+    assert_eq!(edge.direction().points_to(), [false, true]);
+    assert!(edge.path_points().is_none());
+
+    // Both ends are required.
+    assert!(EdgeDataBuilder::default().source_id(source_id).build().is_err());
+    assert_eq!(EdgeData::new(source_id, target_id, None).direction(), EdgeDirection::None);
+    assert_eq!("both".parse(), Ok(EdgeDirection::Both));
+    assert!("up".parse::<EdgeDirection>().is_err());
+}
+
+#[test]
+fn notes() {
+    let src = "erd {
+%columns 2
+users { id int PK }
+posts { id int PK\nuser_id int FK }
+posts.user_id o--o users.id
+}";
+    let (ast, _, _) = parse(src);
+    let (mut doc, node_paths) =
+        ast.unwrap().into_mir_with_node_paths(&ConvertOptions::default());
+    let users = node_paths[&EntityPath::Entity("users".into())];
+    let posts = node_paths[&EntityPath::Entity("posts".into())];
+    let note = |text: &str, max_width: Option<f32>, anchor: Option<_>| {
+        NoteShapeBuilder::default()
+            .text(TextSpanBuilder::default().text(text).build().unwrap())
+            .max_width(max_width)
+            .anchor(anchor)
+            .build()
+            .unwrap()
+    };
+    let posts_note = doc.create_note(note("Partitioned by month", None, Some(posts)));
+    let users_note =
+        doc.create_note(note("Owned by the auth team, synced nightly", Some(120.0), Some(users)));
+    let free_note = doc.create_note(note("Draft", None, None));
+
+    let view_box = SimpleLayoutEngine::default().layout(&mut doc).view_box.unwrap();
+    let rect = |node_id| doc.get_node(node_id).unwrap().rect().unwrap();
+
+    // Right of posts, and below users because posts is right of it.
+    assert_eq!(rect(posts_note).origin, Point::new(rect(posts).max_x() + 80.0, 50.0));
+    assert_eq!(rect(users_note).origin, Point::new(50.0, rect(users).max_y() + 80.0));
+    assert!(rect(users_note).width() <= 120.0);
+    assert!(rect(users_note).height() > rect(posts_note).height());
+    // Unanchored notes are stacked below everything else.
+    assert_eq!(rect(free_note).min_x(), 50.0);
+    assert_eq!(rect(free_note).min_y(), rect(users_note).max_y() + 80.0);
+    for note_id in [posts_note, users_note, free_note] {
+        assert!(view_box.max_x() >= rect(note_id).max_x() + 50.0);
+        assert!(view_box.max_y() >= rect(note_id).max_y() + 50.0);
+    }
+    assert!(layout::validate_layout(&doc).is_empty());
+
+    let mut bytes: Vec<u8> = vec![];
+    SVGRenderer::new().render(&doc, &mut bytes).expect("generate SVG");
+    let svg = String::from_utf8(bytes).unwrap();
+
+    assert!(svg.contains(">\nPartitioned by month\n</text>"));
+    assert!(svg.contains(">\nDraft\n</text>"));
+    assert!(!svg.contains("Owned by the auth team, synced nightly"));
+}
+
+#[test]
+fn notes_follow_layout() {
+    let src = "erd {
+users { id int PK }
+posts { id int PK\nuser_id int FK }
+posts.user_id o--o users.id
+}";
+    let (ast, _, _) = parse(src);
+    let (mut doc, node_paths) =
+        ast.unwrap().into_mir_with_node_paths(&ConvertOptions::default());
+    let users = node_paths[&EntityPath::Entity("users".into())];
+    let text = TextSpanBuilder::default().text("Owned by the auth team").build().unwrap();
+    let note = doc.create_note(
+        NoteShapeBuilder::default().text(text).anchor(Some(users)).build().unwrap(),
+    );
+    let mut engine = SimpleLayoutEngine::default();
+
+    // Wide characters wrap the text at each space.
+    engine.text_measure = Box::new(CharWidth(3.0));
+    engine.layout(&mut doc);
+
+    let lines = |doc: &Document| match doc.get_node(note).unwrap().kind() {
+        ShapeKind::Note(note) => note.lines.clone(),
+        _ => vec![],
+    };
+    assert_eq!(lines(&doc), ["Owned", "by", "the", "auth", "team"]);
+
+    let mut bytes: Vec<u8> = vec![];
+    SVGRenderer::new().render(&doc, &mut bytes).expect("generate SVG");
+    let svg = String::from_utf8(bytes).unwrap();
+    assert!(svg.contains(">\nauth\n</text>"));
+
+    // The note moves along with its record.
+    let rect = |doc: &Document, node_id| doc.get_node(node_id).unwrap().rect().unwrap();
+    let (users_rect, note_rect) = (rect(&doc, users), rect(&doc, note));
+    let origin = Point::new(users_rect.min_x(), users_rect.min_y() + 400.0);
+
+    engine.move_node(&mut doc, users, origin);
+
+    assert_eq!(rect(&doc, note).min_x(), note_rect.min_x());
+    assert_eq!(rect(&doc, note).min_y(), note_rect.min_y() + 400.0);
+    assert!(doc.body().rect().unwrap().max_y() >= rect(&doc, note).max_y());
 }